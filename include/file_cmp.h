@@ -0,0 +1,35 @@
+#ifndef FILE_CMP_H
+#define FILE_CMP_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Results are the offset of the first difference, or one of these. */
+#define FILE_CMP_EQUAL -1
+#define FILE_CMP_LEFT_ONLY -2
+#define FILE_CMP_RIGHT_ONLY -3
+#define FILE_CMP_ERROR -4
+
+typedef void (*file_cmp_callback)(const char *path, int64_t result, void *user_data);
+
+/* Compares two files. With quick set, differences are reported at offset 0. */
+int64_t file_cmp_compare_files(const char *path1, const char *path2, int quick);
+
+/* Compares two directory trees, calling callback with each path and its
+   result. Returns 0, or FILE_CMP_ERROR if the directories can't be read, or
+   after the walk if any directory or pair of files in them couldn't be read,
+   with the first error in file_cmp_last_error. */
+int64_t file_cmp_compare_dirs(const char *dir1, const char *dir2, int quick,
+                              file_cmp_callback callback, void *user_data);
+
+/* Message of the last error on this thread, or NULL. */
+const char *file_cmp_last_error(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,134 @@
+use crate::hash::{hash_file, to_hex, HashAlgorithm};
+use crate::{walk, CompareOptions, FileDiff};
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+
+/// Starts the record stream, followed by the hash algorithm's name.
+const HEADER: &str = "file_cmp-agent";
+
+/// A file in the agent's tree: its `/`-separated path relative to the
+/// root, its size and its hash as lowercase hex.
+#[derive(Debug, Eq, PartialEq)]
+pub struct Record {
+    pub path: String,
+    pub len: u64,
+    pub hash: String,
+}
+
+/// Hashes every file in the tree and writes a record for each as soon as it
+/// is hashed, so the other side can start comparing while this one is still
+/// reading. Records are NUL-terminated, after a header naming the algorithm.
+pub fn write_records<P: AsRef<Path>, W: Write>(
+    dir: P,
+    options: &CompareOptions,
+    out: &mut W,
+) -> io::Result<()> {
+    write!(out, "{} {}\0", HEADER, options.hash)?;
+    for (relative, path) in walk::list_files(dir.as_ref())? {
+        let len = path.metadata()?.len();
+        let hash = hash_file(&path, options.hash, options.throttle.as_deref())?;
+        write!(out, "{} {} {}\0", to_hex(&hash), len, relative)?;
+        out.flush()?;
+    }
+    out.flush()
+}
+
+/// Iterator over the records written by [`write_records`].
+pub struct Records<R> {
+    reader: R,
+    algorithm: HashAlgorithm,
+}
+
+impl<R: BufRead> Records<R> {
+    /// Reads the header, returning an error if the agent hashes files with a
+    /// different algorithm than the one expected.
+    pub fn new(mut reader: R, algorithm: HashAlgorithm) -> io::Result<Self> {
+        let header = read_record(&mut reader)?.unwrap_or_default();
+        match header.split_once(' ') {
+            Some((HEADER, name)) if name == algorithm.name() => Ok(Self { reader, algorithm }),
+            Some((HEADER, name)) => Err(invalid(&format!(
+                "agent hashes with {} instead of {}",
+                name, algorithm
+            ))),
+            _ => Err(invalid("not a file_cmp agent stream")),
+        }
+    }
+
+    pub fn algorithm(&self) -> HashAlgorithm {
+        self.algorithm
+    }
+}
+
+impl<R: BufRead> Iterator for Records<R> {
+    type Item = io::Result<Record>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let record = match read_record(&mut self.reader) {
+            Ok(record) => record?,
+            Err(e) => return Some(Err(e)),
+        };
+        let mut fields = record.splitn(3, ' ');
+        let parsed = match (fields.next(), fields.next(), fields.next()) {
+            (Some(hash), Some(len), Some(path)) => len.parse().ok().map(|len| Record {
+                path: path.to_string(),
+                len,
+                hash: hash.to_string(),
+            }),
+            _ => None,
+        };
+        Some(parsed.ok_or_else(|| invalid(&format!("invalid agent record '{}'", record))))
+    }
+}
+
+pub(crate) fn read_record<R: BufRead>(reader: &mut R) -> io::Result<Option<String>> {
+    let mut record = vec![];
+    if reader.read_until(b'\0', &mut record)? == 0 {
+        return Ok(None);
+    }
+    if record.pop() != Some(b'\0') {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "agent stream ended in the middle of a record",
+        ));
+    }
+    String::from_utf8(record)
+        .map(Some)
+        .map_err(|_| invalid("agent record is not valid UTF-8"))
+}
+
+pub(crate) fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// Compares a local tree with an agent's records by size and hash. Only
+/// hashes cross the connection, so differing files report an offset of 0
+/// like in quick mode. Local files are reported by path and files missing
+/// locally by the path `remote_path` gives for them.
+pub fn compare_records<R: BufRead, P: AsRef<Path>>(
+    records: Records<R>,
+    dir: P,
+    options: &CompareOptions,
+    remote_path: impl Fn(&str) -> PathBuf,
+) -> io::Result<Vec<(PathBuf, FileDiff)>> {
+    let algorithm = records.algorithm();
+    let mut local = walk::list_files(dir.as_ref())?;
+    let mut results = vec![];
+    for record in records {
+        let record = record?;
+        let Some(path) = local.remove(&record.path) else {
+            results.push((remote_path(&record.path), FileDiff::RightOnly));
+            continue;
+        };
+        let equal = path.metadata()?.len() == record.len
+            && to_hex(&hash_file(&path, algorithm, options.throttle.as_deref())?) == record.hash;
+        let result = match equal {
+            true => FileDiff::Equal,
+            false => FileDiff::Different(0),
+        };
+        results.push((path, result));
+    }
+    for path in local.into_values() {
+        results.push((path, FileDiff::LeftOnly));
+    }
+    Ok(results)
+}
@@ -0,0 +1,56 @@
+use crate::{capability, long_path};
+use std::fs::{self, Metadata};
+use std::io;
+use std::path::Path;
+
+pub const READONLY: u32 = 0x1;
+pub const HIDDEN: u32 = 0x2;
+pub const SYSTEM: u32 = 0x4;
+pub const ARCHIVE: u32 = 0x20;
+
+/// The attributes that are compared, by their `FILE_ATTRIBUTE_*` bit.
+pub const ATTRIBUTES: [(u32, &str); 4] = [
+    (READONLY, "readonly"),
+    (HIDDEN, "hidden"),
+    (SYSTEM, "system"),
+    (ARCHIVE, "archive"),
+];
+
+/// Returns the attribute bits of a file. Windows reports all of them; other
+/// platforms only have the readonly attribute, derived from the permissions.
+pub fn attributes(meta: &Metadata) -> u32 {
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::MetadataExt;
+        meta.file_attributes() & ATTRIBUTES.iter().fold(0, |all, (bit, _)| all | bit)
+    }
+    #[cfg(not(windows))]
+    {
+        match meta.permissions().readonly() {
+            true => READONLY,
+            false => 0,
+        }
+    }
+}
+
+/// Returns the names of the attributes that are set in one of the bit sets
+/// but not the other.
+pub fn attribute_names(bits1: u32, bits2: u32) -> Vec<&'static str> {
+    ATTRIBUTES
+        .iter()
+        .filter(|(bit, _)| (bits1 ^ bits2) & bit != 0)
+        .map(|&(_, name)| name)
+        .collect()
+}
+
+/// Compares the attributes of two files, e.g. to validate a tree copied
+/// with robocopy, returning the names of those that differ, followed by the
+/// file capabilities that differ on Linux.
+pub fn compare_attributes<P: AsRef<Path>>(path1: P, path2: P) -> io::Result<Vec<&'static str>> {
+    let (path1, path2) = (path1.as_ref(), path2.as_ref());
+    let attributes1 = attributes(&fs::metadata(long_path::extended(path1))?);
+    let attributes2 = attributes(&fs::metadata(long_path::extended(path2))?);
+    let mut names = attribute_names(attributes1, attributes2);
+    names.extend(capability::compare_capabilities(path1, path2)?);
+    Ok(names)
+}
@@ -0,0 +1,220 @@
+use crate::cloud::{self, element, elements, md5_reader, unescape, uri_encode};
+use crate::http::{agent, call_error};
+use crate::{CompareOptions, FileDiff};
+use base64::Engine;
+use std::env;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Version of the Blob service REST API the requests are made against.
+const API_VERSION: &str = "2021-08-06";
+
+/// A storage account, container and blob name (or name prefix) parsed from
+/// `az://account/container/blob`.
+#[derive(Debug, Eq, PartialEq)]
+pub struct AzureUrl {
+    pub account: String,
+    pub container: String,
+    pub blob: String,
+}
+
+impl AzureUrl {
+    pub fn parse(url: &str) -> Option<AzureUrl> {
+        let rest = url.strip_prefix("az://")?;
+        let (account, rest) = rest.split_once('/')?;
+        let (container, blob) = rest.split_once('/').unwrap_or((rest, ""));
+        match account.is_empty() || container.is_empty() {
+            true => None,
+            false => Some(AzureUrl {
+                account: account.to_string(),
+                container: container.to_string(),
+                blob: blob.to_string(),
+            }),
+        }
+    }
+}
+
+pub fn is_azure_url(path: &str) -> bool {
+    path.starts_with("az://")
+}
+
+/// Size and checksum of a blob, from a HEAD request or a listing.
+#[derive(Debug)]
+pub struct BlobInfo {
+    pub name: String,
+    pub len: u64,
+    /// Base64 MD5 of the whole blob, if it was uploaded with one
+    pub content_md5: Option<String>,
+}
+
+/// Minimal Blob service client, authorized with a shared access signature.
+pub struct Client {
+    agent: ureq::Agent,
+    /// Custom endpoint such as Azurite's, addressed with the account name
+    /// in the path
+    endpoint: Option<String>,
+    sas_token: Option<String>,
+}
+
+impl Client {
+    /// Configures the client from the environment: a shared access
+    /// signature in `AZURE_STORAGE_SAS_TOKEN` and a custom endpoint in
+    /// `AZURE_STORAGE_ENDPOINT`. Requests are anonymous when no SAS token is
+    /// set, for containers with public access.
+    pub fn from_env(timeout: Option<Duration>) -> Client {
+        let var = |name| env::var(name).ok().filter(|v: &String| !v.is_empty());
+        Client {
+            agent: agent(timeout),
+            endpoint: var("AZURE_STORAGE_ENDPOINT").map(|e| e.trim_end_matches('/').to_string()),
+            sas_token: var("AZURE_STORAGE_SAS_TOKEN")
+                .map(|t| t.trim_start_matches('?').to_string()),
+        }
+    }
+
+    /// Sends requests to a custom endpoint such as
+    /// `http://127.0.0.1:10000` instead of Azure.
+    pub fn with_endpoint(mut self, endpoint: &str) -> Client {
+        self.endpoint = Some(endpoint.trim_end_matches('/').to_string());
+        self
+    }
+
+    pub fn head(&self, url: &AzureUrl) -> io::Result<BlobInfo> {
+        let response = self.request("HEAD", &url.account, &url.container, &url.blob, &[])?;
+        let len = response
+            .header("Content-Length")
+            .and_then(|len| len.parse().ok())
+            .ok_or_else(|| io::Error::other("response has no Content-Length"))?;
+        Ok(BlobInfo {
+            name: url.blob.clone(),
+            len,
+            content_md5: response.header("Content-MD5").map(str::to_string),
+        })
+    }
+
+    pub fn get(&self, url: &AzureUrl, name: &str) -> io::Result<Box<dyn Read + Send>> {
+        let response = self.request("GET", &url.account, &url.container, name, &[])?;
+        Ok(Box::new(response.into_reader()))
+    }
+
+    /// Lists every blob under the prefix, following continuation markers.
+    pub fn list(&self, url: &AzureUrl, prefix: &str) -> io::Result<Vec<BlobInfo>> {
+        let mut blobs = vec![];
+        let mut marker = None;
+        loop {
+            let mut query = vec![
+                ("restype", "container".to_string()),
+                ("comp", "list".to_string()),
+                ("prefix", prefix.to_string()),
+            ];
+            if let Some(marker) = marker.take() {
+                query.push(("marker", marker));
+            }
+            let body = self
+                .request("GET", &url.account, &url.container, "", &query)?
+                .into_string()?;
+            for blob in elements(&body, "Blob") {
+                let name = element(blob, "Name").map(unescape).unwrap_or_default();
+                let len = element(blob, "Content-Length")
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(0);
+                blobs.push(BlobInfo {
+                    name,
+                    len,
+                    content_md5: element(blob, "Content-MD5")
+                        .filter(|md5| !md5.is_empty())
+                        .map(unescape),
+                });
+            }
+            match element(&body, "NextMarker") {
+                Some(next) if !next.is_empty() => marker = Some(unescape(next)),
+                _ => return Ok(blobs),
+            }
+        }
+    }
+
+    fn request(
+        &self,
+        method: &str,
+        account: &str,
+        container: &str,
+        name: &str,
+        query: &[(&str, String)],
+    ) -> io::Result<ureq::Response> {
+        let path = match name.is_empty() {
+            true => uri_encode(&format!("/{}", container), false),
+            false => uri_encode(&format!("/{}/{}", container, name), false),
+        };
+        let base = match &self.endpoint {
+            Some(endpoint) => format!("{}/{}", endpoint, uri_encode(account, true)),
+            None => format!("https://{}.blob.core.windows.net", account),
+        };
+        let query: Vec<_> = query
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, uri_encode(value, true)))
+            .chain(self.sas_token.clone())
+            .collect();
+        let url = match query.is_empty() {
+            true => format!("{}{}", base, path),
+            false => format!("{}{}?{}", base, path, query.join("&")),
+        };
+        self.agent
+            .request(method, &url)
+            .set("x-ms-version", API_VERSION)
+            .call()
+            .map_err(|e| call_error(&url, e))
+    }
+}
+
+/// Checks the blob's Content-MD5 against the local file, returning `None`
+/// when the blob has none, as for large blobs uploaded in blocks.
+pub fn checksum_matches<P: AsRef<Path>>(path: P, blob: &BlobInfo) -> io::Result<Option<bool>> {
+    let Some(content_md5) = blob.content_md5.as_deref() else {
+        return Ok(None);
+    };
+    let (digest, _) = md5_reader(&mut File::open(path)?)?;
+    Ok(Some(
+        base64::engine::general_purpose::STANDARD.encode(digest) == content_md5,
+    ))
+}
+
+/// Compares a blob with a local file. The sizes and Content-MD5 are checked
+/// first, and the blob is only downloaded when they can't decide or the
+/// offset of the first difference is wanted.
+pub fn compare_blob<P: AsRef<Path>>(
+    client: &Client,
+    url: &AzureUrl,
+    blob: &BlobInfo,
+    path: P,
+    options: &CompareOptions,
+) -> io::Result<FileDiff> {
+    cloud::compare_object(
+        blob.len,
+        path,
+        options,
+        |path| checksum_matches(path, blob),
+        || client.get(url, &blob.name),
+    )
+}
+
+/// Compares a local directory tree with the blobs under a prefix. Local
+/// files are reported by path and blobs missing locally by URL.
+pub fn compare_tree<P: AsRef<Path>>(
+    client: &Client,
+    url: &AzureUrl,
+    dir: P,
+    options: &CompareOptions,
+) -> io::Result<Vec<(PathBuf, FileDiff)>> {
+    let prefix = cloud::dir_prefix(&url.blob);
+    let blobs = client
+        .list(url, &prefix)?
+        .into_iter()
+        .filter(|blob| !blob.name.ends_with('/'))
+        .map(|blob| (blob.name[prefix.len()..].to_string(), blob))
+        .collect();
+    let prefix_url = format!("az://{}/{}/{}", url.account, url.container, prefix);
+    cloud::compare_tree(blobs, &prefix_url, dir.as_ref(), |blob, path| {
+        compare_blob(client, url, blob, path, options)
+    })
+}
@@ -0,0 +1,123 @@
+use std::io::{self, BufRead};
+#[cfg(unix)]
+use std::os::unix::ffi::OsStringExt;
+use std::path::PathBuf;
+
+/// Iterator over comparison pairs read from a list, one pair per line with
+/// the two paths separated by a tab or NUL. Blank lines are skipped.
+pub struct Pairs<R> {
+    reader: R,
+    line: usize,
+    nul_terminated: bool,
+}
+
+impl<R: BufRead> Pairs<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            line: 0,
+            nul_terminated: false,
+        }
+    }
+
+    /// Reads paths that are each terminated by a NUL instead, as printed by
+    /// `find -print0`, taking every two paths as a pair. Paths may then
+    /// contain tabs and newlines.
+    pub fn nul_terminated(reader: R) -> Self {
+        Self {
+            nul_terminated: true,
+            ..Self::new(reader)
+        }
+    }
+
+    fn next_nul_terminated(&mut self) -> Option<io::Result<(PathBuf, PathBuf)>> {
+        let path1 = match self.read_path() {
+            Ok(Some(path)) => path,
+            Ok(None) => return None,
+            Err(e) => return Some(Err(e)),
+        };
+        match self.read_path() {
+            Ok(Some(path2)) => Some(Ok((path1, path2))),
+            Ok(None) => Some(Err(self.error("missing the second path of the pair"))),
+            Err(e) => Some(Err(e)),
+        }
+    }
+
+    fn read_path(&mut self) -> io::Result<Option<PathBuf>> {
+        let mut record = vec![];
+        if self.reader.read_until(b'\0', &mut record)? == 0 {
+            return Ok(None);
+        }
+        self.line += 1;
+        if record.last() == Some(&b'\0') {
+            record.pop();
+        }
+        match record.is_empty() {
+            true => Err(self.error("empty path")),
+            false => self.path(record).map(Some),
+        }
+    }
+
+    fn parse(&self, record: &[u8]) -> io::Result<(PathBuf, PathBuf)> {
+        match record
+            .iter()
+            .position(|&byte| byte == b'\t' || byte == b'\0')
+        {
+            Some(i) if i > 0 && i + 1 < record.len() => Ok((
+                self.path(record[..i].to_vec())?,
+                self.path(record[i + 1..].to_vec())?,
+            )),
+            _ => Err(self.error("expected two paths separated by a tab or NUL")),
+        }
+    }
+
+    /// Turns the bytes of a path into one. Paths on Unix are any bytes, such
+    /// as the names `find -print0` lists, so only elsewhere must they be
+    /// UTF-8.
+    #[cfg(unix)]
+    fn path(&self, bytes: Vec<u8>) -> io::Result<PathBuf> {
+        Ok(PathBuf::from(std::ffi::OsString::from_vec(bytes)))
+    }
+
+    #[cfg(not(unix))]
+    fn path(&self, bytes: Vec<u8>) -> io::Result<PathBuf> {
+        String::from_utf8(bytes)
+            .map(PathBuf::from)
+            .map_err(|_| self.error("path is not valid UTF-8"))
+    }
+
+    fn error(&self, message: &str) -> io::Error {
+        let record = match self.nul_terminated {
+            true => "path",
+            false => "line",
+        };
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} {}: {}", record, self.line, message),
+        )
+    }
+}
+
+impl<R: BufRead> Iterator for Pairs<R> {
+    type Item = io::Result<(PathBuf, PathBuf)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.nul_terminated {
+            return self.next_nul_terminated();
+        }
+        loop {
+            let mut record = vec![];
+            match self.reader.read_until(b'\n', &mut record) {
+                Ok(0) => return None,
+                Ok(_) => self.line += 1,
+                Err(e) => return Some(Err(e)),
+            }
+            while matches!(record.last(), Some(b'\n' | b'\r')) {
+                record.pop();
+            }
+            if !record.is_empty() {
+                return Some(self.parse(&record));
+            }
+        }
+    }
+}
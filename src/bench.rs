@@ -0,0 +1,130 @@
+use crate::{compare_files_with, CompareOptions};
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Chunk sizes tried for buffered reads.
+pub const CHUNK_SIZES: [usize; 5] = [64 << 10, 256 << 10, 1 << 20, 4 << 20, 8 << 20];
+
+/// Timings of one way of comparing the files.
+#[derive(Debug)]
+pub struct BenchResult {
+    pub mode: &'static str,
+    /// The fixed chunk size, or `None` for adaptive chunks
+    pub chunk_size: Option<usize>,
+    pub threads: usize,
+    /// Bytes read from both files per run
+    pub bytes: u64,
+    pub times: Vec<Duration>,
+}
+
+impl BenchResult {
+    pub fn median(&self) -> Duration {
+        let mut times = self.times.clone();
+        times.sort();
+        times.get(times.len() / 2).copied().unwrap_or_default()
+    }
+
+    /// Median throughput in bytes per second.
+    pub fn throughput(&self) -> f64 {
+        self.bytes as f64 / self.median().as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+/// Returns the comparison modes to benchmark, as a name and the options
+/// selecting it.
+pub fn cases(threads: usize) -> Vec<(&'static str, CompareOptions)> {
+    let mut cases = vec![];
+    for chunk_size in CHUNK_SIZES {
+        cases.push((
+            "buffered",
+            CompareOptions {
+                chunk_size: Some(chunk_size),
+                ..Default::default()
+            },
+        ));
+    }
+    cases.push(("buffered", CompareOptions::default()));
+    cases.push((
+        "pipelined",
+        CompareOptions {
+            pipeline_depth: 2,
+            ..Default::default()
+        },
+    ));
+    cases.push((
+        "mmap",
+        CompareOptions {
+            mmap: true,
+            ..Default::default()
+        },
+    ));
+    cases.push((
+        "parallel",
+        CompareOptions {
+            threads,
+            ..Default::default()
+        },
+    ));
+    cases
+}
+
+/// Compares the files `runs` times in every mode, dropping them from the
+/// page cache before each run if `drop_caches` is set and the platform
+/// allows it, and calls `progress` with each finished result.
+pub fn run<P: AsRef<Path>>(
+    path1: P,
+    path2: P,
+    runs: usize,
+    threads: usize,
+    drop_caches: bool,
+    mut progress: impl FnMut(&BenchResult),
+) -> io::Result<Vec<BenchResult>> {
+    let bytes = fs::metadata(&path1)?.len() + fs::metadata(&path2)?.len();
+    let mut results = vec![];
+
+    for (mode, options) in cases(threads) {
+        let mut times = vec![];
+        for _ in 0..runs.max(1) {
+            if drop_caches {
+                drop_cache(&path1)?;
+                drop_cache(&path2)?;
+            }
+            let start = Instant::now();
+            compare_files_with(&path1, &path2, &options)?;
+            times.push(start.elapsed());
+        }
+        let result = BenchResult {
+            mode,
+            chunk_size: options.chunk_size,
+            threads: options.threads.max(1),
+            bytes,
+            times,
+        };
+        progress(&result);
+        results.push(result);
+    }
+
+    Ok(results)
+}
+
+/// Evicts the file's pages from the page cache so the next read comes from
+/// storage. Returns false if this isn't possible on the platform.
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "freebsd"))]
+pub fn drop_cache<P: AsRef<Path>>(path: P) -> io::Result<bool> {
+    use std::os::unix::io::AsRawFd;
+
+    let file = File::open(path)?;
+    let ret = unsafe { libc::posix_fadvise(file.as_raw_fd(), 0, 0, libc::POSIX_FADV_DONTNEED) };
+    match ret {
+        0 => Ok(true),
+        _ => Err(io::Error::from_raw_os_error(ret)),
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "freebsd")))]
+pub fn drop_cache<P: AsRef<Path>>(path: P) -> io::Result<bool> {
+    File::open(path)?;
+    Ok(false)
+}
@@ -0,0 +1,132 @@
+use crate::hash::HashAlgorithm;
+use crate::read_full;
+use sha2::digest::DynDigest;
+use std::io::{self, Read};
+
+/// Size of the aligned blocks checked for filler bytes.
+pub const BLOCK_SIZE: usize = 4096;
+
+/// Returns the filler byte if the block consists only of 0x00 or only of
+/// 0xFF bytes, scanning it a machine word at a time.
+pub fn filler(block: &[u8]) -> Option<u8> {
+    let byte = *block.first()?;
+    if byte != 0x00 && byte != 0xff {
+        return None;
+    }
+    let word = u64::from_ne_bytes([byte; 8]);
+    let mut words = block.chunks_exact(8);
+    let blank = words
+        .by_ref()
+        .all(|w| u64::from_ne_bytes(w.try_into().unwrap()) == word)
+        && words.remainder().iter().all(|&b| b == byte);
+    blank.then_some(byte)
+}
+
+/// Trailing bytes to ignore when one file is a prefix of the other, e.g. a
+/// flash dump against the smaller firmware image written to it.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Padding {
+    /// A run of either 0x00 or 0xFF, whichever comes first
+    Filler,
+    /// A run of this byte
+    Byte(u8),
+    /// A final newline, `\n` or `\r\n`, e.g. for text files saved by
+    /// editors that add it and ones that don't
+    FinalNewline,
+}
+
+/// Returns the offset of the first byte read that isn't padding, or `None`
+/// if the reader holds nothing else.
+pub fn padding_end<R: Read>(reader: &mut R, padding: Padding) -> io::Result<Option<u64>> {
+    let mut byte = match padding {
+        Padding::Filler => None,
+        Padding::Byte(byte) => Some(byte),
+        Padding::FinalNewline => {
+            let mut rest = [0; 3];
+            let len = read_full(reader, &mut rest)?;
+            let newline = match &rest[..len] {
+                [b'\r', b'\n', ..] => 2,
+                [b'\n', ..] => 1,
+                _ => 0,
+            };
+            return Ok((newline < len).then_some(newline as u64));
+        }
+    };
+    let mut buffer = vec![0; 256 * BLOCK_SIZE];
+    let mut pos = 0;
+    loop {
+        let len = read_full(reader, &mut buffer)?;
+        let data = &buffer[..len];
+        if let (None, Some(&first)) = (byte, data.first()) {
+            if first != 0x00 && first != 0xff {
+                return Ok(Some(pos));
+            }
+            byte = Some(first);
+        }
+        if let Some(i) = data.iter().position(|&b| Some(b) != byte) {
+            return Ok(Some(pos + i as u64));
+        }
+        if len < buffer.len() {
+            return Ok(None);
+        }
+        pos += len as u64;
+    }
+}
+
+/// Hashes the reader's contents like `hash_reader`, except that runs of
+/// blank blocks (see `filler`) at block-aligned offsets are folded into a
+/// single (filler, count) record instead of being hashed byte by byte. Equal
+/// contents still give equal digests, but the digest isn't a standard one,
+/// so it must only be compared with other folded digests.
+pub fn folded_hash<R: Read>(reader: &mut R, algorithm: HashAlgorithm) -> io::Result<Vec<u8>> {
+    let mut folder = Folder {
+        hasher: algorithm.hasher(),
+        run: None,
+    };
+    let mut buffer = vec![0; 256 * BLOCK_SIZE];
+    loop {
+        let len = read_full(reader, &mut buffer)?;
+        for block in buffer[..len].chunks(BLOCK_SIZE) {
+            folder.block(block);
+        }
+        if len < buffer.len() {
+            folder.flush();
+            return Ok(folder.hasher.finalize().to_vec());
+        }
+    }
+}
+
+struct Folder {
+    hasher: Box<dyn DynDigest>,
+    /// Filler byte and number of blocks of the current blank run
+    run: Option<(u8, u64)>,
+}
+
+impl Folder {
+    fn block(&mut self, block: &[u8]) {
+        let filler = match block.len() {
+            BLOCK_SIZE => filler(block),
+            _ => None,
+        };
+        match (filler, &mut self.run) {
+            (Some(byte), Some((run_byte, count))) if byte == *run_byte => *count += 1,
+            (Some(byte), _) => {
+                self.flush();
+                self.run = Some((byte, 1));
+            }
+            (None, _) => {
+                self.flush();
+                // Tagged so data can't be mistaken for a run record
+                self.hasher.update(&[0]);
+                self.hasher.update(block);
+            }
+        }
+    }
+
+    fn flush(&mut self) {
+        if let Some((byte, count)) = self.run.take() {
+            self.hasher.update(&[1, byte]);
+            self.hasher.update(&count.to_le_bytes());
+        }
+    }
+}
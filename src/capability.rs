@@ -0,0 +1,143 @@
+use crate::{long_path, xattr};
+use std::io;
+use std::path::Path;
+
+/// The extended attribute holding the file capabilities of an executable.
+pub const XATTR: &str = "security.capability";
+
+/// `VFS_CAP_REVISION_*` in the high byte of the first word.
+const REVISION_MASK: u32 = 0xff00_0000;
+const REVISION_2: u32 = 0x0200_0000;
+const REVISION_3: u32 = 0x0300_0000;
+/// `VFS_CAP_FLAGS_EFFECTIVE`
+const EFFECTIVE: u32 = 0x1;
+
+/// Names of the capabilities by their number.
+pub const NAMES: [&str; 41] = [
+    "cap_chown",
+    "cap_dac_override",
+    "cap_dac_read_search",
+    "cap_fowner",
+    "cap_fsetid",
+    "cap_kill",
+    "cap_setgid",
+    "cap_setuid",
+    "cap_setpcap",
+    "cap_linux_immutable",
+    "cap_net_bind_service",
+    "cap_net_broadcast",
+    "cap_net_admin",
+    "cap_net_raw",
+    "cap_ipc_lock",
+    "cap_ipc_owner",
+    "cap_sys_module",
+    "cap_sys_rawio",
+    "cap_sys_chroot",
+    "cap_sys_ptrace",
+    "cap_sys_pacct",
+    "cap_sys_admin",
+    "cap_sys_boot",
+    "cap_sys_nice",
+    "cap_sys_resource",
+    "cap_sys_time",
+    "cap_sys_tty_config",
+    "cap_mknod",
+    "cap_lease",
+    "cap_audit_write",
+    "cap_audit_control",
+    "cap_setfcap",
+    "cap_mac_override",
+    "cap_mac_admin",
+    "cap_syslog",
+    "cap_wake_alarm",
+    "cap_block_suspend",
+    "cap_audit_read",
+    "cap_perfmon",
+    "cap_bpf",
+    "cap_checkpoint_restore",
+];
+
+/// The file capabilities of an executable, as `setcap` sets them.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Capabilities {
+    /// Bit sets of the permitted and inheritable capabilities
+    pub permitted: u64,
+    pub inheritable: u64,
+    /// Whether the permitted ones are raised in the effective set on exec
+    pub effective: bool,
+    /// The root user of the namespace the capabilities apply in, from
+    /// revision 3
+    pub root_id: Option<u32>,
+}
+
+impl Capabilities {
+    /// Parses the value of `security.capability` (`struct vfs_cap_data`),
+    /// returning `None` for an unknown revision or a truncated value.
+    pub fn parse(value: &[u8]) -> Option<Capabilities> {
+        let word = |i: usize| {
+            let bytes = value.get(i * 4..i * 4 + 4)?;
+            Some(u32::from_le_bytes(bytes.try_into().ok()?))
+        };
+        let magic = word(0)?;
+        let root_id = match magic & REVISION_MASK {
+            REVISION_2 => None,
+            REVISION_3 => Some(word(5)?),
+            _ => return None,
+        };
+        Some(Capabilities {
+            permitted: word(1)? as u64 | (word(3)? as u64) << 32,
+            inheritable: word(2)? as u64 | (word(4)? as u64) << 32,
+            effective: magic & EFFECTIVE != 0,
+            root_id,
+        })
+    }
+
+    /// Reads the capabilities of a file, `None` if it has none.
+    pub fn read(path: &Path) -> io::Result<Option<Capabilities>> {
+        let value = match xattr::get(&long_path::extended(path), XATTR)? {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+        match Capabilities::parse(&value) {
+            Some(capabilities) => Ok(Some(capabilities)),
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "unknown format of security.capability",
+            )),
+        }
+    }
+}
+
+/// Returns the names of the capabilities that only one of the files has,
+/// permitted or inheritable, or `capabilities` if they only differ in the
+/// effective flag, the namespace or capabilities without a name.
+pub fn capability_names(
+    capabilities1: Option<Capabilities>,
+    capabilities2: Option<Capabilities>,
+) -> Vec<&'static str> {
+    let (capabilities1, capabilities2) = (
+        capabilities1.unwrap_or_default(),
+        capabilities2.unwrap_or_default(),
+    );
+    let differing = (capabilities1.permitted ^ capabilities2.permitted)
+        | (capabilities1.inheritable ^ capabilities2.inheritable);
+    let names: Vec<_> = NAMES
+        .iter()
+        .enumerate()
+        .filter(|(bit, _)| differing & 1 << bit != 0)
+        .map(|(_, &name)| name)
+        .collect();
+    match names.is_empty() && capabilities1 != capabilities2 {
+        true => vec!["capabilities"],
+        false => names,
+    }
+}
+
+/// Compares the file capabilities of two files, e.g. of binaries restored
+/// from a backup that may have lost them, returning the names of those
+/// that differ.
+pub fn compare_capabilities<P: AsRef<Path>>(path1: P, path2: P) -> io::Result<Vec<&'static str>> {
+    let capabilities1 = Capabilities::read(path1.as_ref())?;
+    let capabilities2 = Capabilities::read(path2.as_ref())?;
+    Ok(capability_names(capabilities1, capabilities2))
+}
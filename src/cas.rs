@@ -0,0 +1,65 @@
+use crate::hash::{hash_file, to_hex, HashAlgorithm};
+use crate::throttle::Throttle;
+use crate::{walk, FileDiff};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Returns the algorithm and hex digest a file in a content-addressed store
+/// is named after, if any. Recognized layouts are `sha256/<hex>` (as in OCI
+/// `blobs/`), `sha256:<hex>` or `sha256-<hex>` names, plain `<hex>` names,
+/// and names sharded by their first two digits like `ab/<rest of hex>`.
+/// Plain names are taken to be SHA-256.
+pub fn expected_hash(relative: &str) -> Option<(HashAlgorithm, String)> {
+    let mut parts = relative.rsplit('/');
+    let name = parts.next()?;
+    let parent = parts.next();
+
+    let named = name
+        .split_once(':')
+        .or_else(|| name.split_once('-'))
+        .and_then(|(algorithm, hex)| Some((algorithm.parse().ok()?, hex.to_string())));
+    let (algorithm, hex) = match (named, parent) {
+        (Some(named), _) => named,
+        (None, Some(parent)) if parent.parse::<HashAlgorithm>().is_ok() => {
+            (parent.parse().ok()?, name.to_string())
+        }
+        (None, Some(shard)) if shard.len() == 2 && is_hex(shard) && !is_full(name) => {
+            (HashAlgorithm::default(), format!("{}{}", shard, name))
+        }
+        (None, _) => (HashAlgorithm::default(), name.to_string()),
+    };
+    match hex.len() == algorithm.digest_len() * 2 && is_hex(&hex) {
+        true => Some((algorithm, hex.to_ascii_lowercase())),
+        false => None,
+    }
+}
+
+fn is_hex(s: &str) -> bool {
+    s.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+fn is_full(name: &str) -> bool {
+    name.len() == HashAlgorithm::default().digest_len() * 2
+}
+
+/// Hashes every file in the store that is named after a hash, reporting it
+/// as equal if its content still matches the name, or different if not.
+/// Files with other names are skipped.
+pub fn verify_store<P: AsRef<Path>>(
+    dir: P,
+    throttle: Option<&Throttle>,
+) -> io::Result<Vec<(PathBuf, FileDiff)>> {
+    let mut results = vec![];
+    for (relative, path) in walk::list_files(dir.as_ref())? {
+        let Some((algorithm, expected)) = expected_hash(&relative) else {
+            continue;
+        };
+        let actual = to_hex(&hash_file(&path, algorithm, throttle)?);
+        let result = match actual == expected {
+            true => FileDiff::Equal,
+            false => FileDiff::Different(0),
+        };
+        results.push((path, result));
+    }
+    Ok(results)
+}
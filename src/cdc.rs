@@ -0,0 +1,195 @@
+use crate::hash::HashAlgorithm;
+use crate::throttle::Throttled;
+use crate::{diff_at_lengths, long_path, read_full, CompareOptions, FileDiff};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{self, Read};
+use std::ops::Range;
+use std::path::Path;
+
+/// Random values of the gear hash by byte, from splitmix64 so the chunk
+/// boundaries are the same in every build.
+const GEAR: [u64; 256] = {
+    let mut table = [0; 256];
+    let mut state: u64 = 0;
+    let mut i = 0;
+    while i < 256 {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        table[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    table
+};
+
+/// Smallest average chunk length accepted.
+pub const MIN_AVG_LEN: usize = 256;
+
+/// Bounds of the chunk lengths of FastCDC.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ChunkSizes {
+    pub min: usize,
+    pub avg: usize,
+    pub max: usize,
+}
+
+impl ChunkSizes {
+    /// Chunks of about `avg` bytes, at least a quarter and at most eight
+    /// times as long.
+    pub fn new(avg: usize) -> ChunkSizes {
+        let avg = avg.max(MIN_AVG_LEN);
+        ChunkSizes {
+            min: avg / 4,
+            avg,
+            max: avg * 8,
+        }
+    }
+
+    /// Masks of the gear hash before and after the average length. The one
+    /// before has more bits set so cuts are rarer, which normalizes the
+    /// lengths around the average.
+    fn masks(&self) -> (u64, u64) {
+        let bits = self.avg.ilog2();
+        let mask = |bits: u32| !0u64 << (64 - bits);
+        (mask(bits + 1), mask(bits - 1))
+    }
+}
+
+/// Returns the length of the chunk at the start of the data, which holds
+/// at least the longest chunk unless it is the end of the file.
+fn cut(data: &[u8], sizes: &ChunkSizes) -> usize {
+    let (mask_small, mask_large) = sizes.masks();
+    let max = sizes.max.min(data.len());
+    if max <= sizes.min {
+        return max;
+    }
+    let normal = sizes.avg.min(max);
+    let mut hash = 0u64;
+    for (i, &byte) in data.iter().enumerate().take(max).skip(sizes.min) {
+        hash = (hash << 1).wrapping_add(GEAR[byte as usize]);
+        let mask = match i < normal {
+            true => mask_small,
+            false => mask_large,
+        };
+        if hash & mask == 0 {
+            return i + 1;
+        }
+    }
+    max
+}
+
+/// A chunk of a file cut by its content, and its digest.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Chunk {
+    pub offset: u64,
+    pub len: usize,
+    pub hash: Vec<u8>,
+}
+
+/// Cuts the reader's data into chunks with FastCDC. The boundaries depend
+/// on the content around them only, so an insertion or deletion changes
+/// the chunks around it but not the ones after.
+pub fn chunks<R: Read>(
+    reader: &mut R,
+    sizes: ChunkSizes,
+    algorithm: HashAlgorithm,
+) -> io::Result<Vec<Chunk>> {
+    let mut hasher = algorithm.hasher();
+    let mut chunks = vec![];
+    let mut buffer = vec![0; sizes.max * 2];
+    let (mut start, mut end, mut offset) = (0, 0, 0u64);
+    let mut eof = false;
+    loop {
+        if !eof && end - start < sizes.max {
+            buffer.copy_within(start..end, 0);
+            end -= start;
+            start = 0;
+            let len = read_full(reader, &mut buffer[end..])?;
+            eof = end + len < buffer.len();
+            end += len;
+        }
+        if start == end {
+            return Ok(chunks);
+        }
+        let len = cut(&buffer[start..end], &sizes);
+        hasher.update(&buffer[start..start + len]);
+        chunks.push(Chunk {
+            offset,
+            len,
+            hash: hasher.finalize_reset().to_vec(),
+        });
+        start += len;
+        offset += len as u64;
+    }
+}
+
+/// Result of comparing two files by their chunks.
+#[derive(Debug, Eq, PartialEq)]
+pub struct ChunkDiff {
+    pub file_diff: FileDiff,
+    /// Byte ranges of the chunks of each file that the other one doesn't
+    /// have anywhere
+    pub changed1: Vec<Range<u64>>,
+    pub changed2: Vec<Range<u64>>,
+}
+
+/// Compares the chunks of two files. They differ at the start of the first
+/// chunk that isn't the same in both, and the chunks of each that the
+/// other doesn't have are the ranges that changed.
+pub fn compare_chunks(chunks1: &[Chunk], chunks2: &[Chunk]) -> ChunkDiff {
+    let changed = |chunks: &[Chunk], other: &[Chunk]| {
+        let other: HashSet<&[u8]> = other.iter().map(|chunk| &*chunk.hash).collect();
+        let mut ranges: Vec<Range<u64>> = vec![];
+        for chunk in chunks.iter().filter(|chunk| !other.contains(&*chunk.hash)) {
+            let range = chunk.offset..chunk.offset + chunk.len as u64;
+            match ranges.last_mut() {
+                Some(last) if last.end == range.start => last.end = range.end,
+                _ => ranges.push(range),
+            }
+        }
+        ranges
+    };
+    let len = |chunks: &[Chunk]| chunks.last().map_or(0, |c| c.offset + c.len as u64);
+    let (len1, len2) = (len(chunks1), len(chunks2));
+    let same = chunks1
+        .iter()
+        .zip(chunks2)
+        .take_while(|(c1, c2)| c1 == c2)
+        .count();
+    let offset = match chunks1.get(same) {
+        Some(chunk) => Some(chunk.offset),
+        None => (len1 != len2).then_some(len1),
+    };
+    ChunkDiff {
+        file_diff: diff_at_lengths(offset, len1, len2, false),
+        changed1: changed(chunks1, chunks2),
+        changed2: changed(chunks2, chunks1),
+    }
+}
+
+/// Compares two files by their content-defined chunks of about `avg` bytes,
+/// reading both at the same time.
+pub fn compare_cdc<P: AsRef<Path>>(
+    path1: P,
+    path2: P,
+    avg: usize,
+    options: &CompareOptions,
+) -> io::Result<ChunkDiff> {
+    let (path1, path2, sizes) = (path1.as_ref(), path2.as_ref(), ChunkSizes::new(avg));
+    let chunk = |path: &Path| {
+        let file = File::open(long_path::extended(path))?;
+        chunks(
+            &mut Throttled::new(file, options.throttle.as_deref()),
+            sizes,
+            options.hash,
+        )
+    };
+    let (chunks1, chunks2) = std::thread::scope(|scope| {
+        let chunks1 = scope.spawn(|| chunk(path1));
+        let chunks2 = chunk(path2);
+        (chunks1.join().expect("Chunking thread panicked"), chunks2)
+    });
+    Ok(compare_chunks(&chunks1?, &chunks2?))
+}
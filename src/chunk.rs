@@ -0,0 +1,67 @@
+use std::fs::Metadata;
+
+/// Smallest chunk read from each file per comparison step.
+pub const MIN_CHUNK_SIZE: usize = 4 * 1024;
+/// Largest chunk adaptive sizing grows to.
+pub const MAX_ADAPTIVE_CHUNK_SIZE: usize = 8 * 1024 * 1024;
+
+/// Decides how much to read from each file per comparison step. A requested
+/// size is used as is; otherwise the size starts at the filesystem block size
+/// and doubles as long as the reads keep filling the buffers, up to
+/// `MAX_ADAPTIVE_CHUNK_SIZE` or the length of the data, whichever is smaller.
+#[derive(Clone, Debug)]
+pub struct ChunkSizer {
+    size: usize,
+    max: usize,
+}
+
+impl ChunkSizer {
+    pub fn new(requested: Option<usize>, len: u64, block_size: usize) -> Self {
+        match requested {
+            Some(size) => Self::fixed(size),
+            None => Self::adaptive(len, block_size),
+        }
+    }
+
+    pub fn fixed(size: usize) -> Self {
+        let size = size.max(1);
+        Self { size, max: size }
+    }
+
+    pub fn adaptive(len: u64, block_size: usize) -> Self {
+        let max = (len.min(MAX_ADAPTIVE_CHUNK_SIZE as u64) as usize)
+            .next_power_of_two()
+            .clamp(MIN_CHUNK_SIZE, MAX_ADAPTIVE_CHUNK_SIZE);
+        Self {
+            size: block_size.clamp(MIN_CHUNK_SIZE, max),
+            max,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn max_size(&self) -> usize {
+        self.max
+    }
+
+    /// Called after both reads filled a whole chunk.
+    pub fn grow(&mut self) {
+        self.size = (self.size * 2).min(self.max);
+    }
+}
+
+/// Returns the preferred IO size of the filesystem the file is on.
+pub fn block_size(meta: &Metadata) -> usize {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        meta.blksize() as usize
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = meta;
+        MIN_CHUNK_SIZE
+    }
+}
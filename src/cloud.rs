@@ -0,0 +1,152 @@
+use crate::{compare_streams, device, fifo, read_full, walk, CompareOptions, FileDiff};
+use md5::{Digest, Md5};
+use std::collections::BTreeMap;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+/// Compares a remote object of `len` bytes with a local file. The sizes and
+/// `checksum` are checked first, and `get` is only called to download the
+/// object when they can't decide or the offset of the first difference is
+/// wanted. `checksum` returns `None` when the object's metadata can't tell.
+pub(crate) fn compare_object<P, C, G>(
+    len: u64,
+    path: P,
+    options: &CompareOptions,
+    checksum: C,
+    get: G,
+) -> io::Result<FileDiff>
+where
+    P: AsRef<Path>,
+    C: FnOnce(&Path) -> io::Result<Option<bool>>,
+    G: FnOnce() -> io::Result<Box<dyn Read + Send>>,
+{
+    let path = path.as_ref();
+    let input = device::Input::open(path)?;
+    let streaming = input.meta.as_ref().is_some_and(fifo::is_fifo);
+    if !streaming {
+        // The metadata of the object isn't comparable with that of the file
+        // beyond its size
+        if !options.policy.reads_data() || len == 0 || input.len == 0 {
+            return match len == input.len {
+                true => Ok(FileDiff::Equal),
+                false => Ok(FileDiff::Different(0)),
+            };
+        }
+        if options.quick && len != input.len {
+            return Ok(FileDiff::Different(0));
+        }
+        if len == input.len {
+            match checksum(path)? {
+                Some(true) => return Ok(FileDiff::Equal),
+                Some(false) if options.quick => return Ok(FileDiff::Different(0)),
+                _ => {}
+            }
+        }
+    }
+
+    let file = fifo::TimeoutReader::new(input.file, options.timeout);
+    compare_streams(get()?, file, options)
+}
+
+/// Returns the key prefix listing the objects "in" a directory key.
+pub(crate) fn dir_prefix(key: &str) -> String {
+    match key.is_empty() || key.ends_with('/') {
+        true => key.to_string(),
+        false => format!("{}/", key),
+    }
+}
+
+/// Compares a local directory tree with listed objects, keyed by their path
+/// relative to the prefix. Local files are reported by path and objects
+/// missing locally by `url` followed by the relative path.
+pub(crate) fn compare_tree<T, F>(
+    mut objects: BTreeMap<String, T>,
+    url: &str,
+    dir: &Path,
+    mut compare: F,
+) -> io::Result<Vec<(PathBuf, FileDiff)>>
+where
+    F: FnMut(&T, &Path) -> io::Result<FileDiff>,
+{
+    let mut results = vec![];
+    for (relative, path) in walk::list_files(dir)? {
+        match objects.remove(&relative) {
+            Some(object) => {
+                let result = compare(&object, &path)?;
+                results.push((path, result));
+            }
+            None => results.push((path, FileDiff::LeftOnly)),
+        }
+    }
+    for relative in objects.into_keys() {
+        results.push((
+            PathBuf::from(format!("{}{}", url, relative)),
+            FileDiff::RightOnly,
+        ));
+    }
+    Ok(results)
+}
+
+/// Percent-encodes everything but unreserved characters, and `/` unless
+/// encoding a query component.
+pub(crate) fn uri_encode(s: &str, encode_slash: bool) -> String {
+    let mut encoded = String::new();
+    for b in s.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(b as char)
+            }
+            b'/' if !encode_slash => encoded.push('/'),
+            _ => encoded.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    encoded
+}
+
+/// Returns the contents of each `<name>` element in the XML.
+#[cfg(any(feature = "s3", feature = "azure"))]
+pub(crate) fn elements<'a>(xml: &'a str, name: &str) -> Vec<&'a str> {
+    let (open, close) = (format!("<{}>", name), format!("</{}>", name));
+    let mut found = vec![];
+    let mut rest = xml;
+    while let Some(start) = rest.find(&open) {
+        rest = &rest[start + open.len()..];
+        match rest.find(&close) {
+            Some(end) => {
+                found.push(&rest[..end]);
+                rest = &rest[end + close.len()..];
+            }
+            None => break,
+        }
+    }
+    found
+}
+
+#[cfg(any(feature = "s3", feature = "azure"))]
+pub(crate) fn element<'a>(xml: &'a str, name: &str) -> Option<&'a str> {
+    elements(xml, name).into_iter().next()
+}
+
+#[cfg(any(feature = "s3", feature = "azure"))]
+pub(crate) fn unescape(s: &str) -> String {
+    s.replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+/// MD5 of everything the reader returns, and the number of bytes read.
+pub(crate) fn md5_reader<R: Read>(reader: &mut R) -> io::Result<(Vec<u8>, u64)> {
+    let mut hasher = Md5::new();
+    let mut buffer = vec![0; 1 << 20];
+    let mut total = 0;
+    loop {
+        let len = read_full(reader, &mut buffer)?;
+        hasher.update(&buffer[..len]);
+        total += len as u64;
+        if len < buffer.len() {
+            return Ok((hasher.finalize().to_vec(), total));
+        }
+    }
+}
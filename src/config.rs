@@ -0,0 +1,171 @@
+use crate::daemon::Job;
+use crate::hooks::Hooks;
+use crate::policy::Policy;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Name of the configuration file that applies to a directory and the
+/// directories below it, e.g. to share settings in a project.
+pub const PROJECT_CONFIG: &str = ".file_cmp.toml";
+
+/// Returns the configuration files that exist, from the lowest precedence
+/// up: the user's `file_cmp/config.toml` and the nearest project
+/// configuration in `dir` or one of its parents.
+pub fn config_files(dir: &Path) -> Vec<PathBuf> {
+    let project = dir
+        .ancestors()
+        .map(|dir| dir.join(PROJECT_CONFIG))
+        .find(|path| path.is_file());
+    user_config()
+        .filter(|path| path.is_file())
+        .into_iter()
+        .chain(project)
+        .collect()
+}
+
+/// Returns the path of the user's configuration file: in `%APPDATA%` on
+/// Windows and in `$XDG_CONFIG_HOME` or `~/.config` elsewhere.
+fn user_config() -> Option<PathBuf> {
+    let dir = match cfg!(windows) {
+        true => PathBuf::from(std::env::var_os("APPDATA")?),
+        false => match std::env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+        },
+    };
+    Some(dir.join("file_cmp").join("config.toml"))
+}
+
+/// A setting: the long flag it sets a default for, and its value or no
+/// value for a switch that is turned on.
+pub type Setting = (String, Option<String>);
+
+/// The contents of a configuration file.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Config {
+    /// Defaults for every comparison
+    pub settings: Vec<Setting>,
+    /// Named sets of settings from `[profile.NAME]` tables, selected with
+    /// --profile
+    pub profiles: BTreeMap<String, Vec<Setting>>,
+    /// Commands comparing files by extension, from the `[comparers]` table,
+    /// e.g. `pdf = "pdf-compare --strict"` or an array of arguments
+    pub comparers: BTreeMap<String, Vec<String>>,
+    /// How to compare files by pattern, from the `[policies]` table, e.g.
+    /// `"*.iso" = "hash-only"`, with `default` for the other files
+    pub policies: BTreeMap<String, Policy>,
+    /// Shell commands to run before and after comparisons, from the
+    /// `[hooks]` table
+    pub hooks: Hooks,
+    /// Comparisons run on a schedule by `file_cmp daemon`, from
+    /// `[jobs.NAME]` tables
+    pub jobs: BTreeMap<String, Job>,
+}
+
+impl Config {
+    /// Parses a configuration file. Each setting is named after the long
+    /// flag it sets a default for, with `_` or `-` between words, e.g.
+    /// `chunk_size = "4M"` or `diffs-only = true`. Switches that are turned
+    /// off are left out, and arrays give the flag once per value.
+    pub fn parse(text: &str) -> Result<Config, String> {
+        let mut table: toml::Table = text
+            .parse()
+            .map_err(|e: toml::de::Error| e.message().to_string())?;
+        let mut config = Config::default();
+        if let Some(toml::Value::Table(profiles)) = table.remove("profile") {
+            for (name, profile) in profiles {
+                let settings = match profile {
+                    toml::Value::Table(profile) => settings(profile)?,
+                    _ => return Err(format!("profile '{}' is not a table", name)),
+                };
+                config.profiles.insert(name, settings);
+            }
+        }
+        if let Some(toml::Value::Table(comparers)) = table.remove("comparers") {
+            for (extension, command) in comparers {
+                let command = match command {
+                    toml::Value::String(command) => {
+                        command.split_whitespace().map(str::to_string).collect()
+                    }
+                    toml::Value::Array(args) => args
+                        .into_iter()
+                        .map(|arg| match arg {
+                            toml::Value::String(arg) => Ok(arg),
+                            _ => Err(format!("invalid argument for comparer '{}'", extension)),
+                        })
+                        .collect::<Result<_, _>>()?,
+                    _ => return Err(format!("invalid command for comparer '{}'", extension)),
+                };
+                config.comparers.insert(extension, command);
+            }
+        }
+        if let Some(toml::Value::Table(policies)) = table.remove("policies") {
+            for (pattern, policy) in policies {
+                let policy = match policy {
+                    toml::Value::String(policy) => policy.parse()?,
+                    _ => return Err(format!("invalid policy for '{}'", pattern)),
+                };
+                config.policies.insert(pattern, policy);
+            }
+        }
+        if let Some(toml::Value::Table(hooks)) = table.remove("hooks") {
+            for (name, command) in hooks {
+                let command = match command {
+                    toml::Value::String(command) => Some(command),
+                    _ => return Err(format!("invalid command for hook '{}'", name)),
+                };
+                match name.as_str() {
+                    "before" => config.hooks.before = command,
+                    "after" => config.hooks.after = command,
+                    _ => return Err(format!("unknown hook '{}'", name)),
+                }
+            }
+        }
+        if let Some(toml::Value::Table(jobs)) = table.remove("jobs") {
+            for (name, job) in jobs {
+                let job = match job {
+                    toml::Value::Table(job) => Job::from_table(&name, job)?,
+                    _ => return Err(format!("job '{}' is not a table", name)),
+                };
+                config.jobs.insert(name, job);
+            }
+        }
+        config.settings = settings(table)?;
+        Ok(config)
+    }
+
+    /// Reads and parses a configuration file.
+    pub fn read(path: &Path) -> io::Result<Config> {
+        Config::parse(&fs::read_to_string(path)?).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), e),
+            )
+        })
+    }
+}
+
+fn settings(table: toml::Table) -> Result<Vec<Setting>, String> {
+    let mut settings = vec![];
+    for (key, value) in table {
+        let flag = key.replace('_', "-");
+        let values = match value {
+            toml::Value::Array(values) => values,
+            value => vec![value],
+        };
+        for value in values {
+            let value = match value {
+                toml::Value::Boolean(true) => None,
+                toml::Value::Boolean(false) => continue,
+                toml::Value::String(value) => Some(value),
+                toml::Value::Integer(value) => Some(value.to_string()),
+                toml::Value::Float(value) => Some(value.to_string()),
+                _ => return Err(format!("unsupported value for '{}'", key)),
+            };
+            settings.push((flag.clone(), value));
+        }
+    }
+    Ok(settings)
+}
@@ -0,0 +1,302 @@
+use crate::report;
+use crate::units::{civil_date, parse_duration};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// A comparison run again and again by the daemon, from a `[jobs.NAME]`
+/// table of the configuration.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Job {
+    /// Arguments of the comparison, as given to `file_cmp compare`
+    pub args: Vec<String>,
+    pub schedule: Schedule,
+}
+
+/// When a job runs.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Schedule {
+    /// At this interval, from when the daemon starts
+    Every(Duration),
+    /// At the times that match a cron schedule, in UTC
+    Cron(Cron),
+}
+
+impl Schedule {
+    /// Returns the time of the next run after one that was due at `time`.
+    pub fn next_after(&self, time: SystemTime) -> SystemTime {
+        match self {
+            Self::Every(interval) => time + *interval,
+            Self::Cron(cron) => {
+                let secs = time
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs();
+                UNIX_EPOCH + Duration::from_secs(cron.next_after(secs))
+            }
+        }
+    }
+
+    /// Returns the time of the first run of a daemon started at `time`:
+    /// right away for an interval, at the next match for a cron schedule.
+    pub fn first(&self, time: SystemTime) -> SystemTime {
+        match self {
+            Self::Every(_) => time,
+            Self::Cron(_) => self.next_after(time),
+        }
+    }
+}
+
+/// A schedule in the five fields of cron: minute, hour, day of the month,
+/// month and day of the week (0 or 7 for Sunday). Each field is `*`, a
+/// number or a range `a-b`, optionally with a step `/n`, or a list of those
+/// separated by commas. As in cron, a day matches if either of the day
+/// fields does when both are restricted.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Cron {
+    /// The values each field matches, as bits
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl FromStr for Cron {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = s.split_whitespace().collect();
+        let [minutes, hours, days, months, weekdays] = fields[..] else {
+            return Err(format!("expected five fields in cron schedule '{}'", s));
+        };
+        let weekdays = cron_field(weekdays, 0, 7)?;
+        Ok(Cron {
+            minutes: cron_field(minutes, 0, 59)?,
+            hours: cron_field(hours, 0, 23)?,
+            days: cron_field(days, 1, 31)?,
+            months: cron_field(months, 1, 12)?,
+            // Sunday is both 0 and 7
+            weekdays: (weekdays | weekdays >> 7) & 0x7f,
+            any_day: fields[2] == "*",
+            any_weekday: fields[4] == "*",
+        })
+    }
+}
+
+/// Parses a field of a cron schedule into the bits of the values it
+/// matches, between `min` and `max`.
+fn cron_field(field: &str, min: u32, max: u32) -> Result<u64, String> {
+    let invalid = || format!("invalid cron field '{}'", field);
+    let number = |s: &str| -> Result<u32, String> {
+        match s.parse() {
+            Ok(n) if (min..=max).contains(&n) => Ok(n),
+            _ => Err(invalid()),
+        }
+    };
+    let mut bits = 0;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse().map_err(|_| invalid())?),
+            None => (part, 1),
+        };
+        let (start, end) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((start, end)) => (number(start)?, number(end)?),
+            None if step > 1 => (number(range)?, max),
+            None => (number(range)?, number(range)?),
+        };
+        if step == 0 || start > end {
+            return Err(invalid());
+        }
+        for value in (start..=end).step_by(step) {
+            bits |= 1 << value;
+        }
+    }
+    Ok(bits)
+}
+
+impl Cron {
+    fn matches_day(&self, days: u64) -> bool {
+        let (_, month, day) = civil_date(days as i64);
+        // 1970-01-01 was a Thursday
+        let weekday = (days + 4) % 7;
+        let day = self.days & 1 << day != 0;
+        let weekday = self.weekdays & 1 << weekday != 0;
+        let day = match (self.any_day, self.any_weekday) {
+            (false, false) => day || weekday,
+            _ => day && weekday,
+        };
+        day && self.months & 1 << month != 0
+    }
+
+    /// Returns the first matching minute after `secs`, in seconds since
+    /// 1970, skipping whole days and hours that don't match. Schedules that
+    /// never match, e.g. on February 30, give up after 8 years.
+    fn next_after(&self, secs: u64) -> u64 {
+        let mut minute = secs / 60 + 1;
+        let limit = minute + 8 * 366 * 24 * 60;
+        while minute < limit {
+            let days = minute / (24 * 60);
+            if !self.matches_day(days) {
+                minute = (days + 1) * 24 * 60;
+            } else if self.hours & 1 << (minute / 60 % 24) == 0 {
+                minute = (minute / 60 + 1) * 60;
+            } else if self.minutes & 1 << (minute % 60) == 0 {
+                minute += 1;
+            } else {
+                return minute * 60;
+            }
+        }
+        u64::MAX / 2
+    }
+}
+
+impl Job {
+    /// Parses a `[jobs.NAME]` table, with the arguments of the comparison
+    /// in `args`, as a string split at whitespace or an array, and either
+    /// `every` with an interval such as `"1h"` or `cron` with a schedule
+    /// such as `"0 3 * * *"`.
+    pub fn from_table(name: &str, mut table: toml::Table) -> Result<Job, String> {
+        let args = match table.remove("args") {
+            Some(toml::Value::String(args)) => {
+                args.split_whitespace().map(str::to_string).collect()
+            }
+            Some(toml::Value::Array(args)) => args
+                .into_iter()
+                .map(|arg| match arg {
+                    toml::Value::String(arg) => Ok(arg),
+                    _ => Err(format!("invalid argument for job '{}'", name)),
+                })
+                .collect::<Result<_, _>>()?,
+            _ => return Err(format!("job '{}' has no args", name)),
+        };
+        let schedule = match (table.remove("every"), table.remove("cron")) {
+            (Some(toml::Value::String(every)), None) => Schedule::Every(parse_duration(&every)?),
+            (Some(toml::Value::Integer(secs)), None) if secs > 0 => {
+                Schedule::Every(Duration::from_secs(secs as u64))
+            }
+            (None, Some(toml::Value::String(cron))) => Schedule::Cron(cron.parse()?),
+            _ => {
+                return Err(format!(
+                    "job '{}' needs either an every interval or a cron schedule",
+                    name
+                ))
+            }
+        };
+        if schedule == Schedule::Every(Duration::ZERO) {
+            return Err(format!("job '{}' needs an interval above zero", name));
+        }
+        if let Some(key) = table.keys().next() {
+            return Err(format!("unknown setting '{}' for job '{}'", key, name));
+        }
+        Ok(Job { args, schedule })
+    }
+
+    /// Runs the comparison of the job with `program`, a build of file_cmp,
+    /// returning its results and exit code. Its errors go to stderr, and
+    /// the name of the job is in FILE_CMP_JOB for its hooks.
+    pub fn run(&self, name: &str, program: &Path) -> io::Result<(Results, Option<i32>)> {
+        let output = Command::new(program)
+            .arg("compare")
+            .arg("--porcelain")
+            .args(&self.args)
+            .env("FILE_CMP_JOB", name)
+            .stdin(Stdio::null())
+            .stderr(Stdio::inherit())
+            .output()?;
+        let results = parse_results(&String::from_utf8_lossy(&output.stdout));
+        Ok((results, output.status.code()))
+    }
+}
+
+/// The results of a run of a job by their pair of paths: the status and
+/// offset fields of --porcelain.
+pub type Results = BTreeMap<(String, String), (String, String)>;
+
+/// Parses the output of --porcelain, skipping lines it doesn't recognize.
+pub fn parse_results(porcelain: &str) -> Results {
+    porcelain
+        .lines()
+        .filter_map(|line| match line.split('\t').collect::<Vec<_>>()[..] {
+            [status, offset, path1, path2] => Some((
+                (path1.to_string(), path2.to_string()),
+                (status.to_string(), offset.to_string()),
+            )),
+            _ => None,
+        })
+        .collect()
+}
+
+/// A pair whose result changed between two runs of a job.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Change {
+    pub path1: String,
+    pub path2: String,
+    /// The status and offset before and after, or `None` for a pair that
+    /// had no result, e.g. with --diffs-only
+    pub before: Option<(String, String)>,
+    pub after: Option<(String, String)>,
+}
+
+/// Returns the pairs whose results differ between two runs.
+pub fn changes(before: &Results, after: &Results) -> Vec<Change> {
+    let mut paths: Vec<&(String, String)> = before.keys().chain(after.keys()).collect();
+    paths.sort();
+    paths.dedup();
+    paths
+        .into_iter()
+        .filter(|paths| before.get(*paths) != after.get(*paths))
+        .map(|(path1, path2)| Change {
+            path1: path1.clone(),
+            path2: path2.clone(),
+            before: before.get(&(path1.clone(), path2.clone())).cloned(),
+            after: after.get(&(path1.clone(), path2.clone())).cloned(),
+        })
+        .collect()
+}
+
+/// Reads the results a job had the last time, from its state file, or none
+/// if it hasn't run yet.
+pub fn load_state(path: &Path) -> io::Result<Results> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Results::new()),
+        Err(e) => return Err(e),
+    };
+    let invalid = || {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: invalid state", path.display()),
+        )
+    };
+    let state: Value = serde_json::from_str(&text).map_err(|_| invalid())?;
+    let mut results = Results::new();
+    for entry in state["results"].as_array().ok_or_else(invalid)? {
+        let field = |name: &str| entry[name].as_str().map(str::to_string).ok_or_else(invalid);
+        results.insert(
+            (field("path1")?, field("path2")?),
+            (field("status")?, field("offset")?),
+        );
+    }
+    Ok(results)
+}
+
+/// Writes the results of a job to its state file, replacing it only once
+/// written so a crash doesn't lose the last state.
+pub fn save_state(path: &Path, results: &Results) -> io::Result<()> {
+    let results: Vec<Value> = results
+        .iter()
+        .map(|((path1, path2), (status, offset))| {
+            json!({ "path1": path1, "path2": path2, "status": status, "offset": offset })
+        })
+        .collect();
+    report::write_atomically(path, json!({ "results": results }).to_string())
+}
@@ -0,0 +1,291 @@
+use crate::agent::{invalid, read_record};
+use crate::hash::{to_hex, HashAlgorithm};
+use crate::{diff_at_lengths, read_full, FileDiff};
+use std::collections::HashMap;
+use std::io::{self, BufRead, Read, Write};
+use std::ops::Range;
+
+/// Starts the signature stream, followed by the hash algorithm's name and
+/// the block length.
+const HEADER: &str = "file_cmp-signature";
+
+/// Bounds of the block length chosen for a file, like rsync's.
+const MIN_BLOCK_LEN: usize = 1024;
+const MAX_BLOCK_LEN: usize = 128 * 1024;
+
+/// Bytes read from the local file at a time while rolling over it.
+const READ_LEN: usize = 1024 * 1024;
+
+/// Returns the block length for signatures of a file of `len` bytes: about
+/// its square root, so that the signature and the resolution of the
+/// differing ranges grow together.
+pub fn block_len(len: u64) -> usize {
+    ((len as f64).sqrt() as usize / 8 * 8).clamp(MIN_BLOCK_LEN, MAX_BLOCK_LEN)
+}
+
+/// The weak checksum of rsync over a window of bytes, which can be rolled
+/// forward one byte at a time.
+#[derive(Clone, Copy, Debug)]
+pub struct Rolling {
+    a: u32,
+    b: u32,
+    len: u32,
+}
+
+impl Rolling {
+    pub fn new(window: &[u8]) -> Rolling {
+        let len = window.len() as u32;
+        let (mut a, mut b) = (0u32, 0u32);
+        for (i, &byte) in window.iter().enumerate() {
+            a = a.wrapping_add(byte as u32);
+            b = b.wrapping_add((len - i as u32).wrapping_mul(byte as u32));
+        }
+        Rolling { a, b, len }
+    }
+
+    /// Moves the window one byte forward, dropping `out` at its start and
+    /// adding `into` at its end.
+    pub fn roll(&mut self, out: u8, into: u8) {
+        self.a = self.a.wrapping_sub(out as u32).wrapping_add(into as u32);
+        self.b = self
+            .b
+            .wrapping_sub(self.len.wrapping_mul(out as u32))
+            .wrapping_add(self.a);
+    }
+
+    pub fn digest(&self) -> u32 {
+        (self.a & 0xffff) | (self.b << 16)
+    }
+}
+
+/// Checksums of a block of the remote file.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BlockSignature {
+    pub len: usize,
+    pub weak: u32,
+    /// Digest with the hash algorithm of the signature
+    pub strong: Vec<u8>,
+}
+
+/// Checksums of each block of a file, which are all that cross the
+/// connection in a delta comparison.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Signature {
+    pub algorithm: HashAlgorithm,
+    pub block_len: usize,
+    pub blocks: Vec<BlockSignature>,
+}
+
+impl Signature {
+    /// Length of the file the signature is of.
+    pub fn len(&self) -> u64 {
+        self.blocks.iter().map(|block| block.len as u64).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    /// Reads the signature written by [`write_signature`], returning an
+    /// error if it was made with a different algorithm than the one
+    /// expected.
+    pub fn read<R: BufRead>(mut reader: R, algorithm: HashAlgorithm) -> io::Result<Signature> {
+        let header = read_record(&mut reader)?.unwrap_or_default();
+        let block_len = match header.split(' ').collect::<Vec<_>>()[..] {
+            [HEADER, name, block_len] if name == algorithm.name() => block_len
+                .parse()
+                .ok()
+                .filter(|&len| len > 0)
+                .ok_or_else(|| invalid("invalid block length in signature"))?,
+            [HEADER, name, _] => {
+                return Err(invalid(&format!(
+                    "agent hashes with {} instead of {}",
+                    name, algorithm
+                )))
+            }
+            _ => return Err(invalid("not a file_cmp signature stream")),
+        };
+        let mut blocks = vec![];
+        while let Some(record) = read_record(&mut reader)? {
+            let block = match record.split(' ').collect::<Vec<_>>()[..] {
+                [weak, strong, len] => (|| {
+                    Some(BlockSignature {
+                        len: len.parse().ok().filter(|&len| len <= block_len)?,
+                        weak: u32::from_str_radix(weak, 16).ok()?,
+                        strong: from_hex(strong)?,
+                    })
+                })(),
+                _ => None,
+            };
+            blocks.push(
+                block.ok_or_else(|| invalid(&format!("invalid signature record '{}'", record)))?,
+            );
+        }
+        Ok(Signature {
+            algorithm,
+            block_len,
+            blocks,
+        })
+    }
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Writes the weak and strong checksums of each block of the reader's
+/// data as NUL-terminated records, after a header naming the algorithm and
+/// block length.
+pub fn write_signature<R: Read, W: Write>(
+    reader: &mut R,
+    block_len: usize,
+    algorithm: HashAlgorithm,
+    out: &mut W,
+) -> io::Result<()> {
+    write!(out, "{} {} {}\0", HEADER, algorithm, block_len)?;
+    let mut hasher = algorithm.hasher();
+    let mut block = vec![0; block_len];
+    loop {
+        let len = read_full(reader, &mut block)?;
+        if len == 0 {
+            break;
+        }
+        hasher.update(&block[..len]);
+        let strong = hasher.finalize_reset();
+        let weak = Rolling::new(&block[..len]).digest();
+        write!(out, "{:08x} {} {}\0", weak, to_hex(&strong), len)?;
+        if len < block_len {
+            break;
+        }
+    }
+    out.flush()
+}
+
+/// Result of comparing a local file with the signature of a remote one.
+#[derive(Debug, Eq, PartialEq)]
+pub struct Delta {
+    pub file_diff: FileDiff,
+    /// Byte ranges of the local file found in no block of the remote one,
+    /// i.e. the data rsync would have to send
+    pub ranges: Vec<Range<u64>>,
+}
+
+/// Looks up the blocks of a signature by their weak checksum.
+struct Blocks<'a> {
+    signature: &'a Signature,
+    by_weak: HashMap<u32, Vec<usize>>,
+    hasher: Box<dyn sha2::digest::DynDigest>,
+}
+
+impl Blocks<'_> {
+    /// Returns the block the window matches, preferring the one at the
+    /// same offset.
+    fn find(&mut self, weak: u32, window: &[u8], offset: u64) -> Option<usize> {
+        let candidates = self.by_weak.get(&weak)?;
+        self.hasher.update(window);
+        let strong = self.hasher.finalize_reset();
+        let (blocks, block_len) = (&self.signature.blocks, self.signature.block_len);
+        candidates
+            .iter()
+            .copied()
+            .filter(|&i| blocks[i].len == window.len() && *blocks[i].strong == *strong)
+            .min_by_key(|&i| (i * block_len) as u64 != offset)
+    }
+}
+
+/// Rolls over the local file like the sender of rsync, matching its
+/// windows against the blocks of the remote file's signature. Bytes that
+/// match no block are the differing ranges, and the first of them or of the
+/// blocks found at another offset than in the remote file is where the
+/// files differ.
+pub fn compare_signature<R: Read>(reader: &mut R, signature: &Signature) -> io::Result<Delta> {
+    let block_len = signature.block_len;
+    let mut by_weak: HashMap<u32, Vec<usize>> = HashMap::new();
+    for (i, block) in signature.blocks.iter().enumerate() {
+        by_weak.entry(block.weak).or_default().push(i);
+    }
+    let mut blocks = Blocks {
+        signature,
+        by_weak,
+        hasher: signature.algorithm.hasher(),
+    };
+
+    // The data read from `base` on, of which the window starts at `pos`
+    let (mut buffer, mut base, mut pos) = (Vec::new(), 0u64, 0usize);
+    let mut eof = false;
+    let mut fill = |buffer: &mut Vec<u8>, needed: usize| -> io::Result<()> {
+        while !eof && buffer.len() < needed {
+            let start = buffer.len();
+            buffer.resize(start + READ_LEN, 0);
+            let len = read_full(reader, &mut buffer[start..])?;
+            buffer.truncate(start + len);
+            eof = len < READ_LEN;
+        }
+        Ok(())
+    };
+    let mut ranges: Vec<Range<u64>> = vec![];
+    let mut first_diff: Option<u64> = None;
+    let mut differ = |ranges: &mut Vec<Range<u64>>, range: Range<u64>, literal: bool| {
+        first_diff = Some(first_diff.map_or(range.start, |first| first.min(range.start)));
+        if !literal {
+            return;
+        }
+        match ranges.last_mut() {
+            Some(last) if last.end == range.start => last.end = range.end,
+            _ => ranges.push(range),
+        }
+    };
+
+    let mut rolling: Option<Rolling> = None;
+    loop {
+        fill(&mut buffer, pos + block_len + 1)?;
+        let window = match buffer.get(pos..pos + block_len) {
+            Some(window) => window,
+            None => break,
+        };
+        let weak = rolling.get_or_insert_with(|| Rolling::new(window));
+        let offset = base + pos as u64;
+        match blocks.find(weak.digest(), window, offset) {
+            Some(i) => {
+                if offset != (i * block_len) as u64 {
+                    differ(&mut ranges, offset..offset, false);
+                }
+                pos += block_len;
+                rolling = None;
+            }
+            None => {
+                differ(&mut ranges, offset..offset + 1, true);
+                match buffer.get(pos + block_len) {
+                    Some(&into) => weak.roll(buffer[pos], into),
+                    None => rolling = None,
+                }
+                pos += 1;
+            }
+        }
+        if pos >= READ_LEN {
+            buffer.drain(..pos);
+            base += pos as u64;
+            pos = 0;
+        }
+    }
+
+    // The rest is shorter than a block and can only match the last one
+    let rest = &buffer[pos..];
+    if !rest.is_empty() {
+        let offset = base + pos as u64;
+        match blocks.find(Rolling::new(rest).digest(), rest, offset) {
+            Some(i) if offset == (i * block_len) as u64 => {}
+            Some(_) => differ(&mut ranges, offset..offset, false),
+            None => differ(&mut ranges, offset..offset + rest.len() as u64, true),
+        }
+    }
+    let (len1, len2) = (base + buffer.len() as u64, signature.len());
+    let offset = first_diff.or((len1 != len2).then_some(len1.min(len2)));
+    Ok(Delta {
+        file_diff: diff_at_lengths(offset, len1, len2, false),
+        ranges,
+    })
+}
@@ -0,0 +1,169 @@
+use crate::blank::{padding_end, Padding};
+use crate::{compare_streams, diff_at_lengths, long_path, CompareOptions, FileDiff};
+use std::fs::{self, File, Metadata};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// One side of a file comparison. Block devices report a length of 0 in
+/// their metadata, so their size is queried from the device instead, and
+/// raw Windows devices (`\\.\PhysicalDriveN`) have no metadata at all.
+pub struct Input {
+    pub file: File,
+    pub len: u64,
+    pub meta: Option<Metadata>,
+}
+
+impl Input {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Input> {
+        let file = File::open(&path)?;
+
+        #[cfg(windows)]
+        if is_device_path(path.as_ref()) {
+            let len = windows_device_len(&file)?;
+            return Ok(Input {
+                file,
+                len,
+                meta: None,
+            });
+        }
+
+        let meta = file.metadata()?;
+        let len = match is_block_device(&meta) {
+            true => block_device_len(&file)?,
+            false => meta.len(),
+        };
+        Ok(Input {
+            file,
+            len,
+            meta: Some(meta),
+        })
+    }
+
+    pub fn is_device(&self) -> bool {
+        self.meta.as_ref().is_none_or(is_block_device)
+    }
+}
+
+/// Returns whether the path is a block device or a raw Windows device.
+pub fn is_device_file<P: AsRef<Path>>(path: P) -> bool {
+    let path = path.as_ref();
+    is_device_path(path) || fs::metadata(path).is_ok_and(|meta| is_block_device(&meta))
+}
+
+/// What a device holds after the end of a disk image written to it.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Trailing {
+    /// Bytes of the device after the image
+    pub len: u64,
+    /// Offset on the device of the first of them that isn't 0x00 or 0xFF
+    /// filler, or `None` if they are all blank
+    pub data_at: Option<u64>,
+}
+
+/// Compares a disk image with the device it was written to, or with
+/// another image of it. A device larger than the image is only compared up
+/// to the length of the image, and what it holds after that is returned
+/// separately, which takes reading the rest of the device. A device smaller
+/// than the image is truncated on the right.
+pub fn compare_image<P: AsRef<Path>>(
+    image: P,
+    device: P,
+    options: &CompareOptions,
+) -> io::Result<(FileDiff, Option<Trailing>)> {
+    let image = Input::open(long_path::extended(image.as_ref()))?;
+    let device = Input::open(long_path::extended(device.as_ref()))?;
+    let compared = device.len.min(image.len);
+    let result = match compare_streams(&image.file, (&device.file).take(compared), options)? {
+        FileDiff::Different(offset) => {
+            diff_at_lengths(Some(offset as u64), image.len, compared, options.quick)
+        }
+        result => result,
+    };
+    if device.len <= image.len {
+        return Ok((result, None));
+    }
+    let mut file = &device.file;
+    file.seek(SeekFrom::Start(image.len))?;
+    let end = padding_end(&mut file.take(device.len - image.len), Padding::Filler)?;
+    let trailing = Trailing {
+        len: device.len - image.len,
+        data_at: end.map(|end| image.len + end),
+    };
+    Ok((result, Some(trailing)))
+}
+
+pub fn is_block_device(meta: &Metadata) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileTypeExt;
+        meta.file_type().is_block_device()
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = meta;
+        false
+    }
+}
+
+/// Returns true for Win32 device namespace paths like `\\.\PhysicalDrive0`
+/// or `\\.\C:`.
+pub fn is_device_path(path: &Path) -> bool {
+    let path = path.to_string_lossy();
+    path.starts_with(r"\\.\") || path.starts_with("//./")
+}
+
+#[cfg(unix)]
+fn block_device_len(file: &File) -> io::Result<u64> {
+    use std::io::{Seek, SeekFrom};
+    let mut file = file;
+    let len = file.seek(SeekFrom::End(0))?;
+    file.rewind()?;
+    Ok(len)
+}
+
+#[cfg(not(unix))]
+fn block_device_len(_file: &File) -> io::Result<u64> {
+    Ok(0)
+}
+
+#[cfg(windows)]
+fn windows_device_len(file: &File) -> io::Result<u64> {
+    use std::os::windows::io::AsRawHandle;
+    use std::os::windows::raw::HANDLE;
+    use std::ptr;
+
+    const IOCTL_DISK_GET_LENGTH_INFO: u32 = 0x0007_405c;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn DeviceIoControl(
+            device: HANDLE,
+            code: u32,
+            in_buffer: *mut u8,
+            in_size: u32,
+            out_buffer: *mut u8,
+            out_size: u32,
+            returned: *mut u32,
+            overlapped: *mut u8,
+        ) -> i32;
+    }
+
+    let mut len: i64 = 0;
+    let mut returned = 0;
+    let ok = unsafe {
+        DeviceIoControl(
+            file.as_raw_handle(),
+            IOCTL_DISK_GET_LENGTH_INFO,
+            ptr::null_mut(),
+            0,
+            &mut len as *mut i64 as *mut u8,
+            std::mem::size_of::<i64>() as u32,
+            &mut returned,
+            ptr::null_mut(),
+        )
+    };
+    match ok {
+        0 => Err(io::Error::last_os_error()),
+        _ => Ok(len as u64),
+    }
+}
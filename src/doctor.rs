@@ -0,0 +1,153 @@
+use crate::sparse;
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+use std::time::{Duration, UNIX_EPOCH};
+
+/// What a filesystem supports, as far as it matters for comparing files on
+/// it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Report {
+    /// Whether names differing only in case are different files
+    pub case_sensitive: bool,
+    /// The precision modification times are stored with, or `None` if they
+    /// can't be set or aren't stored with any of the usual precisions
+    pub mtime_granularity: Option<Duration>,
+    /// Whether files can have holes that sparse comparisons skip
+    pub sparse_files: bool,
+    /// Whether symbolic links can be created
+    pub symlinks: bool,
+    /// The longest file name in bytes, if limited and known
+    pub max_name_len: Option<u64>,
+    /// The longest path in bytes, if limited and known
+    pub max_path_len: Option<u64>,
+}
+
+impl Report {
+    /// Returns advice on comparing files on the filesystem.
+    pub fn advice(&self) -> Vec<String> {
+        let mut advice = vec![];
+        advice.push(match self.sparse_files {
+            true => "Sparse files are compared by their data extents, skipping holes".to_string(),
+            false => {
+                "Holes read as zeros; use --hash-first --skip-blank for mostly empty disk images"
+                    .to_string()
+            }
+        });
+        if !self.case_sensitive {
+            advice.push("Names differing only in case are the same file, so comparing with a case-sensitive tree can report them as left only and right only".to_string());
+        }
+        if !self.symlinks {
+            advice.push("Symbolic links can't be created, so trees copied here have them replaced by files or left out".to_string());
+        }
+        match self.mtime_granularity {
+            Some(granularity) if granularity >= Duration::from_secs(1) => advice.push(format!(
+                "Modification times are rounded to {:?}, so tools copying by time should allow that much difference",
+                granularity
+            )),
+            None => advice.push("Modification times can't be relied on".to_string()),
+            _ => {}
+        }
+        advice
+    }
+}
+
+/// Probes the filesystem of a directory by creating scratch files in it,
+/// which are removed again.
+pub fn probe(dir: &Path) -> io::Result<Report> {
+    let scratch = dir.join(format!(".file_cmp_doctor_{}", std::process::id()));
+    fs::create_dir(&scratch)?;
+    let report = probe_in(&scratch);
+    fs::remove_dir_all(&scratch)?;
+    report
+}
+
+fn probe_in(scratch: &Path) -> io::Result<Report> {
+    fs::write(scratch.join("Probe"), "")?;
+    let case_sensitive = !scratch.join("PROBE").exists();
+
+    let (max_name_len, max_path_len) = limits(scratch);
+    Ok(Report {
+        case_sensitive,
+        mtime_granularity: mtime_granularity(&scratch.join("mtime"))?,
+        sparse_files: supports_sparse(&scratch.join("sparse"))?,
+        symlinks: supports_symlinks(scratch),
+        max_name_len,
+        max_path_len,
+    })
+}
+
+/// Sets a modification time with nanoseconds and finds the coarsest
+/// precision it was stored with, e.g. 100ns on NTFS or 2s on FAT.
+fn mtime_granularity(path: &Path) -> io::Result<Option<Duration>> {
+    const GRANULARITIES: [Duration; 7] = [
+        Duration::from_nanos(1),
+        Duration::from_nanos(100),
+        Duration::from_micros(1),
+        Duration::from_millis(1),
+        Duration::from_millis(10),
+        Duration::from_secs(1),
+        Duration::from_secs(2),
+    ];
+    let set = Duration::new(1_700_000_001, 123_456_789);
+    let file = File::create(path)?;
+    if file.set_modified(UNIX_EPOCH + set).is_err() {
+        return Ok(None);
+    }
+    let stored = match fs::metadata(path)?.modified()?.duration_since(UNIX_EPOCH) {
+        Ok(stored) => stored.as_nanos(),
+        Err(_) => return Ok(None),
+    };
+    Ok(GRANULARITIES.into_iter().find(|granularity| {
+        let floor = set.as_nanos() / granularity.as_nanos() * granularity.as_nanos();
+        stored == floor || stored == floor + granularity.as_nanos()
+    }))
+}
+
+/// Creates a file that is all hole and checks that it takes no space and
+/// its holes can be found.
+fn supports_sparse(path: &Path) -> io::Result<bool> {
+    const LEN: u64 = 16 * 1024 * 1024;
+    let file = File::create(path)?;
+    file.set_len(LEN)?;
+    Ok(sparse::is_sparse(&file.metadata()?) && sparse::data_extents(&file, LEN)?.is_some())
+}
+
+fn supports_symlinks(scratch: &Path) -> bool {
+    let (target, link) = (scratch.join("Probe"), scratch.join("link"));
+    #[cfg(unix)]
+    return std::os::unix::fs::symlink(target, link).is_ok();
+    #[cfg(windows)]
+    return std::os::windows::fs::symlink_file(target, link).is_ok();
+    #[cfg(not(any(unix, windows)))]
+    {
+        let _ = (target, link);
+        false
+    }
+}
+
+/// Returns the longest name and path, from `pathconf` on Unix. On Windows,
+/// paths are only limited by the \\?\ form file_cmp uses for long paths.
+#[cfg(unix)]
+fn limits(dir: &Path) -> (Option<u64>, Option<u64>) {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = match CString::new(dir.as_os_str().as_bytes()) {
+        Ok(path) => path,
+        Err(_) => return (None, None),
+    };
+    // -1 for no limit as well as errors
+    let limit = |name| u64::try_from(unsafe { libc::pathconf(path.as_ptr(), name) }).ok();
+    (limit(libc::_PC_NAME_MAX), limit(libc::_PC_PATH_MAX))
+}
+
+#[cfg(windows)]
+fn limits(_dir: &Path) -> (Option<u64>, Option<u64>) {
+    (Some(255), Some(32767))
+}
+
+#[cfg(not(any(unix, windows)))]
+fn limits(_dir: &Path) -> (Option<u64>, Option<u64>) {
+    (None, None)
+}
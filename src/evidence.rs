@@ -0,0 +1,129 @@
+use crate::hash::{self, HashAlgorithm};
+use crate::units::civil_date;
+use crate::FileDiff;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The `prev` of the first record of a log.
+pub const GENESIS: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// An append-only log of comparisons for forensic documentation. Each line
+/// is a JSON record of one comparison, with the path, size, SHA-256 and
+/// modification time of both files, the verdict and when it was made. The
+/// records are chained: each has the SHA-256 of the line before it in
+/// `prev`, so removing or changing a record breaks the chain after it.
+#[derive(Debug)]
+pub struct EvidenceLog {
+    file: File,
+    prev: String,
+}
+
+impl EvidenceLog {
+    /// Opens a log to append to, continuing the chain of the records it
+    /// already has.
+    pub fn open(path: &Path) -> io::Result<EvidenceLog> {
+        let prev = match File::open(path) {
+            Ok(file) => last_line(BufReader::new(file))?
+                .map_or_else(|| GENESIS.to_string(), |line| chain_hash(&line)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => GENESIS.to_string(),
+            Err(e) => return Err(e),
+        };
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(EvidenceLog { file, prev })
+    }
+
+    /// Appends the record of a comparison. A file missing on one side is
+    /// recorded as null.
+    pub fn record(&mut self, path1: &Path, path2: &Path, file_diff: &FileDiff) -> io::Result<()> {
+        let mut record = json!({
+            "time": format_time(SystemTime::now()),
+            "left": describe_file(path1, *file_diff != FileDiff::RightOnly)?,
+            "right": describe_file(path2, *file_diff != FileDiff::LeftOnly)?,
+            "verdict": file_diff.as_desc(),
+            "prev": self.prev,
+        });
+        if let FileDiff::Different(offset) | FileDiff::Truncated(_, offset) = file_diff {
+            record["offset"] = json!(offset);
+        }
+        let line = record.to_string();
+        writeln!(self.file, "{}", line)?;
+        self.file.flush()?;
+        self.prev = chain_hash(&line);
+        Ok(())
+    }
+}
+
+/// Checks the chain of a log, returning the number of records or an error
+/// naming the first line that doesn't follow from the one before it.
+pub fn verify(path: &Path) -> io::Result<usize> {
+    let mut prev = GENESIS.to_string();
+    let mut count = 0;
+    for line in BufReader::new(File::open(path)?).lines() {
+        let line = line?;
+        let record: Value = serde_json::from_str(&line).map_err(|e| broken(count + 1, e))?;
+        if record["prev"] != prev.as_str() {
+            return Err(broken(count + 1, "the chain is broken"));
+        }
+        prev = chain_hash(&line);
+        count += 1;
+    }
+    Ok(count)
+}
+
+fn broken(line: usize, e: impl std::fmt::Display) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("evidence log line {}: {}", line, e),
+    )
+}
+
+fn chain_hash(line: &str) -> String {
+    hash::to_hex(&Sha256::digest(line.as_bytes()))
+}
+
+fn last_line<R: BufRead>(reader: R) -> io::Result<Option<String>> {
+    let mut last = None;
+    for line in reader.lines() {
+        let line = line?;
+        if !line.is_empty() {
+            last = Some(line);
+        }
+    }
+    Ok(last)
+}
+
+fn describe_file(path: &Path, exists: bool) -> io::Result<Value> {
+    if !exists {
+        return Ok(Value::Null);
+    }
+    let meta = fs::metadata(path)?;
+    let digest = hash::hash_file(path, HashAlgorithm::Sha256, None)?;
+    Ok(json!({
+        "path": path.display().to_string(),
+        "size": meta.len(),
+        "sha256": hash::to_hex(&digest),
+        "modified": meta.modified().ok().map(format_time),
+    }))
+}
+
+/// Formats a time as UTC in ISO 8601 with nanoseconds, e.g.
+/// `2024-03-01T12:00:00.000000000Z`. Times before 1970 are clamped to it.
+pub fn format_time(time: SystemTime) -> String {
+    let since = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since.as_secs();
+    let (year, month, day) = civil_date((secs / 86400) as i64);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:09}Z",
+        year,
+        month,
+        day,
+        secs % 86400 / 3600,
+        secs % 3600 / 60,
+        secs % 60,
+        since.subsec_nanos()
+    )
+}
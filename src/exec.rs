@@ -0,0 +1,100 @@
+use std::collections::VecDeque;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+
+/// Runs a command for each pair of files that don't match, e.g. to repair
+/// or report them, with up to `limit` of them running at a time. The
+/// placeholders `{left}`, `{right}` and `{status}` in the command are
+/// replaced by the paths and the status of the pair, as in --porcelain.
+pub struct Exec {
+    command: String,
+    limit: usize,
+    /// The commands started and not waited for yet, oldest first, with the
+    /// left path they were started for
+    running: VecDeque<(Child, PathBuf)>,
+    failed: u64,
+}
+
+impl Exec {
+    pub fn new(command: &str, limit: usize) -> Self {
+        Self {
+            command: command.to_string(),
+            limit: limit.max(1),
+            running: VecDeque::new(),
+            failed: 0,
+        }
+    }
+
+    /// Starts the command for a pair of files, first waiting for the oldest
+    /// command still running if the limit of them are.
+    pub fn run<P: AsRef<Path>>(&mut self, path1: P, path2: P, status: &str) -> io::Result<()> {
+        while self.running.len() >= self.limit {
+            self.wait_oldest()?;
+        }
+        let (path1, path2) = (path1.as_ref(), path2.as_ref());
+        let child = command(&self.command, path1, path2, status)
+            .stdin(Stdio::null())
+            .spawn()?;
+        self.running.push_back((child, path1.to_path_buf()));
+        Ok(())
+    }
+
+    /// Waits for the commands still running, returning how many of all the
+    /// commands run failed.
+    pub fn finish(&mut self) -> io::Result<u64> {
+        while !self.running.is_empty() {
+            self.wait_oldest()?;
+        }
+        Ok(self.failed)
+    }
+
+    fn wait_oldest(&mut self) -> io::Result<()> {
+        let Some((mut child, path)) = self.running.pop_front() else {
+            return Ok(());
+        };
+        let status = child.wait()?;
+        if !status.success() {
+            eprintln!(
+                "Error: '{}' failed with {} for {}",
+                self.command,
+                status,
+                path.display()
+            );
+            self.failed += 1;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the shell command for a pair. On Unix the paths and status are
+/// passed as positional parameters so they need no quoting.
+fn command(command: &str, path1: &Path, path2: &Path, status: &str) -> Command {
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        let command = command
+            .replace("{left}", &format!("\"{}\"", path1.display()))
+            .replace("{right}", &format!("\"{}\"", path2.display()))
+            .replace("{status}", status);
+        let mut shell = Command::new("cmd");
+        shell.arg("/C").raw_arg(format!("\"{}\"", command));
+        shell
+    }
+    #[cfg(not(windows))]
+    {
+        let command = command
+            .replace("{left}", "\"$1\"")
+            .replace("{right}", "\"$2\"")
+            .replace("{status}", "\"$3\"");
+        let mut shell = Command::new("sh");
+        shell
+            .arg("-c")
+            .arg(command)
+            .arg("file_cmp")
+            .arg(path1)
+            .arg(path2)
+            .arg(status);
+        shell
+    }
+}
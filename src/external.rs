@@ -0,0 +1,65 @@
+use crate::FileDiff;
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// External programs that compare files of a type, by file extension. Each
+/// is run with the two paths appended to its arguments and exits with 0 if
+/// the files are equal and 1 if they differ, like `cmp` and `diff`.
+#[derive(Clone, Debug, Default)]
+pub struct Comparers {
+    by_extension: HashMap<String, Vec<String>>,
+}
+
+impl Comparers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the program and arguments comparing files with the extension,
+    /// which is matched without regard to case.
+    pub fn insert(&mut self, extension: &str, command: Vec<String>) {
+        let extension = extension.trim_start_matches('.').to_lowercase();
+        self.by_extension.insert(extension, command);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_extension.is_empty()
+    }
+
+    /// Returns the command for comparing the file, if its extension has one.
+    pub fn get(&self, path: &Path) -> Option<&[String]> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        self.by_extension.get(&extension).map(Vec::as_slice)
+    }
+}
+
+/// Compares two files by running an external command on them. Since the
+/// command doesn't report where the files differ, a difference is at offset
+/// 0 like in quick mode.
+pub fn compare_with<P: AsRef<Path>>(
+    command: &[String],
+    path1: P,
+    path2: P,
+) -> io::Result<FileDiff> {
+    let (program, args) = command
+        .split_first()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "empty comparer command"))?;
+    let status = Command::new(program)
+        .args(args)
+        .arg(path1.as_ref())
+        .arg(path2.as_ref())
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .status()
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", program, e)))?;
+    match status.code() {
+        Some(0) => Ok(FileDiff::Equal),
+        Some(1) => Ok(FileDiff::Different(0)),
+        _ => Err(io::Error::other(format!(
+            "{} failed with {}",
+            program, status
+        ))),
+    }
+}
@@ -0,0 +1,136 @@
+use crate::walk::DirWalk;
+use crate::{compare_files_with, CompareOptions, FileDiff};
+use std::cell::RefCell;
+use std::ffi::{c_char, c_int, c_void, CStr, CString};
+use std::io;
+use std::path::PathBuf;
+use std::ptr;
+
+// Results of the C interface (see include/file_cmp.h) use the same numbers
+// as `FileDiff::as_number`: the offset of the first difference, or one of
+// these negative status codes.
+pub const FILE_CMP_EQUAL: i64 = -1;
+pub const FILE_CMP_LEFT_ONLY: i64 = -2;
+pub const FILE_CMP_RIGHT_ONLY: i64 = -3;
+/// The comparison failed; `file_cmp_last_error` describes why
+pub const FILE_CMP_ERROR: i64 = -4;
+
+/// Called with each result of comparing directories.
+pub type Callback = extern "C" fn(path: *const c_char, result: i64, user_data: *mut c_void);
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_error(e: io::Error) -> i64 {
+    let message = CString::new(e.to_string().replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+    FILE_CMP_ERROR
+}
+
+fn status(diff: &FileDiff) -> i64 {
+    match diff {
+        FileDiff::Equal => FILE_CMP_EQUAL,
+        FileDiff::Different(offset) | FileDiff::Truncated(_, offset) => *offset as i64,
+        FileDiff::LeftOnly => FILE_CMP_LEFT_ONLY,
+        FileDiff::RightOnly => FILE_CMP_RIGHT_ONLY,
+    }
+}
+
+unsafe fn path(path: *const c_char) -> io::Result<PathBuf> {
+    if path.is_null() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "path is null"));
+    }
+    let path = CStr::from_ptr(path);
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        Ok(PathBuf::from(std::ffi::OsStr::from_bytes(path.to_bytes())))
+    }
+    #[cfg(not(unix))]
+    {
+        path.to_str()
+            .map(PathBuf::from)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "path is not valid UTF-8"))
+    }
+}
+
+fn options(quick: c_int) -> CompareOptions {
+    CompareOptions {
+        quick: quick != 0,
+        ..Default::default()
+    }
+}
+
+/// Compares two files, returning the offset of the first difference,
+/// `FILE_CMP_EQUAL` or `FILE_CMP_ERROR`.
+///
+/// # Safety
+///
+/// The paths must be valid NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn file_cmp_compare_files(
+    path1: *const c_char,
+    path2: *const c_char,
+    quick: c_int,
+) -> i64 {
+    let result = path(path1)
+        .and_then(|path1| Ok((path1, path(path2)?)))
+        .and_then(|(path1, path2)| compare_files_with(path1, path2, &options(quick)));
+    match result {
+        Ok(diff) => status(&diff),
+        Err(e) => set_error(e),
+    }
+}
+
+/// Compares two directory trees, calling `callback` with each path and its
+/// result. Returns 0, or `FILE_CMP_ERROR` if the directories can't be read,
+/// or after the walk if any directory or pair of files in them couldn't be
+/// read, with the first error.
+///
+/// # Safety
+///
+/// The paths must be valid NUL-terminated strings. The path passed to the
+/// callback is only valid during the call.
+#[no_mangle]
+pub unsafe extern "C" fn file_cmp_compare_dirs(
+    dir1: *const c_char,
+    dir2: *const c_char,
+    quick: c_int,
+    callback: Callback,
+    user_data: *mut c_void,
+) -> i64 {
+    let dirs = path(dir1).and_then(|dir1| Ok((dir1, path(dir2)?)));
+    let (dir1, dir2) = match dirs {
+        Ok(dirs) => dirs,
+        Err(e) => return set_error(e),
+    };
+    for dir in [&dir1, &dir2] {
+        if let Err(e) = std::fs::read_dir(dir) {
+            return set_error(e);
+        }
+    }
+    let options = options(quick);
+    let mut walk = DirWalk::new(&dir1, &dir2, &options);
+    let mut failed = vec![];
+    while let Some((path, diff)) = walk.next() {
+        let path = CString::new(path.to_string_lossy().into_owned()).unwrap_or_default();
+        callback(path.as_ptr(), status(&diff), user_data);
+        failed.extend(walk.take_failed());
+    }
+    failed.extend(walk.take_failed());
+    match failed.into_iter().next() {
+        Some((_, _, e)) => set_error(e),
+        None => 0,
+    }
+}
+
+/// Returns the message of the last error on this thread, or NULL. The
+/// string is valid until the next call on the same thread.
+#[no_mangle]
+pub extern "C" fn file_cmp_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| match &*last.borrow() {
+        Some(message) => message.as_ptr(),
+        None => ptr::null(),
+    })
+}
@@ -0,0 +1,78 @@
+use std::fs::Metadata;
+use std::io::{self, Read};
+use std::time::Duration;
+
+/// Returns true for FIFOs (named pipes). Their metadata length is always 0,
+/// so they can only be compared by reading them to the end.
+pub fn is_fifo(meta: &Metadata) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileTypeExt;
+        meta.file_type().is_fifo()
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = meta;
+        false
+    }
+}
+
+/// Reader that fails with `TimedOut` when a read gets no data within the
+/// timeout, so a stalled pipe doesn't block the comparison forever. Without
+/// a timeout, or on platforms that can't wait for a handle, reads pass
+/// straight through.
+pub struct TimeoutReader<R> {
+    inner: R,
+    timeout: Option<Duration>,
+}
+
+impl<R> TimeoutReader<R> {
+    pub fn new(inner: R, timeout: Option<Duration>) -> Self {
+        Self { inner, timeout }
+    }
+}
+
+#[cfg(unix)]
+impl<R: Read + std::os::unix::io::AsRawFd> Read for TimeoutReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if let Some(timeout) = self.timeout {
+            wait_readable(self.inner.as_raw_fd(), timeout)?;
+        }
+        self.inner.read(buf)
+    }
+}
+
+#[cfg(not(unix))]
+impl<R: Read> Read for TimeoutReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let _ = self.timeout;
+        self.inner.read(buf)
+    }
+}
+
+#[cfg(unix)]
+fn wait_readable(fd: std::os::unix::io::RawFd, timeout: Duration) -> io::Result<()> {
+    let mut poll_fd = libc::pollfd {
+        fd,
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    let millis = timeout.as_millis().min(libc::c_int::MAX as u128) as libc::c_int;
+    loop {
+        match unsafe { libc::poll(&mut poll_fd, 1, millis) } {
+            -1 => {
+                let err = io::Error::last_os_error();
+                if err.kind() != io::ErrorKind::Interrupted {
+                    return Err(err);
+                }
+            }
+            0 => {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!("no data received for {:?}", timeout),
+                ))
+            }
+            _ => return Ok(()),
+        }
+    }
+}
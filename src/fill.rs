@@ -0,0 +1,16 @@
+use crate::{compare_streams, device, long_path, CompareOptions, FileDiff};
+use std::io::{self, Read};
+use std::path::Path;
+
+/// Compares a file or block device with a constant byte pattern of the same
+/// length, e.g. to verify that a disk was wiped or an image zeroed. Returns
+/// the offset of the first byte that deviates from the fill.
+pub fn compare_fill<P: AsRef<Path>>(
+    path: P,
+    byte: u8,
+    options: &CompareOptions,
+) -> io::Result<FileDiff> {
+    let input = device::Input::open(long_path::extended(path.as_ref()))?;
+    let fill = io::repeat(byte).take(input.len);
+    compare_streams(input.file, fill, options)
+}
@@ -0,0 +1,253 @@
+use crate::cloud::{self, md5_reader, uri_encode};
+use crate::http::{agent, call_error};
+use crate::{read_full, CompareOptions, FileDiff};
+use base64::Engine;
+use serde_json::Value;
+use std::env;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Fields of an object requested from the JSON API.
+const FIELDS: &str = "name,size,md5Hash,crc32c";
+
+/// A bucket and object name (or name prefix) parsed from `gs://bucket/name`.
+#[derive(Debug, Eq, PartialEq)]
+pub struct GcsUrl {
+    pub bucket: String,
+    pub name: String,
+}
+
+impl GcsUrl {
+    pub fn parse(url: &str) -> Option<GcsUrl> {
+        let rest = url.strip_prefix("gs://")?;
+        let (bucket, name) = rest.split_once('/').unwrap_or((rest, ""));
+        match bucket.is_empty() {
+            true => None,
+            false => Some(GcsUrl {
+                bucket: bucket.to_string(),
+                name: name.to_string(),
+            }),
+        }
+    }
+}
+
+pub fn is_gcs_url(path: &str) -> bool {
+    path.starts_with("gs://")
+}
+
+/// Size and checksums of an object, from its metadata or a listing.
+#[derive(Debug)]
+pub struct ObjectInfo {
+    pub name: String,
+    pub len: u64,
+    /// Base64 MD5 of the whole object, which composite objects don't have
+    pub md5_hash: Option<String>,
+    /// Base64 big-endian CRC32C of the whole object
+    pub crc32c: Option<String>,
+}
+
+impl ObjectInfo {
+    fn from_json(object: &Value) -> ObjectInfo {
+        let field = |name| object[name].as_str().map(str::to_string);
+        ObjectInfo {
+            name: field("name").unwrap_or_default(),
+            // The JSON API returns 64-bit integers as strings
+            len: object["size"]
+                .as_str()
+                .and_then(|len| len.parse().ok())
+                .unwrap_or(0),
+            md5_hash: field("md5Hash"),
+            crc32c: field("crc32c"),
+        }
+    }
+}
+
+/// Minimal Cloud Storage client for the JSON API.
+pub struct Client {
+    agent: ureq::Agent,
+    endpoint: String,
+    token: Option<String>,
+}
+
+impl Client {
+    /// Configures the client from the environment: an OAuth 2.0 access
+    /// token in `GOOGLE_OAUTH_ACCESS_TOKEN`, e.g. from
+    /// `gcloud auth print-access-token`, and an emulator's endpoint in
+    /// `STORAGE_EMULATOR_HOST`. Requests are anonymous when no token is set,
+    /// for public buckets.
+    pub fn from_env(timeout: Option<Duration>) -> Client {
+        let var = |name| env::var(name).ok().filter(|v: &String| !v.is_empty());
+        Client {
+            agent: agent(timeout),
+            endpoint: var("STORAGE_EMULATOR_HOST")
+                .map(|e| e.trim_end_matches('/').to_string())
+                .unwrap_or_else(|| "https://storage.googleapis.com".to_string()),
+            token: var("GOOGLE_OAUTH_ACCESS_TOKEN"),
+        }
+    }
+
+    /// Sends requests to a custom endpoint such as `http://localhost:4443`
+    /// instead of Google Cloud.
+    pub fn with_endpoint(mut self, endpoint: &str) -> Client {
+        self.endpoint = endpoint.trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn metadata(&self, bucket: &str, name: &str) -> io::Result<ObjectInfo> {
+        let response = self.request(bucket, Some(name), &[("fields", FIELDS.to_string())])?;
+        Ok(ObjectInfo::from_json(&json(response)?))
+    }
+
+    pub fn get(&self, bucket: &str, name: &str) -> io::Result<Box<dyn Read + Send>> {
+        let response = self.request(bucket, Some(name), &[("alt", "media".to_string())])?;
+        Ok(Box::new(response.into_reader()))
+    }
+
+    /// Lists every object under the prefix, following page tokens.
+    pub fn list(&self, bucket: &str, prefix: &str) -> io::Result<Vec<ObjectInfo>> {
+        let mut objects = vec![];
+        let mut token = None;
+        loop {
+            let mut query = vec![
+                ("prefix", prefix.to_string()),
+                ("fields", format!("items({}),nextPageToken", FIELDS)),
+            ];
+            if let Some(token) = token.take() {
+                query.push(("pageToken", token));
+            }
+            let page = json(self.request(bucket, None, &query)?)?;
+            if let Some(items) = page["items"].as_array() {
+                objects.extend(items.iter().map(ObjectInfo::from_json));
+            }
+            match page["nextPageToken"].as_str() {
+                Some(next) if !next.is_empty() => token = Some(next.to_string()),
+                _ => return Ok(objects),
+            }
+        }
+    }
+
+    fn request(
+        &self,
+        bucket: &str,
+        name: Option<&str>,
+        query: &[(&str, String)],
+    ) -> io::Result<ureq::Response> {
+        let mut url = format!(
+            "{}/storage/v1/b/{}/o",
+            self.endpoint,
+            uri_encode(bucket, true)
+        );
+        if let Some(name) = name {
+            url = format!("{}/{}", url, uri_encode(name, true));
+        }
+        let query: Vec<_> = query
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, uri_encode(value, true)))
+            .collect();
+        let url = format!("{}?{}", url, query.join("&"));
+        let mut request = self.agent.get(&url);
+        if let Some(token) = &self.token {
+            request = request.set("Authorization", &format!("Bearer {}", token));
+        }
+        request.call().map_err(|e| call_error(&url, e))
+    }
+}
+
+fn json(response: ureq::Response) -> io::Result<Value> {
+    serde_json::from_str(&response.into_string()?)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Lookup table for CRC32C (Castagnoli), reflected.
+const CRC32C_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = match crc & 1 {
+                1 => (crc >> 1) ^ 0x82f6_3b78,
+                _ => crc >> 1,
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// CRC32C of everything the reader returns.
+pub fn crc32c<R: Read>(reader: &mut R) -> io::Result<u32> {
+    let mut crc = !0u32;
+    let mut buffer = vec![0; 1 << 20];
+    loop {
+        let len = read_full(reader, &mut buffer)?;
+        for &b in &buffer[..len] {
+            crc = CRC32C_TABLE[((crc ^ b as u32) & 0xff) as usize] ^ (crc >> 8);
+        }
+        if len < buffer.len() {
+            return Ok(!crc);
+        }
+    }
+}
+
+/// Checks the object's MD5 hash, or its CRC32C for composite objects,
+/// against the local file, returning `None` when it has neither.
+pub fn checksum_matches<P: AsRef<Path>>(path: P, object: &ObjectInfo) -> io::Result<Option<bool>> {
+    let base64 = base64::engine::general_purpose::STANDARD;
+    if let Some(md5_hash) = object.md5_hash.as_deref() {
+        let (digest, _) = md5_reader(&mut File::open(path)?)?;
+        return Ok(Some(base64.encode(digest) == md5_hash));
+    }
+    match object.crc32c.as_deref() {
+        Some(checksum) => {
+            let crc = crc32c(&mut File::open(path)?)?;
+            Ok(Some(base64.encode(crc.to_be_bytes()) == checksum))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Compares an object with a local file. The sizes and checksums are
+/// checked first, and the object is only downloaded when they can't decide
+/// or the offset of the first difference is wanted.
+pub fn compare_object<P: AsRef<Path>>(
+    client: &Client,
+    url: &GcsUrl,
+    object: &ObjectInfo,
+    path: P,
+    options: &CompareOptions,
+) -> io::Result<FileDiff> {
+    cloud::compare_object(
+        object.len,
+        path,
+        options,
+        |path| checksum_matches(path, object),
+        || client.get(&url.bucket, &object.name),
+    )
+}
+
+/// Compares a local directory tree with the objects under a prefix. Local
+/// files are reported by path and objects missing locally by URL.
+pub fn compare_tree<P: AsRef<Path>>(
+    client: &Client,
+    url: &GcsUrl,
+    dir: P,
+    options: &CompareOptions,
+) -> io::Result<Vec<(PathBuf, FileDiff)>> {
+    let prefix = cloud::dir_prefix(&url.name);
+    let objects = client
+        .list(&url.bucket, &prefix)?
+        .into_iter()
+        .filter(|object| !object.name.ends_with('/'))
+        .map(|object| (object.name[prefix.len()..].to_string(), object))
+        .collect();
+    let prefix_url = format!("gs://{}/{}", url.bucket, prefix);
+    cloud::compare_tree(objects, &prefix_url, dir.as_ref(), |object, path| {
+        compare_object(client, url, object, path, options)
+    })
+}
@@ -0,0 +1,159 @@
+use crate::walk;
+use crate::{compare_streams, CompareOptions, FileDiff};
+use git2::{ErrorCode, ObjectType, Oid, Repository, TreeWalkMode, TreeWalkResult};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Mode of symlinks in git trees.
+const SYMLINK_MODE: i32 = 0o120000;
+
+/// A tree or file in a git repository, given as `git:REPO[#REV]`. REV is
+/// anything `git rev-parse` accepts and defaults to HEAD, so `v1.0:docs`
+/// selects a subdirectory and `v1.0:README.md` a single file.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GitRef {
+    pub repo: PathBuf,
+    pub rev: String,
+}
+
+impl GitRef {
+    pub fn parse(path: &str) -> Option<GitRef> {
+        let path = path.strip_prefix("git:")?;
+        let (repo, rev) = match path.rsplit_once('#') {
+            Some((repo, rev)) if !rev.is_empty() => (repo, rev),
+            Some((repo, _)) => (repo, "HEAD"),
+            None => (path, "HEAD"),
+        };
+        Some(GitRef {
+            repo: PathBuf::from(match repo {
+                "" => ".",
+                _ => repo,
+            }),
+            rev: rev.to_string(),
+        })
+    }
+
+    /// Returns the revision syntax for a path below the referenced tree,
+    /// e.g. `v1.0:src/main.rs`, for reporting.
+    pub fn object_path(&self, relative: &str) -> String {
+        match self.rev.contains(':') {
+            true => format!("{}/{}", self.rev.trim_end_matches('/'), relative),
+            false => format!("{}:{}", self.rev, relative),
+        }
+    }
+}
+
+fn git_error(e: git2::Error) -> io::Error {
+    let kind = match e.code() {
+        ErrorCode::NotFound => io::ErrorKind::NotFound,
+        _ => io::ErrorKind::Other,
+    };
+    io::Error::new(kind, e.message().to_string())
+}
+
+/// A file in the referenced tree.
+struct Blob {
+    id: Oid,
+    mode: i32,
+}
+
+/// Compares a blob's contents with a local file. Regular files are compared
+/// by their git object id first, so only differing files are read in full.
+/// Contents are compared as stored, without line ending conversion.
+fn compare_blob(
+    repo: &Repository,
+    blob: &Blob,
+    path: &Path,
+    options: &CompareOptions,
+) -> io::Result<FileDiff> {
+    let object = repo.find_blob(blob.id).map_err(git_error)?;
+    if blob.mode == SYMLINK_MODE {
+        return match fs::read_link(path) {
+            Ok(target) if target.as_os_str().as_encoded_bytes() == object.content() => {
+                Ok(FileDiff::Equal)
+            }
+            _ => Ok(FileDiff::Different(0)),
+        };
+    }
+
+    let len = fs::metadata(path)?.len();
+    if options.quick && len != object.size() as u64 {
+        return Ok(FileDiff::Different(0));
+    }
+    if Oid::hash_file(ObjectType::Blob, path).map_err(git_error)? == blob.id {
+        return Ok(FileDiff::Equal);
+    }
+    if options.quick {
+        return Ok(FileDiff::Different(0));
+    }
+    compare_streams(object.content(), File::open(path)?, options)
+}
+
+/// Compares a file in a git repository with a local file.
+pub fn compare_file<P: AsRef<Path>>(
+    git: &GitRef,
+    path: P,
+    options: &CompareOptions,
+) -> io::Result<FileDiff> {
+    let repo = Repository::open(&git.repo).map_err(git_error)?;
+    let object = repo.revparse_single(&git.rev).map_err(git_error)?;
+    let blob = object.peel_to_blob().map_err(git_error)?;
+    let blob = Blob {
+        id: blob.id(),
+        mode: 0o100644,
+    };
+    compare_blob(&repo, &blob, path.as_ref(), options)
+}
+
+/// Compares the tree of a commit, tag or tree in a git repository with a
+/// local directory tree, e.g. to check how a deployed directory drifted
+/// from a release. Results are by local path for files on disk, and by
+/// revision syntax for files only in the repository. A top-level `.git`
+/// directory is ignored, so a working tree can be checked against its
+/// commits. Submodules are skipped.
+pub fn compare_tree<P: AsRef<Path>>(
+    git: &GitRef,
+    dir: P,
+    options: &CompareOptions,
+) -> io::Result<Vec<(PathBuf, FileDiff)>> {
+    let repo = Repository::open(&git.repo).map_err(git_error)?;
+    let object = repo.revparse_single(&git.rev).map_err(git_error)?;
+    let tree = object.peel_to_tree().map_err(git_error)?;
+
+    let mut blobs = BTreeMap::new();
+    tree.walk(TreeWalkMode::PreOrder, |root, entry| {
+        if entry.kind() == Some(ObjectType::Blob) {
+            let relative = format!("{}{}", root, String::from_utf8_lossy(entry.name_bytes()));
+            let blob = Blob {
+                id: entry.id(),
+                mode: entry.filemode(),
+            };
+            blobs.insert(relative, blob);
+        }
+        TreeWalkResult::Ok
+    })
+    .map_err(git_error)?;
+
+    let mut results = vec![];
+    for (relative, path) in walk::list_files(dir.as_ref())? {
+        if relative.starts_with(".git/") {
+            continue;
+        }
+        match blobs.remove(&relative) {
+            Some(blob) => {
+                let result = compare_blob(&repo, &blob, &path, options)?;
+                results.push((path, result));
+            }
+            None => results.push((path, FileDiff::LeftOnly)),
+        }
+    }
+    for relative in blobs.into_keys() {
+        results.push((
+            PathBuf::from(git.object_path(&relative)),
+            FileDiff::RightOnly,
+        ));
+    }
+    Ok(results)
+}
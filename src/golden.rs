@@ -0,0 +1,167 @@
+use crate::mmap::compare_slices;
+use crate::report;
+use crate::{diff_at_lengths, FileDiff, Side};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Environment variable that makes golden files take the actual contents
+/// instead of checking them, e.g. `FILE_CMP_UPDATE_GOLDEN=1 cargo test`.
+pub const UPDATE_VAR: &str = "FILE_CMP_UPDATE_GOLDEN";
+
+/// Bytes of context shown on each side of the first difference.
+const CONTEXT: usize = 16;
+
+/// A file holding the expected output of a test, for snapshot testing.
+#[derive(Clone, Debug)]
+pub struct GoldenFile {
+    path: PathBuf,
+    update: bool,
+}
+
+impl GoldenFile {
+    /// Returns the golden file at the path, which is updated instead of
+    /// checked if `FILE_CMP_UPDATE_GOLDEN` is set to anything but 0.
+    pub fn new<P: AsRef<Path>>(path: P) -> GoldenFile {
+        let update =
+            std::env::var_os(UPDATE_VAR).is_some_and(|value| value != "0" && !value.is_empty());
+        GoldenFile {
+            path: path.as_ref().to_path_buf(),
+            update,
+        }
+    }
+
+    /// Sets whether to update the golden file instead of checking it,
+    /// overriding `FILE_CMP_UPDATE_GOLDEN`.
+    pub fn updating(mut self, update: bool) -> GoldenFile {
+        self.update = update;
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Checks the actual contents against the golden file, or writes them
+    /// to it when updating. Returns a description of the first difference,
+    /// or `None` if they match.
+    pub fn check(&self, actual: &[u8]) -> io::Result<Option<String>> {
+        if self.update {
+            if let Some(parent) = self.path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            report::write_atomically(&self.path, actual)?;
+            return Ok(None);
+        }
+        let expected = fs::read(&self.path).map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                format!(
+                    "{}: {} (set {}=1 to create it)",
+                    self.path.display(),
+                    e,
+                    UPDATE_VAR
+                ),
+            )
+        })?;
+        Ok(describe_difference(&expected, actual))
+    }
+
+    /// Asserts that the actual contents match the golden file, or updates
+    /// it, panicking with the first difference and the bytes around it.
+    #[track_caller]
+    pub fn assert<T: AsRef<[u8]>>(&self, actual: T) {
+        match self.check(actual.as_ref()) {
+            Ok(None) => {}
+            Ok(Some(difference)) => panic!(
+                "output doesn't match golden file {}: {}\n(set {}=1 to update it)",
+                self.path.display(),
+                difference,
+                UPDATE_VAR
+            ),
+            Err(e) => panic!("cannot check golden file: {}", e),
+        }
+    }
+}
+
+/// Asserts that two files have the same contents, panicking with the first
+/// difference and the bytes around it otherwise. Both files are read into
+/// memory, so this is meant for test fixtures.
+#[track_caller]
+pub fn assert_files_equal<P: AsRef<Path>, Q: AsRef<Path>>(expected: P, actual: Q) {
+    let (expected, actual) = (expected.as_ref(), actual.as_ref());
+    let read = |path: &Path| {
+        fs::read(path).unwrap_or_else(|e| panic!("cannot read {}: {}", path.display(), e))
+    };
+    if let Some(difference) = describe_difference(&read(expected), &read(actual)) {
+        panic!(
+            "{} and {} differ: {}",
+            expected.display(),
+            actual.display(),
+            difference
+        );
+    }
+}
+
+/// Asserts that two files have the same contents, like `assert_eq!` for
+/// files, reporting the offset, line and column of the first difference
+/// with the bytes around it.
+#[macro_export]
+macro_rules! assert_files_equal {
+    ($expected:expr, $actual:expr $(,)?) => {
+        $crate::golden::assert_files_equal($expected, $actual)
+    };
+}
+
+/// Describes the first difference between the expected and actual
+/// contents, or returns `None` if they are equal.
+pub fn describe_difference(expected: &[u8], actual: &[u8]) -> Option<String> {
+    let offset = compare_slices(expected, actual);
+    let (offset, summary) =
+        match diff_at_lengths(offset, expected.len() as u64, actual.len() as u64, false) {
+            FileDiff::Equal => return None,
+            FileDiff::Truncated(Side::Left, offset) => {
+                (offset, format!("expected ends after byte {}", offset))
+            }
+            FileDiff::Truncated(Side::Right, offset) => {
+                (offset, format!("actual ends after byte {}", offset))
+            }
+            FileDiff::Different(offset) => (offset, format!("first difference at byte {}", offset)),
+            FileDiff::LeftOnly | FileDiff::RightOnly => unreachable!("both inputs exist"),
+        };
+    let before = &expected[..offset];
+    let line = before.iter().filter(|&&b| b == b'\n').count() + 1;
+    let column = offset
+        - before
+            .iter()
+            .rposition(|&b| b == b'\n')
+            .map_or(0, |i| i + 1)
+        + 1;
+    Some(format!(
+        "{} (line {}, column {})\n  expected: {}\n    actual: {}",
+        summary,
+        line,
+        column,
+        context(expected, offset),
+        context(actual, offset)
+    ))
+}
+
+/// Shows the bytes around an offset, escaped, with `...` where more were
+/// left out.
+fn context(data: &[u8], offset: usize) -> String {
+    let start = offset.saturating_sub(CONTEXT).min(data.len());
+    let end = (offset + CONTEXT).min(data.len());
+    format!(
+        "{}\"{}\"{}",
+        match start > 0 {
+            true => "...",
+            false => "",
+        },
+        data[start..end].escape_ascii(),
+        match end < data.len() {
+            true => "...",
+            false => "",
+        }
+    )
+}
@@ -0,0 +1,83 @@
+use crate::read_full;
+use crate::throttle::{Throttle, Throttled};
+use sha2::digest::DynDigest;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+use std::str::FromStr;
+
+/// Bytes hashed per read.
+const HASH_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// A hash function for content digests.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum HashAlgorithm {
+    #[default]
+    Sha256,
+}
+
+impl HashAlgorithm {
+    pub const ALL: [HashAlgorithm; 1] = [HashAlgorithm::Sha256];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Sha256 => "sha256",
+        }
+    }
+
+    /// Length of a digest in bytes.
+    pub fn digest_len(&self) -> usize {
+        self.hasher().output_size()
+    }
+
+    pub(crate) fn hasher(&self) -> Box<dyn DynDigest> {
+        match self {
+            Self::Sha256 => Box::new(sha2::Sha256::default()),
+        }
+    }
+}
+
+impl fmt::Display for HashAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for HashAlgorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|a| a.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("unknown hash algorithm '{}'", s))
+    }
+}
+
+/// Hashes everything the reader returns in one sequential pass.
+pub fn hash_reader<R: Read>(reader: &mut R, algorithm: HashAlgorithm) -> io::Result<Vec<u8>> {
+    let mut hasher = algorithm.hasher();
+    let mut buffer = vec![0; HASH_CHUNK_SIZE];
+    loop {
+        let len = read_full(reader, &mut buffer)?;
+        hasher.update(&buffer[..len]);
+        if len < buffer.len() {
+            return Ok(hasher.finalize().to_vec());
+        }
+    }
+}
+
+/// Hashes the contents of a file, counting the reads against the throttle.
+pub fn hash_file<P: AsRef<Path>>(
+    path: P,
+    algorithm: HashAlgorithm,
+    throttle: Option<&Throttle>,
+) -> io::Result<Vec<u8>> {
+    hash_reader(&mut Throttled::new(File::open(path)?, throttle), algorithm)
+}
+
+/// Formats a digest as lowercase hex.
+pub fn to_hex(digest: &[u8]) -> String {
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
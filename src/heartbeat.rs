@@ -0,0 +1,217 @@
+use crate::hooks::Summary;
+use crate::units::format_size;
+use crate::FileDiff;
+use serde_json::{json, Value};
+use std::fmt;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// How far a run has got, updated as files are compared and read by the
+/// heartbeat.
+#[derive(Debug, Default)]
+pub struct Progress {
+    /// The file being compared and when that started
+    current: Mutex<Option<(PathBuf, Instant)>>,
+    done: AtomicU64,
+    /// Number of files in the run if known beforehand, or 0
+    total: AtomicU64,
+    /// Bytes of the files done
+    bytes: AtomicU64,
+    events: Option<EventStream>,
+}
+
+/// Where the progress events of --progress-json go, one JSON object per
+/// line.
+struct EventStream {
+    out: Mutex<Box<dyn Write + Send>>,
+    started: Instant,
+}
+
+impl fmt::Debug for EventStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventStream")
+            .field("started", &self.started)
+            .finish_non_exhaustive()
+    }
+}
+
+impl EventStream {
+    /// Writes an event as a line at once, so lines from other threads
+    /// don't cut in. A reader that went away doesn't stop the comparison.
+    fn send(&self, event: Value) {
+        let line = format!("{}\n", event);
+        let mut out = self.out.lock().unwrap();
+        let _ = out.write_all(line.as_bytes()).and_then(|_| out.flush());
+    }
+}
+
+impl Progress {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the progress like `new`, and also writes it as a stream of
+    /// events: `started`, then `file_done` for each pair of files and, when
+    /// the number of files is known, `percent` and `eta`, and `finished`.
+    pub fn with_events<W: Write + Send + 'static>(out: W) -> Self {
+        let events = EventStream {
+            out: Mutex::new(Box::new(out)),
+            started: Instant::now(),
+        };
+        events.send(json!({ "event": "started", "version": env!("CARGO_PKG_VERSION") }));
+        Self {
+            events: Some(events),
+            ..Self::default()
+        }
+    }
+
+    /// Returns the number of bytes of the files compared so far.
+    pub fn bytes(&self) -> u64 {
+        self.bytes.load(Ordering::Relaxed)
+    }
+
+    pub fn set_total(&self, total: u64) {
+        self.total.store(total, Ordering::Relaxed);
+    }
+
+    /// Records that the comparison of a file started.
+    pub fn start(&self, path: &Path) {
+        *self.current.lock().unwrap() = Some((path.to_path_buf(), Instant::now()));
+    }
+
+    /// Records that the comparison of the current file, of `bytes` bytes,
+    /// finished.
+    pub fn finish(&self, bytes: u64) {
+        *self.current.lock().unwrap() = None;
+        self.bytes.fetch_add(bytes, Ordering::Relaxed);
+        self.done.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records that the comparison of a pair of files, of `bytes` bytes,
+    /// finished with the result, and writes its events.
+    pub fn file_done(&self, path1: &Path, path2: &Path, bytes: u64, result: &io::Result<FileDiff>) {
+        self.finish(bytes);
+        let Some(events) = &self.events else {
+            return;
+        };
+        let done = self.done.load(Ordering::Relaxed);
+        let mut event = json!({
+            "event": "file_done",
+            "path1": path1.display().to_string(),
+            "path2": path2.display().to_string(),
+            "bytes": bytes,
+            "done": done,
+        });
+        match result {
+            Ok(file_diff) => {
+                event["result"] = json!(file_diff.as_desc());
+                if let FileDiff::Different(offset) | FileDiff::Truncated(_, offset) = file_diff {
+                    event["offset"] = json!(offset);
+                }
+            }
+            Err(e) => event["error"] = json!(e.to_string()),
+        }
+        events.send(event);
+
+        let total = self.total.load(Ordering::Relaxed);
+        if total == 0 {
+            return;
+        }
+        let done = done.min(total);
+        events.send(json!({
+            "event": "percent",
+            "done": done,
+            "total": total,
+            "percent": done as f64 * 100.0 / total as f64,
+        }));
+        // At the rate of the files done so far
+        let elapsed = events.started.elapsed().as_secs_f64();
+        events.send(json!({
+            "event": "eta",
+            "seconds": (elapsed / done as f64 * (total - done) as f64).round() as u64,
+        }));
+    }
+
+    /// Writes the `finished` event with the results of the run.
+    pub fn finished(&self, summary: &Summary, success: bool) {
+        if let Some(events) = &self.events {
+            events.send(json!({
+                "event": "finished",
+                "success": success,
+                "equal": summary.equal,
+                "different": summary.different,
+                "left_only": summary.left_only,
+                "right_only": summary.right_only,
+                "bytes": self.bytes.load(Ordering::Relaxed),
+                "seconds": events.started.elapsed().as_secs_f64(),
+            }));
+        }
+    }
+
+    /// Returns the one-line status after `elapsed` of the run, e.g.
+    /// `12/40 files done, 35.20 MiB/s, comparing a/b.bin for 3s`.
+    pub fn status(&self, elapsed: Duration) -> String {
+        let done = self.done.load(Ordering::Relaxed);
+        let mut status = match self.total.load(Ordering::Relaxed) {
+            0 => format!("{} files done", done),
+            total => format!("{}/{} files done", done, total),
+        };
+        let rate = self.bytes.load(Ordering::Relaxed) as f64 / elapsed.as_secs_f64().max(1e-3);
+        status.push_str(&format!(", {}/s", format_size(rate as u64)));
+        if let Some((path, since)) = &*self.current.lock().unwrap() {
+            status.push_str(&format!(
+                ", comparing {} for {}s",
+                path.display(),
+                since.elapsed().as_secs()
+            ));
+        }
+        status
+    }
+}
+
+/// A thread writing the status of a run at an interval, so that a wedged
+/// job can be told from a slow one. It stops when dropped.
+pub struct Heartbeat {
+    stop: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Heartbeat {
+    /// Starts writing a `heartbeat: ` line with the status every `interval`.
+    pub fn start<W: Write + Send + 'static>(
+        progress: Arc<Progress>,
+        interval: Duration,
+        mut out: W,
+    ) -> Heartbeat {
+        let (stop, stopped) = mpsc::channel();
+        let started = Instant::now();
+        let thread = thread::spawn(move || {
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                // Written at once, so lines from other threads don't cut in
+                let line = format!("heartbeat: {}\n", progress.status(started.elapsed()));
+                if out.write_all(line.as_bytes()).is_err() {
+                    return;
+                }
+                let _ = out.flush();
+            }
+        });
+        Heartbeat {
+            stop: Some(stop),
+            thread: Some(thread),
+        }
+    }
+}
+
+impl Drop for Heartbeat {
+    fn drop(&mut self) {
+        drop(self.stop.take());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
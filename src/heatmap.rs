@@ -0,0 +1,186 @@
+use crate::throttle::Throttled;
+use crate::units::format_size;
+use crate::{diff_at_lengths, long_path, read_full, CompareOptions, FileDiff};
+use std::fmt::Write;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+/// Bytes read from each file at a time.
+const READ_LEN: usize = 1024 * 1024;
+
+/// Bytes compared at once before looking at them one by one.
+const BLOCK_LEN: usize = 4096;
+
+/// Cells of the terminal map per row and rows, and of the SVG map.
+pub const TERMINAL_GRID: (usize, usize) = (64, 8);
+pub const SVG_GRID: (usize, usize) = (128, 32);
+
+/// Characters of the terminal map by how much of a cell differs.
+const SHADES: [char; 5] = [' ', '░', '▒', '▓', '█'];
+
+/// The differing bytes of two files counted by region, for a map of where
+/// they differ.
+#[derive(Debug, Eq, PartialEq)]
+pub struct DiffMap {
+    pub file_diff: FileDiff,
+    /// Length of the longer file, whose extra bytes all differ
+    pub len: u64,
+    pub cell_len: u64,
+    /// Differing bytes in each cell of `cell_len` bytes
+    pub counts: Vec<u64>,
+}
+
+impl DiffMap {
+    fn len_of(&self, cell: usize) -> u64 {
+        let start = cell as u64 * self.cell_len;
+        self.cell_len.min(self.len - start)
+    }
+
+    /// How much of a cell differs: 0 for nothing, 1 for some bytes, 2 for
+    /// over a tenth, 3 for over half and 4 for all of them.
+    pub fn level(&self, cell: usize) -> usize {
+        let (count, len) = (self.counts[cell], self.len_of(cell));
+        match count {
+            0 => 0,
+            _ if count == len => 4,
+            _ if count * 2 > len => 3,
+            _ if count * 10 > len => 2,
+            _ => 1,
+        }
+    }
+
+    /// Draws the map with a row of block characters per `columns` cells,
+    /// each starting with its offset.
+    pub fn render_terminal(&self, columns: usize) -> String {
+        let mut out = format!(
+            "Each block is {}: {} some bytes differ, {} over 10%, {} over 50%, {} all\n",
+            format_size(self.cell_len),
+            SHADES[1],
+            SHADES[2],
+            SHADES[3],
+            SHADES[4]
+        );
+        for (row, cells) in (0..self.counts.len())
+            .collect::<Vec<_>>()
+            .chunks(columns)
+            .enumerate()
+        {
+            let offset = (row * columns) as u64 * self.cell_len;
+            let line: String = cells.iter().map(|&cell| SHADES[self.level(cell)]).collect();
+            let _ = writeln!(out, "{:>14} |{:<width$}|", offset, line, width = columns);
+        }
+        out
+    }
+
+    /// Draws the map as an SVG image with a square per cell, in rows of
+    /// `columns` cells.
+    pub fn render_svg(&self, columns: usize) -> String {
+        const CELL: usize = 8;
+        const COLORS: [&str; 5] = ["#f2f2f2", "#fdd49e", "#fc8d59", "#e34a33", "#b30000"];
+        let rows = self.counts.len().div_ceil(columns);
+        let (width, height) = (columns * CELL, rows * CELL);
+        let mut out = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">\n",
+            width, height, width, height
+        );
+        let _ = writeln!(
+            out,
+            "<title>Differing bytes of {} in cells of {}</title>",
+            format_size(self.len),
+            format_size(self.cell_len)
+        );
+        for cell in 0..self.counts.len() {
+            let (x, y) = ((cell % columns) * CELL, (cell / columns) * CELL);
+            let start = cell as u64 * self.cell_len;
+            let _ = writeln!(
+                out,
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"><title>{}-{}: {} bytes differ</title></rect>",
+                x,
+                y,
+                CELL,
+                CELL,
+                COLORS[self.level(cell)],
+                start,
+                start + self.len_of(cell) - 1,
+                self.counts[cell]
+            );
+        }
+        out.push_str("</svg>\n");
+        out
+    }
+}
+
+/// Compares two files to the end, counting their differing bytes in about
+/// `cells` regions of the longer one.
+pub fn diff_map<P: AsRef<Path>>(
+    path1: P,
+    path2: P,
+    cells: usize,
+    options: &CompareOptions,
+) -> io::Result<DiffMap> {
+    let open = |path: &Path| -> io::Result<_> {
+        let file = File::open(long_path::extended(path))?;
+        let len = file.metadata()?.len();
+        Ok((Throttled::new(file, options.throttle.as_deref()), len))
+    };
+    let (mut file1, len1) = open(path1.as_ref())?;
+    let (mut file2, len2) = open(path2.as_ref())?;
+    let len = len1.max(len2);
+    let cell_len = len.div_ceil(cells.max(1) as u64).max(1);
+    let mut counts = vec![0; len.div_ceil(cell_len) as usize];
+    let mut count = |offset: u64, differing: u64| {
+        // A run of differing bytes may span cells
+        let (mut offset, mut left) = (offset, differing);
+        while left > 0 {
+            let cell = (offset / cell_len) as usize;
+            let n = left.min((cell as u64 + 1) * cell_len - offset);
+            counts[cell] += n;
+            offset += n;
+            left -= n;
+        }
+    };
+
+    let (mut buffer1, mut buffer2) = (vec![0; READ_LEN], vec![0; READ_LEN]);
+    let (mut offset, mut first_diff) = (0u64, None);
+    loop {
+        let read1 = read_full(&mut file1, &mut buffer1)?;
+        let read2 = read_full(&mut file2, &mut buffer2)?;
+        let both = read1.min(read2);
+        for (i, (block1, block2)) in buffer1[..both]
+            .chunks(BLOCK_LEN)
+            .zip(buffer2[..both].chunks(BLOCK_LEN))
+            .enumerate()
+        {
+            if block1 == block2 {
+                continue;
+            }
+            let start = offset + (i * BLOCK_LEN) as u64;
+            for (j, _) in block1
+                .iter()
+                .zip(block2)
+                .enumerate()
+                .filter(|(_, (a, b))| a != b)
+            {
+                first_diff.get_or_insert(start + j as u64);
+                count(start + j as u64, 1);
+            }
+        }
+        // Bytes only one of the files has
+        let extra = read1.max(read2) - both;
+        if extra > 0 {
+            first_diff.get_or_insert(offset + both as u64);
+            count(offset + both as u64, extra as u64);
+        }
+        offset += read1.max(read2) as u64;
+        if read1 < READ_LEN && read2 < READ_LEN {
+            break;
+        }
+    }
+    Ok(DiffMap {
+        file_diff: diff_at_lengths(first_diff, len1, len2, false),
+        len,
+        cell_len,
+        counts,
+    })
+}
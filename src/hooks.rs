@@ -0,0 +1,112 @@
+use crate::FileDiff;
+use serde_json::{json, Value};
+use std::io::{self, Write};
+use std::process::{Command, ExitStatus, Stdio};
+
+/// Shell commands run around a comparison, e.g. to mount a snapshot before
+/// and unmount it and send a notification after.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Hooks {
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+/// Counts of the results of a run, passed to the after hook.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Summary {
+    pub equal: u64,
+    pub different: u64,
+    pub left_only: u64,
+    pub right_only: u64,
+}
+
+impl Summary {
+    pub const fn new() -> Self {
+        Self {
+            equal: 0,
+            different: 0,
+            left_only: 0,
+            right_only: 0,
+        }
+    }
+
+    pub fn add(&mut self, file_diff: &FileDiff) {
+        match file_diff {
+            FileDiff::Equal => self.equal += 1,
+            FileDiff::Different(_) | FileDiff::Truncated(..) => self.different += 1,
+            FileDiff::LeftOnly => self.left_only += 1,
+            FileDiff::RightOnly => self.right_only += 1,
+        }
+    }
+
+    /// Returns the number of files that differ or are on one side only.
+    pub fn differing(&self) -> u64 {
+        self.different + self.left_only + self.right_only
+    }
+
+    /// Returns the summary as JSON, with whether the run succeeded.
+    pub fn to_json(&self, success: bool) -> Value {
+        json!({
+            "equal": self.equal,
+            "different": self.different,
+            "left_only": self.left_only,
+            "right_only": self.right_only,
+            "success": success,
+        })
+    }
+}
+
+fn shell(command: &str) -> Command {
+    #[cfg(windows)]
+    {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C").arg(command);
+        shell
+    }
+    #[cfg(not(windows))]
+    {
+        let mut shell = Command::new("sh");
+        shell.arg("-c").arg(command);
+        shell
+    }
+}
+
+fn check(command: &str, status: ExitStatus) -> io::Result<()> {
+    match status.success() {
+        true => Ok(()),
+        false => Err(io::Error::other(format!(
+            "hook '{}' failed with {}",
+            command, status
+        ))),
+    }
+}
+
+/// Runs the before hook with the shell, failing if it does.
+pub fn run_before(command: &str) -> io::Result<()> {
+    check(command, shell(command).stdin(Stdio::null()).status()?)
+}
+
+/// Runs the after hook with the shell. The summary is passed in the
+/// FILE_CMP_RESULT_* environment variables (EQUAL, DIFFERENT, LEFT_ONLY,
+/// RIGHT_ONLY and SUCCESS, which is 1 or 0) and as JSON on stdin.
+pub fn run_after(command: &str, summary: &Summary, success: bool) -> io::Result<()> {
+    let mut child = shell(command)
+        .env("FILE_CMP_RESULT_EQUAL", summary.equal.to_string())
+        .env("FILE_CMP_RESULT_DIFFERENT", summary.different.to_string())
+        .env("FILE_CMP_RESULT_LEFT_ONLY", summary.left_only.to_string())
+        .env("FILE_CMP_RESULT_RIGHT_ONLY", summary.right_only.to_string())
+        .env("FILE_CMP_RESULT_SUCCESS", (success as u8).to_string())
+        .stdin(Stdio::piped())
+        .spawn()?;
+    // Closes stdin after writing, so the hook sees the end of it
+    let written = {
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        writeln!(stdin, "{}", summary.to_json(success))
+    };
+    let status = child.wait()?;
+    match written {
+        // Hooks that don't read stdin may exit before it is written
+        Err(e) if e.kind() != io::ErrorKind::BrokenPipe => Err(e),
+        _ => check(command, status),
+    }
+}
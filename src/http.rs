@@ -0,0 +1,125 @@
+use crate::{compare_streams, device, diff_at_lengths, fifo, CompareOptions, FileDiff};
+use std::io::{self, Read};
+use std::path::Path;
+use std::time::Duration;
+
+/// Returns true for `http://` and `https://` URLs.
+pub fn is_url(path: &str) -> bool {
+    let scheme = path.split_once("://").map(|(scheme, _)| scheme);
+    matches!(scheme, Some(s) if s.eq_ignore_ascii_case("http") || s.eq_ignore_ascii_case("https"))
+}
+
+/// The body of a GET request, and its length if the server sent one.
+pub struct Response {
+    pub reader: Box<dyn Read + Send>,
+    pub len: Option<u64>,
+}
+
+/// Starts downloading the URL. The timeout applies to connecting and to
+/// each read of the body.
+#[cfg(feature = "http")]
+pub fn get(url: &str, timeout: Option<Duration>) -> io::Result<Response> {
+    let response = agent(timeout)
+        .get(url)
+        .call()
+        .map_err(|e| call_error(url, e))?;
+    let len = response
+        .header("Content-Length")
+        .and_then(|len| len.parse().ok());
+    Ok(Response {
+        reader: Box::new(response.into_reader()),
+        len,
+    })
+}
+
+#[cfg(feature = "http")]
+pub(crate) fn agent(timeout: Option<Duration>) -> ureq::Agent {
+    let mut agent = ureq::AgentBuilder::new();
+    if let Some(timeout) = timeout {
+        agent = agent.timeout_connect(timeout).timeout_read(timeout);
+    }
+    agent.build()
+}
+
+#[cfg(feature = "http")]
+pub(crate) fn call_error(url: &str, e: ureq::Error) -> io::Error {
+    match e {
+        ureq::Error::Status(404, _) => {
+            io::Error::new(io::ErrorKind::NotFound, format!("{} was not found", url))
+        }
+        ureq::Error::Status(code, response) => io::Error::other(format!(
+            "{} returned HTTP {} {}",
+            url,
+            code,
+            response.status_text()
+        )),
+        ureq::Error::Transport(e) => io::Error::other(e),
+    }
+}
+
+#[cfg(not(feature = "http"))]
+pub fn get(url: &str, _timeout: Option<Duration>) -> io::Result<Response> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("cannot fetch {}: built without the http feature", url),
+    ))
+}
+
+/// Compares the body of a URL with a local file while it downloads, without
+/// storing it. The Content-Length, when sent, is used for the same size
+/// checks as between two files.
+pub fn compare_url<P: AsRef<Path>>(
+    url: &str,
+    path: P,
+    options: &CompareOptions,
+) -> io::Result<FileDiff> {
+    let input = device::Input::open(path)?;
+    let response = get(url, options.timeout)?;
+
+    let local_len = match input.meta.as_ref().is_some_and(fifo::is_fifo) {
+        true => None,
+        false => Some(input.len),
+    };
+    let lens = response.len.zip(local_len);
+    if let Some((len1, len2)) = lens {
+        if len1 == 0 || len2 == 0 {
+            let offset = (len1 != len2).then_some(0);
+            return Ok(diff_at_lengths(offset, len1, len2, options.quick));
+        }
+        if options.quick && len1 != len2 {
+            return Ok(FileDiff::Different(0));
+        }
+    }
+
+    let file = fifo::TimeoutReader::new(input.file, options.timeout);
+    let result = compare_streams(response.reader, file, options)?;
+    Ok(finish_at_lengths(result, lens, options))
+}
+
+/// Compares the bodies of two URLs while they download.
+pub fn compare_urls(url1: &str, url2: &str, options: &CompareOptions) -> io::Result<FileDiff> {
+    let response1 = get(url1, options.timeout)?;
+    let response2 = get(url2, options.timeout)?;
+    let lens = response1.len.zip(response2.len);
+    if options.quick && lens.is_some_and(|(a, b)| a != b) {
+        return Ok(FileDiff::Different(0));
+    }
+    let result = compare_streams(response1.reader, response2.reader, options)?;
+    Ok(finish_at_lengths(result, lens, options))
+}
+
+/// Tells a body or file that is a prefix of the other apart from one that
+/// differs in its contents, when both lengths are known, as between two
+/// files.
+fn finish_at_lengths(
+    result: FileDiff,
+    lens: Option<(u64, u64)>,
+    options: &CompareOptions,
+) -> FileDiff {
+    match (result, lens) {
+        (FileDiff::Different(offset), Some((len1, len2))) => {
+            diff_at_lengths(Some(offset as u64), len1, len2, options.quick)
+        }
+        (result, _) => result,
+    }
+}
@@ -0,0 +1,60 @@
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Returns whether Ctrl-C was pressed since `catch_interrupt`.
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::Relaxed)
+}
+
+/// Catches Ctrl-C so that a long run can check `interrupted` and stop
+/// cleanly, keeping its partial results. Pressing it a second time ends the
+/// process right away.
+#[cfg(unix)]
+pub fn catch_interrupt() -> io::Result<()> {
+    extern "C" fn on_interrupt(_signal: libc::c_int) {
+        if INTERRUPTED.swap(true, Ordering::Relaxed) {
+            unsafe { libc::_exit(128 + libc::SIGINT) };
+        }
+    }
+
+    let handler = on_interrupt as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    match unsafe { libc::signal(libc::SIGINT, handler) } {
+        libc::SIG_ERR => Err(io::Error::last_os_error()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(windows)]
+pub fn catch_interrupt() -> io::Result<()> {
+    const CTRL_C_EVENT: u32 = 0;
+    const CTRL_BREAK_EVENT: u32 = 1;
+
+    type HandlerRoutine = unsafe extern "system" fn(ctrl_type: u32) -> i32;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn SetConsoleCtrlHandler(handler: Option<HandlerRoutine>, add: i32) -> i32;
+    }
+
+    // Returning 0 passes the event on to the default handler, which ends
+    // the process
+    unsafe extern "system" fn on_interrupt(ctrl_type: u32) -> i32 {
+        let ctrl_c = ctrl_type == CTRL_C_EVENT || ctrl_type == CTRL_BREAK_EVENT;
+        (ctrl_c && !INTERRUPTED.swap(true, Ordering::Relaxed)) as i32
+    }
+
+    match unsafe { SetConsoleCtrlHandler(Some(on_interrupt), 1) } {
+        0 => Err(io::Error::last_os_error()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn catch_interrupt() -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "catching Ctrl-C is not supported on this platform",
+    ))
+}
@@ -1,137 +1,870 @@
-use std::fs::{self, File};
-use std::io::{self, BufReader, Read};
-use std::path::{Path, PathBuf};
-
-#[derive(Debug, Eq, PartialEq)]
-pub enum FileDiff {
-    Equal,
-    Different(usize),
-    LeftOnly,
-    RightOnly,
-}
-
-impl FileDiff {
-    pub fn as_number(&self) -> String {
-        match self {
-            Self::Equal => "-1".to_string(),
-            Self::Different(d @ _) => format!("{}", d),
-            Self::LeftOnly => "-2".to_string(),
-            Self::RightOnly => "-3".to_string(),
-        }
-    }
-
-    pub fn as_desc(&self) -> &'static str {
-        match self {
-            Self::Equal => "equal",
-            Self::Different(_) => "diff",
-            Self::LeftOnly => "left only",
-            Self::RightOnly => "right only",
-        }
-    }
-}
-
-pub fn is_dir<P: AsRef<Path>>(path1: P) -> io::Result<bool> {
-    let file1_meta = fs::metadata(&path1)?;
-    Ok(file1_meta.is_dir())
-}
-
-pub fn compare_files<P: AsRef<Path>>(path1: P, path2: P, quick: bool) -> io::Result<FileDiff> {
-    let file1_meta = fs::metadata(&path1)?;
-    let file2_meta = fs::metadata(&path2)?;
-
-    if file1_meta.len() == 0 || file2_meta.len() == 0 {
-        return match file1_meta.len() == file2_meta.len() {
-            true => Ok(FileDiff::Equal),
-            false => Ok(FileDiff::Different(0)),
-        };
-    }
-
-    if quick && file1_meta.len() != file2_meta.len() {
-        return Ok(FileDiff::Different(0));
-    }
-
-    let mut file1 = BufReader::new(File::open(path1)?);
-    let mut file2 = BufReader::new(File::open(path2)?);
-
-    let mut buffer1 = [0; 4096];
-    let mut buffer2 = [0; 4096];
-    let mut pos = 0;
-
-    loop {
-        let len1 = file1.read(&mut buffer1)?;
-        let len2 = file2.read(&mut buffer2)?;
-
-        if len1 == 0 && len2 == 0 {
-            return Ok(FileDiff::Equal);
-        }
-
-        if buffer1[..len1] != buffer2[..len2] {
-            if quick {
-                return Ok(FileDiff::Different(0));
-            }
-            for i in 0..len1 {
-                if buffer1[i] != buffer2[i] {
-                    return Ok(FileDiff::Different(pos + i));
-                }
-            }
-        }
-
-        pos += len1;
-    }
-}
-
-pub fn compare_dirs<P: AsRef<Path>>(dir1: P, dir2: P, quick: bool) -> Vec<(PathBuf, FileDiff)> {
-    let mut results = vec![];
-
-    for entry in fs::read_dir(&dir1).expect("Failed to read directory") {
-        let entry = entry.expect("Failed to read directory entry");
-        let path = entry.path();
-
-        if path.is_dir() {
-            let other_path = dir2
-                .as_ref()
-                .join(path.file_name().expect("Failed to get filename"));
-            if other_path.is_dir() {
-                results.extend(compare_dirs(&path, &other_path, quick));
-            } else {
-                results.push((path, FileDiff::LeftOnly));
-            }
-        } else {
-            let other_path = dir2
-                .as_ref()
-                .join(path.file_name().expect("Failed to get filename"));
-            if other_path.exists() {
-                match compare_files(&path, &other_path, quick) {
-                    Ok(result @ _) => results.push((path, result)),
-                    Err(e) => eprintln!("Error: {}", e),
-                }
-            } else {
-                results.push((path, FileDiff::LeftOnly));
-            }
-        }
-    }
-
-    for entry in fs::read_dir(dir2).expect("Failed to read directory") {
-        let entry = entry.expect("Failed to read directory entry");
-        let path = entry.path();
-        if path.is_dir() {
-            let other_path = dir1
-                .as_ref()
-                .join(path.file_name().expect("Failed to get filename"));
-            if other_path.is_dir() {
-                results.extend(compare_dirs(&other_path, &path, quick));
-            } else {
-                results.push((path, FileDiff::RightOnly));
-            }
-        } else {
-            let other_path = dir1
-                .as_ref()
-                .join(path.file_name().expect("Failed to get filename"));
-            if !other_path.exists() {
-                results.push((path, FileDiff::RightOnly));
-            }
-        }
-    }
-
-    results
-}
+use std::fs::{self, File, Metadata};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::ops::{ControlFlow, Range};
+use std::path::{Path, PathBuf};
+
+pub mod agent;
+pub mod attributes;
+#[cfg(feature = "azure")]
+pub mod azure;
+pub mod batch;
+pub mod bench;
+pub mod blank;
+pub mod capability;
+pub mod cas;
+pub mod cdc;
+pub mod chunk;
+#[cfg(any(feature = "s3", feature = "azure", feature = "gcs"))]
+mod cloud;
+pub mod config;
+pub mod daemon;
+pub mod delta;
+pub mod device;
+pub mod doctor;
+pub mod evidence;
+pub mod exec;
+pub mod external;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod fifo;
+pub mod fill;
+#[cfg(feature = "gcs")]
+pub mod gcs;
+#[cfg(feature = "git")]
+pub mod git;
+pub mod golden;
+pub mod hash;
+pub mod heartbeat;
+pub mod heatmap;
+pub mod hooks;
+pub mod http;
+pub mod interrupt;
+pub mod lines;
+pub mod long_path;
+pub mod media;
+pub mod memory;
+pub mod mmap;
+#[cfg(feature = "oci")]
+pub mod oci;
+#[cfg(feature = "office")]
+pub mod office;
+#[cfg(feature = "otel")]
+pub mod otel;
+pub mod parallel;
+pub mod paranoid;
+#[cfg(feature = "parquet")]
+pub mod parquet;
+#[cfg(feature = "pdf")]
+pub mod pdf;
+pub mod pipeline;
+pub mod policy;
+pub mod prefetch;
+pub mod priority;
+pub mod quorum;
+pub mod reflink;
+pub mod report;
+pub mod retry;
+pub mod rpc;
+#[cfg(feature = "s3")]
+pub mod s3;
+pub mod same_file;
+pub mod selinux;
+pub mod serve;
+pub mod sidecar;
+pub mod similar;
+pub mod sparse;
+pub mod sqldump;
+pub mod ssh;
+pub mod strategy;
+pub mod suffix;
+pub mod swap;
+pub mod throttle;
+pub mod tool;
+#[cfg(feature = "torrent")]
+pub mod torrent;
+pub mod units;
+pub mod vfs;
+pub mod walk;
+pub mod wav;
+pub mod xattr;
+
+use chunk::ChunkSizer;
+use serde::ser::{SerializeMap, Serializer};
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::Duration;
+use throttle::{Throttle, Throttled};
+
+/// One of the two compared inputs.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FileDiff {
+    Equal,
+    Different(usize),
+    /// The input on one side ends after the given number of bytes, all of
+    /// which match the other one, e.g. after an interrupted copy
+    Truncated(Side, usize),
+    LeftOnly,
+    RightOnly,
+}
+
+impl FileDiff {
+    /// Returns the result of comparing the inputs the other way around.
+    pub fn swap_sides(self) -> FileDiff {
+        match self {
+            Self::Truncated(Side::Left, offset) => Self::Truncated(Side::Right, offset),
+            Self::Truncated(Side::Right, offset) => Self::Truncated(Side::Left, offset),
+            Self::LeftOnly => Self::RightOnly,
+            Self::RightOnly => Self::LeftOnly,
+            file_diff => file_diff,
+        }
+    }
+
+    pub fn as_number(&self) -> String {
+        match self {
+            Self::Equal => "-1".to_string(),
+            Self::Different(d) | Self::Truncated(_, d) => format!("{}", d),
+            Self::LeftOnly => "-2".to_string(),
+            Self::RightOnly => "-3".to_string(),
+        }
+    }
+
+    pub fn as_desc(&self) -> &'static str {
+        match self {
+            Self::Equal => "equal",
+            Self::Different(_) => "diff",
+            Self::Truncated(Side::Left, _) => "eof on left",
+            Self::Truncated(Side::Right, _) => "eof on right",
+            Self::LeftOnly => "left only",
+            Self::RightOnly => "right only",
+        }
+    }
+
+    /// Returns the status of the result as printed by --porcelain.
+    pub fn as_status(&self) -> &'static str {
+        match self {
+            Self::Equal => "equal",
+            Self::Different(_) => "differ",
+            Self::Truncated(Side::Left, _) => "eof-left",
+            Self::Truncated(Side::Right, _) => "eof-right",
+            Self::LeftOnly => "left-only",
+            Self::RightOnly => "right-only",
+        }
+    }
+
+    /// Returns the offset of the first difference, if the inputs differ in
+    /// their content.
+    pub fn offset(&self) -> Option<usize> {
+        match self {
+            Self::Different(offset) | Self::Truncated(_, offset) => Some(*offset),
+            _ => None,
+        }
+    }
+}
+
+/// Serializes as `{"status": "diff", "offset": 42}`, with the status as in
+/// `as_desc` and the offset only if the inputs differ in their content.
+impl Serialize for FileDiff {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let offset = self.offset();
+        let mut map = serializer.serialize_map(Some(1 + offset.is_some() as usize))?;
+        map.serialize_entry("status", self.as_desc())?;
+        if let Some(offset) = offset {
+            map.serialize_entry("offset", &offset)?;
+        }
+        map.end()
+    }
+}
+
+/// The result of comparing a pair of files, by the path it is reported
+/// under, as printed by --json.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct PathDiff {
+    pub path: String,
+    #[serde(flatten)]
+    pub diff: FileDiff,
+}
+
+/// How thoroughly files are compared, from every byte of them down to their
+/// metadata. More may be added, so matches on it need a wildcard arm.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum Policy {
+    /// Compare them byte by byte, finding the offset of the first difference
+    #[default]
+    ByteExact,
+    /// Only compare their sizes and hashes, without looking for the offset
+    /// of a difference
+    HashOnly,
+    /// Only compare their sizes, without reading them: files of the same
+    /// size are taken to be equal and the others differ at offset 0
+    SizeOnly,
+    /// Only compare their sizes and modification times, like the quick check
+    /// of rsync
+    SizeAndMtime,
+    /// Only compare their sizes, modification times and permissions
+    MetadataOnly,
+}
+
+impl Policy {
+    /// Returns whether the contents of files are read.
+    pub fn reads_data(&self) -> bool {
+        matches!(self, Self::ByteExact | Self::HashOnly)
+    }
+}
+
+/// Options controlling how files are compared.
+#[derive(Clone, Debug, Default)]
+pub struct CompareOptions {
+    /// Only determine whether files differ, not the offset of the first difference
+    pub quick: bool,
+    /// How thoroughly to compare files
+    pub policy: Policy,
+    /// Number of threads comparing ranges of a single large file in parallel
+    /// (0 or 1 to compare sequentially)
+    pub threads: usize,
+    /// Number of pairs of files of a directory comparison compared at once
+    /// (0 or 1 to compare one at a time)
+    pub jobs: usize,
+    /// Bytes to read from each file per step, or `None` to size chunks adaptively
+    pub chunk_size: Option<usize>,
+    /// Number of chunks read ahead of the comparison on separate threads
+    /// (0 to read and compare on the same thread)
+    pub pipeline_depth: usize,
+    /// Limit on the combined read throughput, shared by every comparison
+    /// using these options
+    pub throttle: Option<Arc<Throttle>>,
+    /// Map the files into memory instead of reading them (ignores the throttle)
+    pub mmap: bool,
+    /// Choose between reading, mapping and parallel ranges for each pair of
+    /// files by their size, filesystem and the memory available, instead of
+    /// `mmap` and `threads`
+    pub auto_io: bool,
+    /// Limit on the memory of the read buffers, which automatic choices are
+    /// scaled down to
+    pub max_memory: Option<u64>,
+    /// Hash both files concurrently first and only compare them byte by byte
+    /// if the hashes differ and the offset of the difference is wanted
+    pub hash_first: bool,
+    /// Hash function used when hashing files
+    pub hash: hash::HashAlgorithm,
+    /// Fold runs of blank (0x00/0xFF) blocks when hashing files first instead
+    /// of hashing every byte, e.g. for mostly empty disk images
+    pub skip_blank: bool,
+    /// Give up when a pipe delivers no data for this long, or `None` to wait
+    /// indefinitely
+    pub timeout: Option<Duration>,
+    /// External programs to compare files of some types with instead
+    pub comparers: Option<Arc<external::Comparers>>,
+    /// Times to compare a pair of files again from the start after a
+    /// transient read error, e.g. EIO from a network filesystem
+    pub retries: u32,
+    /// Read each file of a pair twice more after comparing it, the second
+    /// time as this says, and fail if the reads return different data, e.g.
+    /// to catch flaky storage or RAM when verifying archives
+    pub paranoid: Option<paranoid::Reread>,
+    /// Trust the checksum in a .sha256, .md5 or .sfv file next to either
+    /// file of a pair instead of reading that file, and fail on files read
+    /// that no longer match their own
+    pub sidecars: bool,
+    /// Treat files as equal if one is a prefix of the other and the rest of
+    /// the longer one is this padding
+    pub trailing_padding: Option<blank::Padding>,
+    /// Compare MP3, FLAC and MP4 files by their audio and video data only,
+    /// ignoring their tags
+    pub media: bool,
+    /// How far the integer samples of WAV files compared as media may be
+    /// apart, in least significant bits
+    pub sample_tolerance: u32,
+    /// Compare Office Open XML documents by their parts, ignoring what a save
+    /// without edits changes (needs the `office` feature)
+    pub office: bool,
+    /// Compare SQL dumps by their statements, ignoring the comments with
+    /// versions and times and the order of the session settings
+    pub sql_dump: bool,
+    /// Compare files line by line without what this regex matches at the
+    /// start of each line, e.g. the timestamps of logs
+    pub strip_prefix: Option<regex::bytes::Regex>,
+    /// Swap the bytes of each word of this many bytes of the second file
+    /// before comparing, e.g. 2 or 4 for raw data of a big-endian device
+    pub word_swap: Option<usize>,
+    /// How directory comparisons treat links, including junctions and mount
+    /// points on Windows
+    pub links: walk::Links,
+    /// When directory comparisons list each directory with the metadata of
+    /// its entries up front, e.g. to save round trips on network shares
+    pub prefetch: prefetch::Prefetch,
+    /// How to compare files by pattern, from the configuration files
+    pub policies: Option<Arc<policy::Policies>>,
+    /// Progress to record the files compared in, e.g. for a heartbeat
+    pub progress: Option<Arc<heartbeat::Progress>>,
+    /// Exporter to record a span per pair of files compared with
+    #[cfg(feature = "otel")]
+    pub tracer: Option<Arc<otel::Tracer>>,
+}
+
+pub fn is_dir<P: AsRef<Path>>(path1: P) -> io::Result<bool> {
+    let file1_meta = fs::metadata(&path1)?;
+    Ok(file1_meta.is_dir())
+}
+
+/// Compares two files as thoroughly as `policy` says, with the other options
+/// at their defaults.
+pub fn compare_files<P: AsRef<Path>>(path1: P, path2: P, policy: Policy) -> io::Result<FileDiff> {
+    let options = CompareOptions {
+        policy,
+        ..Default::default()
+    };
+    compare_files_with(path1, path2, &options)
+}
+
+pub fn compare_files_with<P: AsRef<Path>>(
+    path1: P,
+    path2: P,
+    options: &CompareOptions,
+) -> io::Result<FileDiff> {
+    compare_files_listed(path1.as_ref(), path2.as_ref(), None, options)
+}
+
+/// Compares two files like `compare_files_with`, given their metadata if it
+/// was listed with their directories, which settles some comparisons
+/// without opening the files.
+pub(crate) fn compare_files_listed(
+    path1: &Path,
+    path2: &Path,
+    metas: Option<[&Metadata; 2]>,
+    options: &CompareOptions,
+) -> io::Result<FileDiff> {
+    if let Some(progress) = &options.progress {
+        progress.start(path1);
+    }
+    #[cfg(feature = "otel")]
+    let started = std::time::SystemTime::now();
+    let settled = metas.and_then(|[meta1, meta2]| settle(path1, meta1, meta2, options));
+    let result = match settled {
+        Some(result) => result,
+        None => compare_by_policy(path1, path2, options),
+    };
+    let result = match (result, options.paranoid) {
+        (Ok(result), Some(reread)) if options.policy.reads_data() => {
+            check_rereads(path1, reread, options)
+                .and_then(|()| check_rereads(path2, reread, options))
+                .map(|()| result)
+        }
+        (result, _) => result,
+    };
+    let len = || fs::metadata(path1).map_or(0, |meta| meta.len());
+    if let Some(progress) = &options.progress {
+        progress.file_done(path1, path2, len(), &result);
+    }
+    #[cfg(feature = "otel")]
+    if let Some(tracer) = &options.tracer {
+        tracer.record(path1, path2, &result, started, len());
+    }
+    result
+}
+
+/// Compares two files as thoroughly as the policy of the options says.
+fn compare_by_policy(path1: &Path, path2: &Path, options: &CompareOptions) -> io::Result<FileDiff> {
+    match (options.policy, options.trailing_padding) {
+        (Policy::ByteExact, Some(padding)) => compare_padded(path1, path2, padding, options),
+        (Policy::ByteExact, None) => retry::with_retries(options.retries, || {
+            compare_files_once(path1, path2, options)
+        }),
+        (Policy::HashOnly, _) => {
+            retry::with_retries(options.retries, || compare_hashes(path1, path2, options))
+        }
+        (policy, _) => compare_metadata(path1, path2, policy),
+    }
+}
+
+/// Returns whether comparisons with these options may be settled by the
+/// metadata of the files alone, so it is worth listing with directories.
+pub(crate) fn metadata_settles(options: &CompareOptions) -> bool {
+    options.quick || options.policy != Policy::ByteExact
+}
+
+/// Settles a comparison by the metadata of both files where that is all it
+/// would look at: for the policies that don't read data, and for regular
+/// files of different sizes that are compared by their hashes or quickly
+/// byte by byte. Returns `None` where the files have to be opened.
+fn settle(
+    path1: &Path,
+    meta1: &Metadata,
+    meta2: &Metadata,
+    options: &CompareOptions,
+) -> Option<io::Result<FileDiff>> {
+    if !meta1.is_file() || !meta2.is_file() {
+        return None;
+    }
+    let sizes_differ = meta1.len() != meta2.len();
+    match options.policy {
+        Policy::ByteExact => (sizes_differ && options.quick && compares_bytes(path1, options))
+            .then_some(Ok(FileDiff::Different(0))),
+        Policy::HashOnly => sizes_differ.then_some(Ok(FileDiff::Different(0))),
+        policy => Some(metadata_diff(meta1, meta2, policy)),
+    }
+}
+
+/// Returns whether `compare_files_once` compares a file by its bytes, as
+/// the other ways of comparing files may find files of different sizes
+/// equal.
+fn compares_bytes(path1: &Path, options: &CompareOptions) -> bool {
+    let semantic = options.policies.as_ref().and_then(|p| p.get(path1));
+    #[cfg(feature = "office")]
+    if options.office && office::is_office(path1) {
+        return false;
+    }
+    semantic != Some(policy::Policy::Semantic)
+        && options.trailing_padding.is_none()
+        && options
+            .comparers
+            .as_ref()
+            .and_then(|c| c.get(path1))
+            .is_none()
+        && !(options.sql_dump && sqldump::is_sql_dump(path1))
+        && !(options.media && media::Format::of(path1).is_some())
+        && options.strip_prefix.is_none()
+        && options.word_swap.is_none()
+        && !options.sidecars
+}
+
+/// Reads a file twice for --paranoid, failing if the reads differ.
+fn check_rereads(
+    path: &Path,
+    reread: paranoid::Reread,
+    options: &CompareOptions,
+) -> io::Result<()> {
+    let throttle = options.throttle.as_deref();
+    match paranoid::check_rereads(path, reread, options.hash, throttle)? {
+        Some(offset) => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "reads of {} returned different data in the block at offset {}",
+                path.display(),
+                offset
+            ),
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Compares files that may be padded to different sizes: where one is a
+/// prefix of the other, the first byte of the rest of the longer one that
+/// isn't padding is the difference.
+fn compare_padded(
+    path1: &Path,
+    path2: &Path,
+    padding: blank::Padding,
+    options: &CompareOptions,
+) -> io::Result<FileDiff> {
+    // Files of different sizes may still be equal, so quick mode can't
+    // stop at the sizes
+    let exact = CompareOptions {
+        quick: false,
+        trailing_padding: None,
+        progress: None,
+        #[cfg(feature = "otel")]
+        tracer: None,
+        ..options.clone()
+    };
+    match compare_files_with(path1, path2, &exact)? {
+        FileDiff::Truncated(shorter, offset) => {
+            let longer = match shorter {
+                Side::Left => path2,
+                Side::Right => path1,
+            };
+            let mut file = File::open(long_path::extended(longer))?;
+            file.seek(SeekFrom::Start(offset as u64))?;
+            let end = blank::padding_end(&mut file, padding)?;
+            Ok(diff_at(end.map(|end| offset as u64 + end), options.quick))
+        }
+        FileDiff::Different(offset) => Ok(diff_at(Some(offset as u64), options.quick)),
+        result => Ok(result),
+    }
+}
+
+fn compare_files_once(
+    path1: &Path,
+    path2: &Path,
+    options: &CompareOptions,
+) -> io::Result<FileDiff> {
+    let quick = options.quick;
+    match options.policies.as_ref().and_then(|p| p.get(path1)) {
+        Some(policy::Policy::HashOnly) => return compare_hashes(path1, path2, options),
+        Some(policy::Policy::Semantic) => {
+            if let Some(result) = policy::compare_semantic(path1, path2, options)? {
+                return Ok(result);
+            }
+        }
+        _ => {}
+    }
+    if let Some(command) = options.comparers.as_ref().and_then(|c| c.get(path1)) {
+        return external::compare_with(command, path1, path2);
+    }
+    #[cfg(feature = "office")]
+    if options.office && office::is_office(path1) {
+        return office::compare_office(path1, path2);
+    }
+    if options.sql_dump && sqldump::is_sql_dump(path1) {
+        return sqldump::compare_sql_dumps(path1, path2, quick);
+    }
+    if let Some(format) = media::Format::of(path1).filter(|_| options.media) {
+        return media::compare_media(path1, path2, format, options);
+    }
+    if let Some(regex) = &options.strip_prefix {
+        let (strip1, strip2) = (lines::strip_prefix(regex), lines::strip_prefix(regex));
+        return lines::compare_lines(path1, path2, strip1, strip2, quick);
+    }
+    if let Some(word) = options.word_swap {
+        return swap::compare_swapped(path1, path2, word, options);
+    }
+    if options.sidecars {
+        let throttle = options.throttle.as_deref();
+        if let Some(result) = sidecar::compare_with_sidecars(path1, path2, throttle)? {
+            return Ok(result);
+        }
+    }
+    let path1 = long_path::extended(path1);
+    let path2 = long_path::extended(path2);
+    let device::Input {
+        file: mut file1,
+        len: len1,
+        meta: meta1,
+    } = device::Input::open(&path1)?;
+    let device::Input {
+        file: mut file2,
+        len: len2,
+        meta: meta2,
+    } = device::Input::open(&path2)?;
+
+    // Pipes have no length, so none of the size-based checks below apply
+    if meta1.as_ref().is_some_and(fifo::is_fifo) || meta2.as_ref().is_some_and(fifo::is_fifo) {
+        return compare_streams(
+            fifo::TimeoutReader::new(file1, options.timeout),
+            fifo::TimeoutReader::new(file2, options.timeout),
+            options,
+        );
+    }
+
+    if len1 == 0 || len2 == 0 {
+        let offset = (len1 != len2).then_some(0);
+        return Ok(diff_at_lengths(offset, len1, len2, quick));
+    }
+
+    if quick && len1 != len2 {
+        return Ok(FileDiff::Different(0));
+    }
+
+    let same_file = match same_file::same_open_file(&file1, &file2) {
+        Some(same) => same,
+        None => fs::canonicalize(&path1)? == fs::canonicalize(&path2)?,
+    };
+    if same_file {
+        return Ok(FileDiff::Equal);
+    }
+
+    if len1 == len2 && reflink::shares_extents(&file1, &file2) {
+        return Ok(FileDiff::Equal);
+    }
+
+    if options.hash_first && len1 == len2 {
+        if hashes_equal(&path1, &path2, options)? {
+            return Ok(FileDiff::Equal);
+        }
+        if quick {
+            return Ok(FileDiff::Different(0));
+        }
+    }
+
+    let sparse = match (&meta1, &meta2) {
+        (Some(meta1), Some(meta2)) => sparse::is_sparse(meta1) && sparse::is_sparse(meta2),
+        _ => false,
+    };
+    if sparse {
+        if let Some(result) = sparse::compare(&mut file1, len1, &mut file2, len2, options)? {
+            return Ok(result);
+        }
+        file1.rewind()?;
+        file2.rewind()?;
+    }
+
+    let auto;
+    let options = match options.auto_io {
+        true => {
+            auto = strategy::apply(options, &file1, len1, &file2, len2);
+            &auto
+        }
+        false => options,
+    };
+
+    if options.threads > 1 && len1.min(len2) > parallel::SEGMENT_SIZE {
+        return parallel::compare_files(path1.as_ref(), len1, path2.as_ref(), len2, options);
+    }
+
+    if options.mmap {
+        return Ok(diff_at_lengths(
+            mmap::compare_files(&file1, len1, &file2, len2)?,
+            len1,
+            len2,
+            quick,
+        ));
+    }
+
+    let mut chunks = ChunkSizer::new(
+        options.chunk_size,
+        len1.max(len2),
+        [&meta1, &meta2]
+            .iter()
+            .map(|meta| {
+                meta.as_ref()
+                    .map_or(chunk::MIN_CHUNK_SIZE, chunk::block_size)
+            })
+            .max()
+            .unwrap_or(chunk::MIN_CHUNK_SIZE),
+    );
+    let pipelined = options.pipeline_depth > 0 && len1.min(len2) >= pipeline::MIN_PIPELINE_LEN;
+    let mut reader1 = Throttled::new(&file1, options.throttle.as_deref());
+    let mut reader2 = Throttled::new(&file2, options.throttle.as_deref());
+    let offset = match pipelined {
+        true => pipeline::compare_readers(
+            &mut reader1,
+            &mut reader2,
+            &mut chunks,
+            options.pipeline_depth,
+        )?,
+        false => compare_readers(&mut reader1, &mut reader2, &mut chunks)?,
+    };
+    Ok(diff_at_lengths(offset, len1, len2, quick))
+}
+
+/// Hashes both files concurrently and returns whether the hashes match.
+fn hashes_equal(path1: &Path, path2: &Path, options: &CompareOptions) -> io::Result<bool> {
+    let throttle = options.throttle.as_deref();
+    let hash = |path: &Path| match options.skip_blank {
+        true => blank::folded_hash(
+            &mut Throttled::new(File::open(path)?, throttle),
+            options.hash,
+        ),
+        false => hash::hash_file(path, options.hash, throttle),
+    };
+    let (hash1, hash2) = std::thread::scope(|scope| {
+        let hash1 = scope.spawn(|| hash(path1));
+        let hash2 = hash(path2);
+        (hash1.join().expect("Hashing thread panicked"), hash2)
+    });
+    Ok(hash1? == hash2?)
+}
+
+/// Compares files by the metadata the policy says, e.g. their sizes only
+/// for --size-only, without reading them.
+fn compare_metadata(path1: &Path, path2: &Path, policy: Policy) -> io::Result<FileDiff> {
+    let meta1 = fs::metadata(long_path::extended(path1))?;
+    let meta2 = fs::metadata(long_path::extended(path2))?;
+    metadata_diff(&meta1, &meta2, policy)
+}
+
+/// Compares the metadata of two files as `policy` says.
+fn metadata_diff(meta1: &Metadata, meta2: &Metadata, policy: Policy) -> io::Result<FileDiff> {
+    let same = meta1.len() == meta2.len()
+        && match policy {
+            Policy::SizeAndMtime => meta1.modified()? == meta2.modified()?,
+            Policy::MetadataOnly => {
+                meta1.modified()? == meta2.modified()? && meta1.permissions() == meta2.permissions()
+            }
+            _ => true,
+        };
+    Ok(match same {
+        true => FileDiff::Equal,
+        false => FileDiff::Different(0),
+    })
+}
+
+/// Compares files by their sizes and hashes only, for the hash-only policy.
+fn compare_hashes(path1: &Path, path2: &Path, options: &CompareOptions) -> io::Result<FileDiff> {
+    let (path1, path2) = (long_path::extended(path1), long_path::extended(path2));
+    let same = fs::metadata(&path1)?.len() == fs::metadata(&path2)?.len()
+        && hashes_equal(&path1, &path2, options)?;
+    Ok(match same {
+        true => FileDiff::Equal,
+        false => FileDiff::Different(0),
+    })
+}
+
+/// Compares two streams whose length isn't known up front, such as stdin or
+/// a pipe, against each other or an open file. None of the size-based
+/// shortcuts apply, so both are read until the first difference or the end.
+pub fn compare_streams<R1: Read + Send, R2: Read + Send>(
+    reader1: R1,
+    reader2: R2,
+    options: &CompareOptions,
+) -> io::Result<FileDiff> {
+    let throttle = options.throttle.as_deref();
+    let mut reader1 = Throttled::new(reader1, throttle);
+    let mut reader2 = Throttled::new(reader2, throttle);
+    let mut chunks = ChunkSizer::new(options.chunk_size, u64::MAX, chunk::MIN_CHUNK_SIZE);
+    let offset = match options.pipeline_depth {
+        0 => compare_readers(&mut reader1, &mut reader2, &mut chunks)?,
+        depth => pipeline::compare_readers(&mut reader1, &mut reader2, &mut chunks, depth)?,
+    };
+    Ok(diff_at(offset, options.quick))
+}
+
+/// Turns the offset of the first difference into a result, hiding the
+/// offset in quick mode.
+pub(crate) fn diff_at(offset: Option<u64>, quick: bool) -> FileDiff {
+    match offset {
+        None => FileDiff::Equal,
+        Some(_) if quick => FileDiff::Different(0),
+        Some(offset) => FileDiff::Different(offset as usize),
+    }
+}
+
+/// Turns the offset of the first difference of two inputs of known lengths
+/// into a result like `diff_at`, telling a shorter input that is a prefix of
+/// the other apart from a difference in the contents.
+pub(crate) fn diff_at_lengths(offset: Option<u64>, len1: u64, len2: u64, quick: bool) -> FileDiff {
+    match offset {
+        Some(offset) if !quick && len1 != len2 && offset == len1.min(len2) => {
+            let shorter = match len1 < len2 {
+                true => Side::Left,
+                false => Side::Right,
+            };
+            FileDiff::Truncated(shorter, offset as usize)
+        }
+        offset => diff_at(offset, quick),
+    }
+}
+
+/// Compares two readers chunk by chunk. Returns the offset of the first
+/// differing byte, the length of the shorter input if it is a prefix of the
+/// other, or `None` if both have the same contents.
+pub fn compare_readers<R1: Read, R2: Read>(
+    reader1: &mut R1,
+    reader2: &mut R2,
+    chunks: &mut ChunkSizer,
+) -> io::Result<Option<u64>> {
+    let mut buffer1 = vec![];
+    let mut buffer2 = vec![];
+    let mut pos = 0;
+
+    loop {
+        let size = chunks.size();
+        buffer1.resize(size, 0);
+        buffer2.resize(size, 0);
+
+        let len1 = read_full(reader1, &mut buffer1)?;
+        let len2 = read_full(reader2, &mut buffer2)?;
+
+        if let ControlFlow::Break(result) =
+            compare_chunks(&buffer1[..len1], &buffer2[..len2], size, pos)
+        {
+            return Ok(result);
+        }
+
+        pos += size as u64;
+        chunks.grow();
+    }
+}
+
+/// Compares one pair of chunks read at `pos` with a requested size of `size`.
+/// Breaks with the comparison result once a difference or the end of both
+/// inputs is reached.
+pub(crate) fn compare_chunks(
+    chunk1: &[u8],
+    chunk2: &[u8],
+    size: usize,
+    pos: u64,
+) -> ControlFlow<Option<u64>> {
+    let len = chunk1.len().min(chunk2.len());
+
+    if chunk1[..len] != chunk2[..len] {
+        let i = (0..len).find(|&i| chunk1[i] != chunk2[i]).unwrap_or(0);
+        return ControlFlow::Break(Some(pos + i as u64));
+    }
+    if chunk1.len() != chunk2.len() {
+        return ControlFlow::Break(Some(pos + len as u64));
+    }
+    if len < size {
+        return ControlFlow::Break(None);
+    }
+    ControlFlow::Continue(())
+}
+
+/// Reads until the buffer is full or the reader is exhausted, so chunks of
+/// both inputs line up even when a read returns less than requested.
+pub(crate) fn read_full<R: Read>(reader: &mut R, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+/// Compares the bytes in `range` of both files, returning the offset of the
+/// first difference if there is one. Both files must extend to `range.end`.
+pub(crate) fn compare_range(
+    file1: &mut File,
+    file2: &mut File,
+    range: Range<u64>,
+    options: &CompareOptions,
+) -> io::Result<Option<u64>> {
+    file1.seek(SeekFrom::Start(range.start))?;
+    file2.seek(SeekFrom::Start(range.start))?;
+
+    let len = range.end - range.start;
+    let mut chunks = ChunkSizer::new(options.chunk_size, len, chunk::MIN_CHUNK_SIZE);
+    let throttle = options.throttle.as_deref();
+    let offset = compare_readers(
+        &mut Throttled::new(file1.take(len), throttle),
+        &mut Throttled::new(file2.take(len), throttle),
+        &mut chunks,
+    )?;
+    Ok(offset.map(|offset| range.start + offset))
+}
+
+/// Compares two directory trees, each pair of files as thoroughly as
+/// `policy` says, with the other options at their defaults.
+pub fn compare_dirs<P: AsRef<Path>>(
+    dir1: P,
+    dir2: P,
+    policy: Policy,
+) -> io::Result<Vec<(PathBuf, FileDiff)>> {
+    let options = CompareOptions {
+        policy,
+        ..Default::default()
+    };
+    compare_dirs_with(dir1, dir2, &options)
+}
+
+/// Compares two directory trees, failing with the error of the first
+/// directory that can't be read or pair of files that can't be compared.
+/// Use `walk::DirWalk` to go on past them.
+pub fn compare_dirs_with<P: AsRef<Path>>(
+    dir1: P,
+    dir2: P,
+    options: &CompareOptions,
+) -> io::Result<Vec<(PathBuf, FileDiff)>> {
+    let mut walk = walk::DirWalk::new(dir1, dir2, options);
+    let mut results = vec![];
+    loop {
+        let entry = walk.next();
+        if let Some((_, _, e)) = walk.take_failed().into_iter().next() {
+            return Err(e);
+        }
+        match entry {
+            Some(entry) => results.push(entry),
+            None => return Ok(results),
+        }
+    }
+}
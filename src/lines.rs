@@ -0,0 +1,119 @@
+use crate::{long_path, FileDiff};
+use regex::bytes::Regex;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::ops::Range;
+use std::path::Path;
+
+/// Bytes at the start of a file checked for NUL bytes to tell whether it
+/// is text, as git does.
+const TEXT_CHECK_LEN: u64 = 8000;
+
+/// Reads the lines of a file that a filter keeps, with the offset each
+/// starts at.
+struct Lines<F> {
+    reader: BufReader<File>,
+    filter: F,
+    offset: u64,
+    line: Vec<u8>,
+}
+
+impl<F: FnMut(u64, &[u8]) -> Option<Range<usize>>> Lines<F> {
+    fn open(path: &Path, filter: F) -> io::Result<Self> {
+        Ok(Lines {
+            reader: BufReader::new(File::open(long_path::extended(path))?),
+            filter,
+            offset: 0,
+            line: vec![],
+        })
+    }
+
+    /// Returns the offset and the filtered contents of the next line that
+    /// is kept, without its line ending.
+    fn next(&mut self) -> io::Result<Option<(u64, Vec<u8>)>> {
+        loop {
+            self.line.clear();
+            let offset = self.offset;
+            let len = self.reader.read_until(b'\n', &mut self.line)?;
+            if len == 0 {
+                return Ok(None);
+            }
+            self.offset += len as u64;
+            let line = self.line.strip_suffix(b"\n").unwrap_or(&self.line);
+            let line = line.strip_suffix(b"\r").unwrap_or(line);
+            if let Some(kept) = (self.filter)(offset, line) {
+                return Ok(Some((offset, line[kept].to_vec())));
+            }
+        }
+    }
+}
+
+/// Compares two text files line by line after passing each line of the
+/// first through `filter1` and of the second through `filter2`, with its
+/// offset. A filter returns the range of the line to compare, or `None` to
+/// skip it. Line endings don't matter. A difference is at the offset of the
+/// first line of the first file that differs, or at its end if the second
+/// file has more lines.
+pub fn compare_lines<F1, F2>(
+    path1: &Path,
+    path2: &Path,
+    filter1: F1,
+    filter2: F2,
+    quick: bool,
+) -> io::Result<FileDiff>
+where
+    F1: FnMut(u64, &[u8]) -> Option<Range<usize>>,
+    F2: FnMut(u64, &[u8]) -> Option<Range<usize>>,
+{
+    let mut lines1 = Lines::open(path1, filter1)?;
+    let mut lines2 = Lines::open(path2, filter2)?;
+    loop {
+        let offset = match (lines1.next()?, lines2.next()?) {
+            (None, None) => return Ok(FileDiff::Equal),
+            (Some((offset, line1)), Some((_, line2))) if line1 != line2 => offset,
+            (Some(_), Some(_)) => continue,
+            (Some((offset, _)), None) => offset,
+            (None, Some(_)) => lines1.offset,
+        };
+        return Ok(crate::diff_at(Some(offset), quick));
+    }
+}
+
+/// Returns a line filter that strips what the regex matches at the start
+/// of each line, e.g. the timestamps of log lines.
+pub fn strip_prefix(regex: &Regex) -> impl FnMut(u64, &[u8]) -> Option<Range<usize>> + '_ {
+    move |_, line| {
+        let start = regex
+            .find(line)
+            .filter(|prefix| prefix.start() == 0)
+            .map_or(0, |prefix| prefix.end());
+        Some(start..line.len())
+    }
+}
+
+/// Returns the line and column of a text file that the byte at `offset` is
+/// on, both from 1 and the column in bytes, or `None` if the file looks
+/// binary: it has a NUL byte in its first 8000 bytes or before the offset.
+pub fn position_at<P: AsRef<Path>>(path: P, offset: u64) -> io::Result<Option<(u64, u64)>> {
+    let file = File::open(long_path::extended(path.as_ref()))?;
+    let mut reader = BufReader::new(file.take(offset.max(TEXT_CHECK_LEN)));
+    let (mut pos, mut line, mut column) = (0, 1, 1);
+    loop {
+        let buffer = reader.fill_buf()?;
+        if buffer.is_empty() {
+            return Ok(Some((line, column)));
+        }
+        if buffer.contains(&0) {
+            return Ok(None);
+        }
+        let before = &buffer[..(offset - pos.min(offset)).min(buffer.len() as u64) as usize];
+        line += before.iter().filter(|&&b| b == b'\n').count() as u64;
+        column = match before.iter().rposition(|&b| b == b'\n') {
+            Some(newline) => (before.len() - newline) as u64,
+            None => column + before.len() as u64,
+        };
+        pos += buffer.len() as u64;
+        let len = buffer.len();
+        reader.consume(len);
+    }
+}
@@ -0,0 +1,61 @@
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+
+/// Returns the `\\?\` form of an absolute Windows path, which lifts the
+/// MAX_PATH limit of 260 characters: `C:\dir` becomes `\\?\C:\dir` and
+/// `\\server\share\dir` becomes `\\?\UNC\server\share\dir`. Returns `None`
+/// for paths that are relative, already extended or device paths.
+pub fn to_extended(path: &str) -> Option<String> {
+    if path.starts_with(r"\\?\") || path.starts_with(r"\\.\") {
+        return None;
+    }
+    if let Some(unc) = path.strip_prefix(r"\\") {
+        return Some(format!(r"\\?\UNC\{}", unc));
+    }
+    let bytes = path.as_bytes();
+    match bytes.len() >= 3 && bytes[0].is_ascii_alphabetic() && &bytes[1..3] == br":\" {
+        true => Some(format!(r"\\?\{}", path)),
+        false => None,
+    }
+}
+
+/// Undoes `to_extended` for showing a path to the user.
+pub fn from_extended(path: &str) -> Cow<'_, str> {
+    if let Some(unc) = path.strip_prefix(r"\\?\UNC\") {
+        return Cow::Owned(format!(r"\\{}", unc));
+    }
+    match path.strip_prefix(r"\\?\") {
+        Some(local) if local.as_bytes().get(1) == Some(&b':') => Cow::Borrowed(local),
+        _ => Cow::Borrowed(path),
+    }
+}
+
+/// Returns the path in a form that can be opened however long it is. On
+/// Windows it is made absolute, which also resolves `.` and `..` since the
+/// `\\?\` form doesn't, and extended. Elsewhere it is returned unchanged.
+pub fn extended(path: &Path) -> Cow<'_, Path> {
+    #[cfg(windows)]
+    {
+        let absolute = match std::path::absolute(path) {
+            Ok(absolute) => absolute,
+            Err(_) => return Cow::Borrowed(path),
+        };
+        match absolute.to_str().and_then(to_extended) {
+            Some(extended) => Cow::Owned(PathBuf::from(extended)),
+            None => Cow::Owned(absolute),
+        }
+    }
+    #[cfg(not(windows))]
+    {
+        Cow::Borrowed(path)
+    }
+}
+
+/// Turns a path found below `extended_root`, the extended form of `root`,
+/// back into one below `root` as the user gave it.
+pub fn restore(path: PathBuf, extended_root: &Path, root: &Path) -> PathBuf {
+    match path.strip_prefix(extended_root) {
+        Ok(relative) if extended_root != root => root.join(relative),
+        _ => path,
+    }
+}
@@ -1,78 +1,2749 @@
-use clap::Parser;
-use file_cmp::{compare_dirs, compare_files, is_dir, FileDiff};
+use clap::builder::{BoolishValueParser, PossibleValuesParser, TypedValueParser};
+use clap::{CommandFactory, Parser, Subcommand};
+use clap_complete::Shell;
+use file_cmp::agent;
+use file_cmp::attributes;
+#[cfg(feature = "azure")]
+use file_cmp::azure;
+use file_cmp::batch::Pairs;
+use file_cmp::bench::{self, BenchResult};
+use file_cmp::blank::Padding;
+use file_cmp::cas;
+use file_cmp::cdc;
+use file_cmp::config::{self, Config, Setting};
+use file_cmp::daemon::{self, Job};
+use file_cmp::delta::{self, Delta};
+use file_cmp::device;
+use file_cmp::doctor;
+use file_cmp::evidence::{self, EvidenceLog};
+use file_cmp::exec::Exec;
+use file_cmp::external::Comparers;
+use file_cmp::fifo::TimeoutReader;
+use file_cmp::fill;
+#[cfg(feature = "gcs")]
+use file_cmp::gcs;
+#[cfg(feature = "git")]
+use file_cmp::git;
+use file_cmp::hash::{self, HashAlgorithm};
+use file_cmp::heartbeat::{Heartbeat, Progress};
+use file_cmp::heatmap;
+use file_cmp::hooks::{self, Hooks, Summary};
+use file_cmp::http;
+use file_cmp::interrupt;
+use file_cmp::lines;
+use file_cmp::long_path;
+use file_cmp::memory;
+#[cfg(feature = "oci")]
+use file_cmp::oci;
+#[cfg(feature = "otel")]
+use file_cmp::otel::Tracer;
+use file_cmp::paranoid::Reread;
+#[cfg(feature = "parquet")]
+use file_cmp::parquet::{self, TableDiff};
+#[cfg(feature = "pdf")]
+use file_cmp::pdf::{self, TextDiff};
+use file_cmp::policy::Policies;
+use file_cmp::prefetch::Prefetch;
+use file_cmp::priority::enter_background_mode;
+use file_cmp::quorum;
+use file_cmp::report::{self, ReportFile};
+use file_cmp::rpc;
+#[cfg(feature = "s3")]
+use file_cmp::s3;
+use file_cmp::same_file::{self, Storage};
+use file_cmp::selinux;
+use file_cmp::serve;
+use file_cmp::similar::{self, Likeness};
+use file_cmp::sparse;
+use file_cmp::ssh::{self, RemotePath};
+use file_cmp::suffix;
+use file_cmp::throttle::Throttle;
+use file_cmp::tool;
+#[cfg(feature = "torrent")]
+use file_cmp::torrent;
+use file_cmp::units::{
+    format_size, format_thousands, parse_byte, parse_duration, parse_percent, parse_size,
+};
+use file_cmp::walk::{DirWalk, Links};
+use file_cmp::{
+    compare_files_with, compare_streams, is_dir, CompareOptions, FileDiff, PathDiff, Policy, Side,
+};
+use regex::bytes::Regex;
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
 use std::process::ExitCode;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant, SystemTime};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)] // Read from `Cargo.toml`
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    // Without a subcommand, the arguments of `compare`
+    #[command(flatten)]
+    args: Args,
+}
+
+// Arguments of a comparison, given to the `compare` subcommand or without a
+// subcommand. Not a doc comment, which would replace the about text.
+#[derive(clap::Args, Debug)]
+#[command(args_override_self = true)]
 struct Args {
-    /// Path to first file or directory to compare
-    path1: String,
-    /// Path to second file or directory to compare
-    path2: String,
+    /// Path to first file or directory to compare, an http(s)://, s3://, az://, gs:// or sftp:// URL, a git:REPO#REV reference, or - for stdin
+    #[arg(required_unless_present_any = ["from_file", "pairs_from_stdin", "pair", "rpc"])]
+    path1: Option<String>,
+    /// Path to second file or directory to compare, an http(s)://, s3://, az://, gs:// or sftp:// URL, a git:REPO#REV reference, or - for stdin
+    #[arg(required_unless_present_any = ["from_file", "pairs_from_stdin", "expect_fill", "pair", "baseline", "rpc"])]
+    path2: Option<String>,
+    /// Paths of further pairs of files to compare, two per pair
+    #[arg(value_name = "MORE", conflicts_with = "expect_fill")]
+    more_paths: Vec<String>,
+    /// Optional parameter to compare a pair of files given as A,B, repeated for each pair
+    #[arg(long, value_name = "A,B", value_parser = parse_pair, conflicts_with_all = ["path1", "path2"])]
+    pair: Vec<(PathBuf, PathBuf)>,
     /// Optional flag to enable machine-readable output
-    #[arg(short('m'), long("machine"))]
+    #[arg(short('m'), long("machine"), env = "FILE_CMP_MACHINE", value_parser = BoolishValueParser::new())]
     machine_readable: bool,
     /// Optional flag to do faster comparison and not output first diff offset
-    #[arg(short, long)]
+    #[arg(short, long, env = "FILE_CMP_QUICK", value_parser = BoolishValueParser::new())]
     quick: bool,
-    /// Optional parameter to set the chunk size for reading the files, e.g. 4k, 2M
-    #[arg(short, long)]
-    chunk_size: Option<String>,
-    /// Optional flag to only output non-equal results (when diffing dirs)
-    #[arg(short, long)]
+    /// Optional flag to only compare the sizes of files, without reading them, as a first pass over large trees: files of the same size count as equal
+    #[arg(long, conflicts_with_all = ["hash_first", "ignore_trailing_padding", "ignore_final_newline"])]
+    size_only: bool,
+    /// Optional parameter to set the chunk size for reading the files, e.g. 4k, 2M (adaptive if not set)
+    #[arg(short, long, value_parser = parse_size, env = "FILE_CMP_CHUNK_SIZE")]
+    chunk_size: Option<usize>,
+    /// Optional parameter to limit the memory taken by the read buffers, e.g. 64M on a small host, using smaller chunks, fewer threads and a shallower pipeline as needed
+    #[arg(long, value_name = "LIMIT", value_parser = parse_size, env = "FILE_CMP_MAX_MEMORY")]
+    max_memory: Option<usize>,
+    /// Optional parameter to print results in a layout for scripts that is frozen for each version, unlike the --machine output. v1 (the default) prints a line per pair of files with four fields separated by tabs: STATUS (equal, differ, eof-left, eof-right, left-only or right-only), OFFSET (the byte where the files differ, or - if they don't), PATH1 and PATH2 (- if not known, e.g. for objects compared with a local tree). Errors go to stderr
+    #[arg(long, value_name = "VERSION", num_args = 0..=1, require_equals = true, default_missing_value = "v1", value_parser = PossibleValuesParser::new(["v1"]), conflicts_with_all = ["brief", "all_paths", "print_hashes", "attributes", "selinux", "extents", "expect_fill", "image", "parquet", "pdf", "cdc", "heatmap", "from_end", "agent", "rpc"])]
+    porcelain: Option<String>,
+    /// Optional flag to print results as JSON for jq and other tools: an object with the path, status (equal, diff, eof on left, eof on right, left only or right only) and offset where the files differ for a pair of files, and an array of them, one per line, for directory trees and lists of pairs. Errors go to stderr
+    #[arg(long, conflicts_with_all = ["porcelain", "brief", "all_paths", "print_hashes", "attributes", "selinux", "extents", "expect_fill", "image", "parquet", "pdf", "cdc", "heatmap", "from_end", "agent", "rpc"])]
+    json: bool,
+    /// Optional flag to only output non-equal results (when diffing dirs or lists of pairs)
+    #[arg(short, long, env = "FILE_CMP_DIFFS_ONLY", value_parser = BoolishValueParser::new())]
     diffs_only: bool,
+    /// Optional flag to only output whether two directories differ and how many files do, instead of each result
+    #[arg(long, conflicts_with = "diffs_only")]
+    brief: bool,
+    /// Optional parameter to compare large files in parallel ranges using this many threads
+    #[arg(short, long, default_value_t = 1, env = "FILE_CMP_THREADS")]
+    threads: usize,
+    /// Optional parameter to compare this many pairs of files of a directory comparison at once, printing each result as its comparison finishes, e.g. 8 for trees of many small files on SSDs
+    #[arg(
+        short,
+        long,
+        value_name = "N",
+        default_value_t = 1,
+        env = "FILE_CMP_JOBS"
+    )]
+    jobs: usize,
+    /// Optional parameter to set how many chunks are read ahead while comparing (0 to disable)
+    #[arg(long, default_value_t = 2, env = "FILE_CMP_PIPELINE_DEPTH")]
+    pipeline_depth: usize,
+    /// Optional parameter to limit the read bandwidth in bytes per second, e.g. 50M
+    #[arg(long, value_parser = parse_size, env = "FILE_CMP_BWLIMIT")]
+    bwlimit: Option<usize>,
+    /// Optional flag to run with low IO priority so other workloads take precedence
+    #[arg(long, env = "FILE_CMP_BACKGROUND", value_parser = BoolishValueParser::new())]
+    background: bool,
+    /// Optional flag to compare files by mapping them into memory instead of reading them
+    #[arg(long)]
+    mmap: bool,
+    /// Optional flag to choose between buffered reads, memory mapping and parallel ranges for each pair of files by its size, filesystem and the memory available
+    #[arg(long, conflicts_with_all = ["mmap", "threads"], env = "FILE_CMP_AUTO_IO", value_parser = BoolishValueParser::new())]
+    auto_io: bool,
+    /// Optional flag to hash both files first and only compare byte by byte if they differ
+    #[arg(long)]
+    hash_first: bool,
+    /// Optional parameter to set the hash algorithm used when hashing files
+    #[arg(long, default_value_t = HashAlgorithm::Sha256, value_parser = hash_algorithm(), ignore_case = true, env = "FILE_CMP_HASH")]
+    hash: HashAlgorithm,
+    /// Optional parameter to treat files as equal when the longer one only adds padding of 0x00 or 0xFF, or of this byte if given, e.g. flash dumps and firmware images
+    #[arg(long, value_name = "BYTE", num_args = 0..=1, require_equals = true, value_parser = parse_byte)]
+    ignore_trailing_padding: Option<Option<u8>>,
+    /// Optional flag to treat files as equal when they only differ by a final newline that one has and the other hasn't, e.g. source files saved by different editors
+    #[arg(long, conflicts_with = "ignore_trailing_padding")]
+    ignore_final_newline: bool,
+    /// Optional flag to skip hashing long runs of 0x00/0xFF filler bytes (with --hash-first), e.g. for disk images
+    #[arg(long, requires = "hash_first")]
+    skip_blank: bool,
+    /// Optional parameter to give up when a pipe or stdin delivers no data for this long, e.g. 30s, 5m
+    #[arg(long, value_parser = parse_duration, env = "FILE_CMP_TIMEOUT")]
+    timeout: Option<Duration>,
+    /// Optional parameter to print a status line to stderr at this interval, with the file being compared, the files done and the throughput, e.g. 60 or 5m
+    #[arg(long, value_name = "INTERVAL", value_parser = parse_duration)]
+    heartbeat: Option<Duration>,
+    /// Optional parameter to write progress events as JSON lines to stderr, or to this file or named pipe: started, file_done, percent, eta and finished, e.g. for a GUI
+    #[arg(long, value_name = "FILE", num_args = 0..=1, require_equals = true)]
+    progress_json: Option<Option<PathBuf>>,
+    /// Optional flag to exit with the number of files that differ or are on one side only, up to 125, so shell scripts get a rough count without parsing the output. A run that fails still exits with 1
+    #[arg(long)]
+    exit_count: bool,
+    /// Optional parameter to write a summary of the run as a line of JSON to stderr, or to this file or file descriptor, e.g. 3: the counts of results, the bytes compared, the seconds taken and the exit code
+    #[arg(long, value_name = "FD|FILE", num_args = 0..=1, require_equals = true)]
+    summary_json: Option<Option<String>>,
+    /// Optional parameter to compare a pair of files again from the start this many times after a transient read error, e.g. EIO on a network filesystem
+    #[arg(long, value_name = "N", default_value_t = 0, env = "FILE_CMP_RETRIES")]
+    retries: u32,
+    /// Optional parameter to read each file twice more after comparing it and fail if the reads differ, e.g. flaky storage or RAM when verifying archives, reading the second time like the first (cached), after dropping the page cache (drop-cache) or with O_DIRECT (direct, Linux only)
+    #[arg(long, value_name = "HOW", num_args = 0..=1, require_equals = true, default_missing_value = "cached", value_parser = reread(), conflicts_with = "size_only")]
+    paranoid: Option<Reread>,
+    /// Optional flag to trust the checksum in a .sha256, .md5 or .sfv file next to either file of a pair instead of reading that file, comparing the other file against it, and to fail on files that no longer match their own, e.g. to verify a copy against archives checksummed when they were made
+    #[arg(long, conflicts_with = "size_only", env = "FILE_CMP_SIDECARS", value_parser = BoolishValueParser::new())]
+    sidecars: bool,
+    /// Optional parameter to compare every pair of files listed in this file, one pair per line separated by a tab or NUL
+    #[arg(long, value_name = "LIST", conflicts_with_all = ["path1", "path2", "pair"])]
+    from_file: Option<String>,
+    /// Optional flag to compare pairs of files read from stdin as they arrive, in the same format as --from-file
+    #[arg(long, conflicts_with_all = ["path1", "path2", "from_file", "pair"])]
+    pairs_from_stdin: bool,
+    /// Optional flag to run as a backend speaking JSON-RPC over stdin and stdout, one message per line, with the methods compareFiles, compareDirs (streaming dirEntry notifications) and cancel, e.g. for editors
+    #[arg(long, conflicts_with_all = ["path1", "path2", "from_file", "pairs_from_stdin", "pair"])]
+    rpc: bool,
+    /// Optional flag to read the pairs as NUL-terminated paths, two per pair (with --from-file or --pairs-from-stdin), e.g. from find -print0, and to write the --recopy-list and --errors-out NUL-terminated
+    #[arg(short('0'), long("null"))]
+    null_terminated: bool,
+    /// Optional parameter to set the ssh program and arguments used for sftp:// and ssh:// paths
+    #[arg(long, default_value = "ssh", env = "FILE_CMP_SSH_COMMAND")]
+    ssh_command: String,
+    /// Optional parameter to compare trees on sftp:// paths by hashing them on the remote host with this file_cmp program, so only hashes are transferred, and files by the rolling checksums of their blocks like rsync
+    #[arg(long, value_name = "PROGRAM", num_args = 0..=1, default_missing_value = "file_cmp")]
+    agent: Option<String>,
+    /// Optional flag to compare two OCI image layouts or saved image archives by the files in their flattened layers
+    #[arg(long)]
+    image: bool,
+    /// Optional flag to compare MP3, FLAC and MP4 files by their audio and video data only, ignoring tags such as ID3, Vorbis comments and MP4 udta atoms
+    #[arg(long)]
+    media: bool,
+    /// Optional parameter to let the samples of WAV files compared with --media differ by up to this many least significant bits
+    #[arg(long, value_name = "LSBS", default_value_t = 0, requires = "media")]
+    sample_tolerance: u32,
+    /// Optional flag to compare Office documents (docx, xlsx, pptx) by their parts, ignoring revision IDs, modification times and other changes of a save without edits
+    #[arg(long)]
+    office: bool,
+    /// Optional flag to compare pg_dump and mysqldump files (*.sql) by their statements, ignoring the comments with versions, hosts and times and the order of SET statements
+    #[arg(long)]
+    sql_dump: bool,
+    /// Optional parameter to compare files line by line without what this regex matches at the start of each line, e.g. '[0-9-]+T[0-9:.]+Z ' for the timestamps of logs
+    #[arg(long, value_name = "REGEX", value_parser = parse_prefix_regex)]
+    strip_prefix_regex: Option<Regex>,
+    /// Optional parameter to swap the bytes of each word of this many bytes of the second file before comparing, e.g. raw data captured from a big-endian device against a little-endian reference dump
+    #[arg(long, value_name = "SIZE", value_parser = word_size(), conflicts_with_all = ["strip_prefix_regex", "hash_first", "size_only"])]
+    word_swap: Option<usize>,
+    /// Optional flag to compare two Parquet files by their schema and rows, regardless of row groups, pages and compression
+    #[arg(long, conflicts_with = "image")]
+    parquet: bool,
+    /// Optional flag to compare two PDF files by the words on their pages, regardless of layout, timestamps and how the files are encoded
+    #[arg(long, conflicts_with_all = ["image", "parquet"])]
+    pdf: bool,
+    /// Optional parameter to compare two large files by content-defined chunks of about this size (1M if not given) and report the ranges of the chunks that changed, e.g. for database dumps
+    #[arg(long, value_name = "SIZE", num_args = 0..=1, require_equals = true, default_missing_value = "1M", value_parser = parse_size, conflicts_with_all = ["image", "parquet", "pdf"])]
+    cdc: Option<usize>,
+    /// Optional parameter to show which regions of two files differ as a map of blocks after the result, or to write the map to this SVG file, e.g. to tell a corrupt sector from scattered damage
+    #[arg(long, value_name = "FILE", num_args = 0..=1, require_equals = true, conflicts_with_all = ["image", "parquet", "pdf", "cdc"])]
+    heatmap: Option<Option<PathBuf>>,
+    /// Optional flag to compare two files backwards from their ends and report the last byte where each differs and the length of the suffix they share, e.g. for append-only files and logs
+    #[arg(long, conflicts_with_all = ["image", "parquet", "pdf", "cdc", "heatmap", "quick", "size_only", "hash_first"])]
+    from_end: bool,
+    /// Optional parameter to apply the settings of a [profile.NAME] table in the configuration files
+    #[arg(long, value_name = "NAME", env = "FILE_CMP_PROFILE")]
+    profile: Option<String>,
+    /// Optional parameter to compare the first file or device with this byte repeated instead of a second file, e.g. 0 or 0xFF
+    #[arg(long, value_name = "BYTE", value_parser = parse_byte, conflicts_with = "path2")]
+    expect_fill: Option<u8>,
+    /// Optional parameter to compare each of the paths with this file and report which match it, e.g. mirrored copies
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["expect_fill", "pair", "from_file", "pairs_from_stdin", "brief"])]
+    baseline: Option<PathBuf>,
+    /// Optional flag to check that three or more replicas of a file are identical and report those that disagree with the majority and where, e.g. for storage keeping multiple copies
+    #[arg(long, conflicts_with_all = ["baseline", "expect_fill", "pair", "from_file", "pairs_from_stdin", "brief"])]
+    quorum: bool,
+    /// Optional parameter to open each pair of differing files in this diff tool, e.g. vimdiff, which gets both paths appended
+    #[arg(long, value_name = "CMD", env = "FILE_CMP_TOOL")]
+    tool: Option<String>,
+    /// Optional flag to ask before opening each pair in the --tool
+    #[arg(long, requires = "tool", conflicts_with = "pairs_from_stdin")]
+    prompt: bool,
+    /// Optional parameter to report files that differ but have at least this percentage of their content in common as similar, with the percentage, e.g. 90 to tell minor drift from a wrong file
+    #[arg(long, value_name = "P", value_parser = parse_percent, conflicts_with_all = ["porcelain", "json"])]
+    similar_threshold: Option<f64>,
+    /// Optional flag to tell equal files that are hardlinks of one file or share their extents from copies that take space of their own, e.g. to audit how well backups are deduplicated
+    #[arg(long, conflicts_with_all = ["porcelain", "json"])]
+    dedup_status: bool,
+    /// Optional parameter to run this command with the shell for each pair of files that don't match, with {left}, {right} and {status} replaced by their paths and status as in --porcelain, e.g. 'cp {left} {right}'
+    #[arg(long, value_name = "COMMAND")]
+    exec: Option<String>,
+    /// Optional parameter to run up to this many --exec commands at a time
+    #[arg(long, value_name = "N", default_value_t = 1, requires = "exec")]
+    parallel_exec: usize,
+    /// Optional parameter to append a hash-chained record of every comparison of local files to this log, with the size, SHA-256 and modification time of both files and the verdict
+    #[arg(long, value_name = "FILE")]
+    evidence_log: Option<PathBuf>,
+    /// Optional flag to export a trace with a span per pair of files and counters of the results to the OpenTelemetry collector at OTEL_EXPORTER_OTLP_ENDPOINT
+    #[arg(long)]
+    otel: bool,
+    /// Optional parameter to write the relative paths of files that differ or are missing on the right to this file after copying a tree, e.g. for rsync --files-from
+    #[arg(long, value_name = "FILE")]
+    recopy_list: Option<PathBuf>,
+    /// Optional parameter to write the pairs of files that could not be compared, e.g. after a read error or timeout, to this file in the format of --from-file, so a later run can retry just those
+    #[arg(long, value_name = "FILE")]
+    errors_out: Option<PathBuf>,
+    /// Optional flag to print each entry of a directory comparison with its relative path and the absolute paths on both sides as separate fields (with --machine)
+    #[arg(long, requires = "machine_readable")]
+    all_paths: bool,
+    /// Optional parameter to set how directory comparisons treat symbolic links, and junctions and mount points on Windows: follow them except where they would loop (follow), leave them out (skip), or compare them as links by their targets (compare)
+    #[arg(long, value_name = "HOW", default_value_t = Links::Follow, value_parser = links(), env = "FILE_CMP_LINKS")]
+    links: Links,
+    /// Optional parameter to set when directory comparisons list each directory with the metadata of its entries up front, statting many at once instead of one path at a time, which saves round trips on network shares: when either directory is on NFS, SMB or another network filesystem (auto), always or never
+    #[arg(long, value_name = "WHEN", default_value_t = Prefetch::Auto, value_parser = prefetch(), env = "FILE_CMP_PREFETCH_METADATA")]
+    prefetch_metadata: Prefetch,
+    /// Optional flag to print the digests of both files of each pair (with --hash) after their paths, so a report can be checked later without reading both sides again
+    #[arg(long)]
+    print_hashes: bool,
+    /// Optional flag to also compare file attributes (readonly, and hidden, system and archive on Windows or file capabilities such as cap_net_bind_service on Linux) and report those that differ
+    #[arg(long)]
+    attributes: bool,
+    /// Optional flag to also compare the SELinux security contexts of files (on Linux) and report those that differ as the attribute selinux
+    #[arg(long)]
+    selinux: bool,
+    /// Optional flag to also compare the hole and data layout of sparse files and report a copy that was densified (attribute densified) or whose layout differs otherwise (attribute extents)
+    #[arg(long)]
+    extents: bool,
+    /// External comparers from the configuration files
+    #[arg(skip)]
+    comparers: Comparers,
+    /// Policies from the configuration files
+    #[arg(skip)]
+    policies: Policies,
+    /// Hooks from the configuration files
+    #[arg(skip)]
+    hooks: Hooks,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Compare two files or directories (the default without a subcommand)
+    Compare(Box<Args>),
+    /// Hash files and print their digests, like sha256sum
+    Hash {
+        /// Paths to the files to hash, or - for stdin
+        #[arg(required = true)]
+        paths: Vec<String>,
+        /// Optional parameter to set the hash algorithm
+        #[arg(long, default_value_t = HashAlgorithm::Sha256, value_parser = hash_algorithm(), ignore_case = true, env = "FILE_CMP_HASH")]
+        hash: HashAlgorithm,
+    },
+    /// Hash every file in a tree and print a record for each, for a remote comparison with --agent
+    Agent {
+        /// Path to the directory to hash, or the file with --blocks
+        path: String,
+        /// Optional parameter to set the hash algorithm used when hashing files
+        #[arg(long, default_value_t = HashAlgorithm::Sha256, value_parser = hash_algorithm(), ignore_case = true)]
+        hash: HashAlgorithm,
+        /// Optional parameter to print the rolling and strong checksums of each block of this size of a file instead, for a delta comparison of a single file with --agent
+        #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+        blocks: Option<usize>,
+    },
+    /// Benchmark the comparison modes on two files and print their throughput
+    Bench {
+        /// Path to first file to compare
+        path1: String,
+        /// Path to second file to compare
+        path2: String,
+        /// Optional parameter to set the number of runs per mode
+        #[arg(short, long, default_value_t = 3)]
+        runs: usize,
+        /// Optional parameter to set the number of threads for the parallel mode (default: number of CPUs)
+        #[arg(short, long)]
+        threads: Option<usize>,
+        /// Optional flag to keep the files in the page cache between runs
+        #[arg(long)]
+        no_drop_caches: bool,
+    },
+    /// Verify that the files of a content-addressed store still match the hashes they are named after
+    VerifyStore {
+        /// Path to the store, e.g. an OCI image layout's blobs directory
+        path: String,
+        /// Optional flag to enable machine-readable output
+        #[arg(short('m'), long("machine"))]
+        machine_readable: bool,
+        /// Optional flag to only output files that don't match their name
+        #[arg(short, long)]
+        diffs_only: bool,
+    },
+    /// Verify a download against the piece hashes of a .torrent file and report the parts of each file that are incomplete or corrupt
+    VerifyTorrent {
+        /// Path to the .torrent file
+        torrent: String,
+        /// Path to the directory the torrent was downloaded into
+        path: String,
+        /// Optional flag to enable machine-readable output
+        #[arg(short('m'), long("machine"))]
+        machine_readable: bool,
+        /// Optional flag to only output files that aren't intact
+        #[arg(short, long)]
+        diffs_only: bool,
+    },
+    /// Serve a JSON API over HTTP for comparing the pairs of paths listed in a file
+    Serve {
+        /// Path to the list of pairs, one per line separated by a tab or NUL
+        list: String,
+        /// Optional parameter to set the address to listen on
+        #[arg(short, long, default_value = "127.0.0.1:8080")]
+        listen: String,
+        /// Optional parameter to compare large files in parallel ranges using this many threads
+        #[arg(short, long, default_value_t = 1)]
+        threads: usize,
+    },
+    /// Run the comparisons of the [jobs.NAME] tables of the configuration files on their schedules, printing the pairs whose results changed since the last run
+    Daemon {
+        /// Optional flag to run each job once and exit
+        #[arg(long)]
+        once: bool,
+        /// Optional parameter to keep the results of the last run of each job in this directory
+        #[arg(long, value_name = "DIR", default_value = ".file_cmp_state")]
+        state: PathBuf,
+    },
+    /// Probe the filesystem of a directory and report what it supports and which comparison options suit it
+    Doctor {
+        /// Path to a writable directory on the filesystem
+        path: String,
+    },
+    /// Print a completion script for a shell
+    Completions {
+        /// The shell to complete in
+        shell: Shell,
+    },
+}
+
+/// Parses a hash algorithm, listing the supported ones for help and
+/// completions.
+fn hash_algorithm() -> impl TypedValueParser<Value = HashAlgorithm> {
+    PossibleValuesParser::new(HashAlgorithm::ALL.map(|algorithm| algorithm.name()))
+        .map(|name| name.parse().expect("possible values are valid algorithms"))
+}
+
+/// Parses how --links treats links.
+fn links() -> impl TypedValueParser<Value = Links> {
+    PossibleValuesParser::new(Links::ALL.map(|links| links.name())).map(|name| {
+        name.parse()
+            .expect("possible values are ways to treat links")
+    })
+}
+
+/// Parses when --prefetch-metadata lists directories with their metadata.
+fn prefetch() -> impl TypedValueParser<Value = Prefetch> {
+    PossibleValuesParser::new(Prefetch::ALL.map(|prefetch| prefetch.name())).map(|name| {
+        name.parse()
+            .expect("possible values are times to prefetch metadata")
+    })
+}
+
+/// Parses how --paranoid reads files the second time.
+fn reread() -> impl TypedValueParser<Value = Reread> {
+    PossibleValuesParser::new(Reread::ALL.map(|reread| reread.name())).map(|name| {
+        name.parse()
+            .expect("possible values are ways to read again")
+    })
+}
+
+/// Parses the size of the words of --word-swap, listing the supported ones
+/// of `swap::WORD_SIZES`.
+fn word_size() -> impl TypedValueParser<Value = usize> {
+    PossibleValuesParser::new(["2", "4"])
+        .map(|size| size.parse().expect("possible values are numbers"))
+}
+
+/// Parses a regex that only matches at the start of a line.
+fn parse_prefix_regex(s: &str) -> Result<Regex, String> {
+    Regex::new(&format!("^(?:{})", s)).map_err(|e| e.to_string())
+}
+
+/// Parses a pair of paths given as A,B. Paths containing a comma can be
+/// given as positional arguments instead.
+fn parse_pair(s: &str) -> Result<(PathBuf, PathBuf), String> {
+    match s.split(',').collect::<Vec<_>>()[..] {
+        [path1, path2] if !path1.is_empty() && !path2.is_empty() => {
+            Ok((PathBuf::from(path1), PathBuf::from(path2)))
+        }
+        _ => Err(format!(
+            "expected two paths separated by a comma, got '{}'",
+            s
+        )),
+    }
 }
 
 fn main() -> ExitCode {
-    let args = Args::parse();
+    let cli = match parse_args() {
+        Ok(cli) => cli,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let args = cli.compare_args();
+    if args.background {
+        if let Err(e) = enter_background_mode() {
+            eprintln!("Warning: could not lower IO priority: {}", e);
+        }
+    }
 
-    match is_dir(&args.path1) {
-        Ok(true) => {
-            let results = compare_dirs(&args.path1, &args.path2, args.quick);
+    match cli.command {
+        Some(Command::Hash { ref paths, hash }) => run_hash(paths, hash),
+        Some(Command::Agent {
+            ref path,
+            hash,
+            blocks,
+        }) => run_agent(path, hash, blocks),
+        Some(Command::Bench {
+            ref path1,
+            ref path2,
+            runs,
+            threads,
+            no_drop_caches,
+        }) => run_bench(path1, path2, runs, threads, !no_drop_caches),
+        Some(Command::VerifyStore {
+            ref path,
+            machine_readable,
+            diffs_only,
+        }) => run_verify_store(path, machine_readable, diffs_only),
+        Some(Command::VerifyTorrent {
+            ref torrent,
+            ref path,
+            machine_readable,
+            diffs_only,
+        }) => run_verify_torrent(torrent, path, machine_readable, diffs_only),
+        Some(Command::Serve {
+            ref list,
+            ref listen,
+            threads,
+        }) => run_serve(list, listen, threads),
+        Some(Command::Daemon { once, ref state }) => run_daemon(once, state),
+        Some(Command::Doctor { ref path }) => run_doctor(path),
+        Some(Command::Completions { shell }) => {
+            clap_complete::generate(shell, &mut Cli::command(), "file_cmp", &mut io::stdout());
+            ExitCode::SUCCESS
+        }
+        Some(Command::Compare(_)) | None => compare_with_hooks(args),
+    }
+}
+
+impl Cli {
+    /// Returns the arguments of the comparison, given with or without the
+    /// `compare` subcommand.
+    fn compare_args(&self) -> &Args {
+        match &self.command {
+            Some(Command::Compare(args)) => args,
+            _ => &self.args,
+        }
+    }
+
+    fn compare_args_mut(&mut self) -> &mut Args {
+        match &mut self.command {
+            Some(Command::Compare(args)) => args,
+            _ => &mut self.args,
+        }
+    }
+}
+
+/// Counts of the results printed so far, for the after hook.
+static SUMMARY: Mutex<Summary> = Mutex::new(Summary::new());
+
+/// The --evidence-log, opened when the comparison starts.
+static EVIDENCE: Mutex<Option<EvidenceLog>> = Mutex::new(None);
+
+/// Records a comparison of local files in the --evidence-log if there is
+/// one, returning false if that fails.
+fn log_evidence(path1: &Path, path2: &Path, file_diff: &FileDiff) -> bool {
+    let mut evidence = EVIDENCE.lock().unwrap();
+    match evidence
+        .as_mut()
+        .map(|log| log.record(path1, path2, file_diff))
+    {
+        Some(Err(e)) => {
+            eprintln!("Error: cannot write the evidence log: {}", e);
+            false
+        }
+        _ => true,
+    }
+}
+
+/// The --errors-out list, created when the comparison starts.
+static ERRORS_OUT: Mutex<Option<ReportFile>> = Mutex::new(None);
+
+/// Adds a pair of files that could not be compared to the --errors-out list
+/// if there is one.
+fn record_failure(path1: &Path, path2: &Path, args: &Args) {
+    let mut errors_out = ERRORS_OUT.lock().unwrap();
+    let Some(list) = errors_out.as_mut() else {
+        return;
+    };
+    let (separator, terminator): (&[u8], &[u8]) = match args.null_terminated {
+        true => (b"\0", b"\0"),
+        false => (b"\t", b"\n"),
+    };
+    let line = [
+        path1.as_os_str().as_encoded_bytes(),
+        separator,
+        path2.as_os_str().as_encoded_bytes(),
+        terminator,
+    ]
+    .concat();
+    if let Err(e) = list.write_all(&line) {
+        eprintln!("Error: cannot write the errors list: {}", e);
+    }
+}
+
+/// The commands of --exec, created when the comparison starts.
+static EXEC: Mutex<Option<Exec>> = Mutex::new(None);
+
+/// Runs the --exec command for a pair of files that don't match.
+fn run_exec(path1: &Path, path2: &Path, file_diff: &FileDiff, args: &Args) {
+    let mut exec = EXEC.lock().unwrap();
+    let (Some(exec), Some(command)) = (exec.as_mut(), &args.exec) else {
+        return;
+    };
+    if *file_diff == FileDiff::Equal {
+        return;
+    }
+    if let Err(e) = exec.run(path1, path2, file_diff.as_status()) {
+        eprintln!("Error: cannot run '{}': {}", command, e);
+    }
+}
+
+/// Moves the --errors-out list into place once the run is over, or keeps
+/// it partial if the run was cancelled. Returns false if it can't be
+/// written.
+fn finish_errors_out(code: ExitCode) -> bool {
+    let Some(mut list) = ERRORS_OUT.lock().unwrap().take() else {
+        return true;
+    };
+    let cancelled = code == ExitCode::from(CANCELLED);
+    if cancelled {
+        eprintln!("Partial errors list kept in {}", list.partial().display());
+    }
+    let result = match cancelled {
+        true => list.flush(),
+        false => list.finish(),
+    };
+    match result {
+        Ok(()) => true,
+        Err(e) => {
+            eprintln!("Error: cannot write the errors list: {}", e);
+            false
+        }
+    }
+}
+
+/// Waits for the --exec commands still running, returning false if any of
+/// them failed.
+fn finish_exec() -> bool {
+    match EXEC.lock().unwrap().as_mut().map(Exec::finish) {
+        Some(Ok(0)) | None => true,
+        Some(Ok(failed)) => {
+            eprintln!("Error: {} of the commands failed", failed);
+            false
+        }
+        Some(Err(e)) => {
+            eprintln!("Error: {}", e);
+            false
+        }
+    }
+}
+
+/// The exporter of --otel, created when the comparison starts.
+#[cfg(feature = "otel")]
+static TRACER: OnceLock<Arc<Tracer>> = OnceLock::new();
+
+/// The progress of --heartbeat and --progress-json, created when the
+/// comparison starts.
+static PROGRESS: OnceLock<Arc<Progress>> = OnceLock::new();
+
+/// Runs the comparison between the hooks of the configuration files. A
+/// failing before hook cancels the comparison.
+fn compare_with_hooks(args: &Args) -> ExitCode {
+    if let Some(before) = &args.hooks.before {
+        if let Err(e) = hooks::run_before(before) {
+            eprintln!("Error: {}", e);
+            return ExitCode::FAILURE;
+        }
+    }
+    if args.otel && !start_telemetry(args) {
+        return ExitCode::FAILURE;
+    }
+    if !start_progress(args) {
+        return ExitCode::FAILURE;
+    }
+    let summary_json = match &args.summary_json {
+        Some(target) => match open_summary_json(target.as_deref()) {
+            Ok(out) => Some(out),
+            Err(e) => {
+                eprintln!("Error: {}: {}", target.as_deref().unwrap_or("stderr"), e);
+                return ExitCode::FAILURE;
+            }
+        },
+        None => None,
+    };
+    let started = Instant::now();
+    let mut code = compare(args);
+    if args.json {
+        finish_json(code);
+    }
+    if !finish_exec() && code == ExitCode::SUCCESS {
+        code = ExitCode::FAILURE;
+    }
+    if !finish_errors_out(code) && code == ExitCode::SUCCESS {
+        code = ExitCode::FAILURE;
+    }
+    if args.otel {
+        export_telemetry(code == ExitCode::SUCCESS);
+    }
+    if let Some(progress) = PROGRESS.get() {
+        progress.finished(&SUMMARY.lock().unwrap(), code == ExitCode::SUCCESS);
+    }
+    if let Some(after) = &args.hooks.after {
+        let summary = *SUMMARY.lock().unwrap();
+        if let Err(e) = hooks::run_after(after, &summary, code == ExitCode::SUCCESS) {
+            eprintln!("Error: {}", e);
+            code = ExitCode::FAILURE;
+        }
+    }
+    let summary = *SUMMARY.lock().unwrap();
+    let exit_code = match code {
+        ExitCode::SUCCESS if args.exit_count => summary.differing().min(MAX_COUNT_EXIT) as u8,
+        ExitCode::SUCCESS => 0,
+        code if code == ExitCode::from(CANCELLED) => CANCELLED,
+        _ => 1,
+    };
+    if let Some(out) = summary_json {
+        let mut json = summary.to_json(code == ExitCode::SUCCESS);
+        json["bytes"] = PROGRESS.get().map_or(0, |progress| progress.bytes()).into();
+        json["seconds"] = started.elapsed().as_secs_f64().into();
+        json["exit_code"] = exit_code.into();
+        let written = match out {
+            SummaryOut::Stream(mut out) => writeln!(out, "{}", json).and_then(|_| out.flush()),
+            SummaryOut::File(mut file) => writeln!(file, "{}", json).and_then(|_| file.finish()),
+        };
+        if let Err(e) = written {
+            eprintln!("Error: cannot write the summary: {}", e);
+            return ExitCode::FAILURE;
+        }
+    }
+    ExitCode::from(exit_code)
+}
+
+/// Highest exit code of --exit-count, below the ones shells use for
+/// commands that cannot run or were killed.
+const MAX_COUNT_EXIT: u64 = 125;
+
+/// The destination of --summary-json.
+enum SummaryOut {
+    /// Stderr or a file descriptor inherited from the caller
+    Stream(Box<dyn Write>),
+    /// A file, which only appears once the summary is written
+    File(ReportFile),
+}
+
+/// Opens the destination of --summary-json: stderr, a file descriptor
+/// inherited from the caller, or a file.
+fn open_summary_json(target: Option<&str>) -> io::Result<SummaryOut> {
+    match target {
+        None => Ok(SummaryOut::Stream(Box::new(io::stderr()))),
+        #[cfg(unix)]
+        Some(fd) if !fd.is_empty() && fd.bytes().all(|b| b.is_ascii_digit()) => {
+            let file = std::fs::OpenOptions::new()
+                .append(true)
+                .open(format!("/dev/fd/{}", fd))?;
+            Ok(SummaryOut::Stream(Box::new(file)))
+        }
+        Some(path) => Ok(SummaryOut::File(ReportFile::create(path)?)),
+    }
+}
+
+/// Creates the progress of the run if it is reported, writing its events
+/// to stderr or the file of --progress-json.
+fn start_progress(args: &Args) -> bool {
+    let progress = match &args.progress_json {
+        Some(Some(path)) => match File::create(path) {
+            Ok(file) => Progress::with_events(file),
+            Err(e) => {
+                eprintln!("Error: {}: {}", path.display(), e);
+                return false;
+            }
+        },
+        Some(None) => Progress::with_events(io::stderr()),
+        None if args.heartbeat.is_some() || args.summary_json.is_some() => Progress::new(),
+        None => return true,
+    };
+    PROGRESS.get_or_init(|| Arc::new(progress));
+    true
+}
+
+#[cfg(feature = "otel")]
+fn start_telemetry(args: &Args) -> bool {
+    TRACER.get_or_init(|| Arc::new(Tracer::from_env(args.timeout)));
+    true
+}
+
+#[cfg(not(feature = "otel"))]
+fn start_telemetry(_args: &Args) -> bool {
+    eprintln!("Error: cannot export telemetry: built without the otel feature");
+    false
+}
+
+/// Sends the spans and counters of the run. A failure only warns, as the
+/// comparison itself is done.
+#[cfg(feature = "otel")]
+fn export_telemetry(success: bool) {
+    if let Some(tracer) = TRACER.get() {
+        let summary = *SUMMARY.lock().unwrap();
+        if let Err(e) = tracer.finish(&summary, success) {
+            eprintln!("Warning: cannot export telemetry: {}", e);
+        }
+    }
+}
+
+#[cfg(not(feature = "otel"))]
+fn export_telemetry(_success: bool) {}
+
+/// Parses the command line. Comparisons take their defaults from FILE_CMP_*
+/// environment variables and the configuration files, whose settings are
+/// put in front of the arguments so that flags given on the command line
+/// override them.
+fn parse_args() -> io::Result<Cli> {
+    let mut argv: Vec<OsString> = std::env::args_os().collect();
+    let cli = Cli::command();
+    let subcommand = argv.get(1).and_then(|arg| arg.to_str());
+    // The settings go after the program, or after `compare`
+    let start = match subcommand {
+        Some("compare") => 2,
+        Some(arg) if arg == "help" || cli.find_subcommand(arg).is_some() => {
+            return Ok(Cli::parse_from(argv));
+        }
+        _ => 1,
+    };
+    let command = cli
+        .find_subcommand("compare")
+        .expect("compare is a subcommand");
+
+    let flags: Vec<&str> = command
+        .get_arguments()
+        .filter_map(|arg| arg.get_long())
+        .collect();
+    // Environment variables take precedence over the configuration files
+    let from_env: Vec<&str> = command
+        .get_arguments()
+        .filter(|arg| {
+            arg.get_env()
+                .is_some_and(|env| std::env::var_os(env).is_some())
+        })
+        .filter_map(|arg| arg.get_long())
+        .collect();
+    let add = |defaults: &mut Vec<OsString>, path: &Path, settings: &[Setting]| {
+        for (flag, value) in settings {
+            if !flags.contains(&flag.as_str()) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}: unknown setting '{}'", path.display(), flag),
+                ));
+            }
+            if from_env.contains(&flag.as_str()) {
+                continue;
+            }
+            // Joined with = for flags whose value is optional
+            defaults.push(OsString::from(match value {
+                Some(value) => format!("--{}={}", flag, value),
+                None => format!("--{}", flag),
+            }));
+        }
+        Ok(())
+    };
+
+    let mut configs = vec![];
+    let mut defaults = vec![];
+    let mut comparers = Comparers::new();
+    let mut policies = Policies::new();
+    let mut hooks = Hooks::default();
+    for path in config::config_files(&std::env::current_dir()?) {
+        let config = Config::read(&path)?;
+        add(&mut defaults, &path, &config.settings)?;
+        for (extension, command) in &config.comparers {
+            comparers.insert(extension, command.clone());
+        }
+        for (pattern, policy) in &config.policies {
+            policies.insert(pattern, *policy);
+        }
+        hooks.before = config.hooks.before.clone().or(hooks.before);
+        hooks.after = config.hooks.after.clone().or(hooks.after);
+        configs.push((path, config));
+    }
+    argv.splice(start..start, defaults.iter().cloned());
+
+    // The profile can be selected on the command line, in FILE_CMP_PROFILE
+    // or in a configuration file, and its settings override the others
+    let profile = command
+        .clone()
+        .ignore_errors(true)
+        .try_get_matches_from(&argv[start - 1..])
+        .ok()
+        .and_then(|matches| matches.get_one::<String>("profile").cloned());
+    if let Some(name) = profile {
+        let mut profile = vec![];
+        let mut found = false;
+        for (path, config) in &configs {
+            if let Some(settings) = config.profiles.get(&name) {
+                add(&mut profile, path, settings)?;
+                found = true;
+            }
+        }
+        if !found {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unknown profile '{}'", name),
+            ));
+        }
+        let end = start + defaults.len();
+        argv.splice(end..end, profile);
+    }
+    let mut cli = Cli::parse_from(argv);
+    let args = cli.compare_args_mut();
+    args.comparers = comparers;
+    args.policies = policies;
+    args.hooks = hooks;
+    Ok(cli)
+}
+
+/// Prints the digest of each file followed by its path, in the format of
+/// sha256sum and its siblings.
+fn run_hash(paths: &[String], algorithm: HashAlgorithm) -> ExitCode {
+    let mut code = ExitCode::SUCCESS;
+    for path in paths {
+        let digest = match path.as_str() {
+            "-" => hash::hash_reader(&mut io::stdin().lock(), algorithm),
+            _ => hash::hash_file(long_path::extended(Path::new(path)), algorithm, None),
+        };
+        match digest {
+            Ok(digest) => println!("{}  {}", hash::to_hex(&digest), path),
+            Err(e) => {
+                eprintln!("Error: {}: {}", path, e);
+                code = ExitCode::FAILURE;
+            }
+        }
+    }
+    code
+}
+
+/// Runs the jobs of the configuration files on their schedules until
+/// Ctrl-C, or each of them once. The pairs whose results changed since the
+/// last run of their job are printed with the job, the status and offset
+/// before and after, and the paths, separated by tabs.
+fn run_daemon(once: bool, state: &Path) -> ExitCode {
+    let jobs = match std::env::current_dir().and_then(|dir| {
+        let mut jobs = BTreeMap::new();
+        for path in config::config_files(&dir) {
+            jobs.extend(Config::read(&path)?.jobs);
+        }
+        Ok(jobs)
+    }) {
+        Ok(jobs) if jobs.is_empty() => {
+            eprintln!("Error: no jobs, add [jobs.NAME] tables to a configuration file");
+            return ExitCode::FAILURE;
+        }
+        Ok(jobs) => jobs,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    if let Err(e) = std::fs::create_dir_all(state) {
+        eprintln!("Error: {}: {}", state.display(), e);
+        return ExitCode::FAILURE;
+    }
+    let program = match std::env::current_exe() {
+        Ok(program) => program,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    catch_interrupt();
+    let started = SystemTime::now();
+    let mut due: Vec<(SystemTime, &String, &Job)> = jobs
+        .iter()
+        .map(|(name, job)| (job.schedule.first(started), name, job))
+        .collect();
+    let mut code = ExitCode::SUCCESS;
+    while let Some(next) = due.iter().map(|(time, ..)| *time).min() {
+        if !once {
+            // Sleeps in steps to stop soon after Ctrl-C
+            while let Ok(left) = next.duration_since(SystemTime::now()) {
+                if interrupt::interrupted() {
+                    return ExitCode::from(CANCELLED);
+                }
+                std::thread::sleep(left.min(Duration::from_secs(1)));
+            }
+        }
+        let i = due
+            .iter()
+            .position(|(time, ..)| *time == next)
+            .expect("next is due");
+        let (time, name, job) = due.remove(i);
+        if !run_job(name, job, &program, state) {
+            code = ExitCode::FAILURE;
+        }
+        if interrupt::interrupted() {
+            return ExitCode::from(CANCELLED);
+        }
+        // A run that overran the next time starts again right away, once
+        if !once {
+            let next = job.schedule.next_after(time).max(SystemTime::now());
+            due.push((next, name, job));
+        }
+    }
+    code
+}
+
+/// Runs a job of the daemon and prints the pairs whose results changed
+/// since its last run, returning false if it could not run.
+fn run_job(name: &str, job: &Job, program: &Path, state: &Path) -> bool {
+    let path = state.join(format!("{}.json", name));
+    let result = daemon::load_state(&path).and_then(|before| {
+        let (after, exit_code) = job.run(name, program)?;
+        daemon::save_state(&path, &after)?;
+        Ok((daemon::changes(&before, &after), after.len(), exit_code))
+    });
+    let (changes, results, exit_code) = match result {
+        Ok(run) => run,
+        Err(e) => {
+            eprintln!("Error: job '{}': {}", name, e);
+            return false;
+        }
+    };
+    let field = |result: &Option<(String, String)>| match result {
+        Some((status, offset)) => format!("{}\t{}", status, offset),
+        None => "-\t-".to_string(),
+    };
+    for change in &changes {
+        println!(
+            "{}\t{}\t{}\t{}\t{}",
+            name,
+            field(&change.before),
+            field(&change.after),
+            change.path1,
+            change.path2
+        );
+    }
+    let _ = io::stdout().flush();
+    eprintln!(
+        "{} {}: {} results, {} changed, exit code {}",
+        evidence::format_time(SystemTime::now()),
+        name,
+        results,
+        changes.len(),
+        exit_code.map_or("-".to_string(), |code| code.to_string())
+    );
+    true
+}
+
+fn run_doctor(path: &str) -> ExitCode {
+    let report = match doctor::probe(Path::new(path)) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("Error: {}: {}", path, e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let yes_no = |supported| match supported {
+        true => "yes",
+        false => "no",
+    };
+    let limit = |limit: Option<u64>| match limit {
+        Some(limit) => format!("{} bytes", limit),
+        None => "unknown".to_string(),
+    };
+    println!("Case-sensitive names:  {}", yes_no(report.case_sensitive));
+    println!(
+        "Timestamp precision:   {}",
+        match report.mtime_granularity {
+            Some(granularity) => format!("{:?}", granularity),
+            None => "unknown".to_string(),
+        }
+    );
+    println!("Sparse files:          {}", yes_no(report.sparse_files));
+    println!("Symbolic links:        {}", yes_no(report.symlinks));
+    println!("Longest name:          {}", limit(report.max_name_len));
+    println!("Longest path:          {}", limit(report.max_path_len));
+    println!();
+    for advice in report.advice() {
+        println!("- {}", advice);
+    }
+    ExitCode::SUCCESS
+}
+
+fn run_agent(path: &str, hash: HashAlgorithm, blocks: Option<usize>) -> ExitCode {
+    let options = CompareOptions {
+        hash,
+        ..Default::default()
+    };
+    let out = &mut io::stdout().lock();
+    let result = match blocks {
+        Some(0) => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "the block size must be positive",
+        )),
+        Some(block_len) => File::open(path)
+            .and_then(|mut file| delta::write_signature(&mut file, block_len, hash, out)),
+        None => agent::write_records(path, &options, out),
+    };
+    match result {
+        Ok(_) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
 
+fn run_verify_store(path: &str, machine_readable: bool, diffs_only: bool) -> ExitCode {
+    match cas::verify_store(path, None) {
+        Ok(results) => {
+            let mut code = ExitCode::SUCCESS;
             for (path, file_diff) in results {
-                if args.diffs_only && file_diff == FileDiff::Equal {
+                if file_diff != FileDiff::Equal {
+                    code = ExitCode::FAILURE;
+                } else if diffs_only {
                     continue;
                 }
                 println!(
                     "{}\t{}{}",
                     file_diff.as_number(),
                     path.display(),
-                    if args.machine_readable {
-                        "".to_string()
-                    } else {
-                        format!("\t({})", file_diff.as_desc())
+                    match (machine_readable, &file_diff) {
+                        (true, _) => "",
+                        (false, FileDiff::Equal) => "\t(ok)",
+                        (false, _) => "\t(corrupt)",
                     }
                 );
             }
-            ExitCode::SUCCESS
+            code
         }
-        Ok(false) => match compare_files(&args.path1, &args.path2, args.quick) {
-            Ok(result @ _) => {
-                if args.machine_readable {
-                    print!("{}", result.as_number())
-                } else {
-                    print!(
-                        "{}",
-                        match result {
-                            FileDiff::Equal => "Files are equal".to_string(),
-                            FileDiff::Different(o @ _) => {
-                                format!("Files differ at byte {}", o)
-                            }
-                            _ => "This should never happen.".to_string(),
-                        }
-                    )
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(feature = "torrent")]
+fn run_verify_torrent(
+    torrent: &str,
+    path: &str,
+    machine_readable: bool,
+    diffs_only: bool,
+) -> ExitCode {
+    match torrent::verify_torrent(torrent, path) {
+        Ok(reports) => {
+            let mut code = ExitCode::SUCCESS;
+            for report in reports {
+                let file_diff = report.file_diff();
+                if file_diff != FileDiff::Equal {
+                    code = ExitCode::FAILURE;
+                } else if diffs_only {
+                    continue;
                 }
-                ExitCode::SUCCESS
+                let ranges = |label, ranges: &[std::ops::Range<u64>]| {
+                    ranges
+                        .iter()
+                        .map(|range| format!("{} {}-{}", label, range.start, range.end - 1))
+                        .collect::<Vec<_>>()
+                };
+                let parts = [
+                    ranges("incomplete", &report.incomplete),
+                    ranges("corrupt", &report.corrupt),
+                ]
+                .concat();
+                println!(
+                    "{}\t{}{}",
+                    file_diff.as_number(),
+                    report.path.display(),
+                    match (machine_readable, parts.is_empty()) {
+                        (true, _) => String::new(),
+                        (false, true) => "\t(ok)".to_string(),
+                        (false, false) => format!("\t({})", parts.join(", ")),
+                    }
+                );
+            }
+            code
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(not(feature = "torrent"))]
+fn run_verify_torrent(
+    _torrent: &str,
+    _path: &str,
+    _machine_readable: bool,
+    _diffs_only: bool,
+) -> ExitCode {
+    eprintln!("Error: cannot verify torrents: built without the torrent feature");
+    ExitCode::FAILURE
+}
+
+fn run_serve(list: &str, listen: &str, threads: usize) -> ExitCode {
+    let options = CompareOptions {
+        threads,
+        pipeline_depth: 2,
+        ..Default::default()
+    };
+    let result = File::open(list)
+        .and_then(|list| Pairs::new(BufReader::new(list)).collect::<io::Result<Vec<_>>>())
+        .and_then(|pairs| {
+            let listener = TcpListener::bind(listen)?;
+            eprintln!(
+                "Serving {} pairs on http://{}",
+                pairs.len(),
+                listener.local_addr()?
+            );
+            serve::serve(listener, pairs, options)
+        });
+    match result {
+        Ok(_) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run_bench(
+    path1: &str,
+    path2: &str,
+    runs: usize,
+    threads: Option<usize>,
+    drop_caches: bool,
+) -> ExitCode {
+    let threads = threads.unwrap_or_else(|| {
+        std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1)
+    });
+    if drop_caches && !matches!(bench::drop_cache(path1), Ok(true)) {
+        eprintln!("Warning: cannot drop the page cache on this platform, reads may be cached");
+    }
+
+    println!(
+        "{:<10} {:>10} {:>8} {:>10} {:>14}",
+        "mode", "chunk", "threads", "median", "throughput"
+    );
+    let print_row = |result: &BenchResult| {
+        println!(
+            "{:<10} {:>10} {:>8} {:>9.3}s {:>12}/s",
+            result.mode,
+            match (result.mode, result.chunk_size) {
+                ("mmap", _) => "-".to_string(),
+                (_, Some(size)) => format_size(size as u64),
+                (_, None) => "adaptive".to_string(),
+            },
+            result.threads,
+            result.median().as_secs_f64(),
+            format_size(result.throughput() as u64),
+        );
+    };
+
+    match bench::run(path1, path2, runs, threads, drop_caches, print_row) {
+        Ok(_) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn compare(args: &Args) -> ExitCode {
+    let mut options = CompareOptions {
+        quick: args.quick,
+        policy: match args.size_only {
+            true => Policy::SizeOnly,
+            false => Policy::ByteExact,
+        },
+        threads: args.threads,
+        jobs: args.jobs,
+        chunk_size: args.chunk_size,
+        pipeline_depth: args.pipeline_depth,
+        throttle: args
+            .bwlimit
+            .map(|rate| Arc::new(Throttle::new(rate as u64))),
+        mmap: args.mmap,
+        auto_io: args.auto_io,
+        max_memory: args.max_memory.map(|limit| limit as u64),
+        hash_first: args.hash_first,
+        hash: args.hash,
+        skip_blank: args.skip_blank,
+        timeout: args.timeout,
+        comparers: match args.comparers.is_empty() {
+            true => None,
+            false => Some(Arc::new(args.comparers.clone())),
+        },
+        retries: args.retries,
+        paranoid: args.paranoid,
+        sidecars: args.sidecars,
+        trailing_padding: match (args.ignore_trailing_padding, args.ignore_final_newline) {
+            (Some(Some(byte)), _) => Some(Padding::Byte(byte)),
+            (Some(None), _) => Some(Padding::Filler),
+            (None, true) => Some(Padding::FinalNewline),
+            (None, false) => None,
+        },
+        media: args.media,
+        sample_tolerance: args.sample_tolerance,
+        office: args.office,
+        sql_dump: args.sql_dump,
+        strip_prefix: args.strip_prefix_regex.clone(),
+        word_swap: args.word_swap,
+        links: args.links,
+        prefetch: args.prefetch_metadata,
+        policies: match args.policies.is_empty() {
+            true => None,
+            false => Some(Arc::new(args.policies.clone())),
+        },
+        progress: PROGRESS.get().cloned(),
+        #[cfg(feature = "otel")]
+        tracer: TRACER.get().cloned(),
+    };
+
+    if let Some(limit) = options.max_memory {
+        if let Err(e) = memory::fit(&mut options, limit) {
+            eprintln!("Error: {}", e);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    if args.selinux {
+        eprintln!("Error: cannot compare SELinux contexts: only supported on Linux");
+        return ExitCode::FAILURE;
+    }
+
+    #[cfg(not(feature = "office"))]
+    if args.office {
+        eprintln!("Error: cannot compare Office documents: built without the office feature");
+        return ExitCode::FAILURE;
+    }
+
+    // Runs until the comparison returns
+    let _heartbeat = match (args.heartbeat, &options.progress) {
+        (Some(interval), Some(progress)) => {
+            Some(Heartbeat::start(progress.clone(), interval, io::stderr()))
+        }
+        _ => None,
+    };
+
+    if let Some(path) = &args.evidence_log {
+        match EvidenceLog::open(path) {
+            Ok(log) => *EVIDENCE.lock().unwrap() = Some(log),
+            Err(e) => {
+                eprintln!("Error: {}: {}", path.display(), e);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    if let Some(command) = &args.exec {
+        *EXEC.lock().unwrap() = Some(Exec::new(command, args.parallel_exec));
+    }
+    if let Some(path) = &args.errors_out {
+        match ReportFile::create(path) {
+            Ok(list) => *ERRORS_OUT.lock().unwrap() = Some(list),
+            Err(e) => {
+                eprintln!("Error: {}: {}", path.display(), e);
+                return ExitCode::FAILURE;
             }
+        }
+    }
+
+    if args.rpc {
+        return match rpc::serve_rpc(io::stdin().lock(), io::stdout(), options) {
+            Ok(()) => ExitCode::SUCCESS,
             Err(e) => {
                 eprintln!("Error: {}", e);
                 ExitCode::FAILURE
             }
-        },
-        Err(e) => {
-            eprintln!("Error: {}", e);
-            ExitCode::FAILURE
+        };
+    }
+    if args.pairs_from_stdin {
+        return compare_pairs(pairs(io::stdin().lock(), args), args, &options);
+    }
+    if let Some(list) = &args.from_file {
+        return match File::open(list) {
+            Ok(list) => compare_pairs(pairs(BufReader::new(list), args), args, &options),
+            Err(e) => {
+                eprintln!("Error: {}: {}", list, e);
+                ExitCode::FAILURE
+            }
+        };
+    }
+
+    if let Some(baseline) = &args.baseline {
+        let candidates = args
+            .path1
+            .iter()
+            .chain(&args.path2)
+            .chain(&args.more_paths)
+            .map(Path::new);
+        return compare_baseline(baseline, candidates, args, &options);
+    }
+    if args.quorum {
+        let replicas: Vec<&Path> = args
+            .path1
+            .iter()
+            .chain(&args.path2)
+            .chain(&args.more_paths)
+            .map(Path::new)
+            .collect();
+        return compare_quorum(&replicas, args, &options);
+    }
+    if !args.pair.is_empty() || !args.more_paths.is_empty() {
+        if !args.more_paths.len().is_multiple_of(2) {
+            eprintln!("Error: expected an even number of paths, two per pair");
+            return ExitCode::FAILURE;
+        }
+        let paths: Vec<_> = args
+            .path1
+            .iter()
+            .chain(&args.path2)
+            .chain(&args.more_paths)
+            .map(PathBuf::from)
+            .collect();
+        let pairs = paths
+            .chunks(2)
+            .map(|pair| (pair[0].clone(), pair[1].clone()))
+            .chain(args.pair.iter().cloned())
+            .map(Ok);
+        if let Some(progress) = &options.progress {
+            progress.set_total((paths.len() / 2 + args.pair.len()) as u64);
         }
+        return compare_pairs(pairs, args, &options);
+    }
+
+    let path1 = args.path1.as_deref().expect("path1 is required");
+    if let Some(byte) = args.expect_fill {
+        return print_fill_result(fill::compare_fill(path1, byte, &options), byte, args);
+    }
+    let path2 = args.path2.as_deref().expect("path2 is required");
+    if args.image {
+        return compare_images(path1, path2, args, &options);
+    }
+    if args.parquet {
+        return compare_parquet(path1, path2, args);
+    }
+    if args.pdf {
+        return compare_pdf(path1, path2, args);
+    }
+    if let Some(avg) = args.cdc {
+        return compare_cdc(path1, path2, avg, args, &options);
     }
+    if args.from_end {
+        return compare_from_end(path1, path2, args, &options);
+    }
+    if let Some(svg) = &args.heatmap {
+        return compare_heatmap(path1, path2, svg.as_deref(), args, &options);
+    }
+    let result = if path1 == "-" || path2 == "-" {
+        compare_stdin(path1, path2, &options)
+    } else if path1.starts_with("s3://") || path2.starts_with("s3://") {
+        return compare_s3(path1, path2, args, &options);
+    } else if path1.starts_with("az://") || path2.starts_with("az://") {
+        return compare_azure(path1, path2, args, &options);
+    } else if path1.starts_with("gs://") || path2.starts_with("gs://") {
+        return compare_gcs(path1, path2, args, &options);
+    } else if path1.starts_with("git:") || path2.starts_with("git:") {
+        return compare_git(path1, path2, args, &options);
+    } else if ssh::is_remote_url(path1) || ssh::is_remote_url(path2) {
+        return compare_ssh(path1, path2, args, &options);
+    } else if http::is_url(path1) || http::is_url(path2) {
+        compare_url(path1, path2, &options)
+    } else {
+        match is_dir(path1) {
+            Ok(true) => return compare_dirs(path1, path2, args, &options),
+            Ok(false) if device::is_device_file(path1) != device::is_device_file(path2) => {
+                return compare_with_device(path1, path2, args, &options);
+            }
+            Ok(false) => {
+                let result = compare_files_with(path1, path2, &options);
+                if result.is_err() {
+                    record_failure(Path::new(path1), Path::new(path2), args);
+                }
+                if let Ok(file_diff) = &result {
+                    if !log_evidence(Path::new(path1), Path::new(path2), file_diff) {
+                        return ExitCode::FAILURE;
+                    }
+                }
+                let attributes = match &result {
+                    Ok(file_diff) => {
+                        attribute_diffs(Path::new(path1), Path::new(path2), file_diff, args)
+                    }
+                    Err(_) => vec![],
+                };
+                let file_diff = result.as_ref().ok().copied();
+                let digests = match (&file_diff, args.print_hashes) {
+                    (Some(file_diff), true) => {
+                        let (path1, path2) = (Path::new(path1), Path::new(path2));
+                        match hash_fields(path1, path2, file_diff, args, &options) {
+                            Ok(digests) => Some(digests),
+                            Err(e) => {
+                                eprintln!("Error: cannot hash {}", e);
+                                return ExitCode::FAILURE;
+                            }
+                        }
+                    }
+                    _ => None,
+                };
+                let similar = file_diff.and_then(|file_diff| {
+                    let (path1, path2) = (Path::new(path1), Path::new(path2));
+                    similarity(path1, path2, &file_diff, args, &options)
+                });
+                let code = print_file_result(result, &attributes, args);
+                if let Some(percent) = similar {
+                    match args.machine_readable {
+                        true => print!("\tsimilar:{:.1}", percent),
+                        false => print!(", {:.1}% similar", percent),
+                    }
+                }
+                if let Some(digests) = digests {
+                    match args.machine_readable {
+                        true => print!("\t{}", digests),
+                        false => print!(", {} {}", args.hash, digests.replace('\t', " and ")),
+                    }
+                }
+                if let Some(file_diff) = &file_diff {
+                    run_exec(Path::new(path1), Path::new(path2), file_diff, args);
+                }
+                if let (Some(file_diff), Some(_)) = (file_diff, &args.tool) {
+                    // The result of a single file doesn't end the line
+                    println!();
+                    launch_tool(Path::new(path1), Path::new(path2), &file_diff, args);
+                }
+                return code;
+            }
+            Err(e) => Err(e),
+        }
+    };
+    print_result(result, args)
+}
+
+/// Returns the attributes that differ between two compared files with
+/// --attributes, --selinux and --extents, to be reported along with the result of
+/// their contents.
+fn attribute_diffs(
+    path1: &Path,
+    path2: &Path,
+    file_diff: &FileDiff,
+    args: &Args,
+) -> Vec<&'static str> {
+    if matches!(file_diff, FileDiff::LeftOnly | FileDiff::RightOnly) {
+        return vec![];
+    }
+    let mut names = match args.attributes {
+        true => attributes::compare_attributes(path1, path2).unwrap_or_else(|e| {
+            eprintln!("Error: {}: {}", path1.display(), e);
+            vec![]
+        }),
+        false => vec![],
+    };
+    if args.selinux {
+        match selinux::contexts_differ(path1, path2) {
+            Ok(true) => names.push("selinux"),
+            Ok(false) => {}
+            Err(e) => eprintln!("Error: {}: {}", path1.display(), e),
+        }
+    }
+    if args.extents {
+        match sparse::compare_layouts(path1, path2) {
+            Ok(layout) => names.extend(layout),
+            Err(e) => eprintln!("Error: {}: {}", path1.display(), e),
+        }
+    }
+    names
+}
+
+/// Opens a pair of differing files in the --tool, after asking with
+/// --prompt.
+fn launch_tool(path1: &Path, path2: &Path, file_diff: &FileDiff, args: &Args) {
+    let command = match (&args.tool, file_diff) {
+        (Some(command), FileDiff::Different(_) | FileDiff::Truncated(..)) => command,
+        _ => return,
+    };
+    if args.prompt {
+        eprint!("Launch '{}' for {}? [Y/n] ", command, path1.display());
+        let mut answer = String::new();
+        if io::stdin().read_line(&mut answer).is_err()
+            || answer.trim().to_lowercase().starts_with('n')
+        {
+            return;
+        }
+    }
+    if let Err(e) = tool::launch(command, path1, path2) {
+        eprintln!("Error: cannot launch '{}': {}", command, e);
+    }
+}
+
+/// Hashes both compared files with --print-hashes, returning the digests
+/// as two fields to follow their paths, with - for a missing side.
+fn hash_fields(
+    path1: &Path,
+    path2: &Path,
+    file_diff: &FileDiff,
+    args: &Args,
+    options: &CompareOptions,
+) -> io::Result<String> {
+    let digest = |path: &Path, present: bool| match present {
+        true => hash::hash_file(
+            long_path::extended(path),
+            args.hash,
+            options.throttle.as_deref(),
+        )
+        .map(|digest| hash::to_hex(&digest))
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e))),
+        false => Ok("-".to_string()),
+    };
+    Ok(format!(
+        "{}\t{}",
+        digest(path1, *file_diff != FileDiff::RightOnly)?,
+        digest(path2, *file_diff != FileDiff::LeftOnly)?
+    ))
+}
+
+/// What an entry tells about a pair beyond its result.
+#[derive(Clone, Copy, Debug)]
+enum Detail {
+    /// The share of the content of differing files in common, with
+    /// --similar-threshold
+    Similar(f64),
+    /// How equal files are stored, with --dedup-status
+    Stored(Storage),
+}
+
+/// Describes a result, followed by the attributes that differ if any.
+fn describe(
+    file_diff: &FileDiff,
+    attributes: &[&str],
+    detail: Option<Detail>,
+    args: &Args,
+) -> String {
+    if args.machine_readable {
+        let mut fields = String::new();
+        if !attributes.is_empty() {
+            fields.push_str(&format!("\t{}", attributes.join(",")));
+        }
+        match detail {
+            Some(Detail::Similar(percent)) => fields.push_str(&format!("\tsimilar:{:.1}", percent)),
+            Some(Detail::Stored(storage)) => {
+                fields.push_str(&format!("\tstorage:{}", storage.name()))
+            }
+            None => {}
+        }
+        return fields;
+    }
+    let desc = match detail {
+        Some(Detail::Similar(percent)) => format!("similar {:.1}%", percent),
+        Some(Detail::Stored(storage)) => format!("{}, {}", file_diff.as_desc(), storage.name()),
+        None => file_diff.as_desc().to_string(),
+    };
+    match attributes.is_empty() {
+        true => format!("\t({})", desc),
+        false => format!("\t({}, attributes differ: {})", desc, attributes.join(", ")),
+    }
+}
+
+/// Returns what to tell about a pair beyond its result: how similar files
+/// that differ are or how equal ones are stored, if asked for.
+fn detail(
+    path1: &Path,
+    path2: &Path,
+    file_diff: &FileDiff,
+    args: &Args,
+    options: &CompareOptions,
+) -> Option<Detail> {
+    if let Some(percent) = similarity(path1, path2, file_diff, args, options) {
+        return Some(Detail::Similar(percent));
+    }
+    if !args.dedup_status || *file_diff != FileDiff::Equal {
+        return None;
+    }
+    match same_file::storage(path1, path2) {
+        Ok(storage) => Some(Detail::Stored(storage)),
+        Err(e) => {
+            eprintln!("Error: {}: {}", path1.display(), e);
+            None
+        }
+    }
+}
+
+/// Returns how much of their content a pair of files that differ have in
+/// common with --similar-threshold, if it is at least the threshold.
+fn similarity(
+    path1: &Path,
+    path2: &Path,
+    file_diff: &FileDiff,
+    args: &Args,
+    options: &CompareOptions,
+) -> Option<f64> {
+    let threshold = args.similar_threshold?;
+    if !matches!(file_diff, FileDiff::Different(_) | FileDiff::Truncated(..)) {
+        return None;
+    }
+    match similar::classify(path1, path2, threshold, options) {
+        Ok(Likeness::SimilarTo(percent)) => Some(percent),
+        Ok(Likeness::Different) => None,
+        Err(e) => {
+            eprintln!("Error: {}: {}", path1.display(), e);
+            None
+        }
+    }
+}
+
+/// Prints a result in the layout of --porcelain=v1, which must not change:
+/// a line with the status, offset and both paths separated by tabs.
+fn print_porcelain(path1: &Path, path2: &Path, file_diff: &FileDiff) {
+    println!(
+        "{}\t{}\t{}\t{}",
+        file_diff.as_status(),
+        file_diff
+            .offset()
+            .as_ref()
+            .map_or("-".to_string(), ToString::to_string),
+        path1.display(),
+        path2.display()
+    );
+}
+
+/// What --json printed so far, to end the output as a whole JSON value.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum JsonOut {
+    Nothing,
+    /// The result of a pair of files
+    Object,
+    /// The array of the results of a run over many files, with the number
+    /// printed, which is opened with the first
+    Array(usize),
+}
+
+static JSON_OUT: Mutex<JsonOut> = Mutex::new(JsonOut::Nothing);
+
+/// Returns the result of a pair of files as a JSON object.
+fn to_json(path: &str, file_diff: &FileDiff) -> String {
+    let result = PathDiff {
+        path: path.to_string(),
+        diff: *file_diff,
+    };
+    serde_json::to_string(&result).unwrap()
+}
+
+/// Prints the result of a pair of files as a JSON object.
+fn print_json(path: &str, file_diff: &FileDiff) {
+    println!("{}", to_json(path, file_diff));
+    *JSON_OUT.lock().unwrap() = JsonOut::Object;
+}
+
+/// Starts the array of the results of a run over many files with --json,
+/// even if none are printed in the end.
+fn start_json_array() {
+    let mut out = JSON_OUT.lock().unwrap();
+    if *out == JsonOut::Nothing {
+        *out = JsonOut::Array(0);
+    }
+}
+
+/// Prints a result as an element of the --json array, on a line of its own.
+fn print_json_entry(path: &str, file_diff: &FileDiff) {
+    let mut out = JSON_OUT.lock().unwrap();
+    let printed = match *out {
+        JsonOut::Array(printed) => printed,
+        _ => 0,
+    };
+    let separator = match printed {
+        0 => "[",
+        _ => ",",
+    };
+    println!("{}", separator);
+    print!("{}", to_json(path, file_diff));
+    let _ = io::stdout().flush();
+    *out = JsonOut::Array(printed + 1);
+}
+
+/// Ends the --json output: closes the array of a run over many files, or
+/// prints an empty one if a run succeeded without any results, e.g. for
+/// empty trees.
+fn finish_json(code: ExitCode) {
+    match *JSON_OUT.lock().unwrap() {
+        JsonOut::Array(0) => println!("[]"),
+        JsonOut::Array(_) => println!("\n]"),
+        JsonOut::Nothing if code == ExitCode::SUCCESS => println!("[]"),
+        JsonOut::Nothing | JsonOut::Object => {}
+    }
+}
+
+/// Prints one result of comparing directory trees, with the paths on both
+/// sides for --porcelain.
+fn print_entry(
+    path: impl Display,
+    sides: (&Path, &Path),
+    file_diff: &FileDiff,
+    attributes: &[&str],
+    detail: Option<Detail>,
+    args: &Args,
+) {
+    SUMMARY.lock().unwrap().add(file_diff);
+    if args.json {
+        start_json_array();
+    }
+    if args.brief {
+        return;
+    }
+    if args.diffs_only && *file_diff == FileDiff::Equal && attributes.is_empty() {
+        return;
+    }
+    if args.porcelain.is_some() {
+        return print_porcelain(sides.0, sides.1, file_diff);
+    }
+    if args.json {
+        return print_json_entry(&path.to_string(), file_diff);
+    }
+    println!(
+        "{}\t{}{}",
+        file_diff.as_number(),
+        path,
+        describe(file_diff, attributes, detail, args)
+    );
+}
+
+/// Exit code of a run cancelled with Ctrl-C, the one shells use for SIGINT.
+const CANCELLED: u8 = 130;
+
+/// Catches Ctrl-C during a run over many files, which then stops after the
+/// current one.
+fn catch_interrupt() {
+    if let Err(e) = interrupt::catch_interrupt() {
+        eprintln!("Warning: could not catch Ctrl-C: {}", e);
+    }
+}
+
+/// Reports that a run was cancelled, with the counts of the results printed
+/// before, and returns the exit code for it.
+fn cancelled() -> ExitCode {
+    let summary = *SUMMARY.lock().unwrap();
+    eprintln!(
+        "Cancelled: {} equal, {} different, {} left only, {} right only",
+        summary.equal, summary.different, summary.left_only, summary.right_only
+    );
+    ExitCode::from(CANCELLED)
+}
+
+/// Returns the absolute form of a path for --all-paths, or the path as given
+/// if the current directory is gone.
+fn absolute(path: &Path) -> PathBuf {
+    std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Prints whether two trees differ with --brief, instead of their entries.
+fn print_verdict(args: &Args) {
+    if !args.brief {
+        return;
+    }
+    let differing = SUMMARY.lock().unwrap().differing();
+    match (args.machine_readable, differing) {
+        (true, _) => print!("{}", differing),
+        (false, 0) => print!("Directories are identical"),
+        (false, 1) => print!("Directories differ (1 file)"),
+        (false, _) => print!("Directories differ ({} files)", differing),
+    }
+}
+
+/// Prints the results of comparing two trees. Remote trees are compared with
+/// the local side first, so `swapped` is set when the remote side was given
+/// first.
+fn print_tree(
+    results: io::Result<Vec<(PathBuf, FileDiff)>>,
+    swapped: bool,
+    args: &Args,
+) -> ExitCode {
+    match results {
+        Ok(results) => {
+            for (path, file_diff) in results {
+                // The other side of a pair isn't known, only that of an object
+                // missing locally
+                let unknown = Path::new("-");
+                let sides = match (file_diff, swapped) {
+                    (FileDiff::RightOnly, false) => (unknown, path.as_path()),
+                    (FileDiff::RightOnly, true) => (path.as_path(), unknown),
+                    (_, false) => (path.as_path(), unknown),
+                    (_, true) => (unknown, path.as_path()),
+                };
+                let file_diff = match swapped {
+                    true => file_diff.swap_sides(),
+                    false => file_diff,
+                };
+                print_entry(path.display(), sides, &file_diff, &[], None, args);
+            }
+            print_verdict(args);
+            ExitCode::SUCCESS
+        }
+        Err(e) => print_result(Err(e), args),
+    }
+}
+
+/// Prints the result of comparing two files.
+fn print_result(result: io::Result<FileDiff>, args: &Args) -> ExitCode {
+    print_file_result(result, &[], args)
+}
+
+/// Prints the result of comparing two files and the attributes that differ.
+fn print_file_result(result: io::Result<FileDiff>, attributes: &[&str], args: &Args) -> ExitCode {
+    match result {
+        Ok(result) => {
+            SUMMARY.lock().unwrap().add(&result);
+            if args.porcelain.is_some() {
+                let path = |path: &Option<String>| PathBuf::from(path.as_deref().unwrap_or("-"));
+                print_porcelain(&path(&args.path1), &path(&args.path2), &result);
+            } else if args.json {
+                print_json(args.path1.as_deref().unwrap_or("-"), &result);
+            } else if args.machine_readable {
+                print!("{}", result.as_number());
+                if !attributes.is_empty() {
+                    print!("\t{}", attributes.join(","));
+                }
+            } else {
+                print!(
+                    "{}",
+                    match result {
+                        FileDiff::Equal => "Files are equal".to_string(),
+                        FileDiff::Different(o) => {
+                            format!(
+                                "Files differ at byte {}{}",
+                                format_thousands(o as u64),
+                                locate(o as u64, args)
+                            )
+                        }
+                        FileDiff::Truncated(side, o) => {
+                            let shorter = match side {
+                                Side::Left => args.path1.as_deref(),
+                                Side::Right => args.path2.as_deref(),
+                            };
+                            format!(
+                                "EOF on {} after byte {}{}",
+                                shorter.unwrap_or("-"),
+                                format_thousands(o as u64),
+                                locate(o as u64, args)
+                            )
+                        }
+                        _ => "This should never happen.".to_string(),
+                    }
+                );
+                if !attributes.is_empty() {
+                    print!(", attributes differ: {}", attributes.join(", "));
+                }
+            }
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Describes where a difference at `offset` of two local files is for
+/// people: how far into them in binary units, when they are large enough
+/// for that to help, and the line and column when both are text, past
+/// their first line.
+fn locate(offset: u64, args: &Args) -> String {
+    let (Some(path1), Some(path2)) = (args.path1.as_deref(), args.path2.as_deref()) else {
+        return String::new();
+    };
+    let metadata = |path: &str| std::fs::metadata(long_path::extended(Path::new(path)));
+    let (len1, len2) = match (metadata(path1), metadata(path2)) {
+        (Ok(m1), Ok(m2)) if m1.is_file() && m2.is_file() => (m1.len(), m2.len()),
+        _ => return String::new(),
+    };
+    let mut location = String::new();
+    if len1.max(len2) >= 1024 {
+        let lens = match len1 == len2 {
+            true => format_size(len1),
+            false => format!("{} and {}", format_size(len1), format_size(len2)),
+        };
+        location.push_str(&format!(" ({} of {})", format_size(offset), lens));
+    }
+    // On the first line the offset tells the column already
+    let position = |path| lines::position_at(path, offset).ok().flatten();
+    if let (Some((line @ 2.., column)), Some(_)) = (position(path1), position(path2)) {
+        location.push_str(&format!(
+            ", line {}, column {}",
+            format_thousands(line),
+            format_thousands(column)
+        ));
+    }
+    location
+}
+
+/// Compares a disk image with the device it was written to, in either
+/// order, and tells what the device holds after the image.
+fn compare_with_device(
+    path1: &str,
+    path2: &str,
+    args: &Args,
+    options: &CompareOptions,
+) -> ExitCode {
+    let result = match device::is_device_file(path1) {
+        true => device::compare_image(path2, path1, options)
+            .map(|(file_diff, trailing)| (file_diff.swap_sides(), trailing)),
+        false => device::compare_image(path1, path2, options),
+    };
+    let (file_diff, trailing) = match result {
+        Ok((file_diff, trailing)) => (Ok(file_diff), trailing),
+        Err(e) => (Err(e), None),
+    };
+    let code = print_file_result(file_diff, &[], args);
+    let plain = args.machine_readable || args.porcelain.is_some();
+    if let (Some(trailing), false) = (trailing, plain) {
+        match trailing.data_at {
+            None => print!(
+                ", the device has {} more after the image, all blank",
+                format_size(trailing.len)
+            ),
+            Some(offset) => print!(
+                ", the device has {} more after the image, with data at byte {}",
+                format_size(trailing.len),
+                offset
+            ),
+        }
+    }
+    code
+}
+
+/// Prints the result of comparing a file with a fill byte.
+fn print_fill_result(result: io::Result<FileDiff>, byte: u8, args: &Args) -> ExitCode {
+    match result {
+        Ok(result) => {
+            SUMMARY.lock().unwrap().add(&result);
+            match (args.machine_readable, result) {
+                (true, result) => print!("{}", result.as_number()),
+                (false, FileDiff::Different(o) | FileDiff::Truncated(_, o)) => {
+                    print!("File deviates from the fill of {:#04x} at byte {}", byte, o)
+                }
+                (false, _) => print!("File is filled with {:#04x}", byte),
+            }
+            ExitCode::SUCCESS
+        }
+        Err(e) => print_result(Err(e), args),
+    }
+}
+
+fn pairs<R: BufRead>(reader: R, args: &Args) -> Pairs<R> {
+    match args.null_terminated {
+        true => Pairs::nul_terminated(reader),
+        false => Pairs::new(reader),
+    }
+}
+
+/// Compares two local directory trees, printing an entry per file.
+fn compare_dirs(path1: &str, path2: &str, args: &Args, options: &CompareOptions) -> ExitCode {
+    let mut recopy = match &args.recopy_list {
+        Some(list) => match ReportFile::create(list) {
+            Ok(file) => Some(file),
+            Err(e) => {
+                eprintln!("Error: {}: {}", list.display(), e);
+                return ExitCode::FAILURE;
+            }
+        },
+        None => None,
+    };
+    catch_interrupt();
+    let mut walk = DirWalk::new(path1, path2, options);
+    while let Some((path, file_diff)) = walk.next() {
+        report_failed(&mut walk, args);
+        if interrupt::interrupted() {
+            if let Some(mut list) = recopy {
+                let _ = list.flush();
+                eprintln!("Partial recopy list kept in {}", list.partial().display());
+            }
+            return cancelled();
+        }
+        let root = match file_diff {
+            FileDiff::RightOnly => path2,
+            _ => path1,
+        };
+        let relative = path.strip_prefix(root).unwrap_or(&path);
+        let left = Path::new(path1).join(relative);
+        let right = Path::new(path2).join(relative);
+        if !log_evidence(&left, &right, &file_diff) {
+            return ExitCode::FAILURE;
+        }
+        let attributes = attribute_diffs(&left, &right, &file_diff, args);
+        let mut paths = match args.all_paths {
+            true => format!(
+                "{}\t{}\t{}",
+                relative.display(),
+                absolute(&left).display(),
+                absolute(&right).display()
+            ),
+            false => path.display().to_string(),
+        };
+        if args.print_hashes {
+            match hash_fields(&left, &right, &file_diff, args, options) {
+                Ok(digests) => paths = format!("{}\t{}", paths, digests),
+                Err(e) => {
+                    eprintln!("Error: cannot hash {}", e);
+                    return ExitCode::FAILURE;
+                }
+            }
+        }
+        let detail = detail(&left, &right, &file_diff, args, options);
+        print_entry(
+            paths,
+            (&left, &right),
+            &file_diff,
+            &attributes,
+            detail,
+            args,
+        );
+        launch_tool(&left, &right, &file_diff, args);
+        run_exec(&left, &right, &file_diff, args);
+        if let Some(list) = &mut recopy {
+            if let Err(e) = write_recopy(list, relative, &file_diff, args) {
+                eprintln!("Error: cannot write the recopy list: {}", e);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    report_failed(&mut walk, args);
+    if let Some(Err(e)) = recopy.map(ReportFile::finish) {
+        eprintln!("Error: cannot write the recopy list: {}", e);
+        return ExitCode::FAILURE;
+    }
+    print_verdict(args);
+    ExitCode::SUCCESS
+}
+
+/// Reports the pairs a directory walk could not compare since the last
+/// call and adds them to the --errors-out list.
+fn report_failed(walk: &mut DirWalk, args: &Args) {
+    for (left, right, e) in walk.take_failed() {
+        eprintln!("Error: {}", e);
+        record_failure(&left, &right, args);
+    }
+}
+
+/// Adds the relative path of a file that has to be copied again to the
+/// --recopy-list: one that differs or is missing on the right.
+fn write_recopy(
+    list: &mut impl Write,
+    relative: &Path,
+    file_diff: &FileDiff,
+    args: &Args,
+) -> io::Result<()> {
+    if !matches!(
+        file_diff,
+        FileDiff::Different(_) | FileDiff::Truncated(..) | FileDiff::LeftOnly
+    ) {
+        return Ok(());
+    }
+    list.write_all(relative.as_os_str().as_encoded_bytes())?;
+    list.write_all(match args.null_terminated {
+        true => b"\0",
+        false => b"\n",
+    })
+}
+
+/// Compares each listed or given pair of files, printing a line per pair.
+/// Pairs that can't be compared are reported and skipped, and make the exit
+/// code fail.
+fn compare_pairs<I>(pairs: I, args: &Args, options: &CompareOptions) -> ExitCode
+where
+    I: IntoIterator<Item = io::Result<(PathBuf, PathBuf)>>,
+{
+    let mut code = ExitCode::SUCCESS;
+    if args.json {
+        start_json_array();
+    }
+    catch_interrupt();
+    for pair in pairs {
+        if interrupt::interrupted() {
+            return cancelled();
+        }
+        let (path1, path2) = match pair {
+            Ok(pair) => pair,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                return ExitCode::FAILURE;
+            }
+        };
+        match compare_files_with(&path1, &path2, options) {
+            Ok(file_diff) => {
+                if !log_evidence(&path1, &path2, &file_diff) {
+                    return ExitCode::FAILURE;
+                }
+                SUMMARY.lock().unwrap().add(&file_diff);
+                let attributes = attribute_diffs(&path1, &path2, &file_diff, args);
+                if args.diffs_only && file_diff == FileDiff::Equal && attributes.is_empty() {
+                    continue;
+                }
+                if args.porcelain.is_some() {
+                    print_porcelain(&path1, &path2, &file_diff);
+                    launch_tool(&path1, &path2, &file_diff, args);
+                    run_exec(&path1, &path2, &file_diff, args);
+                    continue;
+                }
+                if args.json {
+                    print_json_entry(&path1.display().to_string(), &file_diff);
+                    launch_tool(&path1, &path2, &file_diff, args);
+                    run_exec(&path1, &path2, &file_diff, args);
+                    continue;
+                }
+                let mut paths = format!("{}\t{}", path1.display(), path2.display());
+                if args.print_hashes {
+                    match hash_fields(&path1, &path2, &file_diff, args, options) {
+                        Ok(digests) => paths = format!("{}\t{}", paths, digests),
+                        Err(e) => {
+                            eprintln!("Error: cannot hash {}", e);
+                            code = ExitCode::FAILURE;
+                            continue;
+                        }
+                    }
+                }
+                let detail = detail(&path1, &path2, &file_diff, args, options);
+                println!(
+                    "{}\t{}{}",
+                    file_diff.as_number(),
+                    paths,
+                    describe(&file_diff, &attributes, detail, args)
+                );
+                launch_tool(&path1, &path2, &file_diff, args);
+                run_exec(&path1, &path2, &file_diff, args);
+            }
+            Err(e) => {
+                eprintln!("Error: {}: {}", path1.display(), e);
+                record_failure(&path1, &path2, args);
+                code = ExitCode::FAILURE;
+            }
+        }
+    }
+    code
+}
+
+/// Compares replicas of a file with --quorum, printing a line per replica
+/// with its result against the majority, like the entries of a directory
+/// comparison. Without a majority, the groups of replicas that agree are
+/// reported instead and the exit code fails.
+fn compare_quorum(replicas: &[&Path], args: &Args, options: &CompareOptions) -> ExitCode {
+    if replicas.len() < 3 {
+        eprintln!("Error: expected at least three replicas");
+        return ExitCode::FAILURE;
+    }
+    let result = match quorum::compare_replicas(replicas, options) {
+        Ok(result) => result,
+        Err(e) => return print_result(Err(e), args),
+    };
+    let Some(majority) = result.majority() else {
+        let groups: Vec<String> = result
+            .groups
+            .iter()
+            .map(|group| {
+                let paths: Vec<String> = group
+                    .iter()
+                    .map(|&i| replicas[i].display().to_string())
+                    .collect();
+                paths.join(", ")
+            })
+            .collect();
+        eprintln!(
+            "Error: no majority of the {} replicas agree, they differ as: {}",
+            replicas.len(),
+            groups.join("; ")
+        );
+        return ExitCode::FAILURE;
+    };
+    let reference = majority[0];
+    for (i, replica) in replicas.iter().enumerate() {
+        let file_diff = result.diff(reference, i);
+        if !log_evidence(replicas[reference], replica, &file_diff) {
+            return ExitCode::FAILURE;
+        }
+        let sides = (replicas[reference], *replica);
+        let detail = detail(sides.0, sides.1, &file_diff, args, options);
+        print_entry(replica.display(), sides, &file_diff, &[], detail, args);
+    }
+    ExitCode::SUCCESS
+}
+
+/// Compares each candidate with the --baseline, printing a line per
+/// candidate like the entries of a directory comparison. Candidates that
+/// can't be compared are reported and skipped, and make the exit code fail.
+fn compare_baseline<'a>(
+    baseline: &Path,
+    candidates: impl Iterator<Item = &'a Path>,
+    args: &Args,
+    options: &CompareOptions,
+) -> ExitCode {
+    let mut code = ExitCode::SUCCESS;
+    for candidate in candidates {
+        match compare_files_with(baseline, candidate, options) {
+            Ok(file_diff) => {
+                if !log_evidence(baseline, candidate, &file_diff) {
+                    return ExitCode::FAILURE;
+                }
+                let attributes = attribute_diffs(baseline, candidate, &file_diff, args);
+                let detail = detail(baseline, candidate, &file_diff, args, options);
+                let sides = (baseline, candidate);
+                print_entry(
+                    candidate.display(),
+                    sides,
+                    &file_diff,
+                    &attributes,
+                    detail,
+                    args,
+                );
+                launch_tool(baseline, candidate, &file_diff, args);
+                run_exec(baseline, candidate, &file_diff, args);
+            }
+            Err(e) => {
+                eprintln!("Error: {}: {}", candidate.display(), e);
+                code = ExitCode::FAILURE;
+            }
+        }
+    }
+    code
+}
+
+/// Compares an S3 object with a local file, or the objects under a prefix
+/// with a local directory tree.
+#[cfg(feature = "s3")]
+fn compare_s3(path1: &str, path2: &str, args: &Args, options: &CompareOptions) -> ExitCode {
+    let (url, local, swapped) = match (s3::S3Url::parse(path1), s3::S3Url::parse(path2)) {
+        (Some(_), Some(_)) => {
+            eprintln!("Error: only one of the paths can be an s3:// URL");
+            return ExitCode::FAILURE;
+        }
+        (Some(url), None) => (url, path2, true),
+        (None, Some(url)) => (url, path1, false),
+        (None, None) => {
+            eprintln!("Error: expected an s3://bucket/key URL");
+            return ExitCode::FAILURE;
+        }
+    };
+    let client = s3::Client::from_env(options.timeout);
+    match is_dir(local) {
+        Ok(true) => print_tree(
+            s3::compare_tree(&client, &url, local, options),
+            swapped,
+            args,
+        ),
+        Ok(false) => print_result(
+            client
+                .head(&url.bucket, &url.key)
+                .and_then(|object| s3::compare_object(&client, &url, &object, local, options)),
+            args,
+        ),
+        Err(e) => print_result(Err(e), args),
+    }
+}
+
+#[cfg(not(feature = "s3"))]
+fn compare_s3(_path1: &str, _path2: &str, _args: &Args, _options: &CompareOptions) -> ExitCode {
+    eprintln!("Error: cannot compare s3:// URLs: built without the s3 feature");
+    ExitCode::FAILURE
+}
+
+/// Compares an Azure blob with a local file, or the blobs under a prefix
+/// with a local directory tree.
+#[cfg(feature = "azure")]
+fn compare_azure(path1: &str, path2: &str, args: &Args, options: &CompareOptions) -> ExitCode {
+    let (url, local, swapped) = match (azure::AzureUrl::parse(path1), azure::AzureUrl::parse(path2))
+    {
+        (Some(_), Some(_)) => {
+            eprintln!("Error: only one of the paths can be an az:// URL");
+            return ExitCode::FAILURE;
+        }
+        (Some(url), None) => (url, path2, true),
+        (None, Some(url)) => (url, path1, false),
+        (None, None) => {
+            eprintln!("Error: expected an az://account/container/blob URL");
+            return ExitCode::FAILURE;
+        }
+    };
+    let client = azure::Client::from_env(options.timeout);
+    match is_dir(local) {
+        Ok(true) => print_tree(
+            azure::compare_tree(&client, &url, local, options),
+            swapped,
+            args,
+        ),
+        Ok(false) => print_result(
+            client
+                .head(&url)
+                .and_then(|blob| azure::compare_blob(&client, &url, &blob, local, options)),
+            args,
+        ),
+        Err(e) => print_result(Err(e), args),
+    }
+}
+
+#[cfg(not(feature = "azure"))]
+fn compare_azure(_path1: &str, _path2: &str, _args: &Args, _options: &CompareOptions) -> ExitCode {
+    eprintln!("Error: cannot compare az:// URLs: built without the azure feature");
+    ExitCode::FAILURE
+}
+
+/// Compares a Cloud Storage object with a local file, or the objects under
+/// a prefix with a local directory tree.
+#[cfg(feature = "gcs")]
+fn compare_gcs(path1: &str, path2: &str, args: &Args, options: &CompareOptions) -> ExitCode {
+    let (url, local, swapped) = match (gcs::GcsUrl::parse(path1), gcs::GcsUrl::parse(path2)) {
+        (Some(_), Some(_)) => {
+            eprintln!("Error: only one of the paths can be a gs:// URL");
+            return ExitCode::FAILURE;
+        }
+        (Some(url), None) => (url, path2, true),
+        (None, Some(url)) => (url, path1, false),
+        (None, None) => {
+            eprintln!("Error: expected a gs://bucket/name URL");
+            return ExitCode::FAILURE;
+        }
+    };
+    let client = gcs::Client::from_env(options.timeout);
+    match is_dir(local) {
+        Ok(true) => print_tree(
+            gcs::compare_tree(&client, &url, local, options),
+            swapped,
+            args,
+        ),
+        Ok(false) => print_result(
+            client
+                .metadata(&url.bucket, &url.name)
+                .and_then(|object| gcs::compare_object(&client, &url, &object, local, options)),
+            args,
+        ),
+        Err(e) => print_result(Err(e), args),
+    }
+}
+
+#[cfg(not(feature = "gcs"))]
+fn compare_gcs(_path1: &str, _path2: &str, _args: &Args, _options: &CompareOptions) -> ExitCode {
+    eprintln!("Error: cannot compare gs:// URLs: built without the gcs feature");
+    ExitCode::FAILURE
+}
+
+/// Compares the filesystems of two container images.
+#[cfg(feature = "oci")]
+fn compare_images(path1: &str, path2: &str, args: &Args, options: &CompareOptions) -> ExitCode {
+    print_tree(oci::compare_images(path1, path2, options.hash), false, args)
+}
+
+#[cfg(not(feature = "oci"))]
+fn compare_images(_path1: &str, _path2: &str, _args: &Args, _options: &CompareOptions) -> ExitCode {
+    eprintln!("Error: cannot compare images: built without the oci feature");
+    ExitCode::FAILURE
+}
+
+/// Compares two Parquet files by their data.
+#[cfg(feature = "parquet")]
+fn compare_parquet(path1: &str, path2: &str, args: &Args) -> ExitCode {
+    match parquet::compare_parquet(path1, path2) {
+        Ok(result) => {
+            match (args.machine_readable, result) {
+                (true, result) => print!("{}", result.as_number()),
+                (false, TableDiff::Equal) => print!("Tables are equal"),
+                (false, TableDiff::Schema) => print!("Tables differ in their schema"),
+                (false, TableDiff::Row(row)) => print!("Tables differ at row {}", row),
+            }
+            ExitCode::SUCCESS
+        }
+        Err(e) => print_result(Err(e), args),
+    }
+}
+
+#[cfg(not(feature = "parquet"))]
+fn compare_parquet(_path1: &str, _path2: &str, _args: &Args) -> ExitCode {
+    eprintln!("Error: cannot compare Parquet files: built without the parquet feature");
+    ExitCode::FAILURE
+}
+
+#[cfg(feature = "pdf")]
+fn compare_pdf(path1: &str, path2: &str, args: &Args) -> ExitCode {
+    match pdf::compare_pdf(path1, path2) {
+        Ok(result) => {
+            match (args.machine_readable, result) {
+                (true, result) => print!("{}", result.as_number()),
+                (false, TextDiff::Equal) => print!("Documents have the same text"),
+                (false, TextDiff::Page(page)) => {
+                    print!("Text of the documents differs on page {}", page)
+                }
+            }
+            ExitCode::SUCCESS
+        }
+        Err(e) => print_result(Err(e), args),
+    }
+}
+
+#[cfg(not(feature = "pdf"))]
+fn compare_pdf(_path1: &str, _path2: &str, _args: &Args) -> ExitCode {
+    eprintln!("Error: cannot compare PDF files: built without the pdf feature");
+    ExitCode::FAILURE
+}
+
+/// Compares a file or tree in a git repository with a local one.
+#[cfg(feature = "git")]
+fn compare_git(path1: &str, path2: &str, args: &Args, options: &CompareOptions) -> ExitCode {
+    let (git, local, swapped) = match (git::GitRef::parse(path1), git::GitRef::parse(path2)) {
+        (Some(_), Some(_)) => {
+            eprintln!("Error: only one of the paths can be a git reference");
+            return ExitCode::FAILURE;
+        }
+        (Some(git), None) => (git, path2, true),
+        (None, Some(git)) => (git, path1, false),
+        (None, None) => unreachable!("one of the paths is a git reference"),
+    };
+    match is_dir(local) {
+        Ok(true) => print_tree(git::compare_tree(&git, local, options), swapped, args),
+        Ok(false) => print_result(git::compare_file(&git, local, options), args),
+        Err(e) => print_result(Err(e), args),
+    }
+}
+
+#[cfg(not(feature = "git"))]
+fn compare_git(_path1: &str, _path2: &str, _args: &Args, _options: &CompareOptions) -> ExitCode {
+    eprintln!("Error: cannot compare with git references: built without the git feature");
+    ExitCode::FAILURE
+}
+
+/// Compares a file or tree on another host with a local one over ssh.
+fn compare_ssh(path1: &str, path2: &str, args: &Args, options: &CompareOptions) -> ExitCode {
+    let (remote, local, swapped) = match (RemotePath::parse(path1), RemotePath::parse(path2)) {
+        (Some(_), Some(_)) => {
+            eprintln!("Error: only one of the paths can be on another host");
+            return ExitCode::FAILURE;
+        }
+        (Some(remote), None) => (remote, path2, true),
+        (None, Some(remote)) => (remote, path1, false),
+        (None, None) => {
+            eprintln!("Error: expected an sftp://[user@]host[:port]/path URL");
+            return ExitCode::FAILURE;
+        }
+    };
+    let session = ssh::Session::new(&args.ssh_command);
+    match is_dir(local) {
+        Ok(true) => {
+            let results = match &args.agent {
+                Some(program) => {
+                    ssh::compare_tree_with_agent(&session, &remote, program, local, options)
+                }
+                None => ssh::compare_tree(&session, &remote, local, options),
+            };
+            print_tree(results, swapped, args)
+        }
+        Ok(false) => match &args.agent {
+            Some(program) => {
+                let delta =
+                    ssh::compare_file_with_agent(&session, &remote, program, local, options);
+                print_delta(delta, swapped, args)
+            }
+            None => print_result(ssh::compare_file(&session, &remote, local, options), args),
+        },
+        Err(e) => print_result(Err(e), args),
+    }
+}
+
+/// Compares two files by their content-defined chunks, printing the result
+/// and the ranges of the chunks of each file that the other doesn't have.
+fn compare_cdc(
+    path1: &str,
+    path2: &str,
+    avg: usize,
+    args: &Args,
+    options: &CompareOptions,
+) -> ExitCode {
+    if avg < cdc::MIN_AVG_LEN {
+        eprintln!(
+            "Error: the chunk size must be at least {}",
+            format_size(cdc::MIN_AVG_LEN as u64)
+        );
+        return ExitCode::FAILURE;
+    }
+    let diff = match cdc::compare_cdc(path1, path2, avg, options) {
+        Ok(diff) => diff,
+        Err(e) => return print_result(Err(e), args),
+    };
+    let code = print_result(Ok(diff.file_diff), args);
+    let ranges = |ranges: &[std::ops::Range<u64>]| {
+        ranges
+            .iter()
+            .map(|range| format!("{}-{}", range.start, range.end - 1))
+            .collect::<Vec<_>>()
+    };
+    let (changed1, changed2) = (ranges(&diff.changed1), ranges(&diff.changed2));
+    match (args.machine_readable, diff.file_diff) {
+        (_, FileDiff::Equal) => {}
+        (true, _) => {
+            let field = |ranges: Vec<String>| match ranges.is_empty() {
+                true => "-".to_string(),
+                false => ranges.join(","),
+            };
+            print!("\t{}\t{}", field(changed1), field(changed2));
+        }
+        (false, _) => {
+            let mut sides = vec![];
+            if !changed1.is_empty() {
+                sides.push(format!("left {}", changed1.join(", ")));
+            }
+            if !changed2.is_empty() {
+                sides.push(format!("right {}", changed2.join(", ")));
+            }
+            if !sides.is_empty() {
+                print!(", chunks differ: {}", sides.join("; "));
+            }
+        }
+    }
+    code
+}
+
+/// Compares two files from their ends, printing the last byte where each
+/// differs and the length of their common suffix.
+fn compare_from_end(path1: &str, path2: &str, args: &Args, options: &CompareOptions) -> ExitCode {
+    let diff = match suffix::compare_from_end(path1, path2, options) {
+        Ok(diff) => diff,
+        Err(e) => return print_result(Err(e), args),
+    };
+    let (last1, last2) = diff.last_diff();
+    let file_diff = match diff.is_equal() {
+        true => FileDiff::Equal,
+        false => FileDiff::Different(last1.or(last2).unwrap_or_default() as usize),
+    };
+    SUMMARY.lock().unwrap().add(&file_diff);
+    if args.machine_readable {
+        let field = |last: Option<u64>| last.map_or("-".to_string(), |o| o.to_string());
+        print!("{}\t{}\t{}", field(last1), field(last2), diff.suffix);
+        return ExitCode::SUCCESS;
+    }
+    match (last1, last2) {
+        _ if diff.is_equal() => print!("Files are equal"),
+        (Some(last1), Some(last2)) => print!(
+            "Files differ last at byte {} of {} and byte {} of {}, with a common suffix of {} bytes",
+            last1, path1, last2, path2, diff.suffix
+        ),
+        (None, Some(last)) => print!(
+            "{} is a suffix of {}, which differs last at byte {}",
+            path1, path2, last
+        ),
+        (Some(last), None) => print!(
+            "{} is a suffix of {}, which differs last at byte {}",
+            path2, path1, last
+        ),
+        (None, None) => unreachable!("files of the same length that are all suffix are equal"),
+    }
+    ExitCode::SUCCESS
+}
+
+/// Compares two files to the end, printing the result and a map of where
+/// they differ, or writing the map to an SVG file. Machine-readable output
+/// has the length of a cell and the differing bytes of each instead.
+fn compare_heatmap(
+    path1: &str,
+    path2: &str,
+    svg: Option<&Path>,
+    args: &Args,
+    options: &CompareOptions,
+) -> ExitCode {
+    let (columns, rows) = match svg {
+        Some(_) => heatmap::SVG_GRID,
+        None => heatmap::TERMINAL_GRID,
+    };
+    let map = match heatmap::diff_map(path1, path2, columns * rows, options) {
+        Ok(map) => map,
+        Err(e) => return print_result(Err(e), args),
+    };
+    if let Some(svg) = svg {
+        if let Err(e) = report::write_atomically(svg, map.render_svg(columns)) {
+            eprintln!("Error: cannot write {}: {}", svg.display(), e);
+            return ExitCode::FAILURE;
+        }
+    }
+    let code = print_result(Ok(map.file_diff), args);
+    match (args.machine_readable, svg) {
+        (true, _) => {
+            let counts: Vec<String> = map.counts.iter().map(u64::to_string).collect();
+            print!("\t{}\t{}", map.cell_len, counts.join(","));
+        }
+        (false, None) if !map.counts.is_empty() => {
+            print!("\n{}", map.render_terminal(columns).trim_end());
+        }
+        (false, _) => {}
+    }
+    code
+}
+
+/// Prints the result of a delta comparison with a remote file and the
+/// ranges of the local file that differ, e.g. `4096-8191`. Remote files are
+/// compared with the local side first, so `swapped` is set when the remote
+/// side was given first.
+fn print_delta(delta: io::Result<Delta>, swapped: bool, args: &Args) -> ExitCode {
+    let delta = match delta {
+        Ok(delta) => delta,
+        Err(e) => return print_result(Err(e), args),
+    };
+    let file_diff = match swapped {
+        true => delta.file_diff.swap_sides(),
+        false => delta.file_diff,
+    };
+    let code = print_result(Ok(file_diff), args);
+    let ranges: Vec<_> = delta
+        .ranges
+        .iter()
+        .map(|range| format!("{}-{}", range.start, range.end - 1))
+        .collect();
+    match (args.machine_readable, ranges.is_empty()) {
+        (_, true) => {}
+        (true, false) => print!("\t{}", ranges.join(",")),
+        (false, false) => print!(", local ranges differ: {}", ranges.join(", ")),
+    }
+    code
+}
+
+/// Compares a URL with a local file, or two URLs with each other.
+fn compare_url(path1: &str, path2: &str, options: &CompareOptions) -> io::Result<FileDiff> {
+    match (http::is_url(path1), http::is_url(path2)) {
+        (true, true) => http::compare_urls(path1, path2, options),
+        (true, false) => http::compare_url(path1, path2, options),
+        _ => http::compare_url(path2, path1, options).map(FileDiff::swap_sides),
+    }
+}
+
+/// Compares stdin, given as "-", with a file, pipe or block device.
+fn compare_stdin(path1: &str, path2: &str, options: &CompareOptions) -> io::Result<FileDiff> {
+    match (path1, path2) {
+        ("-", "-") => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "only one of the paths can be stdin",
+        )),
+        ("-", path) => compare_streams(stdin(options), open(path, options)?, options),
+        (path, _) => compare_streams(open(path, options)?, stdin(options), options),
+    }
+}
+
+fn stdin(options: &CompareOptions) -> TimeoutReader<io::Stdin> {
+    TimeoutReader::new(io::stdin(), options.timeout)
+}
+
+fn open(path: &str, options: &CompareOptions) -> io::Result<TimeoutReader<File>> {
+    Ok(TimeoutReader::new(File::open(path)?, options.timeout))
 }
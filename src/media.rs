@@ -0,0 +1,212 @@
+use crate::{compare_streams, diff_at_lengths, long_path, wav, CompareOptions, FileDiff};
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::ops::Range;
+use std::path::Path;
+
+/// Media containers whose audio and video data can be compared apart from
+/// their tags.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Format {
+    /// MPEG audio, with ID3v1, ID3v2 and APEv2 tags
+    Mp3,
+    /// FLAC, with Vorbis comments, pictures and other metadata blocks
+    Flac,
+    /// MP4 and QuickTime, with `moov` metadata such as `udta` atoms
+    Mp4,
+    /// WAV, with `LIST`, `bext` and other chunks besides the format and the
+    /// samples
+    Wav,
+}
+
+impl Format {
+    /// Returns the format of a file by its extension, if it is a media file.
+    pub fn of(path: &Path) -> Option<Format> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "mp3" => Some(Format::Mp3),
+            "flac" => Some(Format::Flac),
+            "mp4" | "m4a" | "m4b" | "m4v" | "mov" => Some(Format::Mp4),
+            "wav" | "wave" => Some(Format::Wav),
+            _ => None,
+        }
+    }
+}
+
+fn invalid(path: &Path, what: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("{}: invalid {}", path.display(), what),
+    )
+}
+
+pub(crate) fn read_at<const N: usize>(file: &mut File, offset: u64) -> io::Result<[u8; N]> {
+    let mut buffer = [0; N];
+    file.seek(SeekFrom::Start(offset))?;
+    file.read_exact(&mut buffer)?;
+    Ok(buffer)
+}
+
+/// Returns the ranges of a media file holding its audio and video data, in
+/// order, leaving out the tags and other metadata that taggers rewrite.
+pub fn essence<P: AsRef<Path>>(path: P, format: Format) -> io::Result<Vec<Range<u64>>> {
+    let path = path.as_ref();
+    let mut file = File::open(long_path::extended(path))?;
+    let len = file.metadata()?.len();
+    match format {
+        Format::Mp3 => mp3_essence(&mut file, len),
+        Format::Flac => flac_essence(&mut file, len, path),
+        Format::Mp4 => mp4_essence(&mut file, len, path),
+        Format::Wav => wav::parse(path).map(|wav| vec![wav.fmt, wav.data]),
+    }
+}
+
+/// Returns the end of the ID3v2 tags at the start of the file.
+fn skip_id3v2(file: &mut File, len: u64) -> io::Result<u64> {
+    let mut pos = 0;
+    while pos + 10 <= len {
+        let header: [u8; 10] = read_at(file, pos)?;
+        if &header[..3] != b"ID3" {
+            break;
+        }
+        // Synchsafe: 7 bits in each byte
+        let size = header[6..]
+            .iter()
+            .fold(0, |size, &b| size << 7 | (b & 0x7f) as u64);
+        let footer = match header[5] & 0x10 != 0 {
+            true => 10,
+            false => 0,
+        };
+        pos += 10 + size + footer;
+    }
+    Ok(pos.min(len))
+}
+
+/// MPEG frames run from the ID3v2 tags at the start to the APEv2 and ID3v1
+/// tags at the end.
+fn mp3_essence(file: &mut File, len: u64) -> io::Result<Vec<Range<u64>>> {
+    let start = skip_id3v2(file, len)?;
+    let mut end = len;
+    if end >= start + 128 && read_at(file, end - 128)? == *b"TAG" {
+        end -= 128;
+    }
+    if end >= start + 32 {
+        let footer: [u8; 32] = read_at(file, end - 32)?;
+        if &footer[..8] == b"APETAGEX" {
+            // The size includes the footer but not the optional header
+            let size = u32::from_le_bytes(footer[12..16].try_into().unwrap()) as u64;
+            let flags = u32::from_le_bytes(footer[20..24].try_into().unwrap());
+            let header = match flags & 1 << 31 != 0 {
+                true => 32,
+                false => 0,
+            };
+            end = end.saturating_sub(size + header).max(start);
+        }
+    }
+    Ok(vec![Range { start, end }])
+}
+
+/// The STREAMINFO block describes the audio, the other metadata blocks hold
+/// tags, pictures and padding, and the frames follow them to the end.
+fn flac_essence(file: &mut File, len: u64, path: &Path) -> io::Result<Vec<Range<u64>>> {
+    let mut pos = skip_id3v2(file, len)?;
+    if pos + 4 > len || read_at(file, pos)? != *b"fLaC" {
+        return Err(invalid(path, "FLAC file"));
+    }
+    pos += 4;
+    let mut ranges = vec![];
+    loop {
+        if pos + 4 > len {
+            return Err(invalid(path, "FLAC metadata"));
+        }
+        let header: [u8; 4] = read_at(file, pos)?;
+        let size = u32::from_be_bytes([0, header[1], header[2], header[3]]) as u64;
+        let block = pos + 4..pos + 4 + size;
+        if block.end > len {
+            return Err(invalid(path, "FLAC metadata"));
+        }
+        if header[0] & 0x7f == 0 {
+            ranges.push(block.clone());
+        }
+        pos = block.end;
+        if header[0] & 0x80 != 0 {
+            break;
+        }
+    }
+    ranges.push(pos..len);
+    Ok(ranges)
+}
+
+/// The samples are in the top-level `mdat` boxes, and everything describing
+/// them, including the tags, is in `moov`.
+fn mp4_essence(file: &mut File, len: u64, path: &Path) -> io::Result<Vec<Range<u64>>> {
+    let mut ranges = vec![];
+    let mut pos = 0;
+    while pos + 8 <= len {
+        let header: [u8; 8] = read_at(file, pos)?;
+        let (start, end) = match u32::from_be_bytes(header[..4].try_into().unwrap()) {
+            // To the end of the file
+            0 => (pos + 8, Some(len)),
+            // With a 64-bit size after the type
+            1 if pos + 16 <= len => (
+                pos + 16,
+                pos.checked_add(u64::from_be_bytes(read_at(file, pos + 8)?)),
+            ),
+            1 => (pos + 16, None),
+            size => (pos + 8, Some(pos + size as u64)),
+        };
+        let end = match end {
+            Some(end) if end >= start && end <= len => end,
+            _ => return Err(invalid(path, "MP4 box")),
+        };
+        if &header[4..] == b"mdat" {
+            ranges.push(start..end);
+        }
+        pos = end;
+    }
+    Ok(ranges)
+}
+
+/// Returns a reader of the ranges of the file one after another, and their
+/// combined length.
+fn ranges_reader(path: &Path, ranges: Vec<Range<u64>>) -> io::Result<(impl Read + Send, u64)> {
+    let mut reader: Box<dyn Read + Send> = Box::new(io::empty());
+    let mut len = 0;
+    for range in ranges {
+        // Each range has its own handle, since clones share the position
+        let mut file = File::open(long_path::extended(path))?;
+        file.seek(SeekFrom::Start(range.start))?;
+        reader = Box::new(reader.chain(file.take(range.end - range.start)));
+        len += range.end - range.start;
+    }
+    Ok((reader, len))
+}
+
+/// Compares two media files of the format by their audio and video data
+/// only, so that retagged files still match their backups. The offset of
+/// a difference is into that data, not into either file.
+pub fn compare_media<P: AsRef<Path>>(
+    path1: P,
+    path2: P,
+    format: Format,
+    options: &CompareOptions,
+) -> io::Result<FileDiff> {
+    let (path1, path2) = (path1.as_ref(), path2.as_ref());
+    if format == Format::Wav && options.sample_tolerance > 0 {
+        return wav::compare_samples(path1, path2, options.sample_tolerance, options.quick);
+    }
+    let (reader1, len1) = ranges_reader(path1, essence(path1, format)?)?;
+    let (reader2, len2) = ranges_reader(path2, essence(path2, format)?)?;
+    if options.quick && len1 != len2 {
+        return Ok(FileDiff::Different(0));
+    }
+    match compare_streams(reader1, reader2, options)? {
+        FileDiff::Different(offset) => Ok(diff_at_lengths(
+            Some(offset as u64),
+            len1,
+            len2,
+            options.quick,
+        )),
+        result => Ok(result),
+    }
+}
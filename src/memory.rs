@@ -0,0 +1,55 @@
+use crate::chunk::{MAX_ADAPTIVE_CHUNK_SIZE, MIN_CHUNK_SIZE};
+use crate::units::format_size;
+use crate::CompareOptions;
+
+/// Chunk size that chunks shrink to before the threads and the pipeline are
+/// cut back, as reads this large still run at full speed.
+const SHRINK_FIRST_TO: usize = 1024 * 1024;
+
+/// Returns the most memory the read buffers of a comparison with these
+/// options take: two chunks when reading sequentially, two per thread when
+/// comparing in parallel, and `depth + 2` per side when pipelined, for each
+/// pair of files compared at once.
+pub fn buffer_usage(options: &CompareOptions) -> u64 {
+    let chunk = options.chunk_size.unwrap_or(MAX_ADAPTIVE_CHUNK_SIZE) as u64;
+    let buffers = [
+        2,
+        2 * options.threads as u64,
+        match options.pipeline_depth {
+            0 => 0,
+            depth => 2 * (depth as u64 + 2),
+        },
+    ];
+    let jobs = options.jobs.max(1) as u64;
+    chunk * buffers.into_iter().max().unwrap_or(2) * jobs
+}
+
+/// Scales the options down until their read buffers fit in `limit` bytes:
+/// first smaller chunks, down to 1 MiB, then fewer pairs at once, then
+/// fewer threads or a shallower pipeline, whichever takes more, and then
+/// chunks down to the smallest size. Fails if even that doesn't fit.
+pub fn fit(options: &mut CompareOptions, limit: u64) -> Result<(), String> {
+    let mut chunk = options.chunk_size.unwrap_or(MAX_ADAPTIVE_CHUNK_SIZE);
+    while buffer_usage(options) > limit {
+        let (threads, depth) = (options.threads, options.pipeline_depth);
+        if chunk > SHRINK_FIRST_TO {
+            chunk = (chunk / 2).max(SHRINK_FIRST_TO);
+            options.chunk_size = Some(chunk);
+        } else if options.jobs > 1 {
+            options.jobs /= 2;
+        } else if threads > 1 && threads >= depth + 2 {
+            options.threads = threads / 2;
+        } else if depth > 0 {
+            options.pipeline_depth = depth / 2;
+        } else if chunk > MIN_CHUNK_SIZE {
+            chunk = (chunk / 2).max(MIN_CHUNK_SIZE);
+            options.chunk_size = Some(chunk);
+        } else {
+            return Err(format!(
+                "cannot compare with less than {} of buffers",
+                format_size(2 * MIN_CHUNK_SIZE as u64)
+            ));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,32 @@
+use crate::compare_chunks;
+use memmap2::MmapOptions;
+use std::fs::File;
+use std::io;
+use std::ops::ControlFlow;
+
+/// Bytes compared per step, so a difference near the start is found
+/// without touching the rest of the mapping.
+const STEP: usize = 1024 * 1024;
+
+/// Compares two files by mapping them into memory and letting the kernel
+/// page them in, returning the offset of the first difference like
+/// `compare_readers`. The lengths must not be 0, and the files must not be
+/// truncated while mapped.
+pub fn compare_files(file1: &File, len1: u64, file2: &File, len2: u64) -> io::Result<Option<u64>> {
+    let map1 = unsafe { MmapOptions::new().len(len1 as usize).map(file1)? };
+    let map2 = unsafe { MmapOptions::new().len(len2 as usize).map(file2)? };
+    Ok(compare_slices(&map1, &map2))
+}
+
+/// Compares two byte slices the same way chunks of two readers are compared.
+pub fn compare_slices(data1: &[u8], data2: &[u8]) -> Option<u64> {
+    let mut pos = 0;
+    loop {
+        let chunk1 = &data1[pos.min(data1.len())..(pos + STEP).min(data1.len())];
+        let chunk2 = &data2[pos.min(data2.len())..(pos + STEP).min(data2.len())];
+        if let ControlFlow::Break(result) = compare_chunks(chunk1, chunk2, STEP, pos as u64) {
+            return result;
+        }
+        pos += STEP;
+    }
+}
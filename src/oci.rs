@@ -0,0 +1,278 @@
+use crate::hash::{hash_reader, HashAlgorithm};
+use crate::FileDiff;
+use flate2::read::GzDecoder;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// A path in an image's flattened filesystem.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Entry {
+    File {
+        len: u64,
+        hash: Vec<u8>,
+    },
+    Dir,
+    Symlink(PathBuf),
+    /// Devices, FIFOs and other special files, by their tar entry type
+    Other(u8),
+}
+
+/// The filesystem of an image with all of its layers applied, by path
+/// relative to the root.
+#[derive(Debug, Default)]
+pub struct Image {
+    pub entries: BTreeMap<String, Entry>,
+}
+
+/// Where the image's blobs are read from.
+enum Source {
+    /// An OCI image layout directory
+    Layout(PathBuf),
+    /// A tar archive, such as `docker save` writes, with the offset and size
+    /// of each file in it
+    Archive {
+        file: File,
+        files: HashMap<String, (u64, u64)>,
+    },
+}
+
+impl Source {
+    fn open(path: &Path) -> io::Result<Source> {
+        if path.is_dir() {
+            return Ok(Source::Layout(path.to_path_buf()));
+        }
+        let mut files = HashMap::new();
+        let mut archive = tar::Archive::new(File::open(path)?);
+        for entry in archive.entries()? {
+            let entry = entry?;
+            let name = normalize(&entry.path()?);
+            files.insert(name, (entry.raw_file_position(), entry.size()));
+        }
+        Ok(Source::Archive {
+            file: File::open(path)?,
+            files,
+        })
+    }
+
+    fn read(&self, name: &str) -> io::Result<Box<dyn Read + '_>> {
+        match self {
+            Source::Layout(dir) => Ok(Box::new(File::open(dir.join(name))?)),
+            Source::Archive { file, files } => {
+                let &(position, size) =
+                    files.get(&normalize(Path::new(name))).ok_or_else(|| {
+                        io::Error::new(
+                            io::ErrorKind::NotFound,
+                            format!("{} is missing from the image", name),
+                        )
+                    })?;
+                let mut file = file;
+                file.seek(SeekFrom::Start(position))?;
+                Ok(Box::new(file.take(size)))
+            }
+        }
+    }
+
+    fn exists(&self, name: &str) -> bool {
+        match self {
+            Source::Layout(dir) => dir.join(name).exists(),
+            Source::Archive { files, .. } => files.contains_key(name),
+        }
+    }
+
+    fn read_json(&self, name: &str) -> io::Result<Value> {
+        serde_json::from_reader(self.read(name)?).map_err(|e| invalid(&format!("{}: {}", name, e)))
+    }
+
+    /// Returns the paths of the image's layers, from the bottom up.
+    fn layers(&self) -> io::Result<Vec<String>> {
+        if self.exists("index.json") {
+            let mut manifest = self.read_json("index.json")?;
+            // Follow nested indexes, e.g. of multi-platform images, to the
+            // first manifest that is for a real platform
+            while let Some(manifests) = manifest["manifests"].as_array() {
+                let descriptor = manifests
+                    .iter()
+                    .find(|m| m["platform"]["os"] != "unknown")
+                    .ok_or_else(|| invalid("image index has no manifests"))?;
+                manifest = self.read_json(&blob_path(descriptor)?)?;
+            }
+            let layers = manifest["layers"]
+                .as_array()
+                .ok_or_else(|| invalid("image manifest has no layers"))?;
+            return layers.iter().map(blob_path).collect();
+        }
+        let manifest = self.read_json("manifest.json")?;
+        manifest[0]["Layers"]
+            .as_array()
+            .ok_or_else(|| invalid("manifest.json has no layers"))?
+            .iter()
+            .map(|layer| {
+                layer
+                    .as_str()
+                    .map(str::to_string)
+                    .ok_or_else(|| invalid("invalid layer in manifest.json"))
+            })
+            .collect()
+    }
+}
+
+fn blob_path(descriptor: &Value) -> io::Result<String> {
+    let digest = descriptor["digest"]
+        .as_str()
+        .ok_or_else(|| invalid("descriptor has no digest"))?;
+    let (algorithm, hex) = digest
+        .split_once(':')
+        .ok_or_else(|| invalid(&format!("invalid digest '{}'", digest)))?;
+    Ok(format!("blobs/{}/{}", algorithm, hex))
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// Turns a path in a tar archive into one relative to the root, without
+/// `./`, leading or trailing slashes.
+fn normalize(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    let path = path.trim_start_matches("./").trim_matches('/');
+    match path {
+        "." => String::new(),
+        _ => path.to_string(),
+    }
+}
+
+impl Image {
+    /// Reads an OCI image layout directory or an image archive, and applies
+    /// its layers in order, hashing each file.
+    pub fn open<P: AsRef<Path>>(path: P, algorithm: HashAlgorithm) -> io::Result<Image> {
+        let source = Source::open(path.as_ref())?;
+        let mut image = Image::default();
+        // The layer each entry came from, for opaque directories
+        let mut origins = HashMap::new();
+        for (index, layer) in source.layers()?.iter().enumerate() {
+            let reader = decompress(source.read(layer)?)?;
+            image.apply_layer(reader, index, &mut origins, algorithm)?;
+        }
+        Ok(image)
+    }
+
+    fn apply_layer<R: Read>(
+        &mut self,
+        layer: R,
+        index: usize,
+        origins: &mut HashMap<String, usize>,
+        algorithm: HashAlgorithm,
+    ) -> io::Result<()> {
+        let mut archive = tar::Archive::new(layer);
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let path = normalize(&entry.path()?);
+            if path.is_empty() {
+                continue;
+            }
+            let (parent, name) = match path.rsplit_once('/') {
+                Some((parent, name)) => (Some(parent), name),
+                None => (None, path.as_str()),
+            };
+            let join = |name: &str| match parent {
+                Some(parent) => format!("{}/{}", parent, name),
+                None => name.to_string(),
+            };
+
+            // Whiteouts delete what lower layers put at a path
+            if name == ".wh..wh..opq" {
+                let prefix = parent.map(|p| format!("{}/", p)).unwrap_or_default();
+                self.entries.retain(|path, _| {
+                    !path.starts_with(&prefix) || origins.get(path).is_some_and(|&i| i == index)
+                });
+                continue;
+            }
+            if let Some(hidden) = name.strip_prefix(".wh.") {
+                self.remove(&join(hidden));
+                continue;
+            }
+
+            let entry_type = entry.header().entry_type();
+            let kind = match entry_type {
+                tar::EntryType::Regular | tar::EntryType::Continuous => Entry::File {
+                    len: entry.size(),
+                    hash: hash_reader(&mut entry, algorithm)?,
+                },
+                tar::EntryType::Directory => Entry::Dir,
+                tar::EntryType::Symlink => {
+                    Entry::Symlink(entry.link_name()?.unwrap_or_default().into_owned())
+                }
+                tar::EntryType::Link => {
+                    let target = normalize(&entry.link_name()?.unwrap_or_default());
+                    self.entries
+                        .get(&target)
+                        .cloned()
+                        .unwrap_or(Entry::Other(entry_type.as_byte()))
+                }
+                _ => Entry::Other(entry_type.as_byte()),
+            };
+            // Anything but a directory replaces what was below the path
+            if kind != Entry::Dir {
+                self.remove(&path);
+            }
+            origins.insert(path.clone(), index);
+            self.entries.insert(path, kind);
+        }
+        Ok(())
+    }
+
+    /// Removes a path and everything below it.
+    fn remove(&mut self, path: &str) {
+        let prefix = format!("{}/", path);
+        self.entries
+            .retain(|p, _| p != path && !p.starts_with(&prefix));
+    }
+}
+
+/// Undoes gzip compression of a layer, which is detected from its content
+/// since archives don't record the media type.
+fn decompress<'a>(layer: Box<dyn Read + 'a>) -> io::Result<Box<dyn Read + 'a>> {
+    let mut layer = BufReader::new(layer);
+    let magic = layer.fill_buf()?;
+    if magic.starts_with(&[0x1f, 0x8b]) {
+        Ok(Box::new(GzDecoder::new(layer)))
+    } else if magic.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "zstd-compressed layers are not supported",
+        ))
+    } else {
+        Ok(Box::new(layer))
+    }
+}
+
+/// Compares the flattened filesystems of two images path by path. Files
+/// are compared by size and hash, so differing files report an offset of 0
+/// like in quick mode. Directories are only reported when missing on one
+/// side.
+pub fn compare_images<P: AsRef<Path>>(
+    image1: P,
+    image2: P,
+    algorithm: HashAlgorithm,
+) -> io::Result<Vec<(PathBuf, FileDiff)>> {
+    let image1 = Image::open(image1, algorithm)?;
+    let mut entries2 = Image::open(image2, algorithm)?.entries;
+    let mut results = vec![];
+    for (path, entry1) in image1.entries {
+        let result = match entries2.remove(&path) {
+            Some(Entry::Dir) if entry1 == Entry::Dir => continue,
+            Some(entry2) if entry1 == entry2 => FileDiff::Equal,
+            Some(_) => FileDiff::Different(0),
+            None => FileDiff::LeftOnly,
+        };
+        results.push((PathBuf::from(path), result));
+    }
+    for path in entries2.into_keys() {
+        results.push((PathBuf::from(path), FileDiff::RightOnly));
+    }
+    results.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(results)
+}
@@ -0,0 +1,195 @@
+use crate::{long_path, FileDiff};
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+use zip::result::ZipError;
+use zip::ZipArchive;
+
+/// Elements that a save without edits rewrites: modification times, the
+/// last author, revision counters and editing time, and the lists of
+/// revision save IDs.
+const VOLATILE_ELEMENTS: [&str; 6] = [
+    "dcterms:modified",
+    "cp:lastModifiedBy",
+    "cp:revision",
+    "TotalTime",
+    "w:rsids",
+    "xr:revisionPtr",
+];
+
+/// Prefix of the revision save ID attributes of Word, e.g. `w:rsidR`.
+const RSID_PREFIX: &str = "w:rsid";
+
+/// Returns whether a file is an Office Open XML document, by its extension.
+pub fn is_office<P: AsRef<Path>>(path: P) -> bool {
+    let extension = path.as_ref().extension().and_then(|e| e.to_str());
+    extension.is_some_and(|extension| {
+        matches!(
+            extension.to_lowercase().as_str(),
+            "docx" | "docm" | "xlsx" | "xlsm" | "pptx" | "pptm"
+        )
+    })
+}
+
+fn zip_error(path: &Path, e: ZipError) -> io::Error {
+    match e {
+        ZipError::Io(e) => e,
+        e => io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {}", path.display(), e),
+        ),
+    }
+}
+
+/// Removes what a save without edits changes from an XML part: the
+/// `VOLATILE_ELEMENTS` and `w:rsid*` attributes, whitespace between tags
+/// and the order of attributes.
+pub fn normalize_xml(xml: &str) -> String {
+    let mut normalized = String::with_capacity(xml.len());
+    // The volatile element being left out and how deeply it is nested in
+    // elements of the same name
+    let mut skipping: Option<(&str, usize)> = None;
+    let mut rest = xml;
+    while !rest.is_empty() {
+        let (token, after) = match rest.starts_with('<') {
+            true => rest.split_at(rest.find('>').map_or(rest.len(), |end| end + 1)),
+            false => rest.split_at(rest.find('<').unwrap_or(rest.len())),
+        };
+        rest = after;
+        if !token.starts_with('<') {
+            if skipping.is_none() && !token.trim().is_empty() {
+                normalized.push_str(token);
+            }
+            continue;
+        }
+        if token.starts_with("<?") || token.starts_with("<!") {
+            if skipping.is_none() {
+                normalized.push_str(token);
+            }
+            continue;
+        }
+        let closing = token.starts_with("</");
+        let empty = token.ends_with("/>");
+        let inner = token
+            .trim_start_matches("</")
+            .trim_start_matches('<')
+            .trim_end_matches('>')
+            .trim_end_matches('/');
+        let name = inner.split_whitespace().next().unwrap_or("");
+        match skipping {
+            Some((skipped, depth)) if skipped == name && closing => {
+                skipping = depth.checked_sub(1).map(|depth| (skipped, depth));
+                continue;
+            }
+            Some((skipped, depth)) if skipped == name && !empty => {
+                skipping = Some((skipped, depth + 1));
+                continue;
+            }
+            Some(_) => continue,
+            None if !closing && VOLATILE_ELEMENTS.contains(&name) => {
+                if !empty {
+                    skipping = Some((name, 0));
+                }
+                continue;
+            }
+            None if closing => {
+                normalized.push_str(token);
+                continue;
+            }
+            None => {}
+        }
+        let mut attributes = attributes(&inner[name.len()..]);
+        attributes.retain(|(key, _)| !key.starts_with(RSID_PREFIX));
+        attributes.sort();
+        normalized.push('<');
+        normalized.push_str(name);
+        for (key, value) in attributes {
+            normalized.push_str(&format!(" {}=\"{}\"", key, value));
+        }
+        normalized.push_str(match empty {
+            true => "/>",
+            false => ">",
+        });
+    }
+    normalized
+}
+
+/// Splits the attributes of a tag into names and values without quotes.
+fn attributes(mut text: &str) -> Vec<(&str, &str)> {
+    let mut attributes = vec![];
+    while let Some(equals) = text.find('=') {
+        let key = text[..equals].trim();
+        let value = text[equals + 1..].trim_start();
+        let Some(quote) = value.chars().next().filter(|&c| c == '"' || c == '\'') else {
+            break;
+        };
+        let value = &value[1..];
+        let end = value.find(quote).unwrap_or(value.len());
+        attributes.push((key, &value[..end]));
+        text = &value[(end + 1).min(value.len())..];
+    }
+    attributes
+}
+
+fn read_parts(path: &Path) -> io::Result<Vec<(String, Vec<u8>)>> {
+    let file = File::open(long_path::extended(path))?;
+    let mut archive = ZipArchive::new(file).map_err(|e| zip_error(path, e))?;
+    let mut parts = vec![];
+    for index in 0..archive.len() {
+        let mut part = archive.by_index(index).map_err(|e| zip_error(path, e))?;
+        if part.is_dir() {
+            continue;
+        }
+        let mut contents = vec![];
+        part.read_to_end(&mut contents)?;
+        parts.push((part.name().to_string(), contents));
+    }
+    parts.sort();
+    Ok(parts)
+}
+
+fn is_xml(name: &str) -> bool {
+    name.ends_with(".xml") || name.ends_with(".rels")
+}
+
+/// Compares two Office Open XML documents part by part, normalizing the XML
+/// parts with `normalize_xml` and comparing the others, such as images,
+/// byte by byte. Returns the name of the first part that differs or is in
+/// only one of them, in order of the names.
+pub fn differing_part<P: AsRef<Path>>(path1: P, path2: P) -> io::Result<Option<String>> {
+    let parts1 = read_parts(path1.as_ref())?;
+    let parts2 = read_parts(path2.as_ref())?;
+    let (mut parts1, mut parts2) = (parts1.into_iter().peekable(), parts2.into_iter().peekable());
+    loop {
+        let (name1, name2) = match (parts1.peek(), parts2.peek()) {
+            (None, None) => return Ok(None),
+            (Some((name, _)), None) | (None, Some((name, _))) => return Ok(Some(name.clone())),
+            (Some((name1, _)), Some((name2, _))) => (name1, name2),
+        };
+        if name1 != name2 {
+            return Ok(Some(name1.min(name2).clone()));
+        }
+        let ((name, contents1), (_, contents2)) = (parts1.next().unwrap(), parts2.next().unwrap());
+        let equal = match is_xml(&name) {
+            true => {
+                let xml1 = String::from_utf8_lossy(&contents1);
+                let xml2 = String::from_utf8_lossy(&contents2);
+                normalize_xml(&xml1) == normalize_xml(&xml2)
+            }
+            false => contents1 == contents2,
+        };
+        if !equal {
+            return Ok(Some(name));
+        }
+    }
+}
+
+/// Compares two Office Open XML documents like `differing_part`. Since the
+/// difference is in a part rather than at an offset of the files, it is at
+/// offset 0 like in quick mode.
+pub fn compare_office<P: AsRef<Path>>(path1: P, path2: P) -> io::Result<FileDiff> {
+    Ok(match differing_part(path1, path2)? {
+        Some(_) => FileDiff::Different(0),
+        None => FileDiff::Equal,
+    })
+}
@@ -0,0 +1,310 @@
+use crate::hooks::Summary;
+use crate::http::{agent, call_error};
+use crate::FileDiff;
+use serde_json::{json, Value};
+use std::env;
+use std::hash::{BuildHasher, RandomState};
+use std::io;
+use std::mem;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Spans buffered before they are sent, bounding the memory of long runs.
+const BATCH: usize = 512;
+
+/// Span kind `SPAN_KIND_INTERNAL`.
+const INTERNAL: u8 = 1;
+
+/// Status codes of spans.
+const STATUS_OK: u8 = 1;
+const STATUS_ERROR: u8 = 2;
+
+/// Aggregation temporality of the counters, which cover the whole run.
+const CUMULATIVE: u8 = 2;
+
+/// Exports a trace of a run, with a span per pair of files compared, and
+/// counters of its results to an OpenTelemetry collector over OTLP/HTTP
+/// with JSON encoding.
+#[derive(Debug)]
+pub struct Tracer {
+    agent: ureq::Agent,
+    traces_url: String,
+    metrics_url: String,
+    headers: Vec<(String, String)>,
+    service_name: String,
+    ids: RandomState,
+    next_id: AtomicU64,
+    trace_id: String,
+    root_id: String,
+    started: SystemTime,
+    spans: Mutex<Vec<Value>>,
+    bytes: AtomicU64,
+    /// The first error sending a batch of spans, returned by `finish`
+    failed: Mutex<Option<io::Error>>,
+}
+
+fn nanos(time: SystemTime) -> String {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos()
+        .to_string()
+}
+
+fn attribute(key: &str, value: Value) -> Value {
+    let value = match value {
+        Value::String(s) => json!({ "stringValue": s }),
+        Value::Bool(b) => json!({ "boolValue": b }),
+        // 64-bit integers are strings in OTLP/JSON
+        number => json!({ "intValue": number.to_string() }),
+    };
+    json!({ "key": key, "value": value })
+}
+
+impl Tracer {
+    /// Configures the exporter from the standard environment variables:
+    /// `OTEL_EXPORTER_OTLP_ENDPOINT` (`http://localhost:4318` by default),
+    /// or `OTEL_EXPORTER_OTLP_TRACES_ENDPOINT` and
+    /// `OTEL_EXPORTER_OTLP_METRICS_ENDPOINT` for each signal,
+    /// `OTEL_EXPORTER_OTLP_HEADERS` as `key=value` pairs separated by commas
+    /// and `OTEL_SERVICE_NAME`.
+    pub fn from_env(timeout: Option<Duration>) -> Tracer {
+        let var = |name| env::var(name).ok().filter(|v: &String| !v.is_empty());
+        let endpoint = var("OTEL_EXPORTER_OTLP_ENDPOINT")
+            .unwrap_or_else(|| "http://localhost:4318".to_string());
+        let endpoint = endpoint.trim_end_matches('/');
+        let headers = var("OTEL_EXPORTER_OTLP_HEADERS")
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|header| header.split_once('='))
+            .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+            .collect();
+        let ids = RandomState::new();
+        let started = SystemTime::now();
+        let trace_id = format!(
+            "{:016x}{:016x}",
+            ids.hash_one((nanos(started), 0)),
+            ids.hash_one((nanos(started), 1))
+        );
+        let root_id = format!("{:016x}", ids.hash_one((&trace_id, u64::MAX)));
+        Tracer {
+            agent: agent(timeout),
+            traces_url: var("OTEL_EXPORTER_OTLP_TRACES_ENDPOINT")
+                .unwrap_or_else(|| format!("{}/v1/traces", endpoint)),
+            metrics_url: var("OTEL_EXPORTER_OTLP_METRICS_ENDPOINT")
+                .unwrap_or_else(|| format!("{}/v1/metrics", endpoint)),
+            headers,
+            service_name: var("OTEL_SERVICE_NAME").unwrap_or_else(|| "file_cmp".to_string()),
+            ids,
+            next_id: AtomicU64::new(0),
+            trace_id,
+            root_id,
+            started,
+            spans: Mutex::new(vec![]),
+            bytes: AtomicU64::new(0),
+            failed: Mutex::new(None),
+        }
+    }
+
+    /// Sends the traces and metrics to a collector at `endpoint` instead,
+    /// e.g. `http://localhost:4318`.
+    pub fn with_endpoint(mut self, endpoint: &str) -> Tracer {
+        let endpoint = endpoint.trim_end_matches('/');
+        self.traces_url = format!("{}/v1/traces", endpoint);
+        self.metrics_url = format!("{}/v1/metrics", endpoint);
+        self
+    }
+
+    pub fn trace_id(&self) -> &str {
+        &self.trace_id
+    }
+
+    fn span_id(&self) -> String {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        format!("{:016x}", self.ids.hash_one((&self.trace_id, id)))
+    }
+
+    fn span(
+        &self,
+        name: &str,
+        id: String,
+        parent: Option<&str>,
+        started: SystemTime,
+        attributes: Vec<Value>,
+        error: Option<String>,
+    ) -> Value {
+        let status = match error {
+            Some(message) => json!({ "code": STATUS_ERROR, "message": message }),
+            None => json!({ "code": STATUS_OK }),
+        };
+        json!({
+            "traceId": self.trace_id,
+            "spanId": id,
+            "parentSpanId": parent.unwrap_or_default(),
+            "name": name,
+            "kind": INTERNAL,
+            "startTimeUnixNano": nanos(started),
+            "endTimeUnixNano": nanos(SystemTime::now()),
+            "attributes": attributes,
+            "status": status,
+        })
+    }
+
+    /// Records the comparison of a pair of files, which started at
+    /// `started` and has just finished, as a span of the run.
+    pub fn record(
+        &self,
+        path1: &Path,
+        path2: &Path,
+        result: &io::Result<FileDiff>,
+        started: SystemTime,
+        bytes: u64,
+    ) {
+        self.bytes.fetch_add(bytes, Ordering::Relaxed);
+        let mut attributes = vec![
+            attribute("file_cmp.path1", json!(path1.display().to_string())),
+            attribute("file_cmp.path2", json!(path2.display().to_string())),
+            attribute("file_cmp.bytes", json!(bytes)),
+        ];
+        if let Ok(file_diff) = result {
+            attributes.push(attribute("file_cmp.result", json!(file_diff.as_desc())));
+            if let FileDiff::Different(offset) | FileDiff::Truncated(_, offset) = file_diff {
+                attributes.push(attribute("file_cmp.offset", json!(offset)));
+            }
+        }
+        let error = result.as_ref().err().map(|e| e.to_string());
+        let span = self.span(
+            "compare",
+            self.span_id(),
+            Some(&self.root_id),
+            started,
+            attributes,
+            error,
+        );
+        let batch = {
+            let mut spans = self.spans.lock().unwrap();
+            spans.push(span);
+            match spans.len() >= BATCH {
+                true => mem::take(&mut *spans),
+                false => return,
+            }
+        };
+        if let Err(e) = self.send_spans(batch) {
+            self.failed.lock().unwrap().get_or_insert(e);
+        }
+    }
+
+    fn resource(&self) -> Value {
+        json!({
+            "attributes": [attribute("service.name", json!(self.service_name))]
+        })
+    }
+
+    fn scope() -> Value {
+        json!({ "name": "file_cmp", "version": env!("CARGO_PKG_VERSION") })
+    }
+
+    fn send_spans(&self, spans: Vec<Value>) -> io::Result<()> {
+        self.post(
+            &self.traces_url,
+            json!({
+                "resourceSpans": [{
+                    "resource": self.resource(),
+                    "scopeSpans": [{ "scope": Self::scope(), "spans": spans }],
+                }]
+            }),
+        )
+    }
+
+    fn post(&self, url: &str, body: Value) -> io::Result<()> {
+        let mut request = self.agent.post(url).set("Content-Type", "application/json");
+        for (name, value) in &self.headers {
+            request = request.set(name, value);
+        }
+        request
+            .send_string(&body.to_string())
+            .map(drop)
+            .map_err(|e| call_error(url, e))
+    }
+
+    /// Ends the run: sends the spans not sent yet with the span of the whole
+    /// run, and the counters of files by result and of bytes compared.
+    pub fn finish(&self, summary: &Summary, success: bool) -> io::Result<()> {
+        let counts = [
+            ("equal", summary.equal),
+            ("different", summary.different),
+            ("left_only", summary.left_only),
+            ("right_only", summary.right_only),
+        ];
+        let attributes = counts
+            .iter()
+            .map(|(result, count)| attribute(&format!("file_cmp.{}", result), json!(count)))
+            .collect();
+        let error = (!success).then(|| "the comparison failed".to_string());
+        let root = self.span(
+            "file_cmp",
+            self.root_id.clone(),
+            None,
+            self.started,
+            attributes,
+            error,
+        );
+        let mut spans = mem::take(&mut *self.spans.lock().unwrap());
+        spans.push(root);
+        self.send_spans(spans)?;
+        if let Some(e) = self.failed.lock().unwrap().take() {
+            return Err(e);
+        }
+
+        let (start, now) = (nanos(self.started), nanos(SystemTime::now()));
+        let files: Vec<Value> = counts
+            .iter()
+            .map(|(result, count)| {
+                json!({
+                    "attributes": [attribute("result", json!(result))],
+                    "startTimeUnixNano": start,
+                    "timeUnixNano": now,
+                    "asInt": count.to_string(),
+                })
+            })
+            .collect();
+        let bytes = json!({
+            "startTimeUnixNano": start,
+            "timeUnixNano": now,
+            "asInt": self.bytes.load(Ordering::Relaxed).to_string(),
+        });
+        let sum = |points: Vec<Value>| {
+            json!({
+                "aggregationTemporality": CUMULATIVE,
+                "isMonotonic": true,
+                "dataPoints": points,
+            })
+        };
+        self.post(
+            &self.metrics_url,
+            json!({
+                "resourceMetrics": [{
+                    "resource": self.resource(),
+                    "scopeMetrics": [{
+                        "scope": Self::scope(),
+                        "metrics": [
+                            {
+                                "name": "file_cmp.files",
+                                "description": "Files compared, by result",
+                                "unit": "{file}",
+                                "sum": sum(files),
+                            },
+                            {
+                                "name": "file_cmp.bytes",
+                                "description": "Bytes of the files compared",
+                                "unit": "By",
+                                "sum": sum(vec![bytes]),
+                            },
+                        ],
+                    }],
+                }]
+            }),
+        )
+    }
+}
@@ -0,0 +1,64 @@
+use crate::{compare_range, diff_at_lengths, CompareOptions, FileDiff};
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::thread;
+
+/// Size of the ranges handed out to the worker threads.
+pub const SEGMENT_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Compares two files by splitting them into ranges that are compared by
+/// `options.threads` worker threads, each with its own file handles. Workers skip
+/// ranges past the earliest difference found so far, so the result is the
+/// same first differing offset a sequential comparison would report.
+pub fn compare_files(
+    path1: &Path,
+    len1: u64,
+    path2: &Path,
+    len2: u64,
+    options: &CompareOptions,
+) -> io::Result<FileDiff> {
+    let quick = options.quick;
+    let len = len1.min(len2);
+    let segments = len.div_ceil(SEGMENT_SIZE) as usize;
+    let next_segment = AtomicUsize::new(0);
+    let earliest = AtomicU64::new(u64::MAX);
+
+    thread::scope(|scope| {
+        let workers: Vec<_> = (0..options.threads.min(segments).max(1))
+            .map(|_| {
+                scope.spawn(|| -> io::Result<()> {
+                    let mut file1 = File::open(path1)?;
+                    let mut file2 = File::open(path2)?;
+                    loop {
+                        let segment = next_segment.fetch_add(1, Ordering::Relaxed);
+                        let start = segment as u64 * SEGMENT_SIZE;
+                        let found = earliest.load(Ordering::Relaxed);
+                        if segment >= segments || start > found || (quick && found != u64::MAX) {
+                            return Ok(());
+                        }
+                        let range = start..(start + SEGMENT_SIZE).min(len);
+                        if let Some(offset) = compare_range(&mut file1, &mut file2, range, options)?
+                        {
+                            earliest.fetch_min(offset, Ordering::Relaxed);
+                            if quick {
+                                return Ok(());
+                            }
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        workers
+            .into_iter()
+            .try_for_each(|worker| worker.join().expect("Comparison thread panicked"))
+    })?;
+
+    let offset = match earliest.into_inner() {
+        u64::MAX => (len1 != len2).then_some(len),
+        offset => Some(offset),
+    };
+    Ok(diff_at_lengths(offset, len1, len2, quick))
+}
@@ -0,0 +1,156 @@
+use crate::bench::drop_cache;
+use crate::hash::HashAlgorithm;
+use crate::throttle::{Throttle, Throttled};
+use crate::{long_path, read_full};
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Bytes of a file hashed separately on each read, so the reads can be told
+/// apart by block.
+pub const BLOCK_SIZE: usize = 1024 * 1024;
+
+/// Alignment of the buffer and reads for O_DIRECT, enough for the logical
+/// block size of any common device.
+const DIRECT_ALIGN: usize = 4096;
+
+/// How the second read of a file for --paranoid gets its data.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Reread {
+    /// Like the first, likely from the page cache, to catch flaky RAM
+    #[default]
+    Cached,
+    /// After evicting the file from the page cache, to read it from storage
+    /// again
+    DropCache,
+    /// With O_DIRECT, bypassing the page cache (Linux only)
+    Direct,
+}
+
+impl Reread {
+    pub const ALL: [Reread; 3] = [Reread::Cached, Reread::DropCache, Reread::Direct];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Cached => "cached",
+            Self::DropCache => "drop-cache",
+            Self::Direct => "direct",
+        }
+    }
+}
+
+impl FromStr for Reread {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|reread| reread.name() == s)
+            .ok_or_else(|| format!("unknown way to read again '{}'", s))
+    }
+}
+
+/// Reads a file twice, the second time as `reread` says, and returns the
+/// offset of the first block of `BLOCK_SIZE` bytes whose reads differ, or
+/// `None` if both reads returned the same data. Only the hashes of the
+/// blocks of the first read are kept. Files that can't be read again from
+/// the start, like pipes, and empty ones aren't read.
+pub fn check_rereads<P: AsRef<Path>>(
+    path: P,
+    reread: Reread,
+    algorithm: HashAlgorithm,
+    throttle: Option<&Throttle>,
+) -> io::Result<Option<u64>> {
+    let path = long_path::extended(path.as_ref());
+    let meta = fs::metadata(&path)?;
+    if !meta.is_file() || meta.len() == 0 {
+        return Ok(None);
+    }
+    let mut first = vec![];
+    let mut buffer = vec![0; BLOCK_SIZE];
+    let mut reader = Throttled::new(File::open(&path)?, throttle);
+    hash_blocks(&mut reader, &mut buffer, algorithm, |hash| {
+        first.push(hash);
+        true
+    })?;
+
+    if reread == Reread::DropCache && !drop_cache(&path)? {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "cannot drop the page cache on this platform",
+        ));
+    }
+    let (file, mut aligned);
+    let buffer = match reread {
+        Reread::Direct => {
+            file = open_direct(&path)?;
+            aligned = vec![0; BLOCK_SIZE + DIRECT_ALIGN];
+            let start = aligned.as_ptr().align_offset(DIRECT_ALIGN);
+            &mut aligned[start..start + BLOCK_SIZE]
+        }
+        _ => {
+            file = File::open(&path)?;
+            &mut buffer[..]
+        }
+    };
+    let mut reader = Throttled::new(file, throttle);
+    let mut block = 0;
+    let mut differs = None;
+    let len = hash_blocks(&mut reader, buffer, algorithm, |hash| {
+        if first.get(block) != Some(&hash) {
+            differs = Some(block as u64 * BLOCK_SIZE as u64);
+        }
+        block += 1;
+        differs.is_none()
+    })?;
+    Ok(differs.or((block < first.len()).then_some(len)))
+}
+
+/// Hashes what a reader returns by block, passing each hash on until `f`
+/// returns false. Returns the bytes read.
+fn hash_blocks<R: io::Read, F: FnMut(Vec<u8>) -> bool>(
+    reader: &mut R,
+    buffer: &mut [u8],
+    algorithm: HashAlgorithm,
+    mut f: F,
+) -> io::Result<u64> {
+    let mut read = 0;
+    loop {
+        let len = read_full(reader, buffer)?;
+        if len == 0 {
+            return Ok(read);
+        }
+        read += len as u64;
+        let mut hasher = algorithm.hasher();
+        hasher.update(&buffer[..len]);
+        if !f(hasher.finalize().to_vec()) || len < buffer.len() {
+            return Ok(read);
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn open_direct(path: &Path) -> io::Result<File> {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    fs::OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_DIRECT)
+        .open(path)
+        .map_err(|e| match e.raw_os_error() {
+            Some(libc::EINVAL) => io::Error::new(
+                io::ErrorKind::Unsupported,
+                "cannot read with O_DIRECT on this filesystem",
+            ),
+            _ => e,
+        })
+}
+
+#[cfg(not(target_os = "linux"))]
+fn open_direct(_path: &Path) -> io::Result<File> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "cannot read with O_DIRECT: only supported on Linux",
+    ))
+}
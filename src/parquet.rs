@@ -0,0 +1,72 @@
+use ::parquet::errors::ParquetError;
+use ::parquet::file::reader::{FileReader, SerializedFileReader};
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+/// How two Parquet files differ in their data.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TableDiff {
+    Equal,
+    /// The columns differ in name, type or nesting
+    Schema,
+    /// The index of the first differing row, or the number of rows of the
+    /// shorter file if it has the first rows of the other
+    Row(u64),
+}
+
+impl TableDiff {
+    /// Returns the result as a number like `FileDiff::as_number`: -1 if
+    /// equal, -4 if the schemas differ, or the index of the first differing
+    /// row.
+    pub fn as_number(&self) -> String {
+        match self {
+            Self::Equal => "-1".to_string(),
+            Self::Schema => "-4".to_string(),
+            Self::Row(row) => row.to_string(),
+        }
+    }
+}
+
+fn parquet_error(path: &Path, e: ParquetError) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("{}: {}", path.display(), e),
+    )
+}
+
+/// Compares two Parquet files by their schema and rows, so that files with
+/// the same data are equal however it is split into row groups and pages
+/// and whichever codec compresses it.
+pub fn compare_parquet<P: AsRef<Path>>(path1: P, path2: P) -> io::Result<TableDiff> {
+    let (path1, path2) = (path1.as_ref(), path2.as_ref());
+    let reader1 =
+        SerializedFileReader::new(File::open(path1)?).map_err(|e| parquet_error(path1, e))?;
+    let reader2 =
+        SerializedFileReader::new(File::open(path2)?).map_err(|e| parquet_error(path2, e))?;
+    if reader1.metadata().file_metadata().schema() != reader2.metadata().file_metadata().schema() {
+        return Ok(TableDiff::Schema);
+    }
+
+    let mut rows1 = reader1
+        .get_row_iter(None)
+        .map_err(|e| parquet_error(path1, e))?;
+    let mut rows2 = reader2
+        .get_row_iter(None)
+        .map_err(|e| parquet_error(path2, e))?;
+    let mut index = 0;
+    loop {
+        match (rows1.next(), rows2.next()) {
+            (None, None) => return Ok(TableDiff::Equal),
+            (Some(row1), Some(row2)) => {
+                let row1 = row1.map_err(|e| parquet_error(path1, e))?;
+                let row2 = row2.map_err(|e| parquet_error(path2, e))?;
+                if row1 != row2 {
+                    return Ok(TableDiff::Row(index));
+                }
+            }
+            _ => return Ok(TableDiff::Row(index)),
+        }
+        index += 1;
+    }
+}
@@ -0,0 +1,63 @@
+use lopdf::Document;
+use std::io;
+use std::path::Path;
+
+/// How the text of two PDF files differs.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TextDiff {
+    Equal,
+    /// The number of the first page whose words differ, counting from 1, or
+    /// the page after the last one of the shorter file if it has the first
+    /// pages of the other
+    Page(u32),
+}
+
+impl TextDiff {
+    /// Returns the result as a number like `FileDiff::as_number`: -1 if
+    /// equal, or the number of the first differing page.
+    pub fn as_number(&self) -> String {
+        match self {
+            Self::Equal => "-1".to_string(),
+            Self::Page(page) => page.to_string(),
+        }
+    }
+}
+
+fn pdf_error(path: &Path, e: lopdf::Error) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("{}: {}", path.display(), e),
+    )
+}
+
+/// Extracts the text of each page of a PDF file, with its words separated
+/// by single spaces however they are laid out.
+pub fn page_text<P: AsRef<Path>>(path: P) -> io::Result<Vec<String>> {
+    let path = path.as_ref();
+    let document = Document::load(path).map_err(|e| pdf_error(path, e))?;
+    document
+        .get_pages()
+        .into_keys()
+        .map(|page| {
+            let text = document
+                .extract_text(&[page])
+                .map_err(|e| pdf_error(path, e))?;
+            Ok(text.split_whitespace().collect::<Vec<_>>().join(" "))
+        })
+        .collect()
+}
+
+/// Compares two PDF files by the words on their pages, so that a document
+/// generated again with the same content is equal although its bytes, such
+/// as timestamps, object numbering and compression, differ.
+pub fn compare_pdf<P: AsRef<Path>>(path1: P, path2: P) -> io::Result<TextDiff> {
+    let (pages1, pages2) = (page_text(path1)?, page_text(path2)?);
+    let differing = pages1.iter().zip(&pages2).position(|(a, b)| a != b);
+    Ok(match differing {
+        Some(index) => TextDiff::Page(index as u32 + 1),
+        None if pages1.len() != pages2.len() => {
+            TextDiff::Page(pages1.len().min(pages2.len()) as u32 + 1)
+        }
+        None => TextDiff::Equal,
+    })
+}
@@ -0,0 +1,102 @@
+use crate::chunk::ChunkSizer;
+use crate::{compare_chunks, read_full};
+use std::io::{self, Read};
+use std::ops::ControlFlow;
+use std::sync::mpsc::{self, Receiver, Sender, SyncSender};
+use std::thread::{self, Scope, ScopedJoinHandle};
+
+/// Inputs shorter than this are compared without the pipeline, as the
+/// threads would cost more than they save.
+pub const MIN_PIPELINE_LEN: u64 = 4 * 1024 * 1024;
+
+/// Compares two readers like `compare_readers`, but reads each of them on
+/// its own thread up to `depth` chunks ahead of the comparison, so reading
+/// the next chunks overlaps with comparing the current ones.
+pub fn compare_readers<R1: Read + Send, R2: Read + Send>(
+    reader1: &mut R1,
+    reader2: &mut R2,
+    chunks: &mut ChunkSizer,
+    depth: usize,
+) -> io::Result<Option<u64>> {
+    thread::scope(|scope| {
+        let (side1, producer1) = Side::spawn(scope, reader1, chunks.clone(), depth);
+        let (side2, producer2) = Side::spawn(scope, reader2, chunks.clone(), depth);
+
+        let mut pos = 0;
+        let result = loop {
+            // A closed channel means the reader thread stopped on an error
+            let (Ok(chunk1), Ok(chunk2)) = (side1.filled.recv(), side2.filled.recv()) else {
+                break None;
+            };
+            let size = chunks.size();
+            if let ControlFlow::Break(result) = compare_chunks(&chunk1, &chunk2, size, pos) {
+                break Some(result);
+            }
+            pos += size as u64;
+            chunks.grow();
+            side1.recycle(chunk1);
+            side2.recycle(chunk2);
+        };
+
+        // Hang up so reader threads blocked on a full channel stop
+        drop(side1);
+        drop(side2);
+        let read1 = producer1.join().expect("Reader thread panicked");
+        let read2 = producer2.join().expect("Reader thread panicked");
+        match result {
+            Some(result) => Ok(result),
+            None => read1.and(read2).map(|_| None),
+        }
+    })
+}
+
+/// The comparison's end of one reader thread: it receives filled chunks and
+/// hands compared ones back for reuse, so at most `depth + 2` buffers exist
+/// per side.
+struct Side {
+    filled: Receiver<Vec<u8>>,
+    empty: Sender<Vec<u8>>,
+}
+
+impl Side {
+    fn spawn<'scope, R: Read + Send>(
+        scope: &'scope Scope<'scope, '_>,
+        reader: &'scope mut R,
+        chunks: ChunkSizer,
+        depth: usize,
+    ) -> (Side, ScopedJoinHandle<'scope, io::Result<()>>) {
+        let (filled_tx, filled_rx) = mpsc::sync_channel(depth.max(1));
+        let (empty_tx, empty_rx) = mpsc::channel();
+        let producer = scope.spawn(move || produce(reader, chunks, filled_tx, empty_rx));
+        let side = Side {
+            filled: filled_rx,
+            empty: empty_tx,
+        };
+        (side, producer)
+    }
+
+    fn recycle(&self, buffer: Vec<u8>) {
+        let _ = self.empty.send(buffer);
+    }
+}
+
+/// Reads chunks with the same sizes the comparison expects until the reader
+/// is exhausted or the comparison hangs up.
+fn produce<R: Read>(
+    reader: &mut R,
+    mut chunks: ChunkSizer,
+    filled: SyncSender<Vec<u8>>,
+    empty: Receiver<Vec<u8>>,
+) -> io::Result<()> {
+    loop {
+        let size = chunks.size();
+        let mut buffer = empty.try_recv().unwrap_or_default();
+        buffer.resize(size, 0);
+        let len = read_full(reader, &mut buffer)?;
+        buffer.truncate(len);
+        if filled.send(buffer).is_err() || len < size {
+            return Ok(());
+        }
+        chunks.grow();
+    }
+}
@@ -0,0 +1,187 @@
+use crate::{media, sqldump, FileDiff};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+/// How files matching a pattern are compared.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Policy {
+    /// Compare them as without a policy
+    Byte,
+    /// Only compare their sizes and hashes, without looking for the offset
+    /// of a difference
+    HashOnly,
+    /// Compare them by their content for the formats that can be: JSON and
+    /// TOML by their values, and SQL dumps, media files and Office documents
+    /// as with --sql-dump, --media and --office
+    Semantic,
+    /// Leave them out of directory comparisons
+    Ignore,
+}
+
+impl Policy {
+    pub const ALL: [Policy; 4] = [
+        Policy::Byte,
+        Policy::HashOnly,
+        Policy::Semantic,
+        Policy::Ignore,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Byte => "byte",
+            Self::HashOnly => "hash-only",
+            Self::Semantic => "semantic",
+            Self::Ignore => "ignore",
+        }
+    }
+}
+
+impl fmt::Display for Policy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Policy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|p| p.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("unknown policy '{}'", s))
+    }
+}
+
+/// Policies by file pattern, e.g. `*.iso` to hash-only and `*.log` to
+/// ignore, with one for the files no pattern matches.
+#[derive(Clone, Debug, Default)]
+pub struct Policies {
+    patterns: Vec<(String, Policy)>,
+    default: Option<Policy>,
+}
+
+impl Policies {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the policy of the files matching the pattern, replacing any it
+    /// had, or of the files no pattern matches for the pattern `default`.
+    pub fn insert(&mut self, pattern: &str, policy: Policy) {
+        if pattern == "default" {
+            self.default = Some(policy);
+            return;
+        }
+        self.patterns.retain(|(p, _)| p != pattern);
+        self.patterns.push((pattern.to_string(), policy));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty() && self.default.is_none()
+    }
+
+    /// Returns the policy of the file: that of the longest matching pattern,
+    /// or the default. Patterns without a `/` match the file name, and the
+    /// others the end of the path with as many components.
+    pub fn get(&self, path: &Path) -> Option<Policy> {
+        let components: Vec<String> = path
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+            .collect();
+        self.patterns
+            .iter()
+            .filter(|(pattern, _)| {
+                let parts: Vec<&str> = pattern.split('/').collect();
+                components.len() >= parts.len()
+                    && parts
+                        .iter()
+                        .zip(&components[components.len() - parts.len()..])
+                        .all(|(part, component)| glob_match(part, component))
+            })
+            .max_by_key(|(pattern, _)| pattern.len())
+            .map(|(_, policy)| *policy)
+            .or(self.default)
+    }
+}
+
+/// Matches a name against a pattern where `*` matches any run of
+/// characters and `?` any one character, without regard to ASCII case.
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_ascii_lowercase().chars().collect();
+    let name: Vec<char> = name.to_ascii_lowercase().chars().collect();
+    // Where the last * is in the pattern and the name, to backtrack to
+    let (mut p, mut n, mut star) = (0, 0, None);
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    p = star_p + 1;
+                    n = star_n + 1;
+                    star = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Compares files by their content if their format is one that can be,
+/// or returns `None` to compare them byte by byte. Files whose values
+/// differ are different at offset 0.
+pub fn compare_semantic(
+    path1: &Path,
+    path2: &Path,
+    options: &crate::CompareOptions,
+) -> io::Result<Option<FileDiff>> {
+    let extension = path1
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase);
+    let equal = match extension.as_deref() {
+        Some("json") => {
+            let parse = |path| -> io::Result<serde_json::Value> {
+                serde_json::from_slice(&fs::read(path)?)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+            };
+            parse(path1)? == parse(path2)?
+        }
+        Some("toml") => {
+            let parse = |path| -> io::Result<toml::Table> {
+                fs::read_to_string(path)?
+                    .parse()
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+            };
+            parse(path1)? == parse(path2)?
+        }
+        _ if sqldump::is_sql_dump(path1) => {
+            return sqldump::compare_sql_dumps(path1, path2, options.quick).map(Some);
+        }
+        _ => {
+            if let Some(format) = media::Format::of(path1) {
+                return media::compare_media(path1, path2, format, options).map(Some);
+            }
+            #[cfg(feature = "office")]
+            if crate::office::is_office(path1) {
+                return crate::office::compare_office(path1, path2).map(Some);
+            }
+            return Ok(None);
+        }
+    };
+    Ok(Some(match equal {
+        true => FileDiff::Equal,
+        false => FileDiff::Different(0),
+    }))
+}
@@ -0,0 +1,144 @@
+use crate::strategy::is_network_fs;
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fmt;
+use std::fs::{self, DirEntry, File, FileType, Metadata};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Threads statting the entries of a directory at once. Stats on network
+/// filesystems wait on round trips rather than the CPU, so this is more
+/// than there usually are CPUs.
+pub const STAT_WORKERS: usize = 16;
+
+/// When directory comparisons list each directory with the metadata of its
+/// entries up front instead of statting paths one at a time.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Prefetch {
+    /// When either directory is on a network filesystem
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl Prefetch {
+    pub const ALL: [Prefetch; 3] = [Prefetch::Auto, Prefetch::Always, Prefetch::Never];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Auto => "auto",
+            Self::Always => "always",
+            Self::Never => "never",
+        }
+    }
+
+    /// Returns whether to prefetch the metadata when comparing these
+    /// directories.
+    pub fn applies(&self, dir1: &Path, dir2: &Path) -> bool {
+        let network = |dir: &Path| File::open(dir).is_ok_and(|dir| is_network_fs(&dir));
+        match self {
+            Self::Auto => network(dir1) || network(dir2),
+            Self::Always => true,
+            Self::Never => false,
+        }
+    }
+}
+
+impl fmt::Display for Prefetch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Prefetch {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|prefetch| prefetch.name() == s)
+            .ok_or_else(|| format!("unknown time to prefetch metadata '{}'", s))
+    }
+}
+
+/// An entry of a listed directory.
+#[derive(Debug)]
+pub struct Entry {
+    /// The type of the entry itself, from the directory listing
+    pub file_type: FileType,
+    /// The metadata of what the entry names, following links, if it was
+    /// wanted and could be read
+    pub meta: Option<Metadata>,
+}
+
+/// The entries of a directory, in the order they were read, with their
+/// metadata.
+#[derive(Debug, Default)]
+pub struct Listing {
+    paths: Vec<PathBuf>,
+    entries: HashMap<OsString, Entry>,
+}
+
+impl Listing {
+    /// Lists a directory. The types of its entries come with the listing on
+    /// all common filesystems; the metadata of links, and with `with_meta`
+    /// of every file, is statted by `STAT_WORKERS` threads at once, except
+    /// on Windows, where the listing has it already for all but links.
+    pub fn read(dir: &Path, with_meta: bool) -> io::Result<Self> {
+        let mut listed = vec![];
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let file_type = entry.file_type()?;
+            listed.push((entry, file_type, None));
+        }
+        let workers = match cfg!(windows) {
+            true => 1,
+            false => STAT_WORKERS,
+        };
+        let mut wanted: Vec<_> = listed
+            .iter_mut()
+            .filter(|(_, file_type, _)| {
+                file_type.is_symlink() || (with_meta && !file_type.is_dir())
+            })
+            .collect();
+        let chunk = wanted.len().div_ceil(workers).max(1);
+        std::thread::scope(|scope| {
+            for chunk in wanted.chunks_mut(chunk) {
+                scope.spawn(move || {
+                    for (entry, file_type, meta) in chunk.iter_mut() {
+                        *meta = stat(entry, *file_type);
+                    }
+                });
+            }
+        });
+        let mut listing = Self::default();
+        for (entry, file_type, meta) in listed {
+            listing.paths.push(entry.path());
+            listing
+                .entries
+                .insert(entry.file_name(), Entry { file_type, meta });
+        }
+        Ok(listing)
+    }
+
+    /// The paths of the entries, in the order they were read.
+    pub fn paths(&self) -> &[PathBuf] {
+        &self.paths
+    }
+
+    /// Returns the entry of a path in the directory, by its name.
+    pub fn get(&self, path: &Path) -> Option<&Entry> {
+        self.entries.get(path.file_name()?)
+    }
+}
+
+/// Returns the metadata of what an entry names, which for all but links is
+/// part of the listing on Windows.
+fn stat(entry: &DirEntry, file_type: FileType) -> Option<Metadata> {
+    match file_type.is_symlink() {
+        true => fs::metadata(entry.path()).ok(),
+        false => entry.metadata().ok(),
+    }
+}
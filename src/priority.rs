@@ -0,0 +1,51 @@
+use std::io;
+
+/// Lowers the IO priority of the current process so that long comparisons
+/// yield to interactive workloads: the idle IO scheduling class on Linux and
+/// background processing mode on Windows. Must be called before spawning
+/// any threads, which inherit the priority.
+#[cfg(target_os = "linux")]
+pub fn enter_background_mode() -> io::Result<()> {
+    const IOPRIO_WHO_PROCESS: libc::c_long = 1;
+    const IOPRIO_CLASS_IDLE: libc::c_long = 3;
+    const IOPRIO_CLASS_SHIFT: libc::c_long = 13;
+
+    let ret = unsafe {
+        libc::syscall(
+            libc::SYS_ioprio_set,
+            IOPRIO_WHO_PROCESS,
+            0,
+            IOPRIO_CLASS_IDLE << IOPRIO_CLASS_SHIFT,
+        )
+    };
+    match ret {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+#[cfg(windows)]
+pub fn enter_background_mode() -> io::Result<()> {
+    use std::os::windows::raw::HANDLE;
+
+    const PROCESS_MODE_BACKGROUND_BEGIN: u32 = 0x0010_0000;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetCurrentProcess() -> HANDLE;
+        fn SetPriorityClass(process: HANDLE, priority_class: u32) -> i32;
+    }
+
+    match unsafe { SetPriorityClass(GetCurrentProcess(), PROCESS_MODE_BACKGROUND_BEGIN) } {
+        0 => Err(io::Error::last_os_error()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+pub fn enter_background_mode() -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "background IO priority is not supported on this platform",
+    ))
+}
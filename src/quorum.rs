@@ -0,0 +1,113 @@
+use crate::chunk::{self, ChunkSizer};
+use crate::throttle::Throttled;
+use crate::{diff_at_lengths, long_path, read_full, CompareOptions, FileDiff};
+use std::cmp::Reverse;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+/// Result of comparing replicas of a file with each other.
+#[derive(Debug)]
+pub struct Replicas {
+    /// Indices of the replicas with the same contents, the largest group
+    /// first and groups of the same size in the order of their first replica
+    pub groups: Vec<Vec<usize>>,
+    lens: Vec<u64>,
+    /// Offset of the first difference between each pair of replicas
+    firsts: Vec<Vec<Option<u64>>>,
+}
+
+impl Replicas {
+    /// Returns the group of replicas that more than half of them are in.
+    pub fn majority(&self) -> Option<&[usize]> {
+        self.groups
+            .first()
+            .filter(|group| group.len() * 2 > self.lens.len())
+            .map(Vec::as_slice)
+    }
+
+    /// Returns the result of comparing replica `i` with replica `j`.
+    pub fn diff(&self, i: usize, j: usize) -> FileDiff {
+        diff_at_lengths(self.firsts[i][j], self.lens[i], self.lens[j], false)
+    }
+}
+
+/// Returns the offset of the first byte where two chunks differ, or the
+/// length of the shorter one.
+fn first_mismatch(chunk1: &[u8], chunk2: &[u8]) -> usize {
+    chunk1
+        .iter()
+        .zip(chunk2)
+        .position(|(a, b)| a != b)
+        .unwrap_or(chunk1.len().min(chunk2.len()))
+}
+
+/// Compares replicas of a file by reading them all side by side, once.
+/// Replicas start in one group, which splits by the contents of each chunk
+/// read, so a replica is only read until the others it agrees with are done
+/// or it disagrees with all of them.
+pub fn compare_replicas<P: AsRef<Path>>(
+    paths: &[P],
+    options: &CompareOptions,
+) -> io::Result<Replicas> {
+    let throttle = options.throttle.as_deref();
+    let mut readers = vec![];
+    let mut lens = vec![];
+    for path in paths {
+        let file = File::open(long_path::extended(path.as_ref()))?;
+        lens.push(file.metadata()?.len());
+        readers.push(Throttled::new(file, throttle));
+    }
+    let n = paths.len();
+    let max_len = lens.iter().copied().max().unwrap_or_default();
+    let mut chunks = ChunkSizer::new(options.chunk_size, max_len, chunk::MIN_CHUNK_SIZE);
+    let mut firsts = vec![vec![None; n]; n];
+    let mut buffers = vec![vec![]; n];
+    let (mut active, mut groups) = (vec![(0..n).collect::<Vec<_>>()], vec![]);
+    let mut pos = 0;
+    while !active.is_empty() {
+        let size = chunks.size();
+        for &i in active.iter().flatten() {
+            buffers[i].resize(size, 0);
+            let len = read_full(&mut readers[i], &mut buffers[i])?;
+            buffers[i].truncate(len);
+        }
+        let mut next = vec![];
+        for group in active {
+            let mut split: Vec<Vec<usize>> = vec![];
+            for i in group {
+                match split.iter_mut().find(|g| buffers[g[0]] == buffers[i]) {
+                    Some(same) => same.push(i),
+                    None => split.push(vec![i]),
+                }
+            }
+            for (k, group1) in split.iter().enumerate() {
+                for group2 in &split[k + 1..] {
+                    let offset =
+                        pos + first_mismatch(&buffers[group1[0]], &buffers[group2[0]]) as u64;
+                    for &i in group1 {
+                        for &j in group2 {
+                            firsts[i][j] = Some(offset);
+                            firsts[j][i] = Some(offset);
+                        }
+                    }
+                }
+            }
+            for group in split {
+                match buffers[group[0]].len() < size {
+                    true => groups.push(group),
+                    false => next.push(group),
+                }
+            }
+        }
+        active = next;
+        pos += size as u64;
+        chunks.grow();
+    }
+    groups.sort_by_key(|group| (Reverse(group.len()), group[0]));
+    Ok(Replicas {
+        groups,
+        lens,
+        firsts,
+    })
+}
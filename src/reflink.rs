@@ -0,0 +1,159 @@
+use std::fs::File;
+
+/// Returns true if both files are backed by exactly the same physical
+/// extents on the same filesystem, e.g. because one is a `cp --reflink` copy
+/// of the other, which means their contents are equal without reading them.
+///
+/// Uses the `FIEMAP` ioctl on Linux (btrfs, XFS). Any error or unsupported
+/// filesystem simply returns false so the caller falls back to reading.
+#[cfg(target_os = "linux")]
+pub fn shares_extents(file1: &File, file2: &File) -> bool {
+    if !same_filesystem(file1, file2) {
+        return false;
+    }
+    match (fiemap::extents(file1), fiemap::extents(file2)) {
+        (Some(extents1), Some(extents2)) => {
+            !extents1.is_empty()
+                && extents1 == extents2
+                && extents1.iter().all(|e| {
+                    e.flags & fiemap::EXTENT_SHARED != 0 && e.flags & fiemap::EXTENT_UNSAFE == 0
+                })
+        }
+        _ => false,
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn shares_extents(_file1: &File, _file2: &File) -> bool {
+    false
+}
+
+/// Physical extent addresses are only comparable within one filesystem. btrfs
+/// reports a different device per subvolume, so snapshots of the same
+/// filesystem are matched by the filesystem UUID instead.
+#[cfg(target_os = "linux")]
+fn same_filesystem(file1: &File, file2: &File) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    use std::os::unix::io::AsRawFd;
+
+    const BTRFS_SUPER_MAGIC: i64 = 0x9123683e;
+    const BTRFS_IOC_FS_INFO: u64 = 0x8400941f;
+
+    fn fs_type(file: &File) -> Option<i64> {
+        let mut stat: libc::statfs = unsafe { std::mem::zeroed() };
+        match unsafe { libc::fstatfs(file.as_raw_fd(), &mut stat) } {
+            0 => Some(stat.f_type as i64),
+            _ => None,
+        }
+    }
+
+    fn btrfs_fsid(file: &File) -> Option<[u8; 16]> {
+        let mut args = [0u8; 1024];
+        match unsafe { libc::ioctl(file.as_raw_fd(), BTRFS_IOC_FS_INFO as _, args.as_mut_ptr()) } {
+            0 => args[16..32].try_into().ok(),
+            _ => None,
+        }
+    }
+
+    match (file1.metadata(), file2.metadata()) {
+        (Ok(meta1), Ok(meta2)) if meta1.dev() == meta2.dev() => return true,
+        (Ok(_), Ok(_)) => {}
+        _ => return false,
+    }
+    fs_type(file1) == Some(BTRFS_SUPER_MAGIC)
+        && fs_type(file2) == Some(BTRFS_SUPER_MAGIC)
+        && btrfs_fsid(file1).is_some()
+        && btrfs_fsid(file1) == btrfs_fsid(file2)
+}
+
+#[cfg(target_os = "linux")]
+mod fiemap {
+    use std::fs::File;
+    use std::os::unix::io::AsRawFd;
+
+    const FS_IOC_FIEMAP: u64 = 0xc020660b;
+    const FLAG_SYNC: u32 = 0x1;
+    const EXTENT_LAST: u32 = 0x1;
+    pub const EXTENT_SHARED: u32 = 0x2000;
+    /// Unknown, delalloc, encoded, encrypted, not aligned, inline and tail
+    /// extents don't have a physical address that identifies their data.
+    pub const EXTENT_UNSAFE: u32 = 0x2 | 0x4 | 0x8 | 0x80 | 0x100 | 0x200 | 0x400;
+    const BATCH: usize = 64;
+
+    #[repr(C)]
+    #[derive(Clone, Copy, Default)]
+    struct RawExtent {
+        logical: u64,
+        physical: u64,
+        length: u64,
+        reserved64: [u64; 2],
+        flags: u32,
+        reserved: [u32; 3],
+    }
+
+    #[repr(C)]
+    struct Request {
+        start: u64,
+        length: u64,
+        flags: u32,
+        mapped_extents: u32,
+        extent_count: u32,
+        reserved: u32,
+        extents: [RawExtent; BATCH],
+    }
+
+    #[derive(Debug, Eq, PartialEq)]
+    pub struct Extent {
+        pub logical: u64,
+        pub physical: u64,
+        pub length: u64,
+        pub flags: u32,
+    }
+
+    pub fn extents(file: &File) -> Option<Vec<Extent>> {
+        let mut result = vec![];
+        let mut start = 0;
+
+        loop {
+            let mut request = Request {
+                start,
+                length: u64::MAX - start,
+                flags: FLAG_SYNC,
+                mapped_extents: 0,
+                extent_count: BATCH as u32,
+                reserved: 0,
+                extents: [RawExtent::default(); BATCH],
+            };
+            let ret = unsafe {
+                libc::ioctl(
+                    file.as_raw_fd(),
+                    FS_IOC_FIEMAP as _,
+                    &mut request as *mut Request,
+                )
+            };
+            if ret != 0 {
+                return None;
+            }
+            if request.mapped_extents == 0 {
+                return Some(result);
+            }
+
+            let mapped = &request.extents[..request.mapped_extents as usize];
+            for e in mapped {
+                result.push(Extent {
+                    logical: e.logical,
+                    physical: e.physical,
+                    length: e.length,
+                    // Only the flags that describe the data, not its position in the list
+                    flags: e.flags & !EXTENT_LAST,
+                });
+            }
+
+            let last = mapped[mapped.len() - 1];
+            if last.flags & EXTENT_LAST != 0 {
+                return Some(result);
+            }
+            start = last.logical + last.length;
+        }
+    }
+}
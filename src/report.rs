@@ -0,0 +1,91 @@
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Added to the name of a report while it is written, so one that is cut
+/// short can't be taken for complete.
+pub const PARTIAL_SUFFIX: &str = ".partial";
+
+/// How often the results written to a report so far are flushed to disk.
+pub const FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Returns the path a report is written to until it is complete.
+pub fn partial_path(path: &Path) -> PathBuf {
+    let mut partial = OsString::from(path.as_os_str());
+    partial.push(PARTIAL_SUFFIX);
+    PathBuf::from(partial)
+}
+
+/// A report written as results come in, such as a list of files. It goes
+/// to `<path>.partial`, flushed every `FLUSH_INTERVAL` so a crashed or
+/// interrupted run leaves what it found there, and only `finish` renames it
+/// to `path`, so a file there is always complete.
+pub struct ReportFile {
+    path: PathBuf,
+    partial: PathBuf,
+    writer: BufWriter<File>,
+    flushed: Instant,
+}
+
+impl ReportFile {
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let partial = partial_path(&path);
+        Ok(Self {
+            writer: BufWriter::new(File::create(&partial)?),
+            path,
+            partial,
+            flushed: Instant::now(),
+        })
+    }
+
+    /// The path of the report until it is finished.
+    pub fn partial(&self) -> &Path {
+        &self.partial
+    }
+
+    /// Writes the rest of the report to disk and moves it into place.
+    pub fn finish(mut self) -> io::Result<()> {
+        self.writer.flush()?;
+        self.writer.get_ref().sync_all()?;
+        fs::rename(&self.partial, &self.path)?;
+        sync_parent(&self.path);
+        Ok(())
+    }
+}
+
+impl Write for ReportFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.writer.write(buf)?;
+        if self.flushed.elapsed() >= FLUSH_INTERVAL {
+            self.flush()?;
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.flushed = Instant::now();
+        self.writer.flush()
+    }
+}
+
+/// Writes a whole file so that it has either its old or its new contents
+/// if the run crashes, through a partial file that is renamed over it.
+pub fn write_atomically<P: AsRef<Path>, C: AsRef<[u8]>>(path: P, contents: C) -> io::Result<()> {
+    let mut report = ReportFile::create(path)?;
+    report.write_all(contents.as_ref())?;
+    report.finish()
+}
+
+/// Makes a rename in a directory last through a crash where the platform
+/// can, which is best effort.
+fn sync_parent(path: &Path) {
+    #[cfg(unix)]
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        let _ = File::open(parent).and_then(|dir| dir.sync_all());
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+}
@@ -0,0 +1,42 @@
+use std::io;
+use std::thread;
+use std::time::Duration;
+
+/// Wait before the first retry, doubled for each further one.
+pub const FIRST_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Longest wait between retries.
+pub const MAX_BACKOFF: Duration = Duration::from_secs(10);
+
+/// Returns whether an error may go away when the read is tried again, like
+/// the intermittent EIO and ETIMEDOUT of network filesystems. Errors that
+/// don't come from the OS, such as the timeout of a pipe, aren't transient:
+/// a pipe can't be read again from the start.
+pub fn is_transient(e: &io::Error) -> bool {
+    #[cfg(unix)]
+    const TRANSIENT: &[i32] = &[libc::EIO, libc::ETIMEDOUT];
+    // ERROR_UNEXP_NET_ERR, ERROR_NETNAME_DELETED and ERROR_SEM_TIMEOUT
+    #[cfg(windows)]
+    const TRANSIENT: &[i32] = &[59, 64, 121];
+    #[cfg(not(any(unix, windows)))]
+    const TRANSIENT: &[i32] = &[];
+    e.raw_os_error()
+        .is_some_and(|code| TRANSIENT.contains(&code))
+}
+
+/// Runs `f` until it succeeds, fails with an error that isn't transient, or
+/// has been retried `retries` times, waiting longer before each retry.
+pub fn with_retries<T, F: FnMut() -> io::Result<T>>(retries: u32, mut f: F) -> io::Result<T> {
+    let mut backoff = FIRST_BACKOFF;
+    let mut attempt = 0;
+    loop {
+        match f() {
+            Err(e) if attempt < retries && is_transient(&e) => {
+                thread::sleep(backoff);
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
@@ -0,0 +1,196 @@
+use crate::hooks::Summary;
+use crate::walk::DirWalk;
+use crate::{compare_files_with, CompareOptions};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+/// Error codes of JSON-RPC 2.0, and of LSP for a cancelled request.
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const SERVER_ERROR: i64 = -32000;
+const REQUEST_CANCELLED: i64 = -32800;
+
+/// Writes whole messages, one per line, from any request's thread.
+#[derive(Clone)]
+struct Output(Arc<Mutex<Box<dyn Write + Send>>>);
+
+impl Output {
+    fn send(&self, message: Value) {
+        let line = format!("{}\n", message);
+        let mut out = self.0.lock().unwrap();
+        let _ = out.write_all(line.as_bytes()).and_then(|_| out.flush());
+    }
+
+    fn reply(&self, id: &Value, result: Result<Value, (i64, String)>) {
+        self.send(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": code, "message": message },
+            }),
+        });
+    }
+}
+
+/// The requests still running, by id, with the flag that cancels them.
+type Running = Arc<Mutex<HashMap<String, Arc<AtomicBool>>>>;
+
+/// Serves JSON-RPC 2.0 over a pair of streams, one message per line, until
+/// the input ends. Each request runs on its own thread, so requests can be
+/// cancelled while others are running:
+///
+/// - `compareFiles` with `path1` and `path2` returns the result of
+///   comparing two files, e.g. `{"status": "diff", "offset": 4096}`
+/// - `compareDirs` with `path1` and `path2` sends a `dirEntry` notification
+///   with the request's `id` for each file as it is compared, and returns
+///   the counts of results when done
+/// - `cancel` with the `id` of a running request stops it, which then
+///   returns error -32800. Directories stop before the next file, while a
+///   file being compared is compared to the end first.
+pub fn serve_rpc<R: BufRead, W: Write + Send + 'static>(
+    input: R,
+    output: W,
+    options: CompareOptions,
+) -> io::Result<()> {
+    let output = Output(Arc::new(Mutex::new(Box::new(output))));
+    let options = Arc::new(options);
+    let running: Running = Arc::default();
+    let mut threads: Vec<JoinHandle<()>> = vec![];
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let message: Value = match serde_json::from_str(&line) {
+            Ok(message) => message,
+            Err(e) => {
+                output.reply(&Value::Null, Err((PARSE_ERROR, e.to_string())));
+                continue;
+            }
+        };
+        // Requests without an id are notifications, which get no reply
+        let id = message.get("id").cloned();
+        let Some(method) = message["method"].as_str() else {
+            output.reply(
+                &id.unwrap_or_default(),
+                Err((INVALID_REQUEST, "missing method".to_string())),
+            );
+            continue;
+        };
+        let params = message.get("params").cloned().unwrap_or_default();
+        match method {
+            "cancel" => {
+                let result = match running.lock().unwrap().get(&params["id"].to_string()) {
+                    Some(cancelled) => {
+                        cancelled.store(true, Ordering::Relaxed);
+                        Ok(json!(true))
+                    }
+                    None => Ok(json!(false)),
+                };
+                if let Some(id) = id {
+                    output.reply(&id, result);
+                }
+            }
+            "compareFiles" | "compareDirs" => {
+                let paths = (params["path1"].as_str(), params["path2"].as_str());
+                let (Some(path1), Some(path2)) = paths else {
+                    if let Some(id) = id {
+                        let message = "expected path1 and path2".to_string();
+                        output.reply(&id, Err((INVALID_PARAMS, message)));
+                    }
+                    continue;
+                };
+                let id = id.unwrap_or_default();
+                let cancelled = Arc::new(AtomicBool::new(false));
+                running
+                    .lock()
+                    .unwrap()
+                    .insert(id.to_string(), cancelled.clone());
+                let request = Request {
+                    id,
+                    method: method.to_string(),
+                    path1: PathBuf::from(path1),
+                    path2: PathBuf::from(path2),
+                    cancelled,
+                };
+                let (output, options, running) = (output.clone(), options.clone(), running.clone());
+                threads.push(thread::spawn(move || {
+                    let result = request.run(&output, &options);
+                    running.lock().unwrap().remove(&request.id.to_string());
+                    if !request.id.is_null() {
+                        output.reply(&request.id, result);
+                    }
+                }));
+            }
+            _ => {
+                if let Some(id) = id {
+                    let message = format!("unknown method '{}'", method);
+                    output.reply(&id, Err((METHOD_NOT_FOUND, message)));
+                }
+            }
+        }
+        threads.retain(|thread| !thread.is_finished());
+    }
+    for thread in threads {
+        let _ = thread.join();
+    }
+    Ok(())
+}
+
+struct Request {
+    id: Value,
+    method: String,
+    path1: PathBuf,
+    path2: PathBuf,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl Request {
+    fn run(&self, output: &Output, options: &CompareOptions) -> Result<Value, (i64, String)> {
+        let cancelled = || (REQUEST_CANCELLED, "request cancelled".to_string());
+        let failed = |e: io::Error| (SERVER_ERROR, e.to_string());
+        if self.method == "compareFiles" {
+            let diff = compare_files_with(&self.path1, &self.path2, options).map_err(failed)?;
+            return match self.cancelled.load(Ordering::Relaxed) {
+                true => Err(cancelled()),
+                false => Ok(json!(diff)),
+            };
+        }
+
+        if !self.path1.is_dir() || !self.path2.is_dir() {
+            let message = "path1 and path2 must be directories".to_string();
+            return Err((INVALID_PARAMS, message));
+        }
+        let mut walk = DirWalk::new(&self.path1, &self.path2, options);
+        let mut summary = Summary::new();
+        // The walk compares each file as it gets to it
+        while !self.cancelled.load(Ordering::Relaxed) {
+            let entry = walk.next();
+            for (_, _, e) in walk.take_failed() {
+                eprintln!("Error: {}", e);
+            }
+            let Some((path, diff)) = entry else {
+                return Ok(json!({
+                    "equal": summary.equal,
+                    "different": summary.different,
+                    "left_only": summary.left_only,
+                    "right_only": summary.right_only,
+                }));
+            };
+            summary.add(&diff);
+            let mut entry = json!(diff);
+            entry["id"] = self.id.clone();
+            entry["path"] = json!(path.display().to_string());
+            output.send(json!({ "jsonrpc": "2.0", "method": "dirEntry", "params": entry }));
+        }
+        Err(cancelled())
+    }
+}
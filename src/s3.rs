@@ -0,0 +1,406 @@
+use crate::cloud::{self, element, elements, md5_reader, unescape, uri_encode};
+use crate::http::{agent, call_error};
+use crate::units::civil_date;
+use crate::{hash, CompareOptions, FileDiff};
+use base64::Engine;
+use hmac::{Hmac, KeyInit, Mac};
+use md5::{Digest, Md5};
+use sha2::Sha256;
+use std::env;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Part sizes commonly used by S3 clients for multipart uploads, tried when
+/// checking a multipart ETag against a local file.
+const PART_SIZES: [u64; 6] = [5 << 20, 8 << 20, 16 << 20, 32 << 20, 64 << 20, 100 << 20];
+
+/// SHA-256 of an empty payload, sent with every request.
+const EMPTY_SHA256: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+/// A bucket and key (or key prefix) parsed from `s3://bucket/key`.
+#[derive(Debug, Eq, PartialEq)]
+pub struct S3Url {
+    pub bucket: String,
+    pub key: String,
+}
+
+impl S3Url {
+    pub fn parse(url: &str) -> Option<S3Url> {
+        let rest = url.strip_prefix("s3://")?;
+        let (bucket, key) = rest.split_once('/').unwrap_or((rest, ""));
+        match bucket.is_empty() {
+            true => None,
+            false => Some(S3Url {
+                bucket: bucket.to_string(),
+                key: key.to_string(),
+            }),
+        }
+    }
+}
+
+pub fn is_s3_url(path: &str) -> bool {
+    path.starts_with("s3://")
+}
+
+/// Size and checksums of an object, from a HEAD request or a listing.
+#[derive(Debug)]
+pub struct ObjectInfo {
+    pub key: String,
+    pub len: u64,
+    pub etag: Option<String>,
+    /// Base64 SHA-256 of the whole object, if it was uploaded with one
+    pub checksum_sha256: Option<String>,
+}
+
+struct Credentials {
+    access_key: String,
+    secret_key: String,
+    session_token: Option<String>,
+}
+
+/// Minimal S3 client signing requests with AWS Signature Version 4.
+pub struct Client {
+    agent: ureq::Agent,
+    region: String,
+    /// Custom endpoint such as MinIO's, addressed with path-style URLs
+    endpoint: Option<String>,
+    credentials: Option<Credentials>,
+}
+
+impl Client {
+    /// Configures the client from the standard AWS environment variables:
+    /// `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY`, `AWS_SESSION_TOKEN`,
+    /// `AWS_REGION` (or `AWS_DEFAULT_REGION`) and `AWS_ENDPOINT_URL`.
+    /// Requests are unsigned when no keys are set, for public buckets.
+    pub fn from_env(timeout: Option<Duration>) -> Client {
+        let var = |name| env::var(name).ok().filter(|v: &String| !v.is_empty());
+        let credentials = match (var("AWS_ACCESS_KEY_ID"), var("AWS_SECRET_ACCESS_KEY")) {
+            (Some(access_key), Some(secret_key)) => Some(Credentials {
+                access_key,
+                secret_key,
+                session_token: var("AWS_SESSION_TOKEN"),
+            }),
+            _ => None,
+        };
+        Client {
+            agent: agent(timeout),
+            region: var("AWS_REGION")
+                .or_else(|| var("AWS_DEFAULT_REGION"))
+                .unwrap_or_else(|| "us-east-1".to_string()),
+            endpoint: var("AWS_ENDPOINT_URL").map(|e| e.trim_end_matches('/').to_string()),
+            credentials,
+        }
+    }
+
+    /// Sends requests to a custom endpoint such as `http://localhost:9000`
+    /// instead of AWS.
+    pub fn with_endpoint(mut self, endpoint: &str) -> Client {
+        self.endpoint = Some(endpoint.trim_end_matches('/').to_string());
+        self
+    }
+
+    pub fn head(&self, bucket: &str, key: &str) -> io::Result<ObjectInfo> {
+        let response = self.request(
+            "HEAD",
+            bucket,
+            key,
+            &[],
+            &[("x-amz-checksum-mode", "ENABLED")],
+        )?;
+        let len = response
+            .header("Content-Length")
+            .and_then(|len| len.parse().ok())
+            .ok_or_else(|| io::Error::other("response has no Content-Length"))?;
+        Ok(ObjectInfo {
+            key: key.to_string(),
+            len,
+            etag: response.header("ETag").map(str::to_string),
+            checksum_sha256: response.header("x-amz-checksum-sha256").map(str::to_string),
+        })
+    }
+
+    pub fn get(&self, bucket: &str, key: &str) -> io::Result<Box<dyn Read + Send>> {
+        let response = self.request("GET", bucket, key, &[], &[])?;
+        Ok(Box::new(response.into_reader()))
+    }
+
+    /// Lists every object under the prefix, following continuation tokens.
+    pub fn list(&self, bucket: &str, prefix: &str) -> io::Result<Vec<ObjectInfo>> {
+        let mut objects = vec![];
+        let mut token = None;
+        loop {
+            let mut query = vec![
+                ("list-type", "2".to_string()),
+                ("prefix", prefix.to_string()),
+            ];
+            if let Some(token) = token.take() {
+                query.push(("continuation-token", token));
+            }
+            let body = self
+                .request("GET", bucket, "", &query, &[])?
+                .into_string()?;
+            for contents in elements(&body, "Contents") {
+                let key = element(contents, "Key").map(unescape).unwrap_or_default();
+                let len = element(contents, "Size")
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(0);
+                objects.push(ObjectInfo {
+                    key,
+                    len,
+                    etag: element(contents, "ETag").map(unescape),
+                    checksum_sha256: None,
+                });
+            }
+            match element(&body, "NextContinuationToken") {
+                Some(next) if element(&body, "IsTruncated") == Some("true") => {
+                    token = Some(unescape(next))
+                }
+                _ => return Ok(objects),
+            }
+        }
+    }
+
+    fn request(
+        &self,
+        method: &str,
+        bucket: &str,
+        key: &str,
+        query: &[(&str, String)],
+        headers: &[(&str, &str)],
+    ) -> io::Result<ureq::Response> {
+        let (host, path) = match &self.endpoint {
+            Some(endpoint) => (
+                endpoint
+                    .split_once("://")
+                    .map_or(endpoint.as_str(), |(_, host)| host)
+                    .to_string(),
+                format!("/{}/{}", bucket, key),
+            ),
+            None => (
+                format!("{}.s3.{}.amazonaws.com", bucket, self.region),
+                format!("/{}", key),
+            ),
+        };
+        let path = uri_encode(&path, false);
+        let mut query: Vec<_> = query
+            .iter()
+            .map(|(name, value)| format!("{}={}", uri_encode(name, true), uri_encode(value, true)))
+            .collect();
+        query.sort();
+        let query = query.join("&");
+        let base = match &self.endpoint {
+            Some(endpoint) => endpoint
+                .split_once("://")
+                .map_or("https", |(scheme, _)| scheme),
+            None => "https",
+        };
+        let url = match query.is_empty() {
+            true => format!("{}://{}{}", base, host, path),
+            false => format!("{}://{}{}?{}", base, host, path, query),
+        };
+
+        let mut request = self.agent.request(method, &url);
+        for (name, value) in headers {
+            request = request.set(name, value);
+        }
+        if let Some(credentials) = &self.credentials {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default();
+            let amz_date = amz_date(now.as_secs());
+            let mut signed: Vec<(String, String)> = headers
+                .iter()
+                .map(|(name, value)| (name.to_ascii_lowercase(), value.to_string()))
+                .collect();
+            signed.push(("host".to_string(), host.clone()));
+            signed.push(("x-amz-content-sha256".to_string(), EMPTY_SHA256.to_string()));
+            signed.push(("x-amz-date".to_string(), amz_date.clone()));
+            if let Some(token) = &credentials.session_token {
+                signed.push(("x-amz-security-token".to_string(), token.clone()));
+            }
+            signed.sort();
+            let authorization =
+                self.authorization(credentials, method, &path, &query, &signed, &amz_date);
+            for (name, value) in &signed {
+                if name != "host" {
+                    request = request.set(name, value);
+                }
+            }
+            request = request.set("Authorization", &authorization);
+        }
+        request.call().map_err(|e| call_error(&url, e))
+    }
+
+    fn authorization(
+        &self,
+        credentials: &Credentials,
+        method: &str,
+        path: &str,
+        query: &str,
+        headers: &[(String, String)],
+        amz_date: &str,
+    ) -> String {
+        let canonical_headers: String = headers
+            .iter()
+            .map(|(name, value)| format!("{}:{}\n", name, value.trim()))
+            .collect();
+        let signed_headers = headers
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>()
+            .join(";");
+        let canonical_request = format!(
+            "{}\n{}\n{}\n{}\n{}\n{}",
+            method, path, query, canonical_headers, signed_headers, EMPTY_SHA256
+        );
+        let date = &amz_date[..8];
+        let scope = format!("{}/{}/s3/aws4_request", date, self.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            hash::to_hex(&Sha256::digest(canonical_request.as_bytes()))
+        );
+
+        let mut key = hmac(format!("AWS4{}", credentials.secret_key).as_bytes(), date);
+        for part in [self.region.as_str(), "s3", "aws4_request"] {
+            key = hmac(&key, part);
+        }
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            credentials.access_key,
+            scope,
+            signed_headers,
+            hash::to_hex(&hmac(&key, &string_to_sign))
+        )
+    }
+}
+
+fn hmac(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Formats seconds since the epoch as `YYYYMMDDTHHMMSSZ`.
+fn amz_date(secs: u64) -> String {
+    let (year, month, day) = civil_date((secs / 86400) as i64);
+    let secs = secs % 86400;
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+/// Checks the object's SHA-256 checksum or ETag against the local file,
+/// returning `None` when neither can tell, e.g. for ETags of encrypted
+/// objects that aren't an MD5 of the content.
+pub fn checksum_matches<P: AsRef<Path>>(path: P, object: &ObjectInfo) -> io::Result<Option<bool>> {
+    if let Some(checksum) = object
+        .checksum_sha256
+        .as_deref()
+        .filter(|c| !c.contains('-'))
+    {
+        let digest = hash::hash_file(&path, hash::HashAlgorithm::Sha256, None)?;
+        return Ok(Some(
+            base64::engine::general_purpose::STANDARD.encode(digest) == checksum,
+        ));
+    }
+    let Some(etag) = object.etag.as_deref() else {
+        return Ok(None);
+    };
+    let etag = etag.trim_matches('"');
+    let (md5, parts) = match etag.split_once('-') {
+        Some((md5, parts)) => (md5, parts.parse::<u64>().ok()),
+        None => (etag, None),
+    };
+    if md5.len() != 32 || !md5.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Ok(None);
+    }
+    let md5 = md5.to_ascii_lowercase();
+    match parts {
+        None => Ok(Some(hash::to_hex(&md5_parts(&path, None)?) == md5)),
+        Some(0) => Ok(None),
+        Some(parts) => {
+            // The part size isn't recorded, so try the usual ones and the
+            // smallest whole MiB that splits the object into that many parts
+            let exact = object.len.div_ceil(parts).div_ceil(1 << 20) << 20;
+            let mut sizes: Vec<u64> = PART_SIZES.into_iter().chain([exact]).collect();
+            sizes.sort();
+            sizes.dedup();
+            for size in sizes {
+                if size > 0
+                    && object.len.div_ceil(size) == parts
+                    && hash::to_hex(&md5_parts(&path, Some(size))?) == md5
+                {
+                    return Ok(Some(true));
+                }
+            }
+            Ok(None)
+        }
+    }
+}
+
+/// MD5 of the file, or for multipart ETags the MD5 of the concatenated MD5s
+/// of each part.
+fn md5_parts<P: AsRef<Path>>(path: P, part_size: Option<u64>) -> io::Result<Vec<u8>> {
+    let mut file = File::open(path)?;
+    let Some(part_size) = part_size else {
+        return md5_reader(&mut file).map(|(digest, _)| digest);
+    };
+    let mut digests = Md5::new();
+    loop {
+        let (digest, len) = md5_reader(&mut (&mut file).take(part_size))?;
+        if len == 0 {
+            return Ok(digests.finalize().to_vec());
+        }
+        digests.update(digest);
+    }
+}
+
+/// Compares an object with a local file. The sizes and checksums are
+/// checked first, and the object is only downloaded when they can't decide
+/// or the offset of the first difference is wanted.
+pub fn compare_object<P: AsRef<Path>>(
+    client: &Client,
+    url: &S3Url,
+    object: &ObjectInfo,
+    path: P,
+    options: &CompareOptions,
+) -> io::Result<FileDiff> {
+    cloud::compare_object(
+        object.len,
+        path,
+        options,
+        |path| checksum_matches(path, object),
+        || client.get(&url.bucket, &object.key),
+    )
+}
+
+/// Compares a local directory tree with the objects under a prefix. Local
+/// files are reported by path and objects missing locally by URL.
+pub fn compare_tree<P: AsRef<Path>>(
+    client: &Client,
+    url: &S3Url,
+    dir: P,
+    options: &CompareOptions,
+) -> io::Result<Vec<(PathBuf, FileDiff)>> {
+    let prefix = cloud::dir_prefix(&url.key);
+    let objects = client
+        .list(&url.bucket, &prefix)?
+        .into_iter()
+        .filter(|object| !object.key.ends_with('/'))
+        .map(|object| (object.key[prefix.len()..].to_string(), object))
+        .collect();
+    let prefix_url = format!("s3://{}/{}", url.bucket, prefix);
+    cloud::compare_tree(objects, &prefix_url, dir.as_ref(), |object, path| {
+        compare_object(client, url, object, path, options)
+    })
+}
@@ -0,0 +1,105 @@
+use crate::{long_path, reflink};
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+
+/// How two files with the same contents are stored.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Storage {
+    /// Hardlinks of one file, already deduplicated
+    Hardlink,
+    /// Separate files sharing their extents, e.g. reflinked copies
+    Reflink,
+    /// Copies taking space of their own
+    Copy,
+}
+
+impl Storage {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Hardlink => "hardlink",
+            Self::Reflink => "reflink",
+            Self::Copy => "copy",
+        }
+    }
+}
+
+/// Tells how two files with the same contents are stored: as hardlinks of
+/// one file, sharing their extents or as independent copies. Files whose
+/// identity or extents can't be determined on this platform are copies.
+pub fn storage<P: AsRef<Path>>(path1: P, path2: P) -> io::Result<Storage> {
+    let file1 = File::open(long_path::extended(path1.as_ref()))?;
+    let file2 = File::open(long_path::extended(path2.as_ref()))?;
+    Ok(match same_open_file(&file1, &file2) {
+        Some(true) => Storage::Hardlink,
+        _ if reflink::shares_extents(&file1, &file2) => Storage::Reflink,
+        _ => Storage::Copy,
+    })
+}
+
+/// Returns true if both paths refer to the same file, even when spelled
+/// differently (relative vs absolute, `..` components, links, hardlinks).
+pub fn is_same_file<P: AsRef<Path>>(path1: P, path2: P) -> io::Result<bool> {
+    let file1 = File::open(&path1)?;
+    let file2 = File::open(&path2)?;
+    if let Some(same) = same_open_file(&file1, &file2) {
+        return Ok(same);
+    }
+    Ok(fs::canonicalize(path1)? == fs::canonicalize(path2)?)
+}
+
+/// Returns whether two open files are the same file, by device and inode
+/// (Unix) or volume serial number and file index (Windows), or `None` if the
+/// file identity can't be determined on this platform.
+pub(crate) fn same_open_file(file1: &File, file2: &File) -> Option<bool> {
+    match (file_id(file1), file_id(file2)) {
+        (Some(id1), Some(id2)) => Some(id1 == id2),
+        _ => None,
+    }
+}
+
+#[cfg(unix)]
+fn file_id(file: &File) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    let meta = file.metadata().ok()?;
+    Some((meta.dev(), meta.ino()))
+}
+
+#[cfg(windows)]
+fn file_id(file: &File) -> Option<(u64, u64)> {
+    use std::os::windows::io::{AsRawHandle, RawHandle};
+
+    #[repr(C)]
+    #[derive(Default)]
+    struct ByHandleFileInformation {
+        file_attributes: u32,
+        creation_time: [u32; 2],
+        last_access_time: [u32; 2],
+        last_write_time: [u32; 2],
+        volume_serial_number: u32,
+        file_size_high: u32,
+        file_size_low: u32,
+        number_of_links: u32,
+        file_index_high: u32,
+        file_index_low: u32,
+    }
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetFileInformationByHandle(file: RawHandle, info: *mut ByHandleFileInformation) -> i32;
+    }
+
+    let mut info = ByHandleFileInformation::default();
+    match unsafe { GetFileInformationByHandle(file.as_raw_handle(), &mut info) } {
+        0 => None,
+        _ => Some((
+            info.volume_serial_number as u64,
+            (info.file_index_high as u64) << 32 | info.file_index_low as u64,
+        )),
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn file_id(_file: &File) -> Option<(u64, u64)> {
+    None
+}
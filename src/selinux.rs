@@ -0,0 +1,22 @@
+use crate::{long_path, xattr};
+use std::io;
+use std::path::Path;
+
+/// The extended attribute holding the SELinux security context of a file.
+pub const XATTR: &str = "security.selinux";
+
+/// Reads the security context of a file, e.g.
+/// `system_u:object_r:httpd_exec_t:s0`, or `None` if it has no label.
+pub fn context(path: &Path) -> io::Result<Option<String>> {
+    Ok(xattr::get(&long_path::extended(path), XATTR)?.map(|value| {
+        // The kernel includes the terminating NUL
+        let value = value.strip_suffix(b"\0").unwrap_or(&value);
+        String::from_utf8_lossy(value).into_owned()
+    }))
+}
+
+/// Compares the security contexts of two files, e.g. of a restored system
+/// tree that lost its labels, returning whether they differ.
+pub fn contexts_differ<P: AsRef<Path>>(path1: P, path2: P) -> io::Result<bool> {
+    Ok(context(path1.as_ref())? != context(path2.as_ref())?)
+}
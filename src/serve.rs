@@ -0,0 +1,180 @@
+use crate::{compare_dirs_with, compare_files_with, is_dir, CompareOptions, PathDiff};
+use serde_json::{json, Value};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// State of one configured pair of paths.
+enum State {
+    Idle,
+    Running,
+    Done(Value),
+    Failed(String),
+}
+
+struct Pair {
+    path1: PathBuf,
+    path2: PathBuf,
+    state: State,
+}
+
+type Pairs = Arc<Mutex<Vec<Pair>>>;
+
+/// Serves a JSON API for comparing the configured pairs, handling each
+/// connection on its own thread:
+///
+/// - `GET /pairs` lists the pairs and their state
+/// - `GET /pairs/ID` returns one pair with its latest result
+/// - `POST /pairs/ID/compare` starts comparing the pair in the background
+///
+/// Pairs are numbered from 0 in the order given. Runs until accepting a
+/// connection fails.
+pub fn serve(
+    listener: TcpListener,
+    pairs: Vec<(PathBuf, PathBuf)>,
+    options: CompareOptions,
+) -> io::Result<()> {
+    let pairs: Pairs = Arc::new(Mutex::new(
+        pairs
+            .into_iter()
+            .map(|(path1, path2)| Pair {
+                path1,
+                path2,
+                state: State::Idle,
+            })
+            .collect(),
+    ));
+    let options = Arc::new(options);
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let pairs = pairs.clone();
+        let options = options.clone();
+        thread::spawn(move || {
+            if let Err(e) = handle(stream, &pairs, &options) {
+                eprintln!("Error: {}", e);
+            }
+        });
+    }
+    Ok(())
+}
+
+fn handle(mut stream: TcpStream, pairs: &Pairs, options: &Arc<CompareOptions>) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+    // Requests have no body worth reading, so skip the headers
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    let mut parts = request.split_whitespace();
+    let method = parts.next().unwrap_or_default();
+    let target = parts.next().unwrap_or_default();
+    let segments: Vec<&str> = target.trim_matches('/').split('/').collect();
+    let (status, body) = match (method, segments.as_slice()) {
+        ("GET", ["pairs"]) => {
+            let pairs = pairs.lock().unwrap();
+            let list: Vec<Value> = pairs
+                .iter()
+                .enumerate()
+                .map(|(id, p)| pair_json(id, p))
+                .collect();
+            ("200 OK", Value::Array(list))
+        }
+        ("GET", ["pairs", id]) => match find(pairs, id) {
+            Some(id) => ("200 OK", pair_json(id, &pairs.lock().unwrap()[id])),
+            None => not_found(),
+        },
+        ("POST", ["pairs", id, "compare"]) => match find(pairs, id) {
+            Some(id) => start(pairs, id, options),
+            None => not_found(),
+        },
+        _ => not_found(),
+    };
+
+    let body = body.to_string();
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )
+}
+
+fn find(pairs: &Pairs, id: &str) -> Option<usize> {
+    id.parse()
+        .ok()
+        .filter(|&id| id < pairs.lock().unwrap().len())
+}
+
+fn not_found() -> (&'static str, Value) {
+    ("404 Not Found", json!({ "error": "not found" }))
+}
+
+/// Starts comparing the pair on another thread unless it is already running.
+fn start(pairs: &Pairs, id: usize, options: &Arc<CompareOptions>) -> (&'static str, Value) {
+    let (path1, path2) = {
+        let mut list = pairs.lock().unwrap();
+        let pair = &mut list[id];
+        if matches!(pair.state, State::Running) {
+            return ("409 Conflict", pair_json(id, pair));
+        }
+        pair.state = State::Running;
+        (pair.path1.clone(), pair.path2.clone())
+    };
+
+    let shared = pairs.clone();
+    let options = options.clone();
+    thread::spawn(move || {
+        let state = match compare(&path1, &path2, &options) {
+            Ok(result) => State::Done(result),
+            Err(e) => State::Failed(e.to_string()),
+        };
+        shared.lock().unwrap()[id].state = state;
+    });
+    ("202 Accepted", pair_json(id, &pairs.lock().unwrap()[id]))
+}
+
+/// Compares two files, or two trees, into the JSON reported for the pair.
+fn compare(path1: &Path, path2: &Path, options: &CompareOptions) -> io::Result<Value> {
+    match is_dir(path1)? {
+        true => {
+            let entries: Vec<Value> = compare_dirs_with(path1, path2, options)?
+                .into_iter()
+                .map(|(path, diff)| {
+                    json!(PathDiff {
+                        path: path.display().to_string(),
+                        diff,
+                    })
+                })
+                .collect();
+            Ok(json!({ "entries": entries }))
+        }
+        false => Ok(json!(compare_files_with(path1, path2, options)?)),
+    }
+}
+
+fn pair_json(id: usize, pair: &Pair) -> Value {
+    let mut value = json!({
+        "id": id,
+        "path1": pair.path1.display().to_string(),
+        "path2": pair.path2.display().to_string(),
+    });
+    match &pair.state {
+        State::Idle => value["status"] = json!("idle"),
+        State::Running => value["status"] = json!("running"),
+        State::Done(result) => {
+            value["status"] = json!("done");
+            value["result"] = result.clone();
+        }
+        State::Failed(error) => {
+            value["status"] = json!("failed");
+            value["error"] = json!(error);
+        }
+    }
+    value
+}
@@ -0,0 +1,239 @@
+use crate::hash::to_hex;
+use crate::throttle::{Throttle, Throttled};
+use crate::{long_path, read_full, FileDiff};
+use md5::Md5;
+use sha2::digest::DynDigest;
+use sha2::Sha256;
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Bytes hashed per read.
+const CHUNK_SIZE: usize = 1024 * 1024;
+
+/// The kind of checksum a sidecar holds, by its extension.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Kind {
+    /// SHA-256, as written by sha256sum
+    Sha256,
+    /// MD5, as written by md5sum
+    Md5,
+    /// CRC-32 in a Simple File Verification file
+    Sfv,
+}
+
+impl Kind {
+    pub const ALL: [Kind; 3] = [Kind::Sha256, Kind::Md5, Kind::Sfv];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Sha256 => "sha256",
+            Self::Md5 => "md5",
+            Self::Sfv => "sfv",
+        }
+    }
+
+    /// Length of a checksum in bytes.
+    fn len(&self) -> usize {
+        match self {
+            Self::Sha256 => 32,
+            Self::Md5 => 16,
+            Self::Sfv => 4,
+        }
+    }
+}
+
+/// A checksum of a file, read from the sidecar file next to it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Sidecar {
+    pub path: PathBuf,
+    pub kind: Kind,
+    pub checksum: Vec<u8>,
+}
+
+/// Returns the checksum of a file from `<file>.sha256`, `<file>.md5` or
+/// `<file>.sfv`, whichever is found first, or `None` if it has no sidecar.
+/// Sidecars may list several files, of which the one with the file's name
+/// is taken, or the only one if there is one, so renamed files keep theirs.
+pub fn find<P: AsRef<Path>>(path: P) -> io::Result<Option<Sidecar>> {
+    let path = path.as_ref();
+    let Some(name) = path.file_name() else {
+        return Ok(None);
+    };
+    for kind in Kind::ALL {
+        let mut sidecar_name = OsString::from(name);
+        sidecar_name.push(".");
+        sidecar_name.push(kind.extension());
+        let sidecar = path.with_file_name(sidecar_name);
+        let text = match fs::read_to_string(long_path::extended(&sidecar)) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        };
+        let checksum = parse(&text, kind, &name.to_string_lossy()).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("no {} checksum for {}", kind.extension(), path.display()),
+            )
+        })?;
+        return Ok(Some(Sidecar {
+            path: sidecar,
+            kind,
+            checksum,
+        }));
+    }
+    Ok(None)
+}
+
+/// Parses the checksum of the file named `name` from the text of a sidecar:
+/// `<hex>  <name>` lines of sha256sum and md5sum, with or without the `*`
+/// of binary mode, `SHA256 (<name>) = <hex>` lines of BSD tools, a bare
+/// checksum, or `<name> <crc>` lines of SFV files.
+pub fn parse(text: &str, kind: Kind, name: &str) -> Option<Vec<u8>> {
+    let entries: Vec<(Option<&str>, &str)> = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with(['#', ';']))
+        .filter_map(|line| match kind {
+            Kind::Sfv => line
+                .rsplit_once(char::is_whitespace)
+                .map(|(name, crc)| (Some(name.trim()), crc)),
+            _ => match line.split_once(" (") {
+                Some((_, rest)) => rest
+                    .rsplit_once(") = ")
+                    .map(|(name, hex)| (Some(name), hex)),
+                None => match line.split_once(char::is_whitespace) {
+                    Some((hex, name)) => {
+                        Some((Some(name.trim_start().trim_start_matches('*')), hex))
+                    }
+                    None => Some((None, line)),
+                },
+            },
+        })
+        .collect();
+    let named = |entry: &&(Option<&str>, &str)| {
+        entry
+            .0
+            .is_some_and(|entry| entry.trim_start_matches("./") == name)
+    };
+    let (_, hex) = match entries.iter().find(named) {
+        Some(entry) => entry,
+        None if entries.len() == 1 => &entries[0],
+        None => return None,
+    };
+    from_hex(hex).filter(|checksum| checksum.len() == kind.len())
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Computes the checksums of a file of each kind in one pass, counting the
+/// reads against the throttle.
+pub fn checksums<P: AsRef<Path>>(
+    path: P,
+    kinds: &[Kind],
+    throttle: Option<&Throttle>,
+) -> io::Result<Vec<Vec<u8>>> {
+    let file = File::open(long_path::extended(path.as_ref()))?;
+    let mut reader = Throttled::new(file, throttle);
+    let mut hashers: Vec<Hasher> = kinds.iter().map(|&kind| Hasher::new(kind)).collect();
+    let mut buffer = vec![0; CHUNK_SIZE];
+    loop {
+        let len = read_full(&mut reader, &mut buffer)?;
+        for hasher in &mut hashers {
+            hasher.update(&buffer[..len]);
+        }
+        if len < buffer.len() {
+            return Ok(hashers.into_iter().map(Hasher::finalize).collect());
+        }
+    }
+}
+
+enum Hasher {
+    Digest(Box<dyn DynDigest>),
+    Crc32(u32),
+}
+
+impl Hasher {
+    fn new(kind: Kind) -> Self {
+        match kind {
+            Kind::Sha256 => Self::Digest(Box::new(Sha256::default())),
+            Kind::Md5 => Self::Digest(Box::new(Md5::default())),
+            Kind::Sfv => Self::Crc32(!0),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Self::Digest(digest) => digest.update(data),
+            Self::Crc32(crc) => *crc = crc32_update(*crc, data),
+        }
+    }
+
+    fn finalize(self) -> Vec<u8> {
+        match self {
+            Self::Digest(digest) => digest.finalize().to_vec(),
+            Self::Crc32(crc) => (!crc).to_be_bytes().to_vec(),
+        }
+    }
+}
+
+/// Updates a CRC-32 (IEEE, as in SFV files and zip) with more data.
+fn crc32_update(mut crc: u32, data: &[u8]) -> u32 {
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = match crc & 1 {
+                1 => (crc >> 1) ^ 0xedb8_8320,
+                _ => crc >> 1,
+            };
+        }
+    }
+    crc
+}
+
+/// Compares two files by the sidecar of either, trusting it instead of
+/// reading its file: the other file is read and its checksum compared with
+/// the sidecar's. Where both have sidecars, the first file is read and must
+/// still match its own. Returns `None` if neither has a sidecar. Checksums
+/// don't tell where files differ, so differences are at offset 0.
+pub fn compare_with_sidecars(
+    path1: &Path,
+    path2: &Path,
+    throttle: Option<&Throttle>,
+) -> io::Result<Option<FileDiff>> {
+    let (sidecar1, sidecar2) = (find(path1)?, find(path2)?);
+    let (read, own, trusted) = match (sidecar1, sidecar2) {
+        (None, None) => return Ok(None),
+        (own, Some(trusted)) => (path1, own, trusted),
+        (Some(trusted), None) => (path2, None, trusted),
+    };
+    let mut kinds = vec![trusted.kind];
+    kinds.extend(own.as_ref().map(|own| own.kind));
+    let checksums = checksums(read, &kinds, throttle)?;
+    if let Some(own) = &own {
+        if checksums[1] != own.checksum {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "{} no longer matches its checksum {} in {}",
+                    read.display(),
+                    to_hex(&own.checksum),
+                    own.path.display()
+                ),
+            ));
+        }
+    }
+    Ok(Some(match checksums[0] == trusted.checksum {
+        true => FileDiff::Equal,
+        false => FileDiff::Different(0),
+    }))
+}
@@ -0,0 +1,56 @@
+use crate::cdc;
+use crate::{long_path, CompareOptions};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// About how many chunks each file is cut into to measure how similar they
+/// are, enough to tell a small edit from a rewrite without keeping a chunk
+/// for every few hundred bytes of a large file.
+const CHUNKS_PER_FILE: u64 = 4096;
+
+/// How a pair of files that differ relate to each other.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Likeness {
+    /// The files have this percentage of their content in common, at least
+    /// the threshold
+    SimilarTo(f64),
+    Different,
+}
+
+/// Returns the percentage of the content of two files they have in common:
+/// the bytes of the chunks of each that the other one has too, out of the
+/// bytes of both. The chunks are cut by content as with --cdc, so an
+/// insertion only changes the chunks around it, not the ones after.
+pub fn similarity<P: AsRef<Path>>(path1: P, path2: P, options: &CompareOptions) -> io::Result<f64> {
+    let (path1, path2) = (path1.as_ref(), path2.as_ref());
+    let len1 = fs::metadata(long_path::extended(path1))?.len();
+    let len2 = fs::metadata(long_path::extended(path2))?.len();
+    if len1 + len2 == 0 {
+        return Ok(100.0);
+    }
+    let avg = (len1.max(len2) / CHUNKS_PER_FILE).next_power_of_two() as usize;
+    let diff = cdc::compare_cdc(path1, path2, avg, options)?;
+    let changed: u64 = diff
+        .changed1
+        .iter()
+        .chain(&diff.changed2)
+        .map(|range| range.end - range.start)
+        .sum();
+    Ok((len1 + len2).saturating_sub(changed) as f64 * 100.0 / (len1 + len2) as f64)
+}
+
+/// Classifies a pair of files that differ as similar if they have at least
+/// `threshold` percent of their content in common.
+pub fn classify<P: AsRef<Path>>(
+    path1: P,
+    path2: P,
+    threshold: f64,
+    options: &CompareOptions,
+) -> io::Result<Likeness> {
+    let percent = similarity(path1, path2, options)?;
+    Ok(match percent >= threshold {
+        true => Likeness::SimilarTo(percent),
+        false => Likeness::Different,
+    })
+}
@@ -0,0 +1,137 @@
+use crate::{compare_range, diff_at_lengths, long_path, CompareOptions, FileDiff};
+use std::fs::{File, Metadata};
+use std::io;
+use std::ops::Range;
+use std::path::Path;
+
+/// Returns true if the file occupies fewer blocks on disk than its length,
+/// i.e. it contains holes.
+pub fn is_sparse(meta: &Metadata) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        meta.is_file() && meta.blocks() * 512 < meta.len()
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = meta;
+        false
+    }
+}
+
+/// Returns the data extents of the file in ascending order, using
+/// `lseek(SEEK_DATA/SEEK_HOLE)`. Everything outside the extents is a hole and
+/// reads as zeros. Returns `None` if hole detection isn't supported.
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "freebsd"))]
+pub fn data_extents(file: &File, len: u64) -> io::Result<Option<Vec<Range<u64>>>> {
+    use std::os::unix::io::AsRawFd;
+
+    let fd = file.as_raw_fd();
+    let mut extents = vec![];
+    let mut pos = 0;
+
+    while pos < len {
+        let start = unsafe { libc::lseek(fd, pos as libc::off_t, libc::SEEK_DATA) };
+        if start < 0 {
+            let err = io::Error::last_os_error();
+            return match err.raw_os_error() {
+                // No more data after `pos`, the rest of the file is a hole
+                Some(libc::ENXIO) => Ok(Some(extents)),
+                Some(libc::EINVAL) | Some(libc::EOPNOTSUPP) => Ok(None),
+                _ => Err(err),
+            };
+        }
+        let end = unsafe { libc::lseek(fd, start, libc::SEEK_HOLE) };
+        if end < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        extents.push(start as u64..(end as u64).min(len));
+        pos = end as u64;
+    }
+
+    Ok(Some(extents))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "freebsd")))]
+pub fn data_extents(_file: &File, _len: u64) -> io::Result<Option<Vec<Range<u64>>>> {
+    Ok(None)
+}
+
+/// Merges two sorted extent lists into their union, clipped to `limit`.
+pub fn merge_extents(a: &[Range<u64>], b: &[Range<u64>], limit: u64) -> Vec<Range<u64>> {
+    let mut all: Vec<Range<u64>> = a
+        .iter()
+        .chain(b)
+        .map(|r| r.start.min(limit)..r.end.min(limit))
+        .filter(|r| !r.is_empty())
+        .collect();
+    all.sort_by_key(|r| r.start);
+
+    let mut merged: Vec<Range<u64>> = vec![];
+    for range in all {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+/// Compares the hole and data layout of a copy with that of the original,
+/// e.g. to find VM images that take up their full size after a copy.
+/// Returns `densified` if the copy has data wherever the original does and
+/// more, `extents` if the layouts differ otherwise, or `None` if they are
+/// the same or can't be determined.
+pub fn compare_layouts<P: AsRef<Path>>(original: P, copy: P) -> io::Result<Option<&'static str>> {
+    let extents = |path: &Path| {
+        let file = File::open(long_path::extended(path))?;
+        let len = file.metadata()?.len();
+        Ok::<_, io::Error>(data_extents(&file, len)?.map(|extents| (extents, len)))
+    };
+    let (original, copy) = match (extents(original.as_ref())?, extents(copy.as_ref())?) {
+        (Some(original), Some(copy)) => (original, copy),
+        _ => return Ok(None),
+    };
+    if original.0 == copy.0 {
+        return Ok(None);
+    }
+    let limit = original.1.max(copy.1);
+    match merge_extents(&original.0, &copy.0, limit) == copy.0 {
+        true => Ok(Some("densified")),
+        false => Ok(Some("extents")),
+    }
+}
+
+/// Compares two sparse files by reading only the regions where at least one
+/// of them has data; regions that are holes in both files are equal (zeros).
+/// Returns `None` if the extents can't be determined, in which case the
+/// caller should fall back to a full comparison.
+pub(crate) fn compare(
+    file1: &mut File,
+    len1: u64,
+    file2: &mut File,
+    len2: u64,
+    options: &CompareOptions,
+) -> io::Result<Option<FileDiff>> {
+    let extents1 = match data_extents(file1, len1)? {
+        Some(e) => e,
+        None => return Ok(None),
+    };
+    let extents2 = match data_extents(file2, len2)? {
+        Some(e) => e,
+        None => return Ok(None),
+    };
+
+    let len = len1.min(len2);
+    for range in merge_extents(&extents1, &extents2, len) {
+        if let Some(offset) = compare_range(file1, file2, range, options)? {
+            return Ok(Some(FileDiff::Different(match options.quick {
+                true => 0,
+                false => offset as usize,
+            })));
+        }
+    }
+
+    let offset = (len1 != len2).then_some(len);
+    Ok(Some(diff_at_lengths(offset, len1, len2, options.quick)))
+}
@@ -0,0 +1,95 @@
+use crate::lines::compare_lines;
+use crate::{diff_at, FileDiff};
+use std::io;
+use std::ops::Range;
+use std::path::Path;
+
+/// Starts of comment and meta-command lines of pg_dump and mysqldump that
+/// hold versions, hosts, times or random keys rather than the dumped data.
+const VOLATILE_LINES: [&str; 10] = [
+    "-- Dumped from database version",
+    "-- Dumped by pg_dump version",
+    "-- Started on",
+    "-- Completed on",
+    "-- MySQL dump",
+    "-- Host:",
+    "-- Server version",
+    "-- Dump completed on",
+    "\\restrict ",
+    "\\unrestrict ",
+];
+
+/// Returns whether a file is an SQL dump, by its extension.
+pub fn is_sql_dump<P: AsRef<Path>>(path: P) -> bool {
+    let extension = path.as_ref().extension().and_then(|e| e.to_str());
+    extension.is_some_and(|extension| extension.eq_ignore_ascii_case("sql"))
+}
+
+fn is_volatile(line: &str) -> bool {
+    VOLATILE_LINES.iter().any(|start| line.starts_with(start))
+}
+
+/// Returns whether a line sets a session variable, like `SET
+/// client_encoding = 'UTF8';`, `/*!40101 SET NAMES utf8mb4 */;` or
+/// `SELECT pg_catalog.set_config('search_path', '', false);`.
+fn is_setting(line: &str) -> bool {
+    let line = line.trim_start();
+    let upper = line.get(..4).map(str::to_ascii_uppercase);
+    upper.as_deref() == Some("SET ")
+        || (line.starts_with("/*!") && line.to_ascii_uppercase().contains(" SET "))
+        || line.starts_with("SELECT pg_catalog.set_config(")
+}
+
+/// Returns a line filter that keeps the statements of a dump, skipping the
+/// volatile comments and collecting the settings with their offsets.
+fn statements(
+    settings: &mut Vec<(Vec<u8>, u64)>,
+) -> impl FnMut(u64, &[u8]) -> Option<Range<usize>> + '_ {
+    move |offset, line| {
+        let text = String::from_utf8_lossy(line);
+        if is_volatile(&text) {
+            return None;
+        }
+        if is_setting(&text) {
+            settings.push((line.to_vec(), offset));
+            return None;
+        }
+        Some(0..line.len())
+    }
+}
+
+/// Compares two SQL dumps of pg_dump or mysqldump by their statements:
+/// the comment lines with the server version, host and time of the dump
+/// are skipped, and the session settings (SET statements) are compared
+/// regardless of their order. Returns the offset of the first line of the
+/// first dump that differs.
+pub fn compare_sql_dumps<P: AsRef<Path>>(path1: P, path2: P, quick: bool) -> io::Result<FileDiff> {
+    let (mut settings1, mut settings2) = (vec![], vec![]);
+    let result = compare_lines(
+        path1.as_ref(),
+        path2.as_ref(),
+        statements(&mut settings1),
+        statements(&mut settings2),
+        quick,
+    )?;
+    if result != FileDiff::Equal {
+        return Ok(result);
+    }
+    settings1.sort();
+    settings2.sort();
+    let differing = settings1
+        .iter()
+        .zip(&settings2)
+        .find(|((line1, _), (line2, _))| line1 != line2)
+        .map(|((_, offset), _)| *offset);
+    Ok(match differing {
+        Some(offset) => diff_at(Some(offset), quick),
+        None if settings1.len() != settings2.len() => {
+            let offset = settings1
+                .get(settings2.len())
+                .map_or(0, |(_, offset)| *offset);
+            diff_at(Some(offset), quick)
+        }
+        None => FileDiff::Equal,
+    })
+}
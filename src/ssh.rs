@@ -0,0 +1,299 @@
+use crate::delta::{self, Delta, Signature};
+use crate::throttle::Throttled;
+use crate::{agent, compare_streams, device, fifo, long_path, walk, CompareOptions, FileDiff};
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdout, Command, Stdio};
+
+/// A file or directory on another host, parsed from
+/// `sftp://[user@]host[:port]/path` or the same with `ssh://`.
+#[derive(Debug, Eq, PartialEq)]
+pub struct RemotePath {
+    /// `host` or `user@host`, as passed to ssh
+    pub destination: String,
+    pub port: Option<u16>,
+    pub path: String,
+}
+
+impl RemotePath {
+    pub fn parse(url: &str) -> Option<RemotePath> {
+        let rest = url
+            .strip_prefix("sftp://")
+            .or_else(|| url.strip_prefix("ssh://"))?;
+        let (authority, path) = rest.split_at(rest.find('/')?);
+        let (user, host) = match authority.rsplit_once('@') {
+            Some((user, host)) => (Some(user), host),
+            None => (None, authority),
+        };
+        let (host, port) = match host.rsplit_once(':') {
+            Some((host, port)) => (host, Some(port.parse().ok()?)),
+            None => (host, None),
+        };
+        if host.is_empty() {
+            return None;
+        }
+        Some(RemotePath {
+            destination: match user {
+                Some(user) => format!("{}@{}", user, host),
+                None => host.to_string(),
+            },
+            port,
+            // `/~/dir` is relative to the home directory, as with sftp
+            path: path.strip_prefix("/~/").unwrap_or(path).to_string(),
+        })
+    }
+
+    /// The URL of a file below this directory, for reporting.
+    fn url_of(&self, relative: &str) -> String {
+        let port = self.port.map(|p| format!(":{}", p)).unwrap_or_default();
+        let dir = self.path.trim_end_matches('/');
+        match dir.starts_with('/') {
+            true => format!("sftp://{}{}{}/{}", self.destination, port, dir, relative),
+            false => format!("sftp://{}{}/~/{}/{}", self.destination, port, dir, relative),
+        }
+    }
+}
+
+pub fn is_remote_url(path: &str) -> bool {
+    path.starts_with("sftp://") || path.starts_with("ssh://")
+}
+
+/// Runs commands on the remote host through the `ssh` client, so the user's
+/// keys, agent and `~/.ssh/config` apply.
+pub struct Session {
+    /// The ssh program and any extra arguments, e.g. `ssh -i key`
+    pub program: Vec<String>,
+}
+
+impl Session {
+    pub fn new(program: &str) -> Session {
+        Session {
+            program: program.split_whitespace().map(str::to_string).collect(),
+        }
+    }
+
+    fn command(&self, remote: &RemotePath, script: &str) -> io::Result<Command> {
+        let (program, args) = self
+            .program
+            .split_first()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "empty ssh command"))?;
+        let mut command = Command::new(program);
+        command.args(args).arg("-o").arg("BatchMode=yes");
+        if let Some(port) = remote.port {
+            command.arg("-p").arg(port.to_string());
+        }
+        command.arg("--").arg(&remote.destination).arg(script);
+        Ok(command)
+    }
+
+    /// Runs the script and returns its output, failing with its stderr if it
+    /// exits unsuccessfully.
+    fn output(&self, remote: &RemotePath, script: &str) -> io::Result<Vec<u8>> {
+        let output = self
+            .command(remote, script)?
+            .stdin(Stdio::null())
+            .output()?;
+        match output.status.success() {
+            true => Ok(output.stdout),
+            false => Err(remote_error(remote, &output.stderr)),
+        }
+    }
+
+    /// Size of a remote file in bytes.
+    pub fn len(&self, remote: &RemotePath) -> io::Result<u64> {
+        let output = self.output(remote, &format!("wc -c < {}", quote(&remote.path)))?;
+        String::from_utf8_lossy(&output)
+            .trim()
+            .parse()
+            .map_err(|_| io::Error::other(format!("unexpected size from {}", remote.destination)))
+    }
+
+    /// Streams the contents of a remote file.
+    pub fn open(&self, remote: &RemotePath) -> io::Result<RemoteReader> {
+        self.spawn(remote, &format!("cat -- {}", quote(&remote.path)))
+    }
+
+    /// Runs the script and streams its output.
+    fn spawn(&self, remote: &RemotePath, script: &str) -> io::Result<RemoteReader> {
+        let mut child = self
+            .command(remote, script)?
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let stdout = child.stdout.take().expect("stdout is piped");
+        Ok(RemoteReader {
+            child,
+            stdout,
+            destination: remote.destination.clone(),
+        })
+    }
+
+    /// Lists the files under a remote directory by their `/`-separated paths
+    /// relative to it.
+    pub fn list_files(&self, remote: &RemotePath) -> io::Result<BTreeSet<String>> {
+        let script = format!("cd {} && find . -type f -print0", quote(&remote.path));
+        let output = self.output(remote, &script)?;
+        Ok(output
+            .split(|&b| b == 0)
+            .filter(|path| !path.is_empty())
+            .map(|path| {
+                let path = String::from_utf8_lossy(path);
+                path.strip_prefix("./").unwrap_or(&path).to_string()
+            })
+            .collect())
+    }
+}
+
+/// Output of a remote `cat`. Reaching the end checks that the command
+/// succeeded, so a missing or unreadable file is an error rather than an
+/// empty stream.
+pub struct RemoteReader {
+    child: Child,
+    stdout: ChildStdout,
+    destination: String,
+}
+
+impl Read for RemoteReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.stdout.read(buf)?;
+        if len == 0 && !buf.is_empty() {
+            let mut stderr = vec![];
+            if let Some(mut pipe) = self.child.stderr.take() {
+                pipe.read_to_end(&mut stderr)?;
+            }
+            if !self.child.wait()?.success() {
+                return Err(io::Error::other(format!(
+                    "{}: {}",
+                    self.destination,
+                    String::from_utf8_lossy(&stderr).trim()
+                )));
+            }
+        }
+        Ok(len)
+    }
+}
+
+impl Drop for RemoteReader {
+    fn drop(&mut self) {
+        // Stop the transfer when the comparison ends early
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn remote_error(remote: &RemotePath, stderr: &[u8]) -> io::Error {
+    io::Error::other(format!(
+        "{}: {}",
+        remote.destination,
+        String::from_utf8_lossy(stderr).trim()
+    ))
+}
+
+/// Quotes a path for the remote POSIX shell.
+pub fn quote(path: &str) -> String {
+    format!("'{}'", path.replace('\'', r"'\''"))
+}
+
+/// Compares a remote file with a local one, streaming it over ssh.
+pub fn compare_file<P: AsRef<Path>>(
+    session: &Session,
+    remote: &RemotePath,
+    path: P,
+    options: &CompareOptions,
+) -> io::Result<FileDiff> {
+    let input = device::Input::open(path)?;
+    if options.quick && !input.meta.as_ref().is_some_and(fifo::is_fifo) {
+        let len = session.len(remote)?;
+        if len != input.len {
+            return Ok(FileDiff::Different(0));
+        }
+    }
+    let file = fifo::TimeoutReader::new(input.file, options.timeout);
+    compare_streams(session.open(remote)?, file, options)
+}
+
+/// Compares a remote directory tree with a local one. Local files are
+/// reported by path and files missing locally by URL.
+pub fn compare_tree<P: AsRef<Path>>(
+    session: &Session,
+    remote: &RemotePath,
+    dir: P,
+    options: &CompareOptions,
+) -> io::Result<Vec<(PathBuf, FileDiff)>> {
+    let mut remote_files = session.list_files(remote)?;
+    let mut results = vec![];
+    for (relative, path) in walk::list_files(dir.as_ref())? {
+        if !remote_files.remove(&relative) {
+            results.push((path, FileDiff::LeftOnly));
+            continue;
+        }
+        let file = RemotePath {
+            path: format!("{}/{}", remote.path.trim_end_matches('/'), relative),
+            destination: remote.destination.clone(),
+            port: remote.port,
+        };
+        let result = compare_file(session, &file, &path, options)?;
+        results.push((path, result));
+    }
+    for relative in remote_files {
+        results.push((PathBuf::from(remote.url_of(&relative)), FileDiff::RightOnly));
+    }
+    Ok(results)
+}
+
+/// Compares a remote tree with a local one by running `file_cmp agent` on
+/// the remote host, so only sizes and hashes cross the connection instead
+/// of the files. `program` is the remote file_cmp command.
+pub fn compare_tree_with_agent<P: AsRef<Path>>(
+    session: &Session,
+    remote: &RemotePath,
+    program: &str,
+    dir: P,
+    options: &CompareOptions,
+) -> io::Result<Vec<(PathBuf, FileDiff)>> {
+    let script = format!(
+        "{} agent --hash {} {}",
+        program,
+        options.hash,
+        quote(&remote.path)
+    );
+    let records = agent::Records::new(
+        BufReader::new(session.spawn(remote, &script)?),
+        options.hash,
+    )?;
+    agent::compare_records(records, dir, options, |relative| {
+        PathBuf::from(remote.url_of(relative))
+    })
+}
+
+/// Compares a remote file with a local one like rsync, by running
+/// `file_cmp agent --blocks` on the remote host for the checksums of its
+/// blocks and rolling over the local file to find them. Only the checksums
+/// cross the connection, and the ranges of the local file that match no
+/// remote block are reported. `program` is the remote file_cmp command.
+pub fn compare_file_with_agent<P: AsRef<Path>>(
+    session: &Session,
+    remote: &RemotePath,
+    program: &str,
+    path: P,
+    options: &CompareOptions,
+) -> io::Result<Delta> {
+    let file = File::open(long_path::extended(path.as_ref()))?;
+    let block_len = delta::block_len(file.metadata()?.len());
+    let script = format!(
+        "{} agent --hash {} --blocks {} {}",
+        program,
+        options.hash,
+        block_len,
+        quote(&remote.path)
+    );
+    let signature = Signature::read(
+        BufReader::new(session.spawn(remote, &script)?),
+        options.hash,
+    )?;
+    let mut reader = Throttled::new(file, options.throttle.as_deref());
+    delta::compare_signature(&mut reader, &signature)
+}
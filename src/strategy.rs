@@ -0,0 +1,110 @@
+use crate::{memory, CompareOptions};
+use std::fs::File;
+
+/// Files smaller than this are read into buffers, as mapping them costs more
+/// than the copies it saves.
+pub const MIN_MMAP_LEN: u64 = 1024 * 1024;
+
+/// Files at least this large are compared in parallel ranges when there is
+/// more than one CPU.
+pub const MIN_PARALLEL_LEN: u64 = 256 * 1024 * 1024;
+
+/// Most threads a parallel comparison is given, as more rarely read faster.
+pub const MAX_AUTO_THREADS: usize = 8;
+
+/// How a pair of files is read for --auto-io.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Strategy {
+    /// Read into buffers, pipelined for large files
+    Buffered,
+    /// Mapped into memory
+    Mmap,
+    /// Compared in ranges by this many threads
+    Parallel(usize),
+}
+
+/// Picks how to read a pair of files whose larger one has `len` bytes.
+/// Network filesystems are read into buffers, since mapping them turns
+/// every page into a round trip and their files can change underneath.
+/// Other files are compared in parallel when they are large and there are
+/// CPUs to spare, else mapped if both fit in half the available memory.
+pub fn choose(len: u64, network: bool, available: Option<u64>, cpus: usize) -> Strategy {
+    if network || len < MIN_MMAP_LEN {
+        return Strategy::Buffered;
+    }
+    if len >= MIN_PARALLEL_LEN && cpus > 1 {
+        return Strategy::Parallel(cpus.min(MAX_AUTO_THREADS));
+    }
+    match available {
+        Some(available) if 2 * len > available / 2 => Strategy::Buffered,
+        _ => Strategy::Mmap,
+    }
+}
+
+/// Returns the options to compare a pair of open files with for --auto-io,
+/// scaled down to their memory limit if they have one.
+pub fn apply(
+    options: &CompareOptions,
+    file1: &File,
+    len1: u64,
+    file2: &File,
+    len2: u64,
+) -> CompareOptions {
+    let cpus = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let network = is_network_fs(file1) || is_network_fs(file2);
+    let mut options = options.clone();
+    (options.mmap, options.threads) =
+        match choose(len1.max(len2), network, available_memory(), cpus) {
+            Strategy::Buffered => (false, 1),
+            Strategy::Mmap => (true, 1),
+            Strategy::Parallel(threads) => (false, threads),
+        };
+    if let Some(limit) = options.max_memory {
+        // Whatever fits at all was checked before the comparison started
+        let _ = memory::fit(&mut options, limit);
+    }
+    options
+}
+
+/// Returns whether a file is on a network filesystem: NFS, SMB, CIFS,
+/// Ceph, AFS or a FUSE mount, which may be sshfs or a cloud drive.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn is_network_fs(file: &File) -> bool {
+    use std::os::unix::io::AsRawFd;
+
+    const NETWORK: [u64; 7] = [
+        0x6969,     // NFS
+        0x517b,     // SMB
+        0xfe534d42, // SMB2
+        0xff534d42, // CIFS
+        0x00c36400, // Ceph
+        0x5346414f, // AFS
+        0x65735546, // FUSE
+    ];
+    let mut stat: libc::statfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::fstatfs(file.as_raw_fd(), &mut stat) } != 0 {
+        return false;
+    }
+    NETWORK.contains(&(stat.f_type as u64 & 0xffff_ffff))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub fn is_network_fs(_file: &File) -> bool {
+    false
+}
+
+/// Returns the memory that can be taken without swapping, if known.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn available_memory() -> Option<u64> {
+    std::fs::read_to_string("/proc/meminfo")
+        .ok()?
+        .lines()
+        .find_map(|line| line.strip_prefix("MemAvailable:"))
+        .and_then(|kb| kb.trim().trim_end_matches("kB").trim().parse::<u64>().ok())
+        .map(|kb| kb * 1024)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub fn available_memory() -> Option<u64> {
+    None
+}
@@ -0,0 +1,72 @@
+use crate::throttle::Throttled;
+use crate::{long_path, CompareOptions};
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Bytes read from the end of each file at a time.
+const READ_LEN: usize = 1024 * 1024;
+
+/// Result of comparing two files backwards from their ends.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SuffixDiff {
+    pub len1: u64,
+    pub len2: u64,
+    /// Length of the bytes both files end with
+    pub suffix: u64,
+}
+
+impl SuffixDiff {
+    pub fn is_equal(&self) -> bool {
+        self.len1 == self.len2 && self.suffix == self.len1
+    }
+
+    /// The last differing byte of each file, the one before the common
+    /// suffix, or `None` for a file that is all suffix.
+    pub fn last_diff(&self) -> (Option<u64>, Option<u64>) {
+        let before = |len: u64| (len > self.suffix).then(|| len - self.suffix - 1);
+        (before(self.len1), before(self.len2))
+    }
+}
+
+/// Compares two files from their ends towards their starts, returning the
+/// length of the suffix they share. Appending to a file or a log leaves the
+/// suffix of its copy empty, while damage to the end of either shows as the
+/// last byte that differs.
+pub fn compare_from_end<P: AsRef<Path>>(
+    path1: P,
+    path2: P,
+    options: &CompareOptions,
+) -> io::Result<SuffixDiff> {
+    let open = |path: &Path| -> io::Result<(File, u64)> {
+        let file = File::open(long_path::extended(path))?;
+        let len = file.metadata()?.len();
+        Ok((file, len))
+    };
+    let (mut file1, len1) = open(path1.as_ref())?;
+    let (mut file2, len2) = open(path2.as_ref())?;
+    let read_len = options.chunk_size.unwrap_or(READ_LEN).max(1) as u64;
+    let (mut buffer1, mut buffer2) = (vec![], vec![]);
+    let mut suffix = 0;
+    while suffix < len1.min(len2) {
+        let len = read_len.min(len1.min(len2) - suffix);
+        let read = |file: &mut File, file_len: u64, buffer: &mut Vec<u8>| {
+            buffer.resize(len as usize, 0);
+            file.seek(SeekFrom::Start(file_len - suffix - len))?;
+            Throttled::new(file, options.throttle.as_deref()).read_exact(buffer)
+        };
+        read(&mut file1, len1, &mut buffer1)?;
+        read(&mut file2, len2, &mut buffer2)?;
+        let same = buffer1
+            .iter()
+            .rev()
+            .zip(buffer2.iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count() as u64;
+        suffix += same;
+        if same < len {
+            break;
+        }
+    }
+    Ok(SuffixDiff { len1, len2, suffix })
+}
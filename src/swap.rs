@@ -0,0 +1,84 @@
+use crate::chunk::{self, ChunkSizer};
+use crate::throttle::Throttled;
+use crate::{compare_readers, diff_at_lengths, long_path, read_full, CompareOptions, FileDiff};
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+/// Word sizes that can be swapped.
+pub const WORD_SIZES: [usize; 2] = [2, 4];
+
+/// Bytes read from the inner reader at a time, a multiple of every word
+/// size.
+const READ_LEN: usize = 64 * 1024;
+
+/// Reader reversing the bytes of each word of its inner reader's data, e.g.
+/// to read 16-bit samples of a big-endian device as little-endian. A partial
+/// word at the end is left as it is.
+pub struct SwappedReader<R> {
+    inner: R,
+    word: usize,
+    buffer: Vec<u8>,
+    /// The swapped bytes in the buffer not read yet
+    start: usize,
+    end: usize,
+}
+
+impl<R: Read> SwappedReader<R> {
+    pub fn new(inner: R, word: usize) -> Self {
+        Self {
+            inner,
+            word,
+            buffer: vec![0; READ_LEN],
+            start: 0,
+            end: 0,
+        }
+    }
+}
+
+impl<R: Read> Read for SwappedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.start == self.end {
+            // Whole buffers keep the words aligned across reads
+            let len = read_full(&mut self.inner, &mut self.buffer)?;
+            for word in self.buffer[..len].chunks_exact_mut(self.word) {
+                word.reverse();
+            }
+            (self.start, self.end) = (0, len);
+        }
+        let len = buf.len().min(self.end - self.start);
+        buf[..len].copy_from_slice(&self.buffer[self.start..self.start + len]);
+        self.start += len;
+        Ok(len)
+    }
+}
+
+/// Compares two files after swapping the bytes of each word of `word` bytes
+/// of the second one. Offsets are those of the files.
+pub fn compare_swapped<P: AsRef<Path>>(
+    path1: P,
+    path2: P,
+    word: usize,
+    options: &CompareOptions,
+) -> io::Result<FileDiff> {
+    if !WORD_SIZES.contains(&word) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("cannot swap words of {} bytes", word),
+        ));
+    }
+    let file1 = File::open(long_path::extended(path1.as_ref()))?;
+    let file2 = File::open(long_path::extended(path2.as_ref()))?;
+    let (len1, len2) = (file1.metadata()?.len(), file2.metadata()?.len());
+    if options.quick && len1 != len2 {
+        return Ok(FileDiff::Different(0));
+    }
+    let throttle = options.throttle.as_deref();
+    let mut chunks = ChunkSizer::new(options.chunk_size, len1.max(len2), chunk::MIN_CHUNK_SIZE);
+    let offset = compare_readers(
+        &mut Throttled::new(file1, throttle),
+        &mut SwappedReader::new(Throttled::new(file2, throttle), word),
+        &mut chunks,
+    )?;
+    Ok(diff_at_lengths(offset, len1, len2, options.quick))
+}
@@ -0,0 +1,61 @@
+use std::io::{self, Read};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Limits the combined read throughput of all readers sharing it, e.g. both
+/// files and all worker threads of a comparison.
+#[derive(Debug)]
+pub struct Throttle {
+    bytes_per_sec: u64,
+    /// The time at which everything read so far is paid for
+    next: Mutex<Option<Instant>>,
+}
+
+impl Throttle {
+    pub fn new(bytes_per_sec: u64) -> Self {
+        Self {
+            bytes_per_sec: bytes_per_sec.max(1),
+            next: Mutex::new(None),
+        }
+    }
+
+    /// Accounts for `bytes` having been read, sleeping as long as needed to
+    /// stay under the rate.
+    pub fn consume(&self, bytes: usize) {
+        let cost = Duration::from_secs_f64(bytes as f64 / self.bytes_per_sec as f64);
+        let now = Instant::now();
+        let wait = {
+            let mut next = self.next.lock().expect("Throttle lock poisoned");
+            // Idle time doesn't accumulate into a burst allowance
+            let start = next.filter(|&n| n > now).unwrap_or(now);
+            *next = Some(start + cost);
+            start - now
+        };
+        if !wait.is_zero() {
+            thread::sleep(wait);
+        }
+    }
+}
+
+/// A reader that counts everything it reads against an optional throttle.
+pub struct Throttled<'a, R> {
+    inner: R,
+    throttle: Option<&'a Throttle>,
+}
+
+impl<'a, R> Throttled<'a, R> {
+    pub fn new(inner: R, throttle: Option<&'a Throttle>) -> Self {
+        Self { inner, throttle }
+    }
+}
+
+impl<R: Read> Read for Throttled<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.inner.read(buf)?;
+        if let Some(throttle) = self.throttle {
+            throttle.consume(len);
+        }
+        Ok(len)
+    }
+}
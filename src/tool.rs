@@ -0,0 +1,34 @@
+use std::io;
+use std::path::Path;
+use std::process::Command;
+
+/// Opens two files in an external diff tool such as `vimdiff` or Beyond
+/// Compare, like `git difftool`, and waits for it to exit. The command is
+/// run by the shell with both paths appended. Its exit status is ignored,
+/// since tools like `diff` exit with 1 when the files differ.
+pub fn launch<P: AsRef<Path>>(command: &str, path1: P, path2: P) -> io::Result<()> {
+    let (path1, path2) = (path1.as_ref(), path2.as_ref());
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        Command::new("cmd")
+            .arg("/C")
+            .raw_arg(format!(
+                "\"{} \"{}\" \"{}\"\"",
+                command,
+                path1.display(),
+                path2.display()
+            ))
+            .status()?;
+    }
+    // The paths are passed as positional parameters so they need no quoting
+    #[cfg(not(windows))]
+    Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$1\" \"$2\"", command))
+        .arg("file_cmp")
+        .arg(path1)
+        .arg(path2)
+        .status()?;
+    Ok(())
+}
@@ -0,0 +1,417 @@
+use crate::FileDiff;
+use sha1::{Digest, Sha1};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::ops::Range;
+use std::path::{Component, Path, PathBuf};
+
+/// A decoded bencode value, borrowing strings from the torrent file.
+#[derive(Debug)]
+enum Value<'a> {
+    Int(i64),
+    Bytes(&'a [u8]),
+    List(Vec<Value<'a>>),
+    Dict(BTreeMap<&'a [u8], Value<'a>>),
+}
+
+impl<'a> Value<'a> {
+    fn get(&self, key: &str) -> Option<&Value<'a>> {
+        match self {
+            Value::Dict(dict) => dict.get(key.as_bytes()),
+            _ => None,
+        }
+    }
+
+    fn int(&self) -> Option<i64> {
+        match self {
+            Value::Int(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    fn bytes(&self) -> Option<&'a [u8]> {
+        match self {
+            Value::Bytes(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    fn str(&self) -> Option<&'a str> {
+        std::str::from_utf8(self.bytes()?).ok()
+    }
+
+    fn list(&self) -> Option<&[Value<'a>]> {
+        match self {
+            Value::List(list) => Some(list),
+            _ => None,
+        }
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("invalid torrent: {}", message),
+    )
+}
+
+/// Decodes the bencode value at the start of `data`, returning it and the
+/// rest of the data.
+fn decode(data: &[u8]) -> io::Result<(Value<'_>, &[u8])> {
+    match data.first() {
+        Some(b'i') => {
+            let end = data
+                .iter()
+                .position(|&b| b == b'e')
+                .ok_or_else(|| invalid("unterminated integer"))?;
+            let int = std::str::from_utf8(&data[1..end])
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .ok_or_else(|| invalid("bad integer"))?;
+            Ok((Value::Int(int), &data[end + 1..]))
+        }
+        Some(b'l') => {
+            let (mut list, mut rest) = (vec![], &data[1..]);
+            while rest.first() != Some(&b'e') {
+                let (value, next) = decode(rest)?;
+                list.push(value);
+                rest = next;
+            }
+            Ok((Value::List(list), &rest[1..]))
+        }
+        Some(b'd') => {
+            let (mut dict, mut rest) = (BTreeMap::new(), &data[1..]);
+            while rest.first() != Some(&b'e') {
+                let (key, next) = decode(rest)?;
+                let key = key.bytes().ok_or_else(|| invalid("key is not a string"))?;
+                let (value, next) = decode(next)?;
+                dict.insert(key, value);
+                rest = next;
+            }
+            Ok((Value::Dict(dict), &rest[1..]))
+        }
+        Some(b'0'..=b'9') => {
+            let colon = data
+                .iter()
+                .position(|&b| b == b':')
+                .ok_or_else(|| invalid("bad string length"))?;
+            let len: usize = std::str::from_utf8(&data[..colon])
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .ok_or_else(|| invalid("bad string length"))?;
+            let rest = &data[colon + 1..];
+            match rest.len() >= len {
+                true => Ok((Value::Bytes(&rest[..len]), &rest[len..])),
+                false => Err(invalid("truncated string")),
+            }
+        }
+        _ => Err(invalid("unexpected end or byte")),
+    }
+}
+
+/// A file of a torrent, in the order its data is hashed.
+#[derive(Debug, Eq, PartialEq)]
+pub struct TorrentFile {
+    /// Path relative to the download directory, starting with the torrent's
+    /// name for multi-file torrents
+    pub path: PathBuf,
+    pub len: u64,
+    /// Padding file (BEP 47) whose content is zeros and not stored on disk
+    pub padding: bool,
+}
+
+/// The parts of a v1 torrent's info dictionary needed to verify a download.
+#[derive(Debug)]
+pub struct Torrent {
+    pub piece_len: u64,
+    pub pieces: Vec<[u8; 20]>,
+    pub files: Vec<TorrentFile>,
+}
+
+/// Turns a name or path component from a torrent into a path, rejecting
+/// ones that could escape the download directory.
+fn safe_path<'a, I: IntoIterator<Item = &'a str>>(components: I) -> io::Result<PathBuf> {
+    let mut path = PathBuf::new();
+    for component in components {
+        let mut parsed = Path::new(component).components();
+        match (parsed.next(), parsed.next()) {
+            (Some(Component::Normal(_)), None) => path.push(component),
+            _ => return Err(invalid(&format!("unsafe path component '{}'", component))),
+        }
+    }
+    match path.as_os_str().is_empty() {
+        true => Err(invalid("empty path")),
+        false => Ok(path),
+    }
+}
+
+impl Torrent {
+    pub fn parse(data: &[u8]) -> io::Result<Torrent> {
+        let (torrent, _) = decode(data)?;
+        let info = torrent.get("info").ok_or_else(|| invalid("no info"))?;
+        let piece_len = info
+            .get("piece length")
+            .and_then(Value::int)
+            .filter(|&len| len > 0)
+            .ok_or_else(|| invalid("no piece length"))? as u64;
+        let pieces = info
+            .get("pieces")
+            .and_then(Value::bytes)
+            .filter(|pieces| pieces.len() % 20 == 0)
+            .ok_or_else(|| invalid("no v1 piece hashes"))?
+            .chunks(20)
+            .map(|hash| hash.try_into().expect("chunks are 20 bytes"))
+            .collect();
+        let name = info
+            .get("name")
+            .and_then(Value::str)
+            .ok_or_else(|| invalid("no name"))?;
+        let len = |value: &Value| {
+            value
+                .get("length")
+                .and_then(Value::int)
+                .filter(|&len| len >= 0)
+                .map(|len| len as u64)
+                .ok_or_else(|| invalid("bad file length"))
+        };
+
+        let files = match info.get("files").and_then(Value::list) {
+            None => vec![TorrentFile {
+                path: safe_path([name])?,
+                len: len(info)?,
+                padding: false,
+            }],
+            Some(files) => files
+                .iter()
+                .map(|file| {
+                    let components = file
+                        .get("path")
+                        .and_then(Value::list)
+                        .ok_or_else(|| invalid("no file path"))?
+                        .iter()
+                        .map(|component| component.str().ok_or_else(|| invalid("bad file path")))
+                        .collect::<io::Result<Vec<_>>>()?;
+                    let padding = file
+                        .get("attr")
+                        .and_then(Value::bytes)
+                        .is_some_and(|attr| attr.contains(&b'p'));
+                    Ok(TorrentFile {
+                        path: safe_path(std::iter::once(name).chain(components))?,
+                        len: len(file)?,
+                        padding,
+                    })
+                })
+                .collect::<io::Result<_>>()?,
+        };
+        Ok(Torrent {
+            piece_len,
+            pieces,
+            files,
+        })
+    }
+
+    pub fn read<P: AsRef<Path>>(path: P) -> io::Result<Torrent> {
+        Torrent::parse(&fs::read(path)?)
+    }
+
+    pub fn total_len(&self) -> u64 {
+        self.files.iter().map(|file| file.len).sum()
+    }
+}
+
+/// The state of a piece of the download.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Piece {
+    Ok,
+    /// Some of the piece's data is missing, or it is all zeros as in files
+    /// preallocated by a client that hasn't downloaded it yet
+    Incomplete,
+    /// The piece's data doesn't match its hash
+    Corrupt,
+}
+
+/// A file of the torrent on disk, with its length there if it exists.
+struct OnDisk {
+    file: Option<File>,
+    len: u64,
+}
+
+/// Reads each piece from the files under `dir` and checks it against its
+/// hash.
+pub fn verify_pieces<P: AsRef<Path>>(torrent: &Torrent, dir: P) -> io::Result<Vec<Piece>> {
+    let mut on_disk = vec![];
+    for file in &torrent.files {
+        let opened = match file.padding {
+            true => None,
+            false => match File::open(dir.as_ref().join(&file.path)) {
+                Ok(opened) => Some(opened),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+                Err(e) => return Err(e),
+            },
+        };
+        let len = match &opened {
+            Some(opened) => opened.metadata()?.len(),
+            None => 0,
+        };
+        on_disk.push(OnDisk { file: opened, len });
+    }
+
+    let starts = file_starts(torrent);
+    let total = torrent.total_len();
+    let mut buffer = vec![0; torrent.piece_len as usize];
+    let mut pieces = vec![];
+    for (index, hash) in torrent.pieces.iter().enumerate() {
+        let start = index as u64 * torrent.piece_len;
+        let end = (start + torrent.piece_len).min(total);
+        if start >= end {
+            pieces.push(Piece::Incomplete);
+            continue;
+        }
+        let piece = &mut buffer[..(end - start) as usize];
+        let mut missing = false;
+        for (file, range) in segments(torrent, &starts, start..end) {
+            let at = (starts[file] + range.start - start) as usize;
+            let data = &mut piece[at..at + (range.end - range.start) as usize];
+            if torrent.files[file].padding {
+                data.fill(0);
+                continue;
+            }
+            match &mut on_disk[file] {
+                OnDisk {
+                    file: Some(opened),
+                    len,
+                } if range.end <= *len => {
+                    opened.seek(SeekFrom::Start(range.start))?;
+                    opened.read_exact(data)?;
+                }
+                _ => missing = true,
+            }
+        }
+        pieces.push(match missing {
+            true => Piece::Incomplete,
+            false if Sha1::digest(&*piece)[..] == hash[..] => Piece::Ok,
+            false if piece.iter().all(|&b| b == 0) => Piece::Incomplete,
+            false => Piece::Corrupt,
+        });
+    }
+    Ok(pieces)
+}
+
+/// Returns the offset of each file in the torrent's data.
+fn file_starts(torrent: &Torrent) -> Vec<u64> {
+    let mut start = 0;
+    torrent
+        .files
+        .iter()
+        .map(|file| {
+            start += file.len;
+            start - file.len
+        })
+        .collect()
+}
+
+/// Splits a range of the torrent's data into the ranges falling in each
+/// file, returned with the index of the file and relative to its start.
+fn segments(torrent: &Torrent, starts: &[u64], range: Range<u64>) -> Vec<(usize, Range<u64>)> {
+    let first = starts
+        .partition_point(|&start| start <= range.start)
+        .saturating_sub(1);
+    let mut segments = vec![];
+    for (index, &start) in starts.iter().enumerate().skip(first) {
+        let end = start + torrent.files[index].len;
+        if start >= range.end {
+            break;
+        }
+        if range.start < end {
+            segments.push((
+                index,
+                range.start.max(start) - start..range.end.min(end) - start,
+            ));
+        }
+    }
+    segments
+}
+
+/// The parts of a file of the torrent that aren't intact on disk.
+#[derive(Debug, Eq, PartialEq)]
+pub struct FileReport {
+    pub path: PathBuf,
+    /// Byte ranges of the file in incomplete pieces
+    pub incomplete: Vec<Range<u64>>,
+    /// Byte ranges of the file in corrupt pieces
+    pub corrupt: Vec<Range<u64>>,
+}
+
+impl FileReport {
+    /// Returns the file as equal if all of it is intact, or as different at
+    /// the first byte that isn't.
+    pub fn file_diff(&self) -> FileDiff {
+        let first = self
+            .incomplete
+            .iter()
+            .chain(&self.corrupt)
+            .map(|r| r.start)
+            .min();
+        match first {
+            None => FileDiff::Equal,
+            Some(offset) => FileDiff::Different(offset as usize),
+        }
+    }
+}
+
+/// Maps the states of the pieces to byte ranges of each file, with adjacent
+/// ranges merged. Padding files are left out.
+pub fn file_reports<P: AsRef<Path>>(
+    torrent: &Torrent,
+    pieces: &[Piece],
+    dir: P,
+) -> Vec<FileReport> {
+    let mut reports: Vec<FileReport> = torrent
+        .files
+        .iter()
+        .map(|file| FileReport {
+            path: dir.as_ref().join(&file.path),
+            incomplete: vec![],
+            corrupt: vec![],
+        })
+        .collect();
+    let starts = file_starts(torrent);
+    let total = torrent.total_len();
+    for (index, piece) in pieces.iter().enumerate() {
+        let start = index as u64 * torrent.piece_len;
+        let end = (start + torrent.piece_len).min(total);
+        if *piece == Piece::Ok || start >= end {
+            continue;
+        }
+        for (file, range) in segments(torrent, &starts, start..end) {
+            let ranges = match piece {
+                Piece::Corrupt => &mut reports[file].corrupt,
+                _ => &mut reports[file].incomplete,
+            };
+            match ranges.last_mut() {
+                Some(last) if last.end == range.start => last.end = range.end,
+                _ => ranges.push(range),
+            }
+        }
+    }
+    reports
+        .into_iter()
+        .zip(&torrent.files)
+        .filter(|(_, file)| !file.padding)
+        .map(|(report, _)| report)
+        .collect()
+}
+
+/// Verifies a download against a .torrent file, reporting which parts of
+/// each file are incomplete or corrupt. `dir` is the directory the torrent
+/// was downloaded into, containing the file or the directory it is named
+/// after.
+pub fn verify_torrent<P: AsRef<Path>, Q: AsRef<Path>>(
+    torrent: P,
+    dir: Q,
+) -> io::Result<Vec<FileReport>> {
+    let torrent = Torrent::read(torrent)?;
+    let pieces = verify_pieces(&torrent, &dir)?;
+    Ok(file_reports(&torrent, &pieces, dir))
+}
@@ -0,0 +1,111 @@
+use std::time::Duration;
+
+/// Parses a size such as `4096`, `4k`, `2M` or `1GiB` into bytes. Suffixes
+/// are binary multiples and case-insensitive.
+pub fn parse_size(s: &str) -> Result<usize, String> {
+    let s = s.trim();
+    let digits = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (number, suffix) = s.split_at(digits);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("invalid size '{}'", s))?;
+    let multiplier: u64 = match suffix.to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" | "kib" => 1 << 10,
+        "m" | "mb" | "mib" => 1 << 20,
+        "g" | "gb" | "gib" => 1 << 30,
+        "t" | "tb" | "tib" => 1 << 40,
+        _ => return Err(format!("invalid size suffix '{}'", suffix)),
+    };
+    number
+        .checked_mul(multiplier)
+        .and_then(|size| usize::try_from(size).ok())
+        .ok_or_else(|| format!("size '{}' is too large", s))
+}
+
+/// Parses a byte value given in decimal or as hex with a `0x` prefix, e.g.
+/// `0`, `255` or `0xFF`.
+pub fn parse_byte(s: &str) -> Result<u8, String> {
+    let s = s.trim();
+    let byte = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u8::from_str_radix(hex, 16),
+        None => s.parse(),
+    };
+    byte.map_err(|_| format!("invalid byte '{}'", s))
+}
+
+/// Parses a percentage such as `90`, `97.5` or `99%`.
+pub fn parse_percent(s: &str) -> Result<f64, String> {
+    let s = s.trim();
+    match s.strip_suffix('%').unwrap_or(s).parse::<f64>() {
+        Ok(percent) if (0.0..=100.0).contains(&percent) => Ok(percent),
+        _ => Err(format!("invalid percentage '{}'", s)),
+    }
+}
+
+/// Formats a number with commas between groups of three digits, e.g.
+/// `1,234,567`.
+pub fn format_thousands(n: u64) -> String {
+    let digits = n.to_string();
+    let mut formatted = String::new();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            formatted.push(',');
+        }
+        formatted.push(digit);
+    }
+    formatted
+}
+
+/// Formats a number of bytes with a binary unit, e.g. `1.50 MiB`.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["KiB", "MiB", "GiB", "TiB", "PiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.2} {}", size, UNITS[unit])
+}
+
+/// Parses a duration such as `30`, `30s`, `500ms`, `5m` or `1h`. A number
+/// without a suffix is in seconds.
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let digits = s
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(s.len());
+    let (number, suffix) = s.split_at(digits);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid duration '{}'", s))?;
+    let seconds = match suffix.to_ascii_lowercase().as_str() {
+        "" | "s" => number,
+        "ms" => number / 1000.0,
+        "m" => number * 60.0,
+        "h" => number * 3600.0,
+        _ => return Err(format!("invalid duration suffix '{}'", suffix)),
+    };
+    Duration::try_from_secs_f64(seconds).map_err(|_| format!("duration '{}' is too large", s))
+}
+
+/// Returns the year, month and day of a number of days since 1970-01-01,
+/// after Howard Hinnant's `civil_from_days`.
+pub(crate) fn civil_date(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = match mp < 10 {
+        true => mp + 3,
+        false => mp - 9,
+    };
+    (yoe + era * 400 + (month <= 2) as i64, month, day)
+}
@@ -0,0 +1,190 @@
+use crate::chunk::{ChunkSizer, MIN_CHUNK_SIZE};
+use crate::throttle::Throttled;
+use crate::{compare_readers, diff_at_lengths, CompareOptions, FileDiff};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, Cursor, Read};
+use std::path::{Path, PathBuf};
+
+/// What the comparison needs to know about a path.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Metadata {
+    pub len: u64,
+    pub is_dir: bool,
+}
+
+/// A filesystem the comparison core can read from. `StdFs` reads the real
+/// filesystem; other implementations can serve files that are fetched or
+/// generated in memory, e.g. when running in a browser.
+pub trait Vfs {
+    type File: Read;
+
+    fn open(&self, path: &Path) -> io::Result<Self::File>;
+
+    fn metadata(&self, path: &Path) -> io::Result<Metadata>;
+
+    /// Returns the paths of the entries in a directory.
+    fn list(&self, path: &Path) -> io::Result<Vec<PathBuf>>;
+}
+
+/// The real filesystem, through `std::fs`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StdFs;
+
+impl Vfs for StdFs {
+    type File = File;
+
+    fn open(&self, path: &Path) -> io::Result<File> {
+        File::open(path)
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+        let meta = fs::metadata(path)?;
+        Ok(Metadata {
+            len: meta.len(),
+            is_dir: meta.is_dir(),
+        })
+    }
+
+    fn list(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        fs::read_dir(path)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect()
+    }
+}
+
+/// Files held in memory. Directories exist implicitly as the parents of the
+/// files.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryFs {
+    files: BTreeMap<PathBuf, Vec<u8>>,
+}
+
+impl MemoryFs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert<P: Into<PathBuf>>(&mut self, path: P, contents: Vec<u8>) {
+        self.files.insert(path.into(), contents);
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.files
+            .keys()
+            .any(|file| file != path && file.starts_with(path))
+    }
+}
+
+impl Vfs for MemoryFs {
+    type File = Cursor<Vec<u8>>;
+
+    fn open(&self, path: &Path) -> io::Result<Self::File> {
+        match self.files.get(path) {
+            Some(contents) => Ok(Cursor::new(contents.clone())),
+            None => Err(not_found(path)),
+        }
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+        match self.files.get(path) {
+            Some(contents) => Ok(Metadata {
+                len: contents.len() as u64,
+                is_dir: false,
+            }),
+            None if self.is_dir(path) => Ok(Metadata {
+                len: 0,
+                is_dir: true,
+            }),
+            None => Err(not_found(path)),
+        }
+    }
+
+    fn list(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        if !self.is_dir(path) {
+            return Err(not_found(path));
+        }
+        let mut entries: Vec<PathBuf> = self
+            .files
+            .keys()
+            .filter_map(|file| {
+                let name = file.strip_prefix(path).ok()?.components().next()?;
+                Some(path.join(name))
+            })
+            .collect();
+        entries.dedup();
+        Ok(entries)
+    }
+}
+
+fn not_found(path: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("{} not found", path.display()),
+    )
+}
+
+/// Compares two files of a filesystem. Only the size checks and the
+/// buffered comparison apply; the platform-specific shortcuts of
+/// `compare_files_with` need the real filesystem.
+pub fn compare_files_in<V: Vfs>(
+    vfs: &V,
+    path1: &Path,
+    path2: &Path,
+    options: &CompareOptions,
+) -> io::Result<FileDiff> {
+    let len1 = vfs.metadata(path1)?.len;
+    let len2 = vfs.metadata(path2)?.len;
+    if len1 == 0 || len2 == 0 {
+        let offset = (len1 != len2).then_some(0);
+        return Ok(diff_at_lengths(offset, len1, len2, options.quick));
+    }
+    if options.quick && len1 != len2 {
+        return Ok(FileDiff::Different(0));
+    }
+
+    let throttle = options.throttle.as_deref();
+    let mut reader1 = Throttled::new(vfs.open(path1)?, throttle);
+    let mut reader2 = Throttled::new(vfs.open(path2)?, throttle);
+    let mut chunks = ChunkSizer::new(options.chunk_size, len1.min(len2), MIN_CHUNK_SIZE);
+    let offset = compare_readers(&mut reader1, &mut reader2, &mut chunks)?;
+    Ok(diff_at_lengths(offset, len1, len2, options.quick))
+}
+
+/// Compares two directory trees of a filesystem, with the same results as
+/// `DirWalk`.
+pub fn compare_dirs_in<V: Vfs>(
+    vfs: &V,
+    dir1: &Path,
+    dir2: &Path,
+    options: &CompareOptions,
+) -> io::Result<Vec<(PathBuf, FileDiff)>> {
+    let is_dir = |path: &Path| vfs.metadata(path).is_ok_and(|meta| meta.is_dir);
+    let exists = |path: &Path| vfs.metadata(path).is_ok();
+
+    let mut results = vec![];
+    let mut pending = vec![(dir1.to_path_buf(), dir2.to_path_buf())];
+    while let Some((dir1, dir2)) = pending.pop() {
+        for path in vfs.list(&dir1)? {
+            let other_path = dir2.join(path.file_name().unwrap_or_default());
+            if is_dir(&path) {
+                match is_dir(&other_path) {
+                    true => pending.push((path, other_path)),
+                    false => results.push((path, FileDiff::LeftOnly)),
+                }
+            } else if exists(&other_path) {
+                let result = compare_files_in(vfs, &path, &other_path, options)?;
+                results.push((path, result));
+            } else {
+                results.push((path, FileDiff::LeftOnly));
+            }
+        }
+        for path in vfs.list(&dir2)? {
+            let other_path = dir1.join(path.file_name().unwrap_or_default());
+            if (is_dir(&path) && !is_dir(&other_path)) || !exists(&other_path) {
+                results.push((path, FileDiff::RightOnly));
+            }
+        }
+    }
+    Ok(results)
+}
@@ -0,0 +1,553 @@
+use crate::policy::Policy;
+use crate::prefetch::{Entry, Listing};
+use crate::{compare_files_listed, long_path, metadata_settles, CompareOptions, FileDiff};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{self, Metadata, ReadDir};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+/// How directory comparisons treat symbolic links, and on Windows the
+/// junctions and mount points that name another directory the same way.
+/// Other reparse points, such as deduplicated or cloud files, are read as
+/// the files they stand for.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Links {
+    /// Walk into the directories and compare the files they name, except
+    /// for links to a directory they are in, which would loop
+    #[default]
+    Follow,
+    /// Leave them out on both sides
+    Skip,
+    /// Compare them as links, by their targets
+    Compare,
+}
+
+impl Links {
+    pub const ALL: [Links; 3] = [Links::Follow, Links::Skip, Links::Compare];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Follow => "follow",
+            Self::Skip => "skip",
+            Self::Compare => "compare",
+        }
+    }
+}
+
+impl fmt::Display for Links {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Links {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|links| links.name() == s)
+            .ok_or_else(|| format!("unknown way to treat links '{}'", s))
+    }
+}
+
+/// What an entry of a directory is for the walk.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Kind {
+    Dir,
+    File,
+    Link,
+    /// Missing, or a link left out
+    Missing,
+}
+
+/// Returns what a path is for the walk, treating links as `links` says.
+fn kind(path: &Path, links: Links) -> Kind {
+    let is_link = fs::symlink_metadata(path).is_ok_and(|meta| meta.is_symlink());
+    match (is_link, links) {
+        (true, Links::Skip) => Kind::Missing,
+        (true, Links::Compare) => Kind::Link,
+        _ if path.is_dir() => Kind::Dir,
+        // Broken links are files that fail to open
+        _ if is_link || path.exists() => Kind::File,
+        _ => Kind::Missing,
+    }
+}
+
+/// Returns what a listed entry is for the walk, as `kind` does for a path.
+fn listed_kind(entry: &Entry, links: Links) -> Kind {
+    let is_link = entry.file_type.is_symlink();
+    let is_dir = match is_link {
+        true => entry.meta.as_ref().is_some_and(Metadata::is_dir),
+        false => entry.file_type.is_dir(),
+    };
+    match (is_link, links) {
+        (true, Links::Skip) => Kind::Missing,
+        (true, Links::Compare) => Kind::Link,
+        _ if is_dir => Kind::Dir,
+        _ => Kind::File,
+    }
+}
+
+/// Adds the directory that could not be read to an error.
+fn reading(dir: &Path) -> impl FnOnce(io::Error) -> io::Error + '_ {
+    move |e| io::Error::new(e.kind(), format!("cannot read {}: {}", dir.display(), e))
+}
+
+/// Returns whether a path is a link to a directory it is in, so following
+/// it would walk the same directories again and again.
+fn loops(path: &Path) -> bool {
+    let Some(parent) = path.parent() else {
+        return false;
+    };
+    match (fs::symlink_metadata(path), fs::canonicalize(path)) {
+        (Ok(meta), Ok(target)) if meta.is_symlink() => {
+            fs::canonicalize(parent).is_ok_and(|parent| parent.starts_with(target))
+        }
+        _ => false,
+    }
+}
+
+/// Compares two links by their targets.
+fn compare_links(path1: &Path, path2: &Path) -> io::Result<FileDiff> {
+    Ok(match fs::read_link(path1)? == fs::read_link(path2)? {
+        true => FileDiff::Equal,
+        false => FileDiff::Different(0),
+    })
+}
+
+/// Iterator comparing two directory trees, yielding each result as soon as
+/// it is known. Instead of recursing it keeps a work queue of directory pairs
+/// still to be visited and reads one directory at a time, so memory use
+/// depends on the number of pending directories, not the size of the tree.
+pub struct DirWalk<'a> {
+    options: &'a CompareOptions,
+    /// The directories as given and in the form they are read through,
+    /// which on Windows lifts the MAX_PATH limit for deep trees
+    roots: [(PathBuf, PathBuf); 2],
+    pending: Vec<(PathBuf, PathBuf)>,
+    current: Option<Visit>,
+    /// Whether each directory is listed with the metadata of its entries
+    /// before its entries are compared
+    prefetch: bool,
+    /// Workers comparing pairs of files at once, with more than one job
+    pool: Option<Pool>,
+    /// Pairs that could not be compared, not yet taken
+    failed: Vec<(PathBuf, PathBuf, io::Error)>,
+}
+
+/// A pair of files to compare, with their listed metadata if they have it.
+type Job = (PathBuf, PathBuf, Option<[Metadata; 2]>);
+
+/// A pair of files and the result of comparing them.
+type Done = (PathBuf, PathBuf, io::Result<FileDiff>);
+
+/// Worker threads comparing pairs of files of a walk at once. The walk
+/// hands them pairs as it finds them and takes the results as they come,
+/// so results are in the order the comparisons finish. Everything else,
+/// such as telling what the entries are, stays with the walk.
+struct Pool {
+    /// Pairs waiting for a worker, up to one per worker
+    queue: Option<SyncSender<Job>>,
+    done: Receiver<Done>,
+    /// Pairs handed out and not taken back yet
+    in_flight: usize,
+    /// Set when the walk is dropped, so workers leave the pairs queued
+    stop: Arc<AtomicBool>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl Pool {
+    fn new(options: &CompareOptions) -> Self {
+        let (queue, jobs) = mpsc::sync_channel::<Job>(options.jobs);
+        let jobs = Arc::new(Mutex::new(jobs));
+        let (results, done) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let workers = (0..options.jobs)
+            .map(|_| {
+                let (jobs, results, stop) = (jobs.clone(), results.clone(), stop.clone());
+                let options = options.clone();
+                thread::spawn(move || loop {
+                    let job = jobs.lock().unwrap().recv();
+                    let Ok((path1, path2, metas)) = job else {
+                        return;
+                    };
+                    if stop.load(Ordering::Relaxed) {
+                        return;
+                    }
+                    let metas = metas.as_ref().map(|[meta1, meta2]| [meta1, meta2]);
+                    let result = compare_files_listed(&path1, &path2, metas, &options);
+                    if results.send((path1, path2, result)).is_err() {
+                        return;
+                    }
+                })
+            })
+            .collect();
+        Self {
+            queue: Some(queue),
+            done,
+            in_flight: 0,
+            stop,
+            workers,
+        }
+    }
+
+    /// Hands a pair to the workers, waiting while the queue is full.
+    fn submit(&mut self, job: Job) {
+        let queue = self.queue.as_ref().expect("Comparison pool is open");
+        queue.send(job).expect("Comparison threads ended");
+        self.in_flight += 1;
+    }
+
+    /// Takes the result of a comparison that finished, waiting for one if
+    /// `wait` says so and any are still running.
+    fn take(&mut self, wait: bool) -> Option<Done> {
+        if self.in_flight == 0 {
+            return None;
+        }
+        let done = match wait {
+            true => self.done.recv().ok(),
+            false => self.done.try_recv().ok(),
+        }?;
+        self.in_flight -= 1;
+        Some(done)
+    }
+}
+
+impl Drop for Pool {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        self.queue = None;
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// The directory pair being visited: first the left directory's entries are
+/// compared against the right, then the right's are checked for ones missing
+/// on the left.
+struct Visit {
+    dir1: PathBuf,
+    dir2: PathBuf,
+    pass: Pass,
+    /// Both directories with the metadata of their entries, if prefetched
+    listings: Option<Box<[Listing; 2]>>,
+}
+
+enum Pass {
+    Left(Entries),
+    Right(Entries),
+}
+
+/// The entries of a directory still to be visited: read one at a time, or
+/// from this position on in its listing.
+enum Entries {
+    Read(Box<ReadDir>),
+    Listed(usize),
+}
+
+impl Visit {
+    fn new(dir1: PathBuf, dir2: PathBuf, prefetch: bool, with_meta: bool) -> io::Result<Self> {
+        let (entries, listings) = match prefetch {
+            true => {
+                let list = |dir| Listing::read(dir, with_meta).map_err(reading(dir));
+                let listings = Box::new([list(&dir1)?, list(&dir2)?]);
+                (Entries::Listed(0), Some(listings))
+            }
+            false => {
+                let entries = fs::read_dir(&dir1).map_err(reading(&dir1))?;
+                (Entries::Read(Box::new(entries)), None)
+            }
+        };
+        Ok(Self {
+            dir1,
+            dir2,
+            pass: Pass::Left(entries),
+            listings,
+        })
+    }
+
+    /// Returns the path of the next entry of the left (0) or right (1)
+    /// directory.
+    fn next_path(&mut self, side: usize) -> Option<io::Result<PathBuf>> {
+        let entries = match &mut self.pass {
+            Pass::Left(entries) | Pass::Right(entries) => entries,
+        };
+        let dir = [&self.dir1, &self.dir2][side];
+        match entries {
+            Entries::Read(entries) => entries
+                .next()
+                .map(|entry| entry.map(|entry| entry.path()).map_err(reading(dir))),
+            Entries::Listed(next) => {
+                let path = self.listings.as_ref()?[side].paths().get(*next)?.clone();
+                *next += 1;
+                Some(Ok(path))
+            }
+        }
+    }
+
+    /// Returns what a path in the left (0) or right (1) directory is, from
+    /// the listing if it has the path. Names it hasn't are looked up, which
+    /// finds them in another case on case-insensitive filesystems.
+    fn kind(&self, side: usize, path: &Path, links: Links) -> Kind {
+        match self.listings.as_ref().and_then(|l| l[side].get(path)) {
+            Some(entry) => listed_kind(entry, links),
+            None => kind(path, links),
+        }
+    }
+
+    /// Returns the listed metadata of a pair of files, if both have it.
+    fn metas(&self, path1: &Path, path2: &Path) -> Option<[&Metadata; 2]> {
+        let [listing1, listing2] = self.listings.as_deref()?;
+        Some([
+            listing1.get(path1)?.meta.as_ref()?,
+            listing2.get(path2)?.meta.as_ref()?,
+        ])
+    }
+}
+
+impl<'a> DirWalk<'a> {
+    pub fn new<P: AsRef<Path>>(dir1: P, dir2: P, options: &'a CompareOptions) -> Self {
+        let root = |dir: &Path| (dir.to_path_buf(), long_path::extended(dir).into_owned());
+        let roots = [root(dir1.as_ref()), root(dir2.as_ref())];
+        Self {
+            options,
+            pending: vec![(roots[0].1.clone(), roots[1].1.clone())],
+            prefetch: options.prefetch.applies(&roots[0].1, &roots[1].1),
+            pool: (options.jobs > 1).then(|| Pool::new(options)),
+            roots,
+            current: None,
+            failed: vec![],
+        }
+    }
+
+    /// Returns the pairs of files that could not be compared since the last
+    /// call, e.g. because of a read error or timeout, and the pairs of
+    /// directories that could not be read, with their errors. They are left
+    /// out of the walk, which goes on with the rest.
+    pub fn take_failed(&mut self) -> Vec<(PathBuf, PathBuf, io::Error)> {
+        let [(dir1, extended1), (dir2, extended2)] = &self.roots;
+        self.failed
+            .drain(..)
+            .map(|(path1, path2, error)| {
+                (
+                    long_path::restore(path1, extended1, dir1),
+                    long_path::restore(path2, extended2, dir2),
+                    error,
+                )
+            })
+            .collect()
+    }
+}
+
+impl Iterator for DirWalk<'_> {
+    type Item = (PathBuf, FileDiff);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (path, diff) = self.walk()?;
+        let [(dir1, extended1), (dir2, extended2)] = &self.roots;
+        let path = match path.starts_with(extended1) {
+            true => long_path::restore(path, extended1, dir1),
+            false => long_path::restore(path, extended2, dir2),
+        };
+        Some((path, diff))
+    }
+}
+
+impl DirWalk<'_> {
+    fn walk(&mut self) -> Option<(PathBuf, FileDiff)> {
+        let with_meta = metadata_settles(self.options);
+        loop {
+            if let Some((path, other_path, result)) = self.pool.as_mut().and_then(|p| p.take(false))
+            {
+                match self.settle(path, other_path, result) {
+                    Some(entry) => return Some(entry),
+                    None => continue,
+                }
+            }
+            let visit = match &mut self.current {
+                Some(visit) => visit,
+                None => {
+                    let Some((dir1, dir2)) = self.pending.pop() else {
+                        // Everything is walked, only the comparisons still
+                        // running are left
+                        let (path, other_path, result) = self.pool.as_mut()?.take(true)?;
+                        match self.settle(path, other_path, result) {
+                            Some(entry) => return Some(entry),
+                            None => continue,
+                        }
+                    };
+                    let dirs = (dir1.clone(), dir2.clone());
+                    match Visit::new(dir1, dir2, self.prefetch, with_meta) {
+                        Ok(visit) => self.current.insert(visit),
+                        Err(e) => {
+                            self.failed.push((dirs.0, dirs.1, e));
+                            continue;
+                        }
+                    }
+                }
+            };
+
+            match visit.pass {
+                Pass::Left(_) => match visit.next_path(0) {
+                    Some(Ok(path)) => {
+                        let other_path = visit
+                            .dir2
+                            .join(path.file_name().expect("Failed to get filename"));
+
+                        if ignored(self.options, &path) {
+                            continue;
+                        }
+                        let links = self.options.links;
+                        let kinds = (
+                            visit.kind(0, &path, links),
+                            visit.kind(1, &other_path, links),
+                        );
+                        let result = match kinds {
+                            (Kind::Missing, _) => continue,
+                            (Kind::Dir, Kind::Dir) => {
+                                let looping = [&path, &other_path].into_iter().find(|p| loops(p));
+                                match looping.cloned() {
+                                    Some(link) => eprintln!(
+                                        "Warning: not following {}, a link to a directory it is in",
+                                        link.display()
+                                    ),
+                                    None => self.pending.push((path, other_path)),
+                                }
+                                continue;
+                            }
+                            (_, Kind::Missing) | (Kind::Dir, _) | (Kind::Link, Kind::Dir) => {
+                                Ok(FileDiff::LeftOnly)
+                            }
+                            (Kind::Link, Kind::Link) => compare_links(&path, &other_path),
+                            (Kind::Link, _) | (_, Kind::Link) => Ok(FileDiff::Different(0)),
+                            (Kind::File, _) => {
+                                let metas = visit.metas(&path, &other_path);
+                                match &mut self.pool {
+                                    Some(pool) => {
+                                        let metas = metas.map(|metas| metas.map(Metadata::clone));
+                                        pool.submit((path, other_path, metas));
+                                        continue;
+                                    }
+                                    None => compare_files_listed(
+                                        &path,
+                                        &other_path,
+                                        metas,
+                                        self.options,
+                                    ),
+                                }
+                            }
+                        };
+                        if let Some(entry) = self.settle(path, other_path, result) {
+                            return Some(entry);
+                        }
+                    }
+                    // The rest of a directory that fails to read is left out
+                    end => {
+                        if let Some(Err(e)) = end {
+                            self.failed
+                                .push((visit.dir1.clone(), visit.dir2.clone(), e));
+                        }
+                        let entries = match visit.listings {
+                            Some(_) => Ok(Entries::Listed(0)),
+                            None => fs::read_dir(&visit.dir2)
+                                .map(|entries| Entries::Read(Box::new(entries)))
+                                .map_err(reading(&visit.dir2)),
+                        };
+                        match entries {
+                            Ok(entries) => visit.pass = Pass::Right(entries),
+                            Err(e) => {
+                                self.failed
+                                    .push((visit.dir1.clone(), visit.dir2.clone(), e));
+                                self.current = None;
+                            }
+                        }
+                    }
+                },
+                Pass::Right(_) => match visit.next_path(1) {
+                    Some(Ok(path)) => {
+                        let other_path = visit
+                            .dir1
+                            .join(path.file_name().expect("Failed to get filename"));
+
+                        if ignored(self.options, &path) {
+                            continue;
+                        }
+                        // Pairs present on both sides were handled by the left pass
+                        let links = self.options.links;
+                        match (
+                            visit.kind(1, &path, links),
+                            visit.kind(0, &other_path, links),
+                        ) {
+                            (Kind::Missing, _) | (Kind::Dir, Kind::Dir) => {}
+                            (Kind::Dir, _) | (_, Kind::Missing) | (Kind::Link, Kind::Dir) => {
+                                return Some((path, FileDiff::RightOnly))
+                            }
+                            _ => {}
+                        }
+                    }
+                    end => {
+                        if let Some(Err(e)) = end {
+                            self.failed
+                                .push((visit.dir1.clone(), visit.dir2.clone(), e));
+                        }
+                        self.current = None;
+                    }
+                },
+            }
+        }
+    }
+}
+
+impl DirWalk<'_> {
+    /// Returns the result of comparing a pair of files, or keeps the pair
+    /// as failed with its error.
+    fn settle(
+        &mut self,
+        path: PathBuf,
+        other_path: PathBuf,
+        result: io::Result<FileDiff>,
+    ) -> Option<(PathBuf, FileDiff)> {
+        match result {
+            Ok(result) => Some((path, result)),
+            Err(e) => {
+                self.failed.push((path, other_path, e));
+                None
+            }
+        }
+    }
+}
+
+/// Returns whether a file is left out of the walk by the ignore policy.
+fn ignored(options: &CompareOptions, path: &Path) -> bool {
+    let policy = options.policies.as_ref().and_then(|p| p.get(path));
+    policy == Some(Policy::Ignore) && !path.is_dir()
+}
+
+/// Lists the files in a tree with their `/`-separated paths relative to it.
+pub fn list_files(dir: &Path) -> io::Result<BTreeMap<String, PathBuf>> {
+    let (root, extended) = (dir, long_path::extended(dir));
+    let mut files = BTreeMap::new();
+    let mut pending = vec![(extended.to_path_buf(), String::new())];
+    while let Some((dir, relative)) = pending.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let relative = format!("{}{}", relative, name);
+            match entry.file_type()?.is_dir() {
+                true => pending.push((entry.path(), format!("{}/", relative))),
+                false => {
+                    files.insert(relative, long_path::restore(entry.path(), &extended, root));
+                }
+            }
+        }
+    }
+    Ok(files)
+}
@@ -0,0 +1,157 @@
+use crate::media::read_at;
+use crate::{diff_at, diff_at_lengths, long_path, read_full, FileDiff};
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::ops::Range;
+use std::path::Path;
+
+/// Format tag of integer PCM samples.
+pub const PCM: u16 = 1;
+/// Format tag of WAVE_FORMAT_EXTENSIBLE, whose actual format follows in
+/// the extension.
+const EXTENSIBLE: u16 = 0xfffe;
+
+/// The chunks of a WAV file that hold its audio.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Wav {
+    /// Format of the samples, e.g. `PCM` or 3 for floating point, with an
+    /// extensible format resolved to its subformat
+    pub format: u16,
+    /// Bits of each sample of a channel
+    pub bits_per_sample: u16,
+    /// The start of the `fmt ` chunk: format, channels, sample rate, byte
+    /// rate, block alignment and bits per sample
+    pub fmt: Range<u64>,
+    /// The samples
+    pub data: Range<u64>,
+}
+
+impl Wav {
+    /// Bytes of each sample of a channel.
+    pub fn sample_size(&self) -> usize {
+        (self.bits_per_sample as usize).div_ceil(8)
+    }
+}
+
+fn invalid(path: &Path, what: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("{}: {}", path.display(), what),
+    )
+}
+
+/// Finds the format and data chunks of a WAV file, skipping the `LIST`,
+/// `bext` and other metadata chunks and the padding between chunks.
+pub fn parse<P: AsRef<Path>>(path: P) -> io::Result<Wav> {
+    let path = path.as_ref();
+    let mut file = File::open(long_path::extended(path))?;
+    let len = file.metadata()?.len();
+    let header: [u8; 12] = match len >= 12 {
+        true => read_at(&mut file, 0)?,
+        false => [0; 12],
+    };
+    if &header[..4] != b"RIFF" || &header[8..] != b"WAVE" {
+        return Err(invalid(path, "not a WAV file"));
+    }
+    let (mut format, mut data) = (None, None);
+    let mut pos = 12;
+    while pos + 8 <= len && (format.is_none() || data.is_none()) {
+        let chunk: [u8; 8] = read_at(&mut file, pos)?;
+        let size = u32::from_le_bytes(chunk[4..].try_into().unwrap()) as u64;
+        // Writers that can't seek back leave the size of the data unset
+        let body = pos + 8..(pos + 8 + size).min(len);
+        match &chunk[..4] {
+            b"fmt " if size >= 16 => {
+                let fmt: [u8; 16] = read_at(&mut file, body.start)?;
+                let mut tag = u16::from_le_bytes([fmt[0], fmt[1]]);
+                if tag == EXTENSIBLE && size >= 26 {
+                    let subformat: [u8; 2] = read_at(&mut file, body.start + 24)?;
+                    tag = u16::from_le_bytes(subformat);
+                }
+                let bits_per_sample = u16::from_le_bytes([fmt[14], fmt[15]]);
+                format = Some((tag, bits_per_sample, body.start..body.start + 16));
+            }
+            b"data" => data = Some(body.clone()),
+            _ => {}
+        }
+        // Chunks are padded to an even size
+        pos = body.end + size % 2;
+    }
+    match (format, data) {
+        (Some((format, bits_per_sample, fmt)), Some(data)) => Ok(Wav {
+            format,
+            bits_per_sample,
+            fmt,
+            data,
+        }),
+        _ => Err(invalid(path, "missing fmt or data chunk")),
+    }
+}
+
+/// Decodes a little-endian PCM sample, which is unsigned if it is 8 bits
+/// and signed otherwise.
+fn sample(bytes: &[u8]) -> i64 {
+    match bytes {
+        [byte] => *byte as i64 - 128,
+        _ => {
+            let mut value = [0; 8];
+            // In the top bytes, so shifting it down extends the sign
+            value[8 - bytes.len()..].copy_from_slice(bytes);
+            i64::from_le_bytes(value) >> (64 - 8 * bytes.len())
+        }
+    }
+}
+
+/// Compares the integer PCM samples of two WAV files, allowing each to be
+/// off by up to `tolerance` in its least significant bits, e.g. after a
+/// lossless round trip through a tool that dithers. Offsets are into the
+/// format fields followed by the samples, like comparing `media::essence`.
+pub fn compare_samples<P: AsRef<Path>>(
+    path1: P,
+    path2: P,
+    tolerance: u32,
+    quick: bool,
+) -> io::Result<FileDiff> {
+    let (path1, path2) = (path1.as_ref(), path2.as_ref());
+    let (wav1, wav2) = (parse(path1)?, parse(path2)?);
+    if wav1.format != PCM || wav1.bits_per_sample == 0 || wav1.bits_per_sample > 64 {
+        return Err(invalid(path1, "tolerance needs integer PCM samples"));
+    }
+    let mut file1 = File::open(long_path::extended(path1))?;
+    let mut file2 = File::open(long_path::extended(path2))?;
+    let fmt1: [u8; 16] = read_at(&mut file1, wav1.fmt.start)?;
+    let fmt2: [u8; 16] = read_at(&mut file2, wav2.fmt.start)?;
+    if let Some(i) = fmt1.iter().zip(&fmt2).position(|(a, b)| a != b) {
+        return Ok(diff_at(Some(i as u64), quick));
+    }
+
+    file1.seek(SeekFrom::Start(wav1.data.start))?;
+    file2.seek(SeekFrom::Start(wav2.data.start))?;
+    let (len1, len2) = (
+        wav1.data.end - wav1.data.start,
+        wav2.data.end - wav2.data.start,
+    );
+    let mut reader1 = BufReader::new(file1.take(len1));
+    let mut reader2 = BufReader::new(file2.take(len2));
+    let size = wav1.sample_size();
+    let mut buffer1 = vec![0; size * 16384];
+    let mut buffer2 = vec![0; size * 16384];
+    let mut pos = 16;
+    loop {
+        let read1 = read_full(&mut reader1, &mut buffer1)?;
+        let read2 = read_full(&mut reader2, &mut buffer2)?;
+        let samples1 = buffer1[..read1].chunks_exact(size);
+        let samples2 = buffer2[..read2].chunks_exact(size);
+        let differing = samples1
+            .zip(samples2)
+            .position(|(a, b)| sample(a).abs_diff(sample(b)) > tolerance as u64);
+        if let Some(i) = differing {
+            return Ok(diff_at(Some(pos + (i * size) as u64), quick));
+        }
+        if read1 < buffer1.len() || read2 < buffer2.len() {
+            let offset = (read1 != read2).then_some(pos + read1.min(read2) as u64);
+            return Ok(diff_at_lengths(offset, 16 + len1, 16 + len2, quick));
+        }
+        pos += read1 as u64;
+    }
+}
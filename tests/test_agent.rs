@@ -0,0 +1,76 @@
+use file_cmp::agent::{compare_records, write_records, Record, Records};
+use file_cmp::CompareOptions;
+use file_cmp::FileDiff::*;
+use std::fs;
+use std::io::{self, Cursor};
+use std::path::PathBuf;
+
+fn p(p: &str) -> String {
+    format!("./tests/testfiles/{}", p)
+}
+
+#[test]
+fn test_records() -> io::Result<()> {
+    // Test that records written by the agent read back
+    let dir = std::env::temp_dir().join("file_cmp_test_agent_records");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("sub dir"))?;
+    fs::copy(p("testing.txt"), dir.join("sub dir").join("a.txt"))?;
+
+    let mut out = vec![];
+    write_records(&dir, &CompareOptions::default(), &mut out)?;
+    let records: Vec<_> =
+        Records::new(Cursor::new(out), Default::default())?.collect::<io::Result<_>>()?;
+    assert_eq!(
+        records,
+        vec![Record {
+            path: "sub dir/a.txt".to_string(),
+            len: 10,
+            hash: "8072b0584a38dc2c57786b95ce4f0787fe5d7551971c63491c8751e7d9f58c06".to_string(),
+        }]
+    );
+
+    // Test streams that aren't from the agent or end early
+    assert!(Records::new(Cursor::new(b"hello\0"), Default::default()).is_err());
+    let truncated = b"file_cmp-agent sha256\0abc 10 a.txt";
+    let mut records = Records::new(Cursor::new(truncated), Default::default())?;
+    assert_eq!(
+        records.next().unwrap().unwrap_err().kind(),
+        io::ErrorKind::UnexpectedEof
+    );
+    Ok(())
+}
+
+#[test]
+fn test_compare_records() -> io::Result<()> {
+    // Test a local tree against records of a tree with one differing file
+    let dir = std::env::temp_dir().join("file_cmp_test_agent_compare");
+    let _ = fs::remove_dir_all(&dir);
+    for side in ["local", "remote"] {
+        fs::create_dir_all(dir.join(side))?;
+        fs::copy(p("test.txt"), dir.join(side).join("equal.txt"))?;
+    }
+    fs::copy(p("test.txt"), dir.join("local/diff.txt"))?;
+    fs::copy(p("tesx.txt"), dir.join("remote/diff.txt"))?;
+    fs::copy(p("test.txt"), dir.join("local/left.txt"))?;
+    fs::copy(p("test.txt"), dir.join("remote/right.txt"))?;
+
+    let options = CompareOptions::default();
+    let mut out = vec![];
+    write_records(dir.join("remote"), &options, &mut out)?;
+    let records = Records::new(Cursor::new(out), options.hash)?;
+    let local = dir.join("local");
+    let res = compare_records(records, &local, &options, |path| {
+        PathBuf::from("remote").join(path)
+    })?;
+    assert_eq!(
+        res,
+        vec![
+            (local.join("diff.txt"), Different(0)),
+            (local.join("equal.txt"), Equal),
+            (PathBuf::from("remote/right.txt"), RightOnly),
+            (local.join("left.txt"), LeftOnly),
+        ]
+    );
+    Ok(())
+}
@@ -0,0 +1,48 @@
+use file_cmp::long_path::from_extended;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// Test printing the relative path and both absolute paths of each entry
+#[test]
+fn test_all_paths() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_all_paths");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("a").join("sub"))?;
+    fs::create_dir_all(dir.join("b").join("sub"))?;
+    fs::write(dir.join("a").join("sub").join("changed"), "one")?;
+    fs::write(dir.join("b").join("sub").join("changed"), "two")?;
+    fs::write(dir.join("b").join("new"), "")?;
+
+    let output = Command::new(env!("CARGO_BIN_EXE_file_cmp"))
+        .current_dir(&dir)
+        .args(["-m", "--all-paths", "a", "b"])
+        .output()?;
+    // The current directory of the process has its symlinks resolved, but
+    // no \\?\ prefix on Windows
+    let root = fs::canonicalize(&dir)?;
+    let root = PathBuf::from(from_extended(&root.to_string_lossy()).into_owned());
+    let (a, b) = (root.join("a"), root.join("b"));
+    let relative = Path::new("sub").join("changed");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        format!(
+            "-3\tnew\t{}\t{}\n0\t{}\t{}\t{}\n",
+            a.join("new").display(),
+            b.join("new").display(),
+            relative.display(),
+            a.join(&relative).display(),
+            b.join(&relative).display()
+        )
+    );
+
+    // The fields are only for machine-readable output
+    let output = Command::new(env!("CARGO_BIN_EXE_file_cmp"))
+        .current_dir(&dir)
+        .env_remove("FILE_CMP_MACHINE")
+        .args(["--all-paths", "a", "b"])
+        .output()?;
+    assert!(!output.status.success());
+    fs::remove_dir_all(&dir)
+}
@@ -0,0 +1,31 @@
+use file_cmp::attributes::{attribute_names, compare_attributes, ARCHIVE, HIDDEN, READONLY};
+use std::fs;
+use std::io;
+
+// Test naming the attributes set on only one side
+#[test]
+fn test_attribute_names() {
+    assert!(attribute_names(0, 0).is_empty());
+    assert!(attribute_names(READONLY | HIDDEN, READONLY | HIDDEN).is_empty());
+    assert_eq!(attribute_names(READONLY, 0), ["readonly"]);
+    assert_eq!(attribute_names(HIDDEN, ARCHIVE), ["hidden", "archive"]);
+    // Other bits, e.g. FILE_ATTRIBUTE_DIRECTORY, are ignored
+    assert!(attribute_names(0x10, 0).is_empty());
+}
+
+// Test comparing the readonly attribute of two files
+#[test]
+fn test_compare_attributes() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_attributes");
+    fs::create_dir_all(&dir)?;
+    let (path1, path2) = (dir.join("1"), dir.join("2"));
+    fs::write(&path1, b"test")?;
+    fs::write(&path2, b"test")?;
+    assert!(compare_attributes(&path1, &path2)?.is_empty());
+
+    let mut permissions = fs::metadata(&path2)?.permissions();
+    permissions.set_readonly(true);
+    fs::set_permissions(&path2, permissions)?;
+    assert_eq!(compare_attributes(&path1, &path2)?, ["readonly"]);
+    fs::remove_dir_all(&dir)
+}
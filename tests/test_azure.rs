@@ -0,0 +1,131 @@
+#![cfg(feature = "azure")]
+
+use file_cmp::azure::{checksum_matches, compare_tree, AzureUrl, BlobInfo, Client};
+use file_cmp::CompareOptions;
+use file_cmp::FileDiff::*;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::thread;
+
+fn p(p: &str) -> String {
+    format!("./tests/testfiles/{}", p)
+}
+
+#[test]
+fn test_azure_url() {
+    let url = AzureUrl::parse("az://account/container/dir/file.bin").unwrap();
+    assert_eq!(url.account, "account");
+    assert_eq!(url.container, "container");
+    assert_eq!(url.blob, "dir/file.bin");
+    assert_eq!(AzureUrl::parse("az://account/container").unwrap().blob, "");
+    assert_eq!(AzureUrl::parse("az://account"), None);
+    assert_eq!(AzureUrl::parse("az://account//blob"), None);
+    assert_eq!(AzureUrl::parse("s3://account/container/blob"), None);
+}
+
+#[test]
+fn test_checksum_matches() -> io::Result<()> {
+    // Test blobs with and without a Content-MD5
+    let blob = |content_md5: Option<&str>| BlobInfo {
+        name: "test.txt".to_string(),
+        len: 7,
+        content_md5: content_md5.map(str::to_string),
+    };
+    let md5 = blob(Some("pVq3US8ND/RSfYmNBq/VxQ=="));
+    assert_eq!(checksum_matches(p("test.txt"), &md5)?, Some(true));
+    assert_eq!(checksum_matches(p("tesx.txt"), &md5)?, Some(false));
+    assert_eq!(checksum_matches(p("test.txt"), &blob(None))?, None);
+    Ok(())
+}
+
+/// Serves a listing of `a.txt`, `b.txt` and `d.txt` under `prefix/` over
+/// two pages. The body of `a.txt` doesn't match its Content-MD5, to tell
+/// when the checksum was used.
+fn serve() -> io::Result<String> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let endpoint = format!("http://{}", listener.local_addr()?);
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request = String::new();
+            reader.read_line(&mut request).unwrap();
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 2 {
+                line.clear();
+            }
+            let target = request.split(' ').nth(1).unwrap_or_default();
+            let (status, body) = match target {
+                t if t.starts_with("/account/container?") && t.contains("marker=page2") => (
+                    "200 OK",
+                    concat!(
+                        "<EnumerationResults><Blobs>",
+                        "<Blob><Name>prefix/d.txt</Name><Properties>",
+                        "<Content-Length>7</Content-Length><Content-MD5 />",
+                        "</Properties></Blob>",
+                        "</Blobs><NextMarker /></EnumerationResults>"
+                    ),
+                ),
+                t if t.starts_with("/account/container?") => (
+                    "200 OK",
+                    concat!(
+                        "<EnumerationResults><Blobs>",
+                        "<Blob><Name>prefix/a.txt</Name><Properties>",
+                        "<Content-Length>7</Content-Length>",
+                        "<Content-MD5>pVq3US8ND/RSfYmNBq/VxQ==</Content-MD5>",
+                        "</Properties></Blob>",
+                        "<Blob><Name>prefix/b.txt</Name><Properties>",
+                        "<Content-Length>7</Content-Length>",
+                        "<Content-MD5>AAAAAAAAAAAAAAAAAAAAAA==</Content-MD5>",
+                        "</Properties></Blob>",
+                        "</Blobs><NextMarker>page2</NextMarker></EnumerationResults>"
+                    ),
+                ),
+                "/account/container/prefix/a.txt" => ("200 OK", "xxxxxxx"),
+                "/account/container/prefix/b.txt" | "/account/container/prefix/d.txt" => {
+                    ("200 OK", "test \r\n")
+                }
+                _ => ("404 Not Found", ""),
+            };
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+    });
+    Ok(endpoint)
+}
+
+#[test]
+fn test_compare_tree() -> io::Result<()> {
+    // Test a local tree against a prefix, with matching and mismatching
+    // checksums
+    let dir = std::env::temp_dir().join("file_cmp_test_azure_tree");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir)?;
+    fs::copy(p("test.txt"), dir.join("a.txt"))?;
+    fs::copy(p("tesx.txt"), dir.join("b.txt"))?;
+    fs::copy(p("test.txt"), dir.join("c.txt"))?;
+
+    let client = Client::from_env(None).with_endpoint(&serve()?);
+    let url = AzureUrl::parse("az://account/container/prefix").unwrap();
+    let res = compare_tree(&client, &url, &dir, &CompareOptions::default())?;
+    assert_eq!(
+        res,
+        vec![
+            (dir.join("a.txt"), Equal),
+            (dir.join("b.txt"), Different(3)),
+            (dir.join("c.txt"), LeftOnly),
+            (
+                PathBuf::from("az://account/container/prefix/d.txt"),
+                RightOnly
+            ),
+        ]
+    );
+    fs::remove_dir_all(&dir)
+}
@@ -0,0 +1,34 @@
+use std::io;
+use std::process::Command;
+
+// Test comparing several candidates with a baseline file
+#[test]
+fn test_baseline() -> io::Result<()> {
+    let output = Command::new(env!("CARGO_BIN_EXE_file_cmp"))
+        .current_dir("tests/testfiles")
+        .env_remove("FILE_CMP_MACHINE")
+        .env_remove("FILE_CMP_DIFFS_ONLY")
+        .args([
+            "--baseline",
+            "test.txt",
+            "tesx.txt",
+            "missing.txt",
+            "test.txt",
+        ])
+        .output()?;
+    assert!(!output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "3\ttesx.txt\t(diff)\n-1\ttest.txt\t(equal)\n"
+    );
+    assert!(String::from_utf8_lossy(&output.stderr).contains("missing.txt"));
+
+    // A single candidate is reported the same way
+    let output = Command::new(env!("CARGO_BIN_EXE_file_cmp"))
+        .current_dir("tests/testfiles")
+        .args(["-m", "-d", "--baseline", "test.txt", "test.txt"])
+        .output()?;
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"");
+    Ok(())
+}
@@ -0,0 +1,93 @@
+use file_cmp::batch::Pairs;
+use std::io::{self, Cursor};
+use std::path::PathBuf;
+
+fn pairs(list: &[u8]) -> io::Result<Vec<(PathBuf, PathBuf)>> {
+    Pairs::new(Cursor::new(list)).collect()
+}
+
+#[test]
+fn test_pairs() -> io::Result<()> {
+    // Test tab and NUL separators, CRLF line endings and blank lines
+    let res = pairs(b"a.txt\tb.txt\r\n\nc d.txt\0e.txt\n")?;
+    assert_eq!(
+        res,
+        vec![
+            (PathBuf::from("a.txt"), PathBuf::from("b.txt")),
+            (PathBuf::from("c d.txt"), PathBuf::from("e.txt")),
+        ]
+    );
+    assert_eq!(pairs(b"")?, vec![]);
+    Ok(())
+}
+
+#[test]
+fn test_pairs_invalid() {
+    // Test when a line doesn't contain two paths
+    let err = pairs(b"a.txt\tb.txt\nc.txt\n").unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert!(err.to_string().starts_with("line 2:"));
+    assert!(pairs(b"a.txt\t\n").is_err());
+}
+
+#[test]
+fn test_pairs_nul_terminated() -> io::Result<()> {
+    // Test NUL-terminated paths, which may contain tabs and newlines
+    let list = b"a\tb.txt\0c.txt\0d\n.txt\0e.txt";
+    let res: Vec<_> = Pairs::nul_terminated(Cursor::new(list)).collect::<io::Result<_>>()?;
+    assert_eq!(
+        res,
+        vec![
+            (PathBuf::from("a\tb.txt"), PathBuf::from("c.txt")),
+            (PathBuf::from("d\n.txt"), PathBuf::from("e.txt")),
+        ]
+    );
+
+    // Test when the last pair is missing its second path
+    let mut pairs = Pairs::nul_terminated(Cursor::new(b"a.txt\0b.txt\0c.txt\0"));
+    assert!(pairs.next().unwrap().is_ok());
+    let err = pairs.next().unwrap().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "path 3: missing the second path of the pair"
+    );
+    Ok(())
+}
+
+// Test NUL-terminated paths that aren't valid UTF-8, as find -print0 lists them
+#[cfg(unix)]
+#[test]
+fn test_pairs_non_utf8() -> io::Result<()> {
+    use std::ffi::OsStr;
+    use std::fs;
+    use std::io::Write;
+    use std::os::unix::ffi::OsStrExt;
+    use std::process::{Command, Stdio};
+
+    let name = OsStr::from_bytes(b"caf\xe9.txt");
+    let list = b"caf\xe9.txt\0b.txt\0";
+    let res: Vec<_> = Pairs::nul_terminated(Cursor::new(list)).collect::<io::Result<_>>()?;
+    assert_eq!(res, vec![(PathBuf::from(name), PathBuf::from("b.txt"))]);
+    assert_eq!(
+        pairs(b"caf\xe9.txt\tb.txt\n")?,
+        vec![(PathBuf::from(name), PathBuf::from("b.txt"))]
+    );
+
+    let dir = std::env::temp_dir().join("file_cmp_test_pairs_non_utf8");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join(name), "same")?;
+    fs::write(dir.join("b.txt"), "same")?;
+    let mut child = Command::new(env!("CARGO_BIN_EXE_file_cmp"))
+        .current_dir(&dir)
+        .env("FILE_CMP_MACHINE", "1")
+        .args(["-0", "--pairs-from-stdin"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+    child.stdin.take().unwrap().write_all(list)?;
+    let output = child.wait_with_output()?;
+    assert!(output.status.success());
+    assert!(output.stdout.starts_with(b"-1\t"));
+    fs::remove_dir_all(&dir)
+}
@@ -0,0 +1,29 @@
+use file_cmp::bench;
+use file_cmp::mmap::compare_slices;
+use std::io;
+
+fn p(p: &str) -> String {
+    format!("./tests/testfiles/{}", p)
+}
+
+#[test]
+fn test_bench_runs_every_mode() -> io::Result<()> {
+    // Test that every mode is timed the requested number of times
+    let mut reported = 0;
+    let results = bench::run(p("test.txt"), p("text.txt"), 2, 2, false, |_| reported += 1)?;
+    assert_eq!(results.len(), bench::cases(2).len());
+    assert_eq!(reported, results.len());
+    assert!(results.iter().all(|r| r.times.len() == 2 && r.bytes == 14));
+    Ok(())
+}
+
+#[test]
+fn test_compare_slices() {
+    // Test mapped data is compared like chunks of readers
+    let data: Vec<u8> = (0..3_000_000).map(|i| (i % 7) as u8).collect();
+    let mut other = data.clone();
+    assert_eq!(compare_slices(&data, &other), None);
+    other[2_500_000] = 99;
+    assert_eq!(compare_slices(&data, &other), Some(2_500_000));
+    assert_eq!(compare_slices(&data[..1_048_576], &data), Some(1_048_576));
+}
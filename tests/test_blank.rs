@@ -0,0 +1,47 @@
+use file_cmp::blank::{filler, folded_hash, BLOCK_SIZE};
+use file_cmp::hash::HashAlgorithm;
+use std::io;
+
+fn image(blocks: &[u8], tail: &[u8]) -> Vec<u8> {
+    let mut data = vec![];
+    for &b in blocks {
+        data.extend(std::iter::repeat_n(b, BLOCK_SIZE));
+    }
+    data.extend_from_slice(tail);
+    data
+}
+
+fn folded(data: &[u8]) -> io::Result<Vec<u8>> {
+    folded_hash(&mut &data[..], HashAlgorithm::Sha256)
+}
+
+#[test]
+fn test_filler() {
+    assert_eq!(filler(&[0; 4096]), Some(0));
+    assert_eq!(filler(&[0xff; 13]), Some(0xff));
+    assert_eq!(filler(&[7; 64]), None);
+    let mut block = [0; 4096];
+    block[4095] = 1;
+    assert_eq!(filler(&block), None);
+    assert_eq!(filler(&[]), None);
+}
+
+#[test]
+fn test_folded_hash() -> io::Result<()> {
+    // Test that folded digests tell apart exactly what the data tells apart
+    let a = image(&[0, 0, 0, 0xff, 5, 0, 0], b"end");
+    assert_eq!(folded(&a)?, folded(&a.clone())?);
+    assert_ne!(
+        folded(&a)?,
+        folded(&image(&[0, 0, 0xff, 0xff, 5, 0, 0], b"end"))?
+    );
+    assert_ne!(folded(&a)?, folded(&image(&[0, 0, 0, 0xff, 5, 0], b"end"))?);
+    assert_ne!(
+        folded(&a)?,
+        folded(&image(&[0, 0, 0, 0xff, 5, 0, 0], b"enD"))?
+    );
+    let mut b = a.clone();
+    b[BLOCK_SIZE + 100] = 1;
+    assert_ne!(folded(&a)?, folded(&b)?);
+    Ok(())
+}
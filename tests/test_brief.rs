@@ -0,0 +1,35 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::process::Command;
+
+fn brief(dir1: &Path, dir2: &Path, machine: bool) -> io::Result<String> {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_file_cmp"));
+    if machine {
+        command.arg("-m");
+    }
+    let output = command.arg("--brief").arg(dir1).arg(dir2).output()?;
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+// Test the single-line verdict for directories
+#[test]
+fn test_brief() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_brief");
+    let (a, b) = (dir.join("a"), dir.join("b"));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&a)?;
+    fs::create_dir_all(&b)?;
+    for path in [a.join("same"), b.join("same")] {
+        fs::write(path, "same")?;
+    }
+    assert_eq!(brief(&a, &b, false)?, "Directories are identical");
+
+    fs::write(a.join("changed"), "one")?;
+    fs::write(b.join("changed"), "two")?;
+    assert_eq!(brief(&a, &b, false)?, "Directories differ (1 file)");
+    fs::write(b.join("new"), "")?;
+    assert_eq!(brief(&a, &b, false)?, "Directories differ (2 files)");
+    assert_eq!(brief(&a, &b, true)?, "2");
+    Ok(())
+}
@@ -0,0 +1,69 @@
+use file_cmp::capability::{capability_names, compare_capabilities, Capabilities};
+use std::fs;
+use std::io;
+
+fn value(words: &[u32]) -> Vec<u8> {
+    words.iter().flat_map(|word| word.to_le_bytes()).collect()
+}
+
+// Test parsing the revisions of security.capability
+#[test]
+fn test_parse() {
+    // cap_net_bind_service+ep, as setcap writes it
+    let bind = Capabilities::parse(&value(&[0x0200_0001, 1 << 10, 0, 0, 0])).unwrap();
+    assert_eq!(
+        bind,
+        Capabilities {
+            permitted: 1 << 10,
+            inheritable: 0,
+            effective: true,
+            root_id: None,
+        }
+    );
+    let namespaced = Capabilities::parse(&value(&[0x0300_0000, 0, 1 << 7, 0, 1, 1000])).unwrap();
+    assert_eq!(namespaced.permitted, 0);
+    assert_eq!(namespaced.inheritable, 1 << 7 | 1 << 32);
+    assert!(!namespaced.effective);
+    assert_eq!(namespaced.root_id, Some(1000));
+
+    assert_eq!(Capabilities::parse(&value(&[0x0200_0001, 1 << 10])), None);
+    assert_eq!(Capabilities::parse(&value(&[0x0100_0000, 0, 0])), None);
+}
+
+// Test naming the capabilities only one of the files has
+#[test]
+fn test_capability_names() {
+    let bind = Capabilities {
+        permitted: 1 << 10,
+        effective: true,
+        ..Default::default()
+    };
+    assert!(capability_names(None, None).is_empty());
+    assert!(capability_names(Some(bind), Some(bind)).is_empty());
+    assert_eq!(capability_names(Some(bind), None), ["cap_net_bind_service"]);
+    let raw = Capabilities {
+        permitted: 1 << 10 | 1 << 13,
+        ..bind
+    };
+    assert_eq!(capability_names(Some(bind), Some(raw)), ["cap_net_raw"]);
+    let ineffective = Capabilities {
+        effective: false,
+        ..bind
+    };
+    assert_eq!(
+        capability_names(Some(bind), Some(ineffective)),
+        ["capabilities"]
+    );
+}
+
+// Test comparing files without capabilities
+#[test]
+fn test_compare_capabilities() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_capability");
+    fs::create_dir_all(&dir)?;
+    let (path1, path2) = (dir.join("1"), dir.join("2"));
+    fs::write(&path1, b"test")?;
+    fs::write(&path2, b"test")?;
+    assert!(compare_capabilities(&path1, &path2)?.is_empty());
+    fs::remove_dir_all(&dir)
+}
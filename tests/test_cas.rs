@@ -0,0 +1,59 @@
+use file_cmp::cas::{expected_hash, verify_store};
+use file_cmp::hash::HashAlgorithm::Sha256;
+use file_cmp::FileDiff::*;
+use std::fs;
+use std::io;
+
+// SHA-256 of "test \r\n"
+const TEST_SHA256: &str = "6722aeed5f5d8297de4eb2cba1c5abb900afe4a4cd64b10e2835463087936666";
+
+#[test]
+fn test_expected_hash() {
+    let expected = Some((Sha256, TEST_SHA256.to_string()));
+    assert_eq!(
+        expected_hash(&format!("blobs/sha256/{}", TEST_SHA256)),
+        expected
+    );
+    assert_eq!(expected_hash(&format!("sha256:{}", TEST_SHA256)), expected);
+    assert_eq!(
+        expected_hash(&format!("cache/sha256-{}", TEST_SHA256)),
+        expected
+    );
+    assert_eq!(expected_hash(TEST_SHA256), expected);
+    assert_eq!(
+        expected_hash(&format!("67/{}", &TEST_SHA256[2..])),
+        expected
+    );
+    assert_eq!(expected_hash(&TEST_SHA256.to_uppercase()), expected);
+    assert_eq!(expected_hash("blobs/sha256/abcd"), None);
+    assert_eq!(expected_hash("index.json"), None);
+    assert_eq!(expected_hash("my-file.txt"), None);
+}
+
+#[test]
+fn test_verify_store() -> io::Result<()> {
+    // Test a store with an intact blob, a corrupt one and an unrelated file
+    let dir = std::env::temp_dir().join("file_cmp_test_cas");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("sha256"))?;
+    fs::copy(
+        "./tests/testfiles/test.txt",
+        dir.join("sha256").join(TEST_SHA256),
+    )?;
+    let corrupt = "0".repeat(64);
+    fs::copy(
+        "./tests/testfiles/test.txt",
+        dir.join("sha256").join(&corrupt),
+    )?;
+    fs::write(dir.join("index.json"), "{}")?;
+
+    let res = verify_store(&dir, None)?;
+    assert_eq!(
+        res,
+        vec![
+            (dir.join("sha256").join(&corrupt), Different(0)),
+            (dir.join("sha256").join(TEST_SHA256), Equal),
+        ]
+    );
+    Ok(())
+}
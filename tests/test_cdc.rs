@@ -0,0 +1,110 @@
+use file_cmp::cdc::{chunks, compare_cdc, compare_chunks, ChunkSizes};
+use file_cmp::hash::HashAlgorithm;
+use file_cmp::FileDiff::*;
+use file_cmp::{CompareOptions, Side};
+use std::fs;
+use std::io::{self, Cursor};
+use std::process::Command;
+
+/// Deterministic data without repeating chunks.
+fn data(len: usize) -> Vec<u8> {
+    let mut state = 0x2545_f491_u32;
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as u8
+        })
+        .collect()
+}
+
+// Test cutting data into chunks within the bounds
+#[test]
+fn test_chunks() -> io::Result<()> {
+    let data = data(1 << 20);
+    let sizes = ChunkSizes::new(4096);
+    let all = chunks(&mut Cursor::new(&data), sizes, HashAlgorithm::Sha256)?;
+    let mut offset = 0;
+    for (i, chunk) in all.iter().enumerate() {
+        assert_eq!(chunk.offset, offset);
+        assert!(chunk.len <= sizes.max);
+        assert!(chunk.len >= sizes.min || i == all.len() - 1);
+        offset += chunk.len as u64;
+    }
+    assert_eq!(offset, 1 << 20);
+    // Normalized around the average
+    let avg = (1 << 20) / all.len();
+    assert!((2048..8192).contains(&avg), "average {}", avg);
+    assert!(chunks(&mut Cursor::new(&[]), sizes, HashAlgorithm::Sha256)?.is_empty());
+    Ok(())
+}
+
+// Test localizing changes to the chunks around them
+#[test]
+fn test_compare_chunks() -> io::Result<()> {
+    let original = data(1 << 20);
+    let sizes = ChunkSizes::new(4096);
+    let chunk = |data: &[u8]| chunks(&mut Cursor::new(data), sizes, HashAlgorithm::Sha256);
+    let chunks1 = chunk(&original)?;
+
+    let diff = compare_chunks(&chunks1, &chunks1);
+    assert_eq!(diff.file_diff, Equal);
+    assert!(diff.changed1.is_empty() && diff.changed2.is_empty());
+
+    let mut inserted = original.clone();
+    inserted.splice(500_000..500_000, *b"inserted");
+    let chunks2 = chunk(&inserted)?;
+    let diff = compare_chunks(&chunks1, &chunks2);
+    let Different(offset) = diff.file_diff else {
+        panic!("expected a difference, got {:?}", diff.file_diff);
+    };
+    assert!(offset <= 500_000 && offset + sizes.max >= 500_000);
+    assert_eq!(diff.changed1.len(), 1);
+    assert_eq!(diff.changed2.len(), 1);
+    assert!(diff.changed1[0].contains(&499_999) || diff.changed1[0].contains(&500_000));
+    assert!(diff.changed2[0].contains(&500_000));
+    assert!(diff.changed2[0].end - diff.changed2[0].start <= 3 * sizes.max as u64);
+
+    let diff = compare_chunks(&chunk(&original[..1000])?, &chunk(&original[..1000])?);
+    assert_eq!(diff.file_diff, Equal);
+    let diff = compare_chunks(&chunks1[..10], &chunks1);
+    assert_eq!(
+        diff.file_diff,
+        Truncated(Side::Left, chunks1[10].offset as usize)
+    );
+    Ok(())
+}
+
+// Test comparing files by their chunks, and the output of --cdc
+#[test]
+fn test_compare_cdc() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_cdc");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir)?;
+    let mut data = data(1 << 20);
+    fs::write(dir.join("a"), &data)?;
+    fs::write(dir.join("b"), &data)?;
+    let options = CompareOptions::default();
+    let diff = compare_cdc(dir.join("a"), dir.join("b"), 4096, &options)?;
+    assert_eq!(diff.file_diff, Equal);
+
+    data[700_000] ^= 1;
+    fs::write(dir.join("b"), &data)?;
+    let diff = compare_cdc(dir.join("a"), dir.join("b"), 4096, &options)?;
+    let range = diff.changed1[0].clone();
+    assert_eq!(diff.file_diff, Different(range.start as usize));
+    assert_eq!(diff.changed1, diff.changed2);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_file_cmp"))
+        .current_dir(&dir)
+        .env_remove("FILE_CMP_MACHINE")
+        .args(["-m", "--cdc=4k", "a", "b"])
+        .output()?;
+    let ranges = format!("{}-{}", range.start, range.end - 1);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        format!("{}\t{}\t{}", range.start, ranges, ranges)
+    );
+    fs::remove_dir_all(&dir)
+}
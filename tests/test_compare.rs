@@ -1,73 +1,268 @@
-use file_cmp::compare_files;
-use file_cmp::FileDiff::*;
-use std::io;
-
-fn p(p: &str) -> String {
-    format!(".\\tests\\testfiles\\{}", p)
-}
-
-#[test]
-fn test_compare_files_equal() -> io::Result<()> {
-    // Test when files are equal
-    let res = compare_files(p("test.txt"), p("test.txt"), false)?;
-    assert_eq!(res, Equal);
-    Ok(())
-}
-
-#[test]
-fn test_compare_files_differ_beginning() -> io::Result<()> {
-    // Test when files differ at the beginning
-    let res = compare_files(p("west.txt"), p("test.txt"), false)?;
-    assert_eq!(res, Different(0));
-    let res = compare_files(p("test.txt"), p("west.txt"), false)?;
-    assert_eq!(res, Different(0));
-    Ok(())
-}
-
-#[test]
-fn test_compare_files_differ_end() -> io::Result<()> {
-    // Test when files differ at the end
-    let res = compare_files(p("test.txt"), p("tesx.txt"), false)?;
-    assert_eq!(res, Different(3));
-    let res = compare_files(p("tesx.txt"), p("test.txt"), false)?;
-    assert_eq!(res, Different(3));
-    Ok(())
-}
-
-#[test]
-fn test_compare_files_middle() -> io::Result<()> {
-    // Test when files differ in the middle
-    let res = compare_files(p("test.txt"), p("text.txt"), false)?;
-    assert_eq!(res, Different(2));
-    let res = compare_files(p("text.txt"), p("test.txt"), false)?;
-    assert_eq!(res, Different(2));
-    Ok(())
-}
-
-#[test]
-fn test_compare_files_one_shorter() -> io::Result<()> {
-    // Test when file1 is shorter than file2
-    let res = compare_files(p("testing.txt"), p("test.txt"), false)?;
-    assert_eq!(res, Different(4));
-    let res = compare_files(p("test.txt"), p("testing.txt"), false)?;
-    assert_eq!(res, Different(4));
-    Ok(())
-}
-
-#[test]
-fn test_compare_files_one_emtpy() -> io::Result<()> {
-    // Test when file1 is empty
-    let res = compare_files(p("emptyfile.txt"), p("test.txt"), false)?;
-    assert_eq!(res, Different(0));
-    let res = compare_files(p("test.txt"), p("emptyfile.txt"), false)?;
-    assert_eq!(res, Different(0));
-    Ok(())
-}
-
-#[test]
-fn test_compare_files_both_emtpy() -> io::Result<()> {
-    // Test when file1 is empty
-    let res = compare_files(p("emptyfile.txt"), p("emptyfile.txt"), false)?;
-    assert_eq!(res, Equal);
-    Ok(())
-}
+use file_cmp::chunk::ChunkSizer;
+use file_cmp::same_file::is_same_file;
+use file_cmp::units::{format_thousands, parse_duration, parse_percent, parse_size};
+use file_cmp::FileDiff::*;
+use file_cmp::{compare_files, compare_files_with, CompareOptions, Policy, Side};
+use std::fs;
+use std::io;
+use std::time::Duration;
+
+fn p(p: &str) -> String {
+    format!("./tests/testfiles/{}", p)
+}
+
+fn quick() -> CompareOptions {
+    CompareOptions {
+        quick: true,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_compare_files_equal() -> io::Result<()> {
+    // Test when files are equal
+    let res = compare_files(p("test.txt"), p("test.txt"), Policy::ByteExact)?;
+    assert_eq!(res, Equal);
+    Ok(())
+}
+
+#[test]
+fn test_compare_files_differ_beginning() -> io::Result<()> {
+    // Test when files differ at the beginning
+    let res = compare_files(p("west.txt"), p("test.txt"), Policy::ByteExact)?;
+    assert_eq!(res, Different(0));
+    let res = compare_files(p("test.txt"), p("west.txt"), Policy::ByteExact)?;
+    assert_eq!(res, Different(0));
+    Ok(())
+}
+
+#[test]
+fn test_compare_files_differ_end() -> io::Result<()> {
+    // Test when files differ at the end
+    let res = compare_files(p("test.txt"), p("tesx.txt"), Policy::ByteExact)?;
+    assert_eq!(res, Different(3));
+    let res = compare_files(p("tesx.txt"), p("test.txt"), Policy::ByteExact)?;
+    assert_eq!(res, Different(3));
+    Ok(())
+}
+
+#[test]
+fn test_compare_files_middle() -> io::Result<()> {
+    // Test when files differ in the middle
+    let res = compare_files(p("test.txt"), p("text.txt"), Policy::ByteExact)?;
+    assert_eq!(res, Different(2));
+    let res = compare_files(p("text.txt"), p("test.txt"), Policy::ByteExact)?;
+    assert_eq!(res, Different(2));
+    Ok(())
+}
+
+#[test]
+fn test_compare_files_one_shorter() -> io::Result<()> {
+    // Test when file1 is shorter than file2
+    let res = compare_files(p("testing.txt"), p("test.txt"), Policy::ByteExact)?;
+    assert_eq!(res, Different(4));
+    let res = compare_files(p("test.txt"), p("testing.txt"), Policy::ByteExact)?;
+    assert_eq!(res, Different(4));
+    Ok(())
+}
+
+#[test]
+fn test_compare_files_one_emtpy() -> io::Result<()> {
+    // Test when file1 is empty
+    let res = compare_files(p("emptyfile.txt"), p("test.txt"), Policy::ByteExact)?;
+    assert_eq!(res, Truncated(Side::Left, 0));
+    let res = compare_files(p("test.txt"), p("emptyfile.txt"), Policy::ByteExact)?;
+    assert_eq!(res, Truncated(Side::Right, 0));
+    let res = compare_files_with(p("test.txt"), p("emptyfile.txt"), &quick())?;
+    assert_eq!(res, Different(0));
+    Ok(())
+}
+
+#[test]
+fn test_compare_files_prefix() -> io::Result<()> {
+    // Test when file1 is a prefix of file2, as after an interrupted copy
+    let dir = std::env::temp_dir().join("file_cmp_test_prefix");
+    fs::create_dir_all(&dir)?;
+    let (partial, full) = (dir.join("partial"), dir.join("full"));
+    fs::write(&partial, "abcd")?;
+    fs::write(&full, "abcdef")?;
+    assert_eq!(
+        compare_files(&partial, &full, Policy::ByteExact)?,
+        Truncated(Side::Left, 4)
+    );
+    assert_eq!(
+        compare_files(&full, &partial, Policy::ByteExact)?,
+        Truncated(Side::Right, 4)
+    );
+    assert_eq!(compare_files_with(&partial, &full, &quick())?, Different(0));
+    let mmap = CompareOptions {
+        mmap: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        compare_files_with(&full, &partial, &mmap)?,
+        Truncated(Side::Right, 4)
+    );
+
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_file_cmp"))
+        .args([&partial, &full])
+        .output()?;
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        format!("EOF on {} after byte 4", partial.display())
+    );
+    fs::remove_dir_all(&dir)
+}
+
+#[test]
+fn test_compare_files_both_emtpy() -> io::Result<()> {
+    // Test when file1 is empty
+    let res = compare_files(p("emptyfile.txt"), p("emptyfile.txt"), Policy::ByteExact)?;
+    assert_eq!(res, Equal);
+    Ok(())
+}
+
+#[test]
+fn test_is_same_file() -> io::Result<()> {
+    // Test the same file spelled differently, and two different files
+    assert!(is_same_file(p("test.txt"), p("../testfiles/test.txt"))?);
+    assert!(!is_same_file(p("test.txt"), p("tesx.txt"))?);
+    let res = compare_files(p("test.txt"), p("./test.txt"), Policy::ByteExact)?;
+    assert_eq!(res, Equal);
+    Ok(())
+}
+
+#[test]
+fn test_compare_files_small_chunks() -> io::Result<()> {
+    // Test that results don't depend on the chunk size
+    for chunk_size in 1..8 {
+        let options = CompareOptions {
+            chunk_size: Some(chunk_size),
+            ..Default::default()
+        };
+        let res = compare_files_with(p("test.txt"), p("text.txt"), &options)?;
+        assert_eq!(res, Different(2));
+        let res = compare_files_with(p("testing.txt"), p("test.txt"), &options)?;
+        assert_eq!(res, Different(4));
+        let res = compare_files_with(p("test.txt"), p("test.txt"), &options)?;
+        assert_eq!(res, Equal);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_adaptive_chunk_size() {
+    // Test that adaptive chunks grow from the block size up to the data length
+    let mut chunks = ChunkSizer::adaptive(100_000, 4096);
+    assert_eq!(chunks.size(), 4096);
+    chunks.grow();
+    assert_eq!(chunks.size(), 8192);
+    for _ in 0..10 {
+        chunks.grow();
+    }
+    assert_eq!(chunks.size(), 131072);
+}
+
+#[test]
+fn test_parse_size() {
+    assert_eq!(parse_size("4096"), Ok(4096));
+    assert_eq!(parse_size("4k"), Ok(4096));
+    assert_eq!(parse_size("2M"), Ok(2 * 1024 * 1024));
+    assert_eq!(parse_size("1GiB"), Ok(1024 * 1024 * 1024));
+    assert!(parse_size("12x").is_err());
+    assert!(parse_size("k").is_err());
+}
+
+#[test]
+fn test_parse_duration() {
+    assert_eq!(parse_duration("30"), Ok(Duration::from_secs(30)));
+    assert_eq!(parse_duration("1.5s"), Ok(Duration::from_millis(1500)));
+    assert_eq!(parse_duration("500ms"), Ok(Duration::from_millis(500)));
+    assert_eq!(parse_duration("5m"), Ok(Duration::from_secs(300)));
+    assert!(parse_duration("5x").is_err());
+    assert!(parse_duration("s").is_err());
+}
+
+#[test]
+fn test_format_thousands() {
+    assert_eq!(format_thousands(0), "0");
+    assert_eq!(format_thousands(999), "999");
+    assert_eq!(format_thousands(1000), "1,000");
+    assert_eq!(format_thousands(1234567), "1,234,567");
+    assert_eq!(format_thousands(123456789012), "123,456,789,012");
+}
+
+#[test]
+fn test_parse_percent() {
+    assert_eq!(parse_percent("90"), Ok(90.0));
+    assert_eq!(parse_percent("97.5%"), Ok(97.5));
+    assert!(parse_percent("101").is_err());
+    assert!(parse_percent("-1").is_err());
+    assert!(parse_percent("%").is_err());
+}
+
+#[test]
+fn test_compare_files_mmap() -> io::Result<()> {
+    // Test comparing memory-mapped files
+    let options = CompareOptions {
+        mmap: true,
+        ..Default::default()
+    };
+    let res = compare_files_with(p("test.txt"), p("tesx.txt"), &options)?;
+    assert_eq!(res, Different(3));
+    let res = compare_files_with(p("test.txt"), p("testing.txt"), &options)?;
+    assert_eq!(res, Different(4));
+    let res = compare_files_with(p("text.txt"), p("text.txt"), &options)?;
+    assert_eq!(res, Equal);
+    Ok(())
+}
+
+#[test]
+fn test_compare_files_policies() -> io::Result<()> {
+    // Test each policy on files with the same size but different contents,
+    // times and permissions
+    let dir = std::env::temp_dir().join("file_cmp_test_policies");
+    fs::create_dir_all(&dir)?;
+    let (a, b) = (dir.join("a"), dir.join("b"));
+    fs::write(&a, "abcdef")?;
+    fs::write(&b, "abcxef")?;
+    let time = std::time::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    fs::File::options()
+        .write(true)
+        .open(&a)?
+        .set_modified(time)?;
+    fs::File::options()
+        .write(true)
+        .open(&b)?
+        .set_modified(time)?;
+    let mut readonly = fs::metadata(&b)?.permissions();
+    readonly.set_readonly(true);
+    fs::set_permissions(&b, readonly.clone())?;
+
+    assert_eq!(compare_files(&a, &b, Policy::ByteExact)?, Different(3));
+    assert_eq!(compare_files(&a, &b, Policy::HashOnly)?, Different(0));
+    assert_eq!(compare_files(&a, &b, Policy::SizeOnly)?, Equal);
+    assert_eq!(compare_files(&a, &b, Policy::SizeAndMtime)?, Equal);
+    assert_eq!(compare_files(&a, &b, Policy::MetadataOnly)?, Different(0));
+    assert_eq!(compare_files(&a, &a, Policy::HashOnly)?, Equal);
+
+    let later = time + Duration::from_secs(2);
+    fs::File::options()
+        .write(true)
+        .open(&a)?
+        .set_modified(later)?;
+    assert_eq!(compare_files(&a, &b, Policy::SizeAndMtime)?, Different(0));
+    fs::File::options()
+        .write(true)
+        .open(&a)?
+        .set_modified(time)?;
+    fs::set_permissions(&a, readonly)?;
+    assert_eq!(compare_files(&a, &b, Policy::MetadataOnly)?, Equal);
+
+    let mut writable = fs::metadata(&a)?.permissions();
+    #[allow(clippy::permissions_set_readonly_false)]
+    writable.set_readonly(false);
+    fs::set_permissions(&a, writable.clone())?;
+    fs::set_permissions(&b, writable)?;
+    fs::remove_dir_all(&dir)
+}
@@ -0,0 +1,20 @@
+use std::io;
+use std::process::Command;
+
+// Test generating completion scripts, with the values of enumerated flags
+#[test]
+fn test_completions() -> io::Result<()> {
+    for shell in ["bash", "zsh", "fish", "powershell", "elvish"] {
+        let output = Command::new(env!("CARGO_BIN_EXE_file_cmp"))
+            .args(["completions", shell])
+            .output()?;
+        assert!(output.status.success(), "{}", shell);
+        let script = String::from_utf8_lossy(&output.stdout);
+        assert!(script.contains("--hash-first"), "{}", shell);
+        // PowerShell and Elvish scripts only complete flags
+        if !matches!(shell, "powershell" | "elvish") {
+            assert!(script.contains("sha256"), "{}", shell);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,154 @@
+use file_cmp::config::{config_files, Config, PROJECT_CONFIG};
+use file_cmp::policy::Policy;
+use std::fs;
+use std::io;
+use std::process::Command;
+
+// Test turning the settings of a configuration file into flags
+#[test]
+fn test_parse_settings() {
+    let settings = Config::parse(
+        "chunk_size = \"4M\"\nthreads = 4\ndiffs-only = true\nmmap = false\nhash = [\"sha256\"]\n",
+    )
+    .map(|config| config.settings);
+    assert_eq!(
+        settings,
+        Ok(vec![
+            ("chunk-size".to_string(), Some("4M".to_string())),
+            ("diffs-only".to_string(), None),
+            ("hash".to_string(), Some("sha256".to_string())),
+            ("threads".to_string(), Some("4".to_string())),
+        ])
+    );
+    assert_eq!(Config::parse(""), Ok(Config::default()));
+    assert!(Config::parse("quick = [true").is_err());
+    assert!(Config::parse("[section]\nquick = true").is_err());
+}
+
+// Test reading named profiles
+#[test]
+fn test_profiles() {
+    let config = Config::parse(
+        "quick = true\n[profile.forensics]\nhash_first = true\n[profile.backup-verify]\nquick = true\nthreads = 8\n",
+    )
+    .unwrap();
+    assert_eq!(config.settings, [("quick".to_string(), None)]);
+    assert_eq!(
+        config.profiles.keys().collect::<Vec<_>>(),
+        ["backup-verify", "forensics"]
+    );
+    assert_eq!(
+        config.profiles["backup-verify"],
+        [
+            ("quick".to_string(), None),
+            ("threads".to_string(), Some("8".to_string()))
+        ]
+    );
+    assert!(Config::parse("[profile]\nfast = 1").is_err());
+}
+
+// Test reading external comparers
+#[test]
+fn test_comparers() {
+    let config = Config::parse(
+        "[comparers]\npdf = \"pdf-compare --strict\"\nxlsx = [\"xlsx compare\", \"-v\"]\n",
+    )
+    .unwrap();
+    assert_eq!(config.comparers["pdf"], ["pdf-compare", "--strict"]);
+    assert_eq!(config.comparers["xlsx"], ["xlsx compare", "-v"]);
+    assert!(config.settings.is_empty());
+    assert!(Config::parse("[comparers]\npdf = 1").is_err());
+}
+
+// Test reading policies
+#[test]
+fn test_policies() {
+    let config = Config::parse(
+        r#"
+[policies]
+"*.iso" = "hash-only"
+default = "byte"
+"#,
+    )
+    .unwrap();
+    assert_eq!(config.policies["*.iso"], Policy::HashOnly);
+    assert_eq!(config.policies["default"], Policy::Byte);
+    assert!(Config::parse("[policies]\n\"*.iso\" = \"fuzzy\"").is_err());
+    assert!(Config::parse("[policies]\n\"*.iso\" = 1").is_err());
+}
+
+// Test reading hooks
+#[test]
+fn test_hooks() {
+    let config = Config::parse("[hooks]\nbefore = \"mount /mnt/snap\"\n").unwrap();
+    assert_eq!(config.hooks.before.as_deref(), Some("mount /mnt/snap"));
+    assert_eq!(config.hooks.after, None);
+    assert!(Config::parse("[hooks]\nduring = \"true\"").is_err());
+    assert!(Config::parse("[hooks]\nafter = [\"true\"]").is_err());
+}
+
+// Test finding the nearest project configuration
+#[test]
+fn test_config_files() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_config");
+    let nested = dir.join("a").join("b");
+    fs::create_dir_all(&nested)?;
+    fs::write(dir.join(PROJECT_CONFIG), "")?;
+    assert_eq!(
+        config_files(&nested).last(),
+        Some(&dir.join(PROJECT_CONFIG))
+    );
+
+    fs::write(nested.join(PROJECT_CONFIG), "")?;
+    let files = config_files(&nested);
+    assert_eq!(files.last(), Some(&nested.join(PROJECT_CONFIG)));
+    assert!(!files.contains(&dir.join(PROJECT_CONFIG)));
+    fs::remove_dir_all(&dir)
+}
+
+// Test that environment variables override the configuration files, and
+// flags on the command line override both
+#[test]
+fn test_precedence() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_config_precedence");
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join(PROJECT_CONFIG), "machine = true\nquick = true\n")?;
+    fs::write(dir.join("1"), "abcdef")?;
+    fs::write(dir.join("2"), "abcxef")?;
+
+    let run = |env: &[(&str, &str)], args: &[&str]| -> io::Result<String> {
+        let output = Command::new(env!("CARGO_BIN_EXE_file_cmp"))
+            .current_dir(&dir)
+            // Keep the user's own configuration out of it
+            .env("XDG_CONFIG_HOME", &dir)
+            .env("APPDATA", &dir)
+            .env_remove("FILE_CMP_MACHINE")
+            .env_remove("FILE_CMP_QUICK")
+            .env_remove("FILE_CMP_PROFILE")
+            .envs(env.iter().copied())
+            .args(args)
+            .args(["1", "2"])
+            .output()?;
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    };
+    assert_eq!(run(&[], &[])?, "0");
+    assert_eq!(run(&[], &["compare"])?, "0");
+    assert_eq!(run(&[("FILE_CMP_QUICK", "0")], &[])?, "3");
+    assert_eq!(
+        run(&[("FILE_CMP_QUICK", "0"), ("FILE_CMP_MACHINE", "no")], &[])?,
+        "Files differ at byte 3"
+    );
+    assert_eq!(run(&[("FILE_CMP_MACHINE", "no")], &["-m"])?, "0");
+
+    // Profiles apply on top of the other settings when selected
+    fs::write(
+        dir.join(PROJECT_CONFIG),
+        "machine = true\n[profile.fast]\nquick = true\n",
+    )?;
+    assert_eq!(run(&[], &[])?, "3");
+    assert_eq!(run(&[], &["--profile", "fast"])?, "0");
+    assert_eq!(run(&[], &["compare", "--profile", "fast"])?, "0");
+    assert_eq!(run(&[("FILE_CMP_PROFILE", "fast")], &[])?, "0");
+    assert_eq!(run(&[], &["--profile", "missing"])?, "");
+    fs::remove_dir_all(&dir)
+}
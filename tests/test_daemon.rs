@@ -0,0 +1,154 @@
+use file_cmp::config::Config;
+use file_cmp::daemon::{changes, load_state, parse_results, save_state, Cron, Schedule};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::process::Command;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// 2024-03-01T00:00:00Z, a Friday.
+const MARCH_1: u64 = 1_709_251_200;
+
+fn next(cron: &str, after: u64) -> u64 {
+    let schedule = Schedule::Cron(cron.parse().unwrap());
+    let next = schedule.next_after(UNIX_EPOCH + Duration::from_secs(after));
+    next.duration_since(UNIX_EPOCH).unwrap().as_secs()
+}
+
+// Test the times cron schedules match
+#[test]
+fn test_cron() {
+    assert_eq!(next("0 3 * * *", MARCH_1), MARCH_1 + 3 * 3600);
+    assert_eq!(next("0 3 * * *", MARCH_1 + 3 * 3600), MARCH_1 + 27 * 3600);
+    assert_eq!(next("*/15 * * * *", MARCH_1 + 1), MARCH_1 + 900);
+    assert_eq!(next("5,10-12 * * * *", MARCH_1 + 600), MARCH_1 + 660);
+    // The next Monday
+    assert_eq!(next("30 2 * * 1", MARCH_1), MARCH_1 + 3 * 86400 + 9000);
+    assert_eq!(next("0 0 * * 7", MARCH_1), next("0 0 * * 0", MARCH_1));
+    // Either day field matches when both are restricted: Friday the 8th
+    // comes before the 13th
+    assert_eq!(next("0 0 13 * 5", MARCH_1), MARCH_1 + 7 * 86400);
+    assert_eq!(next("0 0 13 * *", MARCH_1), MARCH_1 + 12 * 86400);
+    assert_eq!(next("0 0 1 1 *", MARCH_1), 1_735_689_600);
+    assert!(next("0 0 30 2 *", MARCH_1) > MARCH_1 + 8 * 365 * 86400);
+
+    for invalid in [
+        "60 * * * *",
+        "* * *",
+        "5-1 * * * *",
+        "*/0 * * * *",
+        "x * * * *",
+    ] {
+        assert!(invalid.parse::<Cron>().is_err(), "{}", invalid);
+    }
+}
+
+// Test reading jobs from the configuration
+#[test]
+fn test_jobs() {
+    let config = Config::parse(
+        r#"
+        diffs_only = true
+        [jobs.backup]
+        args = ["--diffs-only", "/data", "/backup"]
+        every = "1h"
+        [jobs.nightly]
+        args = "/srv /mirror"
+        cron = "0 3 * * *"
+        "#,
+    )
+    .unwrap();
+    assert_eq!(config.settings, [("diffs-only".to_string(), None)]);
+    let backup = &config.jobs["backup"];
+    assert_eq!(backup.args, ["--diffs-only", "/data", "/backup"]);
+    assert_eq!(backup.schedule, Schedule::Every(Duration::from_secs(3600)));
+    let start = SystemTime::now();
+    assert_eq!(backup.schedule.first(start), start);
+    assert_eq!(config.jobs["nightly"].args, ["/srv", "/mirror"]);
+
+    for invalid in [
+        "[jobs.a]\nevery = \"1h\"",
+        "[jobs.a]\nargs = \"x y\"",
+        "[jobs.a]\nargs = \"x y\"\nevery = \"1h\"\ncron = \"* * * * *\"",
+        "[jobs.a]\nargs = \"x y\"\nevery = \"0\"",
+        "[jobs.a]\nargs = \"x y\"\nevery = \"1h\"\nwhen = 3",
+    ] {
+        assert!(Config::parse(invalid).is_err(), "{}", invalid);
+    }
+}
+
+// Test finding the results that changed between runs, and keeping them
+#[test]
+fn test_changes() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_daemon_changes");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir)?;
+    let before = parse_results("equal\t-\ta/x\tb/x\ndiffer\t3\ta/y\tb/y\nequal\t-\ta/z\tb/z\n");
+    let after = parse_results("equal\t-\ta/x\tb/x\ndiffer\t5\ta/y\tb/y\nleft-only\t-\ta/n\tb/n\n");
+    let changed: Vec<_> = changes(&before, &after)
+        .into_iter()
+        .map(|change| (change.path1, change.before, change.after))
+        .collect();
+    let result = |status: &str, offset: &str| Some((status.to_string(), offset.to_string()));
+    assert_eq!(
+        changed,
+        [
+            ("a/n".to_string(), None, result("left-only", "-")),
+            (
+                "a/y".to_string(),
+                result("differ", "3"),
+                result("differ", "5")
+            ),
+            ("a/z".to_string(), result("equal", "-"), None),
+        ]
+    );
+
+    let state = dir.join("job.json");
+    assert!(load_state(&state)?.is_empty());
+    save_state(&state, &after)?;
+    assert_eq!(load_state(&state)?, after);
+    fs::remove_dir_all(&dir)
+}
+
+// Test running the jobs of the configuration once, and again after a change
+#[test]
+fn test_daemon_once() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_daemon_once");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("a"))?;
+    fs::create_dir_all(dir.join("b"))?;
+    fs::write(dir.join("a/x"), "data")?;
+    fs::write(dir.join("b/x"), "data")?;
+    fs::write(
+        dir.join(".file_cmp.toml"),
+        "[jobs.copy]\nargs = \"a b\"\ncron = \"0 3 * * *\"\n",
+    )?;
+    let run = || {
+        Command::new(env!("CARGO_BIN_EXE_file_cmp"))
+            .current_dir(&dir)
+            .env_remove("FILE_CMP_MACHINE")
+            .args(["daemon", "--once", "--state", "state"])
+            .output()
+    };
+    let paths = format!(
+        "{}\t{}",
+        Path::new("a").join("x").display(),
+        Path::new("b").join("x").display()
+    );
+    let output = run()?;
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        format!("copy\t-\t-\tequal\t-\t{}\n", paths)
+    );
+    assert!(String::from_utf8_lossy(&output.stderr).contains("copy: 1 results, 1 changed"));
+    assert!(run()?.stdout.is_empty());
+
+    fs::write(dir.join("b/x"), "date")?;
+    let output = run()?;
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        format!("copy\tequal\t-\tdiffer\t3\t{}\n", paths)
+    );
+    fs::remove_dir_all(&dir)
+}
@@ -0,0 +1,60 @@
+use file_cmp::same_file::{storage, Storage};
+use std::fs;
+use std::io;
+use std::process::Command;
+
+// Test telling hardlinks from copies among equal files
+#[test]
+fn test_dedup_status() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_dedup_status");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("a"))?;
+    fs::create_dir_all(dir.join("b"))?;
+    fs::write(dir.join("a/linked"), "same")?;
+    fs::hard_link(dir.join("a/linked"), dir.join("b/linked"))?;
+    fs::write(dir.join("a/copied"), "same")?;
+    fs::write(dir.join("b/copied"), "same")?;
+    fs::write(dir.join("a/changed"), "left")?;
+    fs::write(dir.join("b/changed"), "lefx")?;
+
+    assert_eq!(
+        storage(dir.join("a/linked"), dir.join("b/linked"))?,
+        Storage::Hardlink
+    );
+    assert_eq!(
+        storage(dir.join("a/copied"), dir.join("b/copied"))?,
+        Storage::Copy
+    );
+
+    let run = |flags: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_file_cmp"))
+            .current_dir(&dir)
+            .env_remove("FILE_CMP_MACHINE")
+            .args(flags)
+            .args(["--dedup-status", "a", "b"])
+            .output()?;
+        let mut lines: Vec<_> = String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(|line| line.replace('\\', "/"))
+            .collect();
+        lines.sort();
+        Ok::<_, io::Error>(lines)
+    };
+    assert_eq!(
+        run(&["-m"])?,
+        [
+            "-1\ta/copied\tstorage:copy",
+            "-1\ta/linked\tstorage:hardlink",
+            "3\ta/changed"
+        ]
+    );
+    assert_eq!(
+        run(&[])?,
+        [
+            "-1\ta/copied\t(equal, copy)",
+            "-1\ta/linked\t(equal, hardlink)",
+            "3\ta/changed\t(diff)"
+        ]
+    );
+    fs::remove_dir_all(&dir)
+}
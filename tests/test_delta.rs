@@ -0,0 +1,101 @@
+use file_cmp::delta::{block_len, compare_signature, write_signature, Rolling, Signature};
+use file_cmp::hash::HashAlgorithm;
+use file_cmp::FileDiff::*;
+use file_cmp::Side;
+use std::io::{self, Cursor};
+
+/// Deterministic data without repeating blocks.
+fn data(len: usize) -> Vec<u8> {
+    let mut state = 0x2545_f491_u32;
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as u8
+        })
+        .collect()
+}
+
+fn signature(data: &[u8], block_len: usize) -> io::Result<Signature> {
+    let mut out = vec![];
+    write_signature(
+        &mut Cursor::new(data),
+        block_len,
+        HashAlgorithm::Sha256,
+        &mut out,
+    )?;
+    Signature::read(Cursor::new(out), HashAlgorithm::Sha256)
+}
+
+// Test rolling the weak checksum gives that of the window it moved to
+#[test]
+fn test_rolling() {
+    let data = data(4096);
+    let mut rolling = Rolling::new(&data[..1000]);
+    for start in 1..=3096 {
+        rolling.roll(data[start - 1], data[start + 999]);
+        assert_eq!(
+            rolling.digest(),
+            Rolling::new(&data[start..start + 1000]).digest()
+        );
+    }
+}
+
+// Test writing and reading back the signature of a file
+#[test]
+fn test_signature() -> io::Result<()> {
+    let signature = signature(&data(2500), 1024)?;
+    assert_eq!(signature.block_len, 1024);
+    assert_eq!(
+        signature
+            .blocks
+            .iter()
+            .map(|block| block.len)
+            .collect::<Vec<_>>(),
+        [1024, 1024, 452]
+    );
+    assert_eq!(signature.len(), 2500);
+    assert_eq!(signature.blocks[0].strong.len(), 32);
+
+    let stream = b"file_cmp-signature sha256 1024\0zz 00 10\0";
+    assert!(Signature::read(Cursor::new(stream), HashAlgorithm::Sha256).is_err());
+    assert!(Signature::read(Cursor::new(b"hello\0"), HashAlgorithm::Sha256).is_err());
+    assert_eq!(block_len(0), 1024);
+    assert_eq!(block_len(1 << 30), 32768);
+    assert_eq!(block_len(u64::MAX), 128 * 1024);
+    Ok(())
+}
+
+// Test finding the ranges of the local file missing from the remote one
+#[test]
+fn test_compare_signature() -> io::Result<()> {
+    let remote = data(10_000);
+    let signature = signature(&remote, 1024)?;
+
+    let delta = compare_signature(&mut Cursor::new(&remote), &signature)?;
+    assert_eq!(delta.file_diff, Equal);
+    assert!(delta.ranges.is_empty());
+
+    // Changed bytes only differ within their block
+    let mut changed = remote.clone();
+    changed[5000] ^= 1;
+    changed[9990] ^= 1;
+    let delta = compare_signature(&mut Cursor::new(&changed), &signature)?;
+    assert_eq!(delta.file_diff, Different(4096));
+    assert_eq!(delta.ranges, [4096..5120, 9216..10_000]);
+
+    // The blocks after inserted bytes are found at their new offsets
+    let mut inserted = remote.clone();
+    inserted.splice(5000..5000, [7; 10]);
+    let delta = compare_signature(&mut Cursor::new(&inserted), &signature)?;
+    assert_eq!(delta.file_diff, Different(4096));
+    assert_eq!(delta.ranges.len(), 1);
+    assert_eq!(delta.ranges[0], 4096..5130);
+
+    // A prefix of the remote file
+    let delta = compare_signature(&mut Cursor::new(&remote[..3072]), &signature)?;
+    assert_eq!(delta.file_diff, Truncated(Side::Left, 3072));
+    assert!(delta.ranges.is_empty());
+    Ok(())
+}
@@ -0,0 +1,72 @@
+use file_cmp::device::{compare_image, is_device_file, is_device_path, Input, Trailing};
+use file_cmp::FileDiff::*;
+use file_cmp::{CompareOptions, Side};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+#[test]
+fn test_input_regular_file() -> io::Result<()> {
+    // Test that regular files report their metadata length
+    let input = Input::open("./tests/testfiles/testing.txt")?;
+    assert_eq!(input.len, 10);
+    assert!(!input.is_device());
+    Ok(())
+}
+
+#[test]
+fn test_is_device_path() {
+    assert!(is_device_path(Path::new(r"\\.\PhysicalDrive0")));
+    assert!(is_device_path(Path::new(r"\\.\C:")));
+    assert!(!is_device_path(Path::new(r"\\server\share\file")));
+    assert!(!is_device_path(Path::new("/dev/sda")));
+}
+
+// Test comparing an image with a larger device it was written to, played by
+// a regular file
+#[test]
+fn test_compare_image() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_device_image");
+    fs::create_dir_all(&dir)?;
+    let (image, device) = (dir.join("disk.img"), dir.join("device"));
+    let mut contents = vec![7; 5000];
+    fs::write(&image, &contents)?;
+    contents.resize(9000, 0);
+    fs::write(&device, &contents)?;
+    assert!(!is_device_file(&image));
+
+    let options = CompareOptions::default();
+    let blank = Trailing {
+        len: 4000,
+        data_at: None,
+    };
+    assert_eq!(
+        compare_image(&image, &device, &options)?,
+        (Equal, Some(blank))
+    );
+
+    contents[6000] = 1;
+    contents[10] = 8;
+    fs::write(&device, &contents)?;
+    let data = Trailing {
+        len: 4000,
+        data_at: Some(6000),
+    };
+    assert_eq!(
+        compare_image(&image, &device, &options)?,
+        (Different(10), Some(data))
+    );
+
+    contents[10] = 7;
+    fs::write(&device, &contents[..100])?;
+    assert_eq!(
+        compare_image(&image, &device, &options)?,
+        (Truncated(Side::Right, 100), None)
+    );
+    assert_eq!(
+        Truncated(Side::Right, 100).swap_sides(),
+        Truncated(Side::Left, 100)
+    );
+    assert_eq!(LeftOnly.swap_sides(), RightOnly);
+    fs::remove_dir_all(&dir)
+}
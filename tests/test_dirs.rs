@@ -0,0 +1,102 @@
+use file_cmp::FileDiff::{self, *};
+use file_cmp::{compare_dirs, Policy};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Creates a fresh directory with the given files, creating parent
+/// directories as needed.
+fn tree(name: &str, files: &[(&str, &str)]) -> io::Result<PathBuf> {
+    let dir = std::env::temp_dir().join("file_cmp_test_dirs").join(name);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir)?;
+    for (path, contents) in files {
+        let path = dir.join(path);
+        fs::create_dir_all(path.parent().unwrap())?;
+        fs::write(path, contents)?;
+    }
+    Ok(dir)
+}
+
+/// Returns the results with paths relative to whichever root they're in.
+fn relative(results: Vec<(PathBuf, FileDiff)>, roots: &[&Path]) -> Vec<(String, FileDiff)> {
+    let mut results: Vec<_> = results
+        .into_iter()
+        .map(|(path, diff)| {
+            let root = roots.iter().find(|r| path.starts_with(r)).unwrap();
+            let rel = path.strip_prefix(root).unwrap();
+            (rel.to_string_lossy().replace('\\', "/"), diff)
+        })
+        .collect();
+    results.sort_by(|a, b| a.0.cmp(&b.0));
+    results
+}
+
+#[test]
+fn test_compare_dirs_nested() -> io::Result<()> {
+    // Test nested trees are compared once per file
+    let left = tree(
+        "nested_left",
+        &[
+            ("same.txt", "same"),
+            ("a/b/deep.txt", "left"),
+            ("a/leftonly.txt", "x"),
+            ("onlyleftdir/f.txt", "x"),
+        ],
+    )?;
+    let right = tree(
+        "nested_right",
+        &[
+            ("same.txt", "same"),
+            ("a/b/deep.txt", "lefx"),
+            ("a/rightonly.txt", "x"),
+            ("onlyrightdir/f.txt", "x"),
+        ],
+    )?;
+    let results = relative(
+        compare_dirs(&left, &right, Policy::ByteExact)?,
+        &[&left, &right],
+    );
+    assert_eq!(
+        results,
+        vec![
+            ("a/b/deep.txt".to_string(), Different(3)),
+            ("a/leftonly.txt".to_string(), LeftOnly),
+            ("a/rightonly.txt".to_string(), RightOnly),
+            ("onlyleftdir".to_string(), LeftOnly),
+            ("onlyrightdir".to_string(), RightOnly),
+            ("same.txt".to_string(), Equal),
+        ]
+    );
+    Ok(())
+}
+
+// Test that directories and files that can't be read fail the comparison
+#[test]
+fn test_compare_dirs_errors() -> io::Result<()> {
+    let left = tree("errors_left", &[("same.txt", "same")])?;
+    let right = tree("errors_right", &[("same.txt", "same")])?;
+    let missing = left.join("missing");
+
+    let error = compare_dirs(&missing, &right, Policy::ByteExact).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::NotFound);
+    assert!(error.to_string().contains("missing"));
+
+    // A pair that can't be compared fails the comparison, while a walk
+    // goes on past it
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink("nowhere", left.join("broken"))?;
+        std::os::unix::fs::symlink("nowhere", right.join("broken"))?;
+        assert!(compare_dirs(&left, &right, Policy::ByteExact).is_err());
+
+        let options = file_cmp::CompareOptions::default();
+        let mut walk = file_cmp::walk::DirWalk::new(&left, &right, &options);
+        let results = relative(walk.by_ref().collect(), &[&left, &right]);
+        assert!(results.contains(&("same.txt".to_string(), Equal)));
+        let failed = walk.take_failed();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].0, left.join("broken"));
+    }
+    Ok(())
+}
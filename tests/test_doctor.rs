@@ -0,0 +1,36 @@
+use file_cmp::doctor::{probe, Report};
+use std::fs;
+use std::io;
+use std::time::Duration;
+
+// Test probing a filesystem, which leaves nothing behind
+#[test]
+fn test_probe() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_doctor");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir)?;
+    let report = probe(&dir)?;
+    assert!(report.max_name_len.is_none_or(|len| len >= 255));
+    #[cfg(target_os = "linux")]
+    assert!(report.case_sensitive && report.symlinks);
+    assert_eq!(fs::read_dir(&dir)?.count(), 0);
+    assert!(probe(&dir.join("missing")).is_err());
+    fs::remove_dir_all(&dir)
+}
+
+// Test advice for a filesystem like FAT
+#[test]
+fn test_advice() {
+    let report = Report {
+        case_sensitive: false,
+        mtime_granularity: Some(Duration::from_secs(2)),
+        sparse_files: false,
+        symlinks: false,
+        max_name_len: Some(255),
+        max_path_len: None,
+    };
+    let advice = report.advice();
+    assert_eq!(advice.len(), 4);
+    assert!(advice[0].contains("--skip-blank"));
+    assert!(advice[3].contains("2s"));
+}
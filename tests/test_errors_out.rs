@@ -0,0 +1,61 @@
+use std::fs;
+use std::io;
+use std::process::Command;
+
+// Test listing the files that could not be compared and retrying them
+#[test]
+fn test_errors_out() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_errors_out");
+    let (a, b) = (dir.join("a"), dir.join("b"));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(a.join("sub"))?;
+    fs::create_dir_all(b.join("sub/broken"))?;
+    fs::write(a.join("sub/broken"), "data")?;
+    fs::write(a.join("same"), "data")?;
+    fs::write(b.join("same"), "data")?;
+
+    // A file on the left that is a directory on the right can't be read
+    let list = dir.join("errors.txt");
+    let output = Command::new(env!("CARGO_BIN_EXE_file_cmp"))
+        .env_remove("FILE_CMP_MACHINE")
+        .arg("--errors-out")
+        .arg(&list)
+        .arg(&a)
+        .arg(&b)
+        .output()?;
+    assert!(output.status.success());
+    let listed = fs::read_to_string(&list)?;
+    assert_eq!(
+        listed,
+        format!(
+            "{}\t{}\n",
+            a.join("sub/broken").display(),
+            b.join("sub/broken").display()
+        )
+    );
+
+    // Retrying the list after fixing the right side
+    fs::remove_dir(b.join("sub/broken"))?;
+    fs::write(b.join("sub/broken"), "data")?;
+    fs::write(dir.join("missing.txt"), listed.replace("broken", "gone"))?;
+    let retry = |list: &str, errors: &str| {
+        Command::new(env!("CARGO_BIN_EXE_file_cmp"))
+            .env_remove("FILE_CMP_MACHINE")
+            .arg("--from-file")
+            .arg(dir.join(list))
+            .arg("--errors-out")
+            .arg(dir.join(errors))
+            .output()
+    };
+    let output = retry("errors.txt", "retried.txt")?;
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).starts_with("-1\t"));
+    assert_eq!(fs::read_to_string(dir.join("retried.txt"))?, "");
+    let output = retry("missing.txt", "still.txt")?;
+    assert!(!output.status.success());
+    assert_eq!(
+        fs::read_to_string(dir.join("still.txt"))?,
+        listed.replace("broken", "gone")
+    );
+    fs::remove_dir_all(&dir)
+}
@@ -0,0 +1,52 @@
+use file_cmp::evidence::{format_time, verify, EvidenceLog, GENESIS};
+use file_cmp::FileDiff::*;
+use std::fs;
+use std::io;
+use std::time::{Duration, UNIX_EPOCH};
+
+// Test that records chain across runs and that changing one breaks it
+#[test]
+fn test_evidence_log() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_evidence");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir)?;
+    let (a, b, log) = (dir.join("a"), dir.join("b"), dir.join("evidence.log"));
+    fs::write(&a, "abc")?;
+    fs::write(&b, "abd")?;
+
+    EvidenceLog::open(&log)?.record(&a, &b, &Different(2))?;
+    let mut reopened = EvidenceLog::open(&log)?;
+    reopened.record(&a, &a, &Equal)?;
+    reopened.record(&a, &dir.join("missing"), &LeftOnly)?;
+    assert_eq!(verify(&log)?, 3);
+
+    let text = fs::read_to_string(&log)?;
+    let first: serde_json::Value = serde_json::from_str(text.lines().next().unwrap())?;
+    assert_eq!(first["prev"], GENESIS);
+    assert_eq!(first["verdict"], "diff");
+    assert_eq!(first["offset"], 2);
+    assert_eq!(first["left"]["size"], 3);
+    assert_eq!(
+        first["left"]["sha256"],
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+    let last: serde_json::Value = serde_json::from_str(text.lines().last().unwrap())?;
+    assert!(last["right"].is_null());
+
+    fs::write(&log, text.replacen("\"size\":3", "\"size\":4", 1))?;
+    assert_eq!(
+        verify(&log).map_err(|e| e.kind()),
+        Err(io::ErrorKind::InvalidData)
+    );
+    fs::remove_dir_all(&dir)
+}
+
+// Test formatting times as UTC, including a leap day
+#[test]
+fn test_format_time() {
+    assert_eq!(format_time(UNIX_EPOCH), "1970-01-01T00:00:00.000000000Z");
+    assert_eq!(
+        format_time(UNIX_EPOCH + Duration::new(951_782_400 + 3723, 5)),
+        "2000-02-29T01:02:03.000000005Z"
+    );
+}
@@ -0,0 +1,71 @@
+#![cfg(unix)]
+
+use file_cmp::exec::Exec;
+use std::fs;
+use std::io;
+use std::process::Command;
+
+// Test running commands for pairs with quotes in their paths, a few at a time
+#[test]
+fn test_exec() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_exec");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir)?;
+    let mut exec = Exec::new("cp -f {left} {right} && echo {status} > {right}.status", 2);
+    for i in 0..5 {
+        let (path1, path2) = (
+            dir.join(format!("'{}' a", i)),
+            dir.join(format!("\"{}\" b", i)),
+        );
+        fs::write(&path1, i.to_string())?;
+        exec.run(&path1, &path2, "differ")?;
+    }
+    exec.run(dir.join("missing"), dir.join("other"), "left-only")?;
+    assert_eq!(exec.finish()?, 1);
+    for i in 0..5 {
+        let path2 = dir.join(format!("\"{}\" b", i));
+        assert_eq!(fs::read_to_string(&path2)?, i.to_string());
+        assert_eq!(
+            fs::read_to_string(dir.join(format!("\"{}\" b.status", i)))?,
+            "differ\n"
+        );
+    }
+    fs::remove_dir_all(&dir)
+}
+
+// Test --exec running only for pairs that don't match and failing the run
+// when a command fails
+#[test]
+fn test_exec_option() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_exec_option");
+    let (a, b) = (dir.join("a"), dir.join("b"));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&a)?;
+    fs::create_dir_all(&b)?;
+    fs::write(a.join("same"), "data")?;
+    fs::write(b.join("same"), "data")?;
+    fs::write(a.join("changed"), "data")?;
+    fs::write(b.join("changed"), "date")?;
+    fs::write(a.join("extra"), "data")?;
+    let run = |command: &str| {
+        Command::new(env!("CARGO_BIN_EXE_file_cmp"))
+            .env_remove("FILE_CMP_MACHINE")
+            .arg("--exec")
+            .arg(command)
+            .arg("--parallel-exec=4")
+            .arg(&a)
+            .arg(&b)
+            .output()
+    };
+    let output = run("cp {left} {right}")?;
+    assert!(output.status.success());
+    assert_eq!(fs::read_to_string(b.join("changed"))?, "data");
+    assert_eq!(fs::read_to_string(b.join("extra"))?, "data");
+    let output = run("false")?;
+    assert!(output.status.success(), "no pair differs any more");
+    fs::write(b.join("changed"), "date")?;
+    let output = run("false")?;
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("1 of the commands failed"));
+    fs::remove_dir_all(&dir)
+}
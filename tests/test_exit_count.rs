@@ -0,0 +1,39 @@
+use std::fs;
+use std::io;
+use std::process::Command;
+
+// Test exiting with the number of files that differ
+#[test]
+fn test_exit_count() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_exit_count");
+    let (a, b) = (dir.join("a"), dir.join("b"));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&a)?;
+    fs::create_dir_all(&b)?;
+    fs::write(a.join("same"), "data")?;
+    fs::write(b.join("same"), "data")?;
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_file_cmp"))
+            .env_remove("FILE_CMP_MACHINE")
+            .arg("--exit-count")
+            .args(args)
+            .arg(&a)
+            .arg(&b)
+            .status()
+    };
+    assert_eq!(run(&[])?.code(), Some(0));
+
+    fs::write(a.join("changed"), "data")?;
+    fs::write(b.join("changed"), "date")?;
+    fs::write(a.join("left"), "")?;
+    fs::write(b.join("right"), "")?;
+    assert_eq!(run(&[])?.code(), Some(3));
+    assert_eq!(run(&["--brief"])?.code(), Some(3));
+
+    // The count is capped below the exit codes shells use
+    for i in 0..130 {
+        fs::write(a.join(format!("extra{}", i)), "")?;
+    }
+    assert_eq!(run(&[])?.code(), Some(125));
+    fs::remove_dir_all(&dir)
+}
@@ -0,0 +1,91 @@
+use file_cmp::external::Comparers;
+use std::path::Path;
+
+fn command(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+// Test finding the comparer for a file by its extension
+#[test]
+fn test_comparers() {
+    let mut comparers = Comparers::new();
+    assert!(comparers.is_empty());
+    comparers.insert("pdf", command(&["pdf-compare"]));
+    comparers.insert(".XLSX", command(&["xlsx-compare", "--values"]));
+    assert_eq!(
+        comparers.get(Path::new("docs/report.PDF")),
+        Some(&command(&["pdf-compare"])[..])
+    );
+    assert_eq!(
+        comparers.get(Path::new("sheet.xlsx")),
+        Some(&command(&["xlsx-compare", "--values"])[..])
+    );
+    assert_eq!(comparers.get(Path::new("pdf")), None);
+    assert_eq!(comparers.get(Path::new("notes.txt")), None);
+}
+
+#[cfg(unix)]
+mod unix {
+    use super::*;
+    use file_cmp::external::compare_with;
+    use file_cmp::walk::DirWalk;
+    use file_cmp::CompareOptions;
+    use file_cmp::FileDiff::*;
+    use std::fs;
+    use std::io;
+    use std::sync::Arc;
+
+    // Test interpreting the exit status of a comparer
+    #[test]
+    fn test_compare_with() -> io::Result<()> {
+        let dir = std::env::temp_dir().join("file_cmp_test_external");
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join("1"), "test")?;
+        fs::write(dir.join("2"), "test")?;
+        fs::write(dir.join("3"), "tent")?;
+        let cmp = command(&["cmp", "-s"]);
+        assert_eq!(compare_with(&cmp, dir.join("1"), dir.join("2"))?, Equal);
+        assert_eq!(
+            compare_with(&cmp, dir.join("1"), dir.join("3"))?,
+            Different(0)
+        );
+        assert!(compare_with(&cmp, dir.join("1"), dir.join("missing")).is_err());
+        assert!(compare_with(
+            &command(&["file_cmp_no_such_program"]),
+            dir.join("1"),
+            dir.join("2")
+        )
+        .is_err());
+        assert!(compare_with(&[], dir.join("1"), dir.join("2")).is_err());
+        Ok(())
+    }
+
+    // Test that files with a comparer are compared with it in directory runs
+    #[test]
+    fn test_dir_walk() -> io::Result<()> {
+        let dir = std::env::temp_dir().join("file_cmp_test_external_dirs");
+        fs::create_dir_all(dir.join("a"))?;
+        fs::create_dir_all(dir.join("b"))?;
+        for name in ["report.pdf", "notes.txt"] {
+            fs::write(dir.join("a").join(name), "one")?;
+            fs::write(dir.join("b").join(name), "two")?;
+        }
+
+        let mut comparers = Comparers::new();
+        comparers.insert("pdf", command(&["true"]));
+        let options = CompareOptions {
+            comparers: Some(Arc::new(comparers)),
+            ..Default::default()
+        };
+        let mut results: Vec<_> = DirWalk::new(dir.join("a"), dir.join("b"), &options).collect();
+        results.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            results,
+            vec![
+                (dir.join("a/notes.txt"), Different(0)),
+                (dir.join("a/report.pdf"), Equal),
+            ]
+        );
+        Ok(())
+    }
+}
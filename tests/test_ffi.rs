@@ -0,0 +1,72 @@
+#![cfg(feature = "ffi")]
+
+use file_cmp::ffi::*;
+use std::ffi::{c_char, c_void, CStr, CString};
+
+fn p(p: &str) -> CString {
+    CString::new(format!("./tests/testfiles/{}", p)).unwrap()
+}
+
+#[test]
+fn test_ffi_compare_files() {
+    unsafe {
+        assert_eq!(
+            file_cmp_compare_files(p("test.txt").as_ptr(), p("tesx.txt").as_ptr(), 0),
+            3
+        );
+        assert_eq!(
+            file_cmp_compare_files(p("test.txt").as_ptr(), p("tesx.txt").as_ptr(), 1),
+            0
+        );
+        assert_eq!(
+            file_cmp_compare_files(p("test.txt").as_ptr(), p("test.txt").as_ptr(), 0),
+            FILE_CMP_EQUAL
+        );
+
+        // Test that errors leave a message
+        let res = file_cmp_compare_files(p("missing.txt").as_ptr(), p("test.txt").as_ptr(), 0);
+        assert_eq!(res, FILE_CMP_ERROR);
+        assert!(!CStr::from_ptr(file_cmp_last_error()).to_bytes().is_empty());
+    }
+}
+
+extern "C" fn collect(path: *const c_char, result: i64, user_data: *mut c_void) {
+    let results = unsafe { &mut *(user_data as *mut Vec<(String, i64)>) };
+    let path = unsafe { CStr::from_ptr(path) }
+        .to_string_lossy()
+        .into_owned();
+    results.push((path, result));
+}
+
+#[test]
+fn test_ffi_compare_dirs() {
+    let dir = std::env::temp_dir().join("file_cmp_test_ffi");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("a")).unwrap();
+    std::fs::create_dir_all(dir.join("b")).unwrap();
+    std::fs::write(dir.join("a/file.txt"), "test").unwrap();
+    std::fs::write(dir.join("b/file.txt"), "text").unwrap();
+    std::fs::write(dir.join("b/right.txt"), "").unwrap();
+
+    let a = CString::new(dir.join("a").to_string_lossy().into_owned()).unwrap();
+    let b = CString::new(dir.join("b").to_string_lossy().into_owned()).unwrap();
+    let mut results: Vec<(String, i64)> = vec![];
+    let user_data = &mut results as *mut _ as *mut c_void;
+    let res = unsafe { file_cmp_compare_dirs(a.as_ptr(), b.as_ptr(), 0, collect, user_data) };
+    assert_eq!(res, 0);
+    results.sort();
+    assert_eq!(
+        results,
+        vec![
+            (dir.join("a/file.txt").to_string_lossy().into_owned(), 2),
+            (
+                dir.join("b/right.txt").to_string_lossy().into_owned(),
+                FILE_CMP_RIGHT_ONLY
+            ),
+        ]
+    );
+
+    let missing = CString::new("missing").unwrap();
+    let res = unsafe { file_cmp_compare_dirs(missing.as_ptr(), b.as_ptr(), 0, collect, user_data) };
+    assert_eq!(res, FILE_CMP_ERROR);
+}
@@ -0,0 +1,73 @@
+#![cfg(unix)]
+
+use file_cmp::FileDiff::*;
+use file_cmp::{compare_files_with, CompareOptions};
+use std::ffi::CString;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+fn p(p: &str) -> String {
+    format!("./tests/testfiles/{}", p)
+}
+
+fn fifo(name: &str) -> io::Result<PathBuf> {
+    let dir = std::env::temp_dir().join(format!("file_cmp_test_{}", name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir)?;
+    let path = dir.join("fifo");
+    let c_path = CString::new(path.as_os_str().as_bytes()).unwrap();
+    match unsafe { libc::mkfifo(c_path.as_ptr(), 0o600) } {
+        0 => Ok(path),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+/// Writes the data into the pipe from another thread, waiting before closing it.
+fn feed(path: &Path, data: &'static [u8], linger: Duration) -> thread::JoinHandle<()> {
+    let path = path.to_path_buf();
+    thread::spawn(move || {
+        let mut pipe = File::options().write(true).open(path).unwrap();
+        let _ = pipe.write_all(data);
+        thread::sleep(linger);
+    })
+}
+
+#[test]
+fn test_fifo() -> io::Result<()> {
+    // Test that pipes are read to the end instead of being treated as empty
+    let path = fifo("fifo")?;
+    let options = CompareOptions::default();
+
+    let writer = feed(&path, b"test \r\n", Duration::ZERO);
+    assert_eq!(
+        compare_files_with(path.as_path(), Path::new(&p("test.txt")), &options)?,
+        Equal
+    );
+    writer.join().unwrap();
+
+    let writer = feed(&path, b"test \r\n", Duration::ZERO);
+    let res = compare_files_with(Path::new(&p("testing.txt")), path.as_path(), &options)?;
+    assert_eq!(res, Different(4));
+    writer.join().unwrap();
+    Ok(())
+}
+
+#[test]
+fn test_fifo_timeout() -> io::Result<()> {
+    // Test when the pipe stalls for longer than the timeout
+    let path = fifo("fifo_timeout")?;
+    let options = CompareOptions {
+        timeout: Some(Duration::from_millis(100)),
+        ..Default::default()
+    };
+
+    let writer = feed(&path, b"test", Duration::from_secs(2));
+    let err = compare_files_with(path.as_path(), Path::new(&p("test.txt")), &options).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    writer.join().unwrap();
+    Ok(())
+}
@@ -0,0 +1,48 @@
+use file_cmp::fill::compare_fill;
+use file_cmp::units::parse_byte;
+use file_cmp::CompareOptions;
+use file_cmp::FileDiff::*;
+use std::fs;
+use std::io;
+
+// Test parsing fill bytes
+#[test]
+fn test_parse_byte() {
+    assert_eq!(parse_byte("0"), Ok(0));
+    assert_eq!(parse_byte("255"), Ok(255));
+    assert_eq!(parse_byte("0xFF"), Ok(255));
+    assert_eq!(parse_byte("0x5a"), Ok(0x5a));
+    assert!(parse_byte("256").is_err());
+    assert!(parse_byte("0x100").is_err());
+    assert!(parse_byte("ff").is_err());
+}
+
+// Test comparing files with a fill byte
+#[test]
+fn test_compare_fill() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_fill");
+    fs::create_dir_all(&dir)?;
+    let path = dir.join("image");
+    let mut image = vec![0xff; 3 << 20];
+    fs::write(&path, &image)?;
+    let options = CompareOptions::default();
+    assert_eq!(compare_fill(&path, 0xff, &options)?, Equal);
+    assert_eq!(compare_fill(&path, 0, &options)?, Different(0));
+
+    image[(2 << 20) + 17] = 0;
+    fs::write(&path, &image)?;
+    assert_eq!(
+        compare_fill(&path, 0xff, &options)?,
+        Different((2 << 20) + 17)
+    );
+    let quick = CompareOptions {
+        quick: true,
+        ..Default::default()
+    };
+    assert_eq!(compare_fill(&path, 0xff, &quick)?, Different(0));
+
+    fs::write(&path, [])?;
+    assert_eq!(compare_fill(&path, 0xff, &options)?, Equal);
+    assert!(compare_fill(dir.join("missing"), 0, &options).is_err());
+    Ok(())
+}
@@ -0,0 +1,79 @@
+use file_cmp::suffix::{compare_from_end, SuffixDiff};
+use file_cmp::CompareOptions;
+use std::fs;
+use std::io;
+use std::process::Command;
+
+// Test the common suffix of files, across reads of the ends
+#[test]
+fn test_compare_from_end() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_compare_from_end");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir)?;
+    let log: Vec<u8> = (0..10_000u32).map(|i| (i * 7 % 251) as u8).collect();
+    let options = CompareOptions {
+        chunk_size: Some(1000),
+        ..Default::default()
+    };
+    let compare = |data1: &[u8], data2: &[u8]| -> io::Result<SuffixDiff> {
+        fs::write(dir.join("a"), data1)?;
+        fs::write(dir.join("b"), data2)?;
+        compare_from_end(dir.join("a"), dir.join("b"), &options)
+    };
+
+    let diff = compare(&log, &log)?;
+    assert!(diff.is_equal());
+    assert_eq!(diff.last_diff(), (None, None));
+
+    let mut damaged = log.clone();
+    damaged[2500] ^= 1;
+    let diff = compare(&log, &damaged)?;
+    assert!(!diff.is_equal());
+    assert_eq!(diff.suffix, 7499);
+    assert_eq!(diff.last_diff(), (Some(2500), Some(2500)));
+
+    // A copy missing the start of a log, e.g. after rotation
+    let diff = compare(&log, &log[4000..])?;
+    assert_eq!(diff.suffix, 6000);
+    assert_eq!(diff.last_diff(), (Some(3999), None));
+
+    let mut appended = log.clone();
+    appended.extend_from_slice(b"new entry\n");
+    let diff = compare(&log, &appended)?;
+    assert_eq!(diff.suffix, 0);
+    assert_eq!(diff.last_diff(), (Some(9999), Some(10_009)));
+
+    assert!(compare(b"", b"")?.is_equal());
+    assert_eq!(compare(b"", b"x")?.last_diff(), (None, Some(0)));
+    fs::remove_dir_all(&dir)
+}
+
+// Test the output of --from-end
+#[test]
+fn test_from_end_output() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_from_end_output");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join("a"), b"first\nsecond\n")?;
+    fs::write(dir.join("b"), b"FIRST\nsecond\n")?;
+    fs::write(dir.join("c"), b"second\n")?;
+    let run = |args: &[&str]| -> io::Result<String> {
+        let output = Command::new(env!("CARGO_BIN_EXE_file_cmp"))
+            .current_dir(&dir)
+            .env_remove("FILE_CMP_MACHINE")
+            .args(args)
+            .output()?;
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    };
+    assert_eq!(
+        run(&["--from-end", "a", "b"])?,
+        "Files differ last at byte 4 of a and byte 4 of b, with a common suffix of 8 bytes"
+    );
+    assert_eq!(
+        run(&["--from-end", "a", "c"])?,
+        "c is a suffix of a, which differs last at byte 5"
+    );
+    assert_eq!(run(&["--from-end", "a", "a"])?, "Files are equal");
+    assert_eq!(run(&["-m", "--from-end", "c", "a"])?, "-\t5\t7");
+    fs::remove_dir_all(&dir)
+}
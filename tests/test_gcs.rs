@@ -0,0 +1,128 @@
+#![cfg(feature = "gcs")]
+
+use file_cmp::gcs::{checksum_matches, compare_tree, crc32c, Client, GcsUrl, ObjectInfo};
+use file_cmp::CompareOptions;
+use file_cmp::FileDiff::*;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::thread;
+
+fn p(p: &str) -> String {
+    format!("./tests/testfiles/{}", p)
+}
+
+fn object(md5_hash: Option<&str>, crc32c: Option<&str>) -> ObjectInfo {
+    ObjectInfo {
+        name: "test.txt".to_string(),
+        len: 7,
+        md5_hash: md5_hash.map(str::to_string),
+        crc32c: crc32c.map(str::to_string),
+    }
+}
+
+#[test]
+fn test_gcs_url() {
+    let url = GcsUrl::parse("gs://bucket/dir/file.bin").unwrap();
+    assert_eq!(url.bucket, "bucket");
+    assert_eq!(url.name, "dir/file.bin");
+    assert_eq!(GcsUrl::parse("gs://bucket").unwrap().name, "");
+    assert_eq!(GcsUrl::parse("gs:///name"), None);
+    assert_eq!(GcsUrl::parse("s3://bucket/name"), None);
+}
+
+#[test]
+fn test_crc32c() -> io::Result<()> {
+    assert_eq!(crc32c(&mut &b"123456789"[..])?, 0xe306_9283);
+    assert_eq!(crc32c(&mut &b""[..])?, 0);
+    Ok(())
+}
+
+#[test]
+fn test_checksum_matches() -> io::Result<()> {
+    // Test MD5 hashes, and CRC32Cs of composite objects without one
+    let md5 = object(Some("pVq3US8ND/RSfYmNBq/VxQ=="), Some("XOvslg=="));
+    assert_eq!(checksum_matches(p("test.txt"), &md5)?, Some(true));
+    assert_eq!(checksum_matches(p("tesx.txt"), &md5)?, Some(false));
+    let composite = object(None, Some("XOvslg=="));
+    assert_eq!(checksum_matches(p("test.txt"), &composite)?, Some(true));
+    assert_eq!(checksum_matches(p("tesx.txt"), &composite)?, Some(false));
+    assert_eq!(checksum_matches(p("test.txt"), &object(None, None))?, None);
+    Ok(())
+}
+
+/// Serves a listing of `a.txt`, `b.txt` and `d.txt` under `prefix/` over
+/// two pages. The body of `a.txt` doesn't match its checksums, to tell when
+/// they were used.
+fn serve() -> io::Result<String> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let endpoint = format!("http://{}", listener.local_addr()?);
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request = String::new();
+            reader.read_line(&mut request).unwrap();
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 2 {
+                line.clear();
+            }
+            let target = request.split(' ').nth(1).unwrap_or_default();
+            let (path, query) = target.split_once('?').unwrap_or((target, ""));
+            let (status, body) = match path {
+                "/storage/v1/b/bucket/o" if query.contains("pageToken=page2") => (
+                    "200 OK",
+                    r#"{"items":[{"name":"prefix/d.txt","size":"7"}]}"#,
+                ),
+                "/storage/v1/b/bucket/o" => (
+                    "200 OK",
+                    concat!(
+                        r#"{"items":["#,
+                        r#"{"name":"prefix/a.txt","size":"7","crc32c":"XOvslg=="},"#,
+                        r#"{"name":"prefix/b.txt","size":"7","md5Hash":"AAAAAAAAAAAAAAAAAAAAAA=="}"#,
+                        r#"],"nextPageToken":"page2"}"#
+                    ),
+                ),
+                "/storage/v1/b/bucket/o/prefix%2Fa.txt" => ("200 OK", "xxxxxxx"),
+                "/storage/v1/b/bucket/o/prefix%2Fb.txt"
+                | "/storage/v1/b/bucket/o/prefix%2Fd.txt" => ("200 OK", "test \r\n"),
+                _ => ("404 Not Found", ""),
+            };
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+    });
+    Ok(endpoint)
+}
+
+#[test]
+fn test_compare_tree() -> io::Result<()> {
+    // Test a local tree against a prefix, with matching and mismatching
+    // checksums
+    let dir = std::env::temp_dir().join("file_cmp_test_gcs_tree");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir)?;
+    fs::copy(p("test.txt"), dir.join("a.txt"))?;
+    fs::copy(p("tesx.txt"), dir.join("b.txt"))?;
+    fs::copy(p("test.txt"), dir.join("c.txt"))?;
+
+    let client = Client::from_env(None).with_endpoint(&serve()?);
+    let url = GcsUrl::parse("gs://bucket/prefix").unwrap();
+    let res = compare_tree(&client, &url, &dir, &CompareOptions::default())?;
+    assert_eq!(
+        res,
+        vec![
+            (dir.join("a.txt"), Equal),
+            (dir.join("b.txt"), Different(3)),
+            (dir.join("c.txt"), LeftOnly),
+            (PathBuf::from("gs://bucket/prefix/d.txt"), RightOnly),
+        ]
+    );
+    fs::remove_dir_all(&dir)
+}
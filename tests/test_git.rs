@@ -0,0 +1,132 @@
+#![cfg(feature = "git")]
+
+use file_cmp::git::{compare_file, compare_tree, GitRef};
+use file_cmp::CompareOptions;
+use file_cmp::FileDiff::*;
+use git2::{Repository, Signature};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Creates a repository with one commit of the given files, tagged v1.0.
+fn repository(dir: &Path, files: &[(&str, &str)]) -> Result<(), git2::Error> {
+    let _ = fs::remove_dir_all(dir);
+    let repo = Repository::init(dir)?;
+    let mut index = repo.index()?;
+    for (path, contents) in files {
+        let path = dir.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, contents).unwrap();
+        index.add_path(path.strip_prefix(dir).unwrap())?;
+    }
+    index.write()?;
+    let tree = repo.find_tree(index.write_tree()?)?;
+    let signature = Signature::now("test", "test@example.com")?;
+    let commit = repo.commit(Some("HEAD"), &signature, &signature, "v1.0", &tree, &[])?;
+    repo.tag_lightweight("v1.0", &repo.find_object(commit, None)?, false)?;
+    Ok(())
+}
+
+// Test parsing git references
+#[test]
+fn test_git_ref() {
+    assert_eq!(
+        GitRef::parse("git:../repo#v1.0:docs"),
+        Some(GitRef {
+            repo: PathBuf::from("../repo"),
+            rev: "v1.0:docs".to_string(),
+        })
+    );
+    assert_eq!(
+        GitRef::parse("git:/srv/repo").map(|git| git.rev),
+        Some("HEAD".to_string())
+    );
+    assert_eq!(
+        GitRef::parse("git:#main").map(|git| git.repo),
+        Some(PathBuf::from("."))
+    );
+    assert_eq!(GitRef::parse("/srv/repo"), None);
+
+    let git = GitRef::parse("git:repo#v1.0").unwrap();
+    assert_eq!(git.object_path("src/main.rs"), "v1.0:src/main.rs");
+    let git = GitRef::parse("git:repo#v1.0:src/").unwrap();
+    assert_eq!(git.object_path("main.rs"), "v1.0:src/main.rs");
+}
+
+// Test comparing a deployed tree against a tagged commit
+#[test]
+fn test_compare_tree() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_git");
+    let (repo, deployed) = (dir.join("repo"), dir.join("deployed"));
+    let files = [
+        ("README.md", "readme\n"),
+        ("src/main.rs", "fn main() {}\n"),
+        ("src/lib.rs", "pub fn lib() {}\n"),
+    ];
+    repository(&repo, &files).map_err(io::Error::other)?;
+
+    let _ = fs::remove_dir_all(&deployed);
+    fs::create_dir_all(deployed.join("src"))?;
+    fs::write(deployed.join("README.md"), "readme\n")?;
+    fs::write(deployed.join("src/main.rs"), "fn main() { }\n")?;
+    fs::write(deployed.join("src/extra.rs"), "")?;
+
+    let git = GitRef {
+        repo: repo.clone(),
+        rev: "v1.0".to_string(),
+    };
+    let mut results = compare_tree(&git, &deployed, &CompareOptions::default())?;
+    results.sort_by(|a, b| a.0.cmp(&b.0));
+    assert_eq!(
+        results,
+        vec![
+            (deployed.join("README.md"), Equal),
+            (deployed.join("src/extra.rs"), LeftOnly),
+            (deployed.join("src/main.rs"), Different(11)),
+            (PathBuf::from("v1.0:src/lib.rs"), RightOnly),
+        ]
+    );
+
+    // A subdirectory of the tree, and the working tree of the repository
+    let git = GitRef {
+        repo: repo.clone(),
+        rev: "v1.0:src".to_string(),
+    };
+    let results = compare_tree(&git, deployed.join("src"), &CompareOptions::default())?;
+    assert_eq!(results.len(), 3);
+    let git = GitRef {
+        repo: repo.clone(),
+        rev: "HEAD".to_string(),
+    };
+    assert!(compare_tree(&git, &repo, &CompareOptions::default())?
+        .iter()
+        .all(|(_, diff)| *diff == Equal));
+    Ok(())
+}
+
+// Test comparing a single file of a commit
+#[test]
+fn test_compare_file() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_git_file");
+    repository(&dir.join("repo"), &[("file", "test \r\n")]).map_err(io::Error::other)?;
+    fs::write(dir.join("local"), "test \n")?;
+
+    let git = GitRef {
+        repo: dir.join("repo"),
+        rev: "v1.0:file".to_string(),
+    };
+    assert_eq!(
+        compare_file(&git, dir.join("repo/file"), &Default::default())?,
+        Equal
+    );
+    assert_eq!(
+        compare_file(&git, dir.join("local"), &Default::default())?,
+        Different(5)
+    );
+    let quick = CompareOptions {
+        quick: true,
+        ..Default::default()
+    };
+    assert_eq!(compare_file(&git, dir.join("local"), &quick)?, Different(0));
+    Ok(())
+}
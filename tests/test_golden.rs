@@ -0,0 +1,61 @@
+use file_cmp::assert_files_equal;
+use file_cmp::golden::{describe_difference, GoldenFile};
+use std::fs;
+use std::io;
+use std::panic;
+
+// Test describing the first difference between expected and actual contents
+#[test]
+fn test_describe_difference() {
+    assert_eq!(describe_difference(b"same\n", b"same\n"), None);
+    assert_eq!(
+        describe_difference(b"one\ntwo\n", b"one\ntwo\nthree\n").as_deref(),
+        Some("expected ends after byte 8 (line 3, column 1)\n  expected: \"one\\ntwo\\n\"\n    actual: \"one\\ntwo\\nthree\\n\"")
+    );
+    assert_eq!(
+        describe_difference(b"abc", b"ab").as_deref(),
+        Some(
+            "actual ends after byte 2 (line 1, column 3)\n  expected: \"abc\"\n    actual: \"ab\""
+        )
+    );
+    let expected = format!("{}\nvalue = 1\n{}", "x".repeat(40), "y".repeat(40));
+    let actual = expected.replace("= 1", "= 2");
+    let difference = describe_difference(expected.as_bytes(), actual.as_bytes()).unwrap();
+    assert!(difference.starts_with("first difference at byte 49 (line 2, column 9)\n"));
+    assert!(difference.contains("expected: ...\"xxxxxxx\\nvalue = 1\\nyyyyyyyyyyyyyy\"..."));
+    assert!(difference.contains("actual: ...\"xxxxxxx\\nvalue = 2\\nyyyyyyyyyyyyyy\"..."));
+}
+
+// Test creating, checking and updating a golden file
+#[test]
+fn test_golden_file() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_golden");
+    let _ = fs::remove_dir_all(&dir);
+    let golden = GoldenFile::new(dir.join("output.txt"));
+
+    assert!(golden.clone().updating(false).check(b"abc").is_err());
+    assert_eq!(golden.clone().updating(true).check(b"abc")?, None);
+    assert_eq!(fs::read(golden.path())?, b"abc");
+
+    let golden = golden.updating(false);
+    assert_eq!(golden.check(b"abc")?, None);
+    assert!(golden.check(b"abd")?.is_some());
+    golden.assert("abc");
+    let result = panic::catch_unwind(|| golden.assert("abd"));
+    assert!(result.is_err());
+    fs::remove_dir_all(&dir)
+}
+
+// Test asserting that two files are equal
+#[test]
+fn test_assert_files_equal() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_golden_assert");
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join("1"), "abc")?;
+    fs::write(dir.join("2"), "abc")?;
+    fs::write(dir.join("3"), "abd")?;
+    assert_files_equal!(dir.join("1"), dir.join("2"));
+    let result = panic::catch_unwind(|| assert_files_equal!(dir.join("1"), dir.join("3")));
+    assert!(result.is_err());
+    fs::remove_dir_all(&dir)
+}
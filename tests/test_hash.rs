@@ -0,0 +1,46 @@
+use file_cmp::hash::{hash_reader, to_hex, HashAlgorithm};
+use file_cmp::FileDiff::*;
+use file_cmp::{compare_files_with, CompareOptions};
+use std::io;
+
+fn p(p: &str) -> String {
+    format!("./tests/testfiles/{}", p)
+}
+
+#[test]
+fn test_hash_sha256() -> io::Result<()> {
+    let digest = hash_reader(&mut &b"abc"[..], HashAlgorithm::Sha256)?;
+    assert_eq!(
+        to_hex(&digest),
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_parse_hash_algorithm() {
+    assert_eq!("SHA256".parse(), Ok(HashAlgorithm::Sha256));
+    assert!("nope".parse::<HashAlgorithm>().is_err());
+}
+
+#[test]
+fn test_compare_hash_first() -> io::Result<()> {
+    // Test that hashing first gives the same results as comparing directly
+    let options = CompareOptions {
+        hash_first: true,
+        ..Default::default()
+    };
+    let res = compare_files_with(p("test.txt"), p("text.txt"), &options)?;
+    assert_eq!(res, Different(2));
+    let res = compare_files_with(p("testing.txt"), p("test.txt"), &options)?;
+    assert_eq!(res, Different(4));
+    let res = compare_files_with(p("test.txt"), p("test.txt"), &options)?;
+    assert_eq!(res, Equal);
+    let quick = CompareOptions {
+        quick: true,
+        ..options
+    };
+    let res = compare_files_with(p("test.txt"), p("west.txt"), &quick)?;
+    assert_eq!(res, Different(0));
+    Ok(())
+}
@@ -0,0 +1,153 @@
+use file_cmp::heartbeat::{Heartbeat, Progress};
+use file_cmp::hooks::Summary;
+use file_cmp::{compare_files_with, CompareOptions, FileDiff};
+use serde_json::Value;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::Command;
+use std::sync::mpsc::{self, Sender};
+use std::sync::Arc;
+use std::time::Duration;
+
+// Test the status line as files are compared
+#[test]
+fn test_status() -> io::Result<()> {
+    let progress = Arc::new(Progress::new());
+    let second = Duration::from_secs(1);
+    assert_eq!(progress.status(second), "0 files done, 0 B/s");
+
+    progress.set_total(3);
+    progress.start(Path::new("a.bin"));
+    assert_eq!(
+        progress.status(second),
+        "0/3 files done, 0 B/s, comparing a.bin for 0s"
+    );
+    progress.finish(2048);
+    assert_eq!(progress.status(second * 2), "1/3 files done, 1.00 KiB/s");
+
+    // Comparisons record themselves in the progress of their options
+    let options = CompareOptions {
+        progress: Some(progress.clone()),
+        ..Default::default()
+    };
+    compare_files_with(
+        "./tests/testfiles/test.txt",
+        "./tests/testfiles/tesx.txt",
+        &options,
+    )?;
+    assert_eq!(progress.status(second * 2055), "2/3 files done, 1 B/s");
+    Ok(())
+}
+
+/// Sends each line written to it.
+struct Lines(Sender<String>);
+
+impl Write for Lines {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let _ = self.0.send(String::from_utf8_lossy(buf).into_owned());
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// Test that the heartbeat writes the status at the interval until dropped
+#[test]
+fn test_heartbeat() {
+    let progress = Arc::new(Progress::new());
+    progress.start(Path::new("a.bin"));
+    let (sender, lines) = mpsc::channel();
+    let heartbeat = Heartbeat::start(progress, Duration::from_millis(10), Lines(sender));
+    let line = lines.recv_timeout(Duration::from_secs(10)).unwrap();
+    assert!(line.starts_with("heartbeat: 0 files done, 0 B/s, comparing a.bin for "));
+    drop(heartbeat);
+    while lines.recv_timeout(Duration::from_secs(10)).is_ok() {}
+}
+
+// Test the progress events of a run with a known number of files
+#[test]
+fn test_progress_events() {
+    let (sender, lines) = mpsc::channel();
+    let progress = Progress::with_events(Lines(sender));
+    progress.set_total(2);
+    let result = Ok(FileDiff::Different(7));
+    progress.file_done(Path::new("a.bin"), Path::new("b.bin"), 100, &result);
+    let error = Err(io::Error::new(io::ErrorKind::NotFound, "gone"));
+    progress.file_done(Path::new("c.bin"), Path::new("d.bin"), 0, &error);
+    progress.finished(&Summary::new(), false);
+    drop(progress);
+
+    // Writes may not be whole lines
+    let written: String = lines.iter().collect();
+    let events: Vec<Value> = written
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let names: Vec<&str> = events
+        .iter()
+        .map(|e| e["event"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        names,
+        [
+            "started",
+            "file_done",
+            "percent",
+            "eta",
+            "file_done",
+            "percent",
+            "eta",
+            "finished"
+        ]
+    );
+    assert_eq!(events[1]["path1"], "a.bin");
+    assert_eq!(events[1]["result"], "diff");
+    assert_eq!(events[1]["offset"], 7);
+    assert_eq!(events[1]["bytes"], 100);
+    assert_eq!(events[2]["percent"], 50.0);
+    assert_eq!(events[4]["error"], "gone");
+    assert_eq!(events[5]["percent"], 100.0);
+    assert_eq!(events[6]["seconds"], 0);
+    assert_eq!(events[7]["success"], false);
+    assert_eq!(events[7]["bytes"], 100);
+}
+
+// Test writing the events of --progress-json to a file
+#[test]
+fn test_progress_json() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_progress_json");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir)?;
+    let output = Command::new(env!("CARGO_BIN_EXE_file_cmp"))
+        .env_remove("FILE_CMP_MACHINE")
+        .arg(format!("--progress-json={}", dir.join("events").display()))
+        .args([
+            "./tests/testfiles/test.txt",
+            "./tests/testfiles/tesx.txt",
+            "./tests/testfiles/test.txt",
+            "./tests/testfiles/test.txt",
+        ])
+        .output()?;
+    assert!(output.status.success());
+    assert!(output.stderr.is_empty());
+    let events = fs::read_to_string(dir.join("events"))?;
+    let events: Vec<Value> = events
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(events[0]["event"], "started");
+    let done: Vec<&Value> = events
+        .iter()
+        .filter(|e| e["event"] == "file_done")
+        .collect();
+    assert_eq!(done.len(), 2);
+    assert_eq!(done[1]["result"], "equal");
+    let finished = events.last().unwrap();
+    assert_eq!(finished["event"], "finished");
+    assert_eq!(finished["equal"], 1);
+    assert_eq!(finished["different"], 1);
+    fs::remove_dir_all(&dir)
+}
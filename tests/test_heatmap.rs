@@ -0,0 +1,115 @@
+use file_cmp::heatmap::diff_map;
+use file_cmp::FileDiff::*;
+use file_cmp::{CompareOptions, Side};
+use std::fs;
+use std::io;
+use std::process::Command;
+
+// Test counting the differing bytes by cell, across cells and past the end
+// of the shorter file
+#[test]
+fn test_diff_map() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_diff_map");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir)?;
+    let data = vec![7u8; 3 << 20];
+    fs::write(dir.join("a"), &data)?;
+    fs::write(dir.join("b"), &data)?;
+    let options = CompareOptions::default();
+    let map = diff_map(dir.join("a"), dir.join("b"), 16, &options)?;
+    assert_eq!(map.file_diff, Equal);
+    assert_eq!(map.cell_len, 3 << 16);
+    assert_eq!(map.counts, vec![0; 16]);
+
+    let mut changed = data.clone();
+    changed[100] = 0;
+    // A run crossing from the second cell into the third and a read
+    for byte in &mut changed[(6 << 16) - 10..(6 << 16) + 20] {
+        *byte = 0;
+    }
+    changed.truncate((3 << 20) - 1000);
+    fs::write(dir.join("b"), &changed)?;
+    let map = diff_map(dir.join("a"), dir.join("b"), 16, &options)?;
+    assert_eq!(map.file_diff, Different(100));
+    assert_eq!(map.len, 3 << 20);
+    let mut expected = vec![0; 16];
+    expected[0] = 1;
+    expected[1] = 10;
+    expected[2] = 20;
+    expected[15] = 1000;
+    assert_eq!(map.counts, expected);
+    assert_eq!(map.level(0), 1);
+    assert_eq!(map.level(3), 0);
+
+    fs::write(dir.join("b"), &data[..1000])?;
+    let map = diff_map(dir.join("a"), dir.join("b"), 16, &options)?;
+    assert_eq!(map.file_diff, Truncated(Side::Right, 1000));
+    assert_eq!(map.level(15), 4);
+
+    fs::write(dir.join("a"), b"")?;
+    fs::write(dir.join("b"), b"")?;
+    let map = diff_map(dir.join("a"), dir.join("b"), 16, &options)?;
+    assert_eq!(map.file_diff, Equal);
+    assert!(map.counts.is_empty());
+    fs::remove_dir_all(&dir)
+}
+
+// Test the terminal map and SVG file of --heatmap
+#[test]
+fn test_heatmap_output() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_heatmap_output");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir)?;
+    let data = vec![1u8; 512];
+    let mut changed = data.clone();
+    changed[8..16].fill(0);
+    fs::write(dir.join("a"), &data)?;
+    fs::write(dir.join("b"), &changed)?;
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_file_cmp"))
+            .current_dir(&dir)
+            .env_remove("FILE_CMP_MACHINE")
+            .args(args)
+            .output()
+    };
+
+    let output = run(&["--heatmap", "a", "b"])?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines[0], "Files differ at byte 8");
+    assert!(lines[1].starts_with("Each block is 1 B:"), "{}", lines[1]);
+    assert_eq!(lines.len(), 10);
+    assert_eq!(
+        lines[2],
+        format!(
+            "{:>14} |{}{}{}|",
+            0,
+            " ".repeat(8),
+            "█".repeat(8),
+            " ".repeat(48)
+        )
+    );
+
+    let output = run(&["-m", "--heatmap", "a", "b"])?;
+    let counts = format!(
+        "{},{},{}",
+        ["0"; 8].join(","),
+        ["1"; 8].join(","),
+        ["0"; 496].join(",")
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        format!("8\t1\t{}", counts)
+    );
+
+    let output = run(&["--heatmap=map.svg", "a", "b"])?;
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "Files differ at byte 8"
+    );
+    let svg = fs::read_to_string(dir.join("map.svg"))?;
+    assert!(svg.starts_with("<svg "));
+    assert_eq!(svg.matches("<rect ").count(), 512);
+    assert!(svg.contains("<title>8-8: 1 bytes differ</title>"));
+    fs::remove_dir_all(&dir)
+}
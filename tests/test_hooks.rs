@@ -0,0 +1,67 @@
+use file_cmp::hooks::Summary;
+use file_cmp::FileDiff::*;
+
+// Test counting results into a summary
+#[test]
+fn test_summary() {
+    let mut summary = Summary::new();
+    for diff in [Equal, Equal, Different(4), LeftOnly, RightOnly, RightOnly] {
+        summary.add(&diff);
+    }
+    assert_eq!(
+        summary,
+        Summary {
+            equal: 2,
+            different: 1,
+            left_only: 1,
+            right_only: 2,
+        }
+    );
+    assert_eq!(
+        summary.to_json(true).to_string(),
+        r#"{"different":1,"equal":2,"left_only":1,"right_only":2,"success":true}"#
+    );
+}
+
+#[cfg(unix)]
+mod unix {
+    use super::*;
+    use file_cmp::hooks::{run_after, run_before};
+    use std::fs;
+    use std::io;
+
+    // Test running hooks and passing the summary to the after hook
+    #[test]
+    fn test_run_hooks() -> io::Result<()> {
+        let dir = std::env::temp_dir().join("file_cmp_test_hooks");
+        fs::create_dir_all(&dir)?;
+        let marker = dir.join("before");
+        let _ = fs::remove_file(&marker);
+        run_before(&format!("touch '{}'", marker.display()))?;
+        assert!(marker.exists());
+        assert!(run_before("exit 3").is_err());
+
+        let summary = Summary {
+            equal: 5,
+            different: 1,
+            ..Default::default()
+        };
+        let output = dir.join("after");
+        run_after(
+            &format!(
+                "{{ echo $FILE_CMP_RESULT_EQUAL $FILE_CMP_RESULT_DIFFERENT $FILE_CMP_RESULT_SUCCESS; cat; }} > '{}'",
+                output.display()
+            ),
+            &summary,
+            false,
+        )?;
+        assert_eq!(
+            fs::read_to_string(&output)?,
+            "5 1 0\n{\"different\":1,\"equal\":5,\"left_only\":0,\"right_only\":0,\"success\":false}\n"
+        );
+        // Hooks don't have to read the summary
+        run_after("true", &summary, true)?;
+        assert!(run_after("false", &summary, true).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,89 @@
+use file_cmp::http::is_url;
+
+#[test]
+fn test_is_url() {
+    assert!(is_url("http://example.com/file.bin"));
+    assert!(is_url("HTTPS://example.com/file.bin"));
+    assert!(!is_url("ftp://example.com/file.bin"));
+    assert!(!is_url("./tests/testfiles/test.txt"));
+    assert!(!is_url(r"C:\\file.bin"));
+}
+
+#[cfg(feature = "http")]
+mod server {
+    use file_cmp::http::{compare_url, compare_urls};
+    use file_cmp::CompareOptions;
+    use file_cmp::FileDiff::*;
+    use file_cmp::Side;
+    use std::io::{self, BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    fn p(p: &str) -> String {
+        format!("./tests/testfiles/{}", p)
+    }
+
+    /// Serves the body to the given number of requests on a local port and
+    /// returns its URL.
+    fn serve(body: &'static [u8], requests: usize) -> io::Result<String> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let url = format!("http://{}/file", listener.local_addr()?);
+        thread::spawn(move || {
+            for stream in listener.incoming().take(requests) {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+                let header = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    body.len()
+                );
+                stream.write_all(header.as_bytes()).unwrap();
+                stream.write_all(body).unwrap();
+            }
+        });
+        Ok(url)
+    }
+
+    #[test]
+    fn test_compare_url() -> io::Result<()> {
+        // Test when the downloaded body is equal to or differs from the file
+        let options = CompareOptions::default();
+        assert_eq!(
+            compare_url(&serve(b"test \r\n", 1)?, p("test.txt"), &options)?,
+            Equal
+        );
+        let res = compare_url(&serve(b"test \r\n", 1)?, p("tesx.txt"), &options)?;
+        assert_eq!(res, Different(3));
+        let res = compare_urls(&serve(b"test", 1)?, &serve(b"test \r\n", 1)?, &options)?;
+        assert_eq!(res, Truncated(Side::Left, 4));
+        Ok(())
+    }
+
+    #[test]
+    fn test_compare_url_prefix() -> io::Result<()> {
+        // Test a body that ends early, e.g. an interrupted upload, as between two files
+        let options = CompareOptions::default();
+        let res = compare_url(&serve(b"test", 1)?, p("test.txt"), &options)?;
+        assert_eq!(res, Truncated(Side::Left, 4));
+        let res = compare_url(&serve(b"", 1)?, p("test.txt"), &options)?;
+        assert_eq!(res, Truncated(Side::Left, 0));
+        let res = compare_url(&serve(b"test \r\n and more", 1)?, p("test.txt"), &options)?;
+        assert_eq!(res, Truncated(Side::Right, 7));
+        Ok(())
+    }
+
+    #[test]
+    fn test_compare_url_quick() -> io::Result<()> {
+        // Test that the Content-Length is checked against the file size
+        let options = CompareOptions {
+            quick: true,
+            ..Default::default()
+        };
+        let res = compare_url(&serve(b"test \r\n", 1)?, p("testing.txt"), &options)?;
+        assert_eq!(res, Different(0));
+        Ok(())
+    }
+}
@@ -0,0 +1,46 @@
+#![cfg(unix)]
+
+use std::fs;
+use std::io;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+// Test that Ctrl-C stops a directory comparison with the results so far
+#[test]
+fn test_interrupt() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_interrupt");
+    let (a, b) = (dir.join("a"), dir.join("b"));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&a)?;
+    fs::create_dir_all(&b)?;
+    for i in 0..20 {
+        let name = format!("{:02}", i);
+        fs::write(a.join(&name), [0; 1024])?;
+        fs::write(b.join(&name), [0; 1024])?;
+    }
+
+    // Throttled to about half a second per pair
+    let child = Command::new(env!("CARGO_BIN_EXE_file_cmp"))
+        .args(["-m", "--bwlimit", "4k"])
+        .arg(&a)
+        .arg(&b)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    thread::sleep(Duration::from_millis(1200));
+    unsafe { libc::kill(child.id() as libc::pid_t, libc::SIGINT) };
+    let output = child.wait_with_output()?;
+
+    assert_eq!(output.status.code(), Some(130));
+    let printed = String::from_utf8_lossy(&output.stdout).lines().count();
+    assert!(printed > 0 && printed < 20, "{} results", printed);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        format!(
+            "Cancelled: {} equal, 0 different, 0 left only, 0 right only\n",
+            printed
+        )
+    );
+    fs::remove_dir_all(&dir)
+}
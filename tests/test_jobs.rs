@@ -0,0 +1,75 @@
+use file_cmp::walk::DirWalk;
+use file_cmp::{CompareOptions, FileDiff};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+type Results = Vec<(PathBuf, FileDiff)>;
+type Failed = Vec<(PathBuf, PathBuf)>;
+
+/// Compares the trees with this many jobs, returning the results and the
+/// pairs that failed, sorted.
+fn walk(a: &Path, b: &Path, jobs: usize) -> (Results, Failed) {
+    let options = CompareOptions {
+        jobs,
+        ..Default::default()
+    };
+    let mut walk = DirWalk::new(a, b, &options);
+    let mut results: Vec<_> = walk.by_ref().collect();
+    results.sort_by(|x, y| x.0.cmp(&y.0));
+    let mut failed: Vec<_> = walk
+        .take_failed()
+        .into_iter()
+        .map(|(path1, path2, _)| (path1, path2))
+        .collect();
+    failed.sort();
+    (results, failed)
+}
+
+// Test comparing the pairs of files of a walk several at a time
+#[test]
+fn test_jobs() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_jobs");
+    let _ = fs::remove_dir_all(&dir);
+    let (a, b) = (dir.join("a"), dir.join("b"));
+    for side in [&a, &b] {
+        for sub in ["x", "y/z"] {
+            fs::create_dir_all(side.join(sub))?;
+        }
+    }
+    for i in 0..60 {
+        let sub = ["", "x", "y/z"][i % 3];
+        let name = format!("f{}", i);
+        fs::write(a.join(sub).join(&name), format!("data {}", i))?;
+        match i % 7 {
+            0 => {}
+            1 => fs::write(b.join(sub).join(&name), "changed")?,
+            _ => fs::write(b.join(sub).join(&name), format!("data {}", i))?,
+        }
+    }
+    fs::write(b.join("x/extra"), "")?;
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink("missing", a.join("broken"))?;
+        std::os::unix::fs::symlink("missing", b.join("broken"))?;
+    }
+
+    let (results, failed) = walk(&a, &b, 4);
+    assert_eq!((results.clone(), failed.clone()), walk(&a, &b, 1));
+    assert_eq!(results.len(), 61);
+    let count = |diff: FileDiff| results.iter().filter(|(_, d)| *d == diff).count();
+    assert_eq!(count(FileDiff::LeftOnly), 9);
+    assert_eq!(count(FileDiff::Different(0)), 9);
+    #[cfg(unix)]
+    assert_eq!(failed, [(a.join("broken"), b.join("broken"))]);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_file_cmp"))
+        .current_dir(&dir)
+        .env_remove("FILE_CMP_MACHINE")
+        .args(["-m", "-j", "4", "a", "b"])
+        .output()?;
+    let lines = String::from_utf8_lossy(&output.stdout).lines().count();
+    assert_eq!(lines, 61);
+    fs::remove_dir_all(&dir)
+}
@@ -0,0 +1,87 @@
+use file_cmp::{FileDiff, PathDiff, Side};
+use serde_json::{json, Value};
+use std::fs;
+use std::io;
+use std::process::Command;
+
+// Test the JSON form of results
+#[test]
+fn test_serialize() {
+    let to_value = |diff: FileDiff| serde_json::to_value(diff).unwrap();
+    assert_eq!(to_value(FileDiff::Equal), json!({ "status": "equal" }));
+    assert_eq!(
+        to_value(FileDiff::Different(42)),
+        json!({ "status": "diff", "offset": 42 })
+    );
+    assert_eq!(
+        to_value(FileDiff::Truncated(Side::Right, 7)),
+        json!({ "status": "eof on right", "offset": 7 })
+    );
+    let result = PathDiff {
+        path: "a/b".to_string(),
+        diff: FileDiff::LeftOnly,
+    };
+    assert_eq!(
+        serde_json::to_value(result).unwrap(),
+        json!({ "path": "a/b", "status": "left only" })
+    );
+}
+
+// Test --json for a pair, trees and listed pairs
+#[test]
+fn test_json() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_json");
+    let _ = fs::remove_dir_all(&dir);
+    for side in ["a", "b", "empty1", "empty2"] {
+        fs::create_dir_all(dir.join(side))?;
+    }
+    fs::write(dir.join("a/differ"), b"abcdef")?;
+    fs::write(dir.join("b/differ"), b"abcxef")?;
+    fs::write(dir.join("a/equal"), b"same")?;
+    fs::write(dir.join("b/equal"), b"same")?;
+    fs::write(dir.join("a/left"), b"")?;
+    let run = |args: &[&str]| -> io::Result<Value> {
+        let output = Command::new(env!("CARGO_BIN_EXE_file_cmp"))
+            .current_dir(&dir)
+            .env("FILE_CMP_MACHINE", "1")
+            .args(args)
+            .output()?;
+        assert!(output.status.success());
+        Ok(serde_json::from_slice(&output.stdout)?)
+    };
+
+    assert_eq!(
+        run(&["--json", "a/differ", "b/differ"])?,
+        json!({ "path": "a/differ", "status": "diff", "offset": 3 })
+    );
+
+    let Value::Array(mut results) = run(&["--json", "a", "b"])? else {
+        panic!("expected an array");
+    };
+    results.sort_by_key(|result| result["path"].to_string());
+    assert_eq!(
+        results,
+        [
+            json!({ "path": "a/differ", "status": "diff", "offset": 3 }),
+            json!({ "path": "a/equal", "status": "equal" }),
+            json!({ "path": "a/left", "status": "left only" }),
+        ]
+    );
+
+    assert_eq!(
+        run(&["--json", "-d", "a/equal", "b/equal", "a/differ", "b/differ"])?,
+        json!([{ "path": "a/differ", "status": "diff", "offset": 3 }])
+    );
+    assert_eq!(
+        run(&["--json", "-d", "a/equal", "b/equal"])?,
+        json!({ "path": "a/equal", "status": "equal" })
+    );
+    assert_eq!(run(&["--json", "empty1", "empty2"])?, json!([]));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_file_cmp"))
+        .current_dir(&dir)
+        .args(["--json", "--porcelain", "a", "b"])
+        .output()?;
+    assert!(!output.status.success());
+    fs::remove_dir_all(&dir)
+}
@@ -0,0 +1,158 @@
+use file_cmp::lines::{compare_lines, position_at, strip_prefix};
+use file_cmp::FileDiff::*;
+use file_cmp::{compare_files_with, CompareOptions};
+use regex::bytes::Regex;
+use std::fs;
+use std::io;
+use std::process::Command;
+
+// Test comparing files line by line with filters
+#[test]
+fn test_compare_lines() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_lines");
+    fs::create_dir_all(&dir)?;
+    let (path1, path2) = (dir.join("1"), dir.join("2"));
+    fs::write(&path1, "a\n# note\nb\nc")?;
+    fs::write(&path2, "a\r\nb\r\nc\r\n")?;
+    let keep = |_, line: &[u8]| Some(0..line.len());
+    let skip_comments = |_, line: &[u8]| (!line.starts_with(b"#")).then_some(0..line.len());
+    assert_eq!(
+        compare_lines(&path1, &path2, keep, keep, false)?,
+        Different(2)
+    );
+    assert_eq!(
+        compare_lines(&path1, &path2, skip_comments, keep, false)?,
+        Equal
+    );
+
+    fs::write(&path2, "a\nb\nc\nd\n")?;
+    assert_eq!(
+        compare_lines(&path1, &path2, skip_comments, keep, false)?,
+        Different(12)
+    );
+    assert_eq!(
+        compare_lines(&path2, &path1, keep, skip_comments, false)?,
+        Different(6)
+    );
+    fs::remove_dir_all(&dir)
+}
+
+// Test comparing logs that differ in their timestamps
+#[test]
+fn test_strip_prefix() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_lines_prefix");
+    fs::create_dir_all(&dir)?;
+    let (path1, path2) = (dir.join("1.log"), dir.join("2.log"));
+    fs::write(
+        &path1,
+        "2024-01-01T10:00:00Z started\n2024-01-01T10:00:01Z done at 10:00\n",
+    )?;
+    fs::write(
+        &path2,
+        "2025-03-04T08:30:00Z started\n2025-03-04T08:30:09Z done at 10:00\n",
+    )?;
+    let options = CompareOptions {
+        strip_prefix: Some(Regex::new("^[0-9-]+T[0-9:]+Z ").unwrap()),
+        ..Default::default()
+    };
+    assert_eq!(compare_files_with(&path1, &path2, &options)?, Equal);
+    let regex = Regex::new("[0-9:]+").unwrap();
+    assert_eq!(
+        compare_lines(
+            &path1,
+            &path2,
+            strip_prefix(&regex),
+            strip_prefix(&regex),
+            false
+        )?,
+        Different(0)
+    );
+
+    let run = |args: &[&str]| -> io::Result<String> {
+        let output = Command::new(env!("CARGO_BIN_EXE_file_cmp"))
+            .current_dir(&dir)
+            .env_remove("FILE_CMP_MACHINE")
+            .args(["-m", "1.log", "2.log"])
+            .args(args)
+            .output()?;
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    };
+    assert_eq!(run(&[])?, "3");
+    assert_eq!(run(&["--strip-prefix-regex", "[0-9-]+T[0-9:]+Z "])?, "-1");
+    // Only matches at the start of a line are stripped
+    assert_eq!(run(&["--strip-prefix-regex", "[a-z]+ "])?, "0");
+    fs::remove_dir_all(&dir)
+}
+
+// Test finding the line and column of an offset, and telling text from
+// binary files
+#[test]
+fn test_position_at() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_position_at");
+    fs::create_dir_all(&dir)?;
+    let (text, binary) = (dir.join("text"), dir.join("binary"));
+    fs::write(&text, "one\ntwo\r\nthree\n")?;
+    assert_eq!(position_at(&text, 0)?, Some((1, 1)));
+    assert_eq!(position_at(&text, 3)?, Some((1, 4)));
+    assert_eq!(position_at(&text, 4)?, Some((2, 1)));
+    assert_eq!(position_at(&text, 8)?, Some((2, 5)));
+    assert_eq!(position_at(&text, 11)?, Some((3, 3)));
+    assert_eq!(position_at(&text, 100)?, Some((4, 1)));
+    // Columns carry on across the reads of a long line
+    let long = format!("a\n{}", "b".repeat(20000));
+    fs::write(&text, &long)?;
+    assert_eq!(position_at(&text, 19000)?, Some((2, 18999)));
+    // Only the first 8000 bytes and the ones before the offset are checked
+    let mut data = "line\n".repeat(2000).into_bytes();
+    data[9000] = 0;
+    fs::write(&binary, &data)?;
+    assert_eq!(position_at(&binary, 5)?, Some((2, 1)));
+    assert_eq!(position_at(&binary, 9500)?, None);
+    data[9000] = b'\n';
+    data[7000] = 0;
+    fs::write(&binary, &data)?;
+    assert_eq!(position_at(&binary, 5)?, None);
+    fs::remove_dir_all(&dir)
+}
+
+// Test the line and the sizes in the description of a difference
+#[test]
+fn test_describe_offset() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_describe_offset");
+    fs::create_dir_all(&dir)?;
+    let (path1, path2) = (dir.join("1"), dir.join("2"));
+    let lines: String = (0..1000).map(|i| format!("line {}\n", i)).collect();
+    fs::write(&path1, &lines)?;
+    fs::write(&path2, lines.replace("line 500", "line five hundred"))?;
+    let output = Command::new(env!("CARGO_BIN_EXE_file_cmp"))
+        .env_remove("FILE_CMP_MACHINE")
+        .arg(&path1)
+        .arg(&path2)
+        .output()?;
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "Files differ at byte 4,395 (4.29 KiB of 8.68 KiB and 8.69 KiB), line 501, column 6"
+    );
+    let output = Command::new(env!("CARGO_BIN_EXE_file_cmp"))
+        .env_remove("FILE_CMP_MACHINE")
+        .arg("-m")
+        .arg(&path1)
+        .arg(&path2)
+        .output()?;
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "4395");
+
+    // No line when either file is binary
+    let mut binary = lines.replace("line 500", "line five hundred").into_bytes();
+    binary[7000] = 0;
+    fs::write(&path2, binary)?;
+    let output = Command::new(env!("CARGO_BIN_EXE_file_cmp"))
+        .env_remove("FILE_CMP_MACHINE")
+        .arg(&path1)
+        .arg(&path2)
+        .output()?;
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "Files differ at byte 4,395 (4.29 KiB of 8.68 KiB and 8.69 KiB)"
+    );
+    fs::remove_dir_all(&dir)
+}
@@ -0,0 +1,82 @@
+#![cfg(unix)]
+
+use file_cmp::walk::{DirWalk, Links};
+use file_cmp::CompareOptions;
+use file_cmp::FileDiff::{self, *};
+use std::fs;
+use std::io;
+use std::os::unix::fs::symlink;
+use std::path::Path;
+use std::process::Command;
+
+/// Compares the trees with links treated as `links` says, returning the
+/// results by relative path.
+fn walk(a: &Path, b: &Path, links: Links) -> Vec<(String, FileDiff)> {
+    let options = CompareOptions {
+        links,
+        ..Default::default()
+    };
+    let mut results: Vec<_> = DirWalk::new(a, b, &options)
+        .map(|(path, diff)| {
+            let root = [a, b].into_iter().find(|r| path.starts_with(r)).unwrap();
+            let relative = path.strip_prefix(root).unwrap();
+            (relative.to_string_lossy().into_owned(), diff)
+        })
+        .collect();
+    results.sort_by(|x, y| x.0.cmp(&y.0));
+    results
+}
+
+// Test following, skipping and comparing links, including one that loops
+#[test]
+fn test_links() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_links");
+    let _ = fs::remove_dir_all(&dir);
+    let (a, b) = (dir.join("a"), dir.join("b"));
+    for side in [&a, &b] {
+        fs::create_dir_all(side.join("sub"))?;
+        fs::write(side.join("x"), "data")?;
+        fs::write(side.join("sub/f"), "f")?;
+        symlink("sub", side.join("dirlink"))?;
+        symlink(".", side.join("loop"))?;
+    }
+    fs::write(b.join("y"), "data")?;
+    symlink("x", a.join("link"))?;
+    symlink("y", b.join("link"))?;
+
+    let files = |mut extra: Vec<(&str, FileDiff)>| {
+        extra.extend([("sub/f", Equal), ("x", Equal), ("y", RightOnly)]);
+        let mut results: Vec<_> = extra
+            .into_iter()
+            .map(|(path, diff)| (path.to_string(), diff))
+            .collect();
+        results.sort_by(|x, y| x.0.cmp(&y.0));
+        results
+    };
+    assert_eq!(
+        walk(&a, &b, Links::Follow),
+        files(vec![("dirlink/f", Equal), ("link", Equal)])
+    );
+    assert_eq!(walk(&a, &b, Links::Skip), files(vec![]));
+    assert_eq!(
+        walk(&a, &b, Links::Compare),
+        files(vec![
+            ("dirlink", Equal),
+            ("link", Different(0)),
+            ("loop", Equal)
+        ])
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_file_cmp"))
+        .current_dir(&dir)
+        .env_remove("FILE_CMP_MACHINE")
+        .args(["-m", "--links", "skip", "a", "b"])
+        .output()?;
+    let mut lines: Vec<_> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::to_string)
+        .collect();
+    lines.sort();
+    assert_eq!(lines, ["-1\ta/sub/f", "-1\ta/x", "-3\tb/y"]);
+    fs::remove_dir_all(&dir)
+}
@@ -0,0 +1,47 @@
+use file_cmp::long_path::{from_extended, restore, to_extended};
+use std::path::{Path, PathBuf};
+
+// Test converting drive and UNC paths to the extended form and back
+#[test]
+fn test_extended() {
+    let cases = [
+        (r"C:\dir\file", r"\\?\C:\dir\file"),
+        (r"d:\", r"\\?\d:\"),
+        (r"\\server\share\dir", r"\\?\UNC\server\share\dir"),
+    ];
+    for (path, extended) in cases {
+        assert_eq!(to_extended(path).as_deref(), Some(extended));
+        assert_eq!(from_extended(extended), path);
+    }
+
+    // Relative and device paths are left alone
+    for path in [r"dir\file", r"C:file", r"\\.\PhysicalDrive0", "/tmp"] {
+        assert_eq!(to_extended(path), None);
+        assert_eq!(from_extended(path), path);
+    }
+    // Already extended paths are only shortened when they can be
+    assert_eq!(to_extended(r"\\?\C:\dir"), None);
+    assert_eq!(
+        from_extended(r"\\?\Volume{1234}\dir"),
+        r"\\?\Volume{1234}\dir"
+    );
+}
+
+// Test mapping paths found below an extended root back below the root as given
+#[test]
+fn test_restore() {
+    let root = Path::new("tree");
+    let extended = Path::new("/abs/tree");
+    assert_eq!(
+        restore(PathBuf::from("/abs/tree/a/b"), extended, root),
+        Path::new("tree/a/b")
+    );
+    assert_eq!(
+        restore(PathBuf::from("/other/a"), extended, root),
+        Path::new("/other/a")
+    );
+    assert_eq!(
+        restore(PathBuf::from("tree/a"), root, root),
+        Path::new("tree/a")
+    );
+}
@@ -0,0 +1,122 @@
+use file_cmp::media::{compare_media, essence, Format};
+use file_cmp::FileDiff::*;
+use file_cmp::{compare_files_with, CompareOptions};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Returns the ranges of a file's essence as pairs of offsets.
+fn ranges(path: &Path, format: Format) -> io::Result<Vec<(u64, u64)>> {
+    let ranges = essence(path, format)?;
+    Ok(ranges.into_iter().map(|r| (r.start, r.end)).collect())
+}
+
+fn id3v2(size: u8) -> Vec<u8> {
+    [
+        &b"ID3\x04\x00\x00\x00\x00\x00"[..],
+        &[size],
+        &vec![0; size as usize],
+    ]
+    .concat()
+}
+
+fn flac_block(kind: u8, last: bool, body: &[u8]) -> Vec<u8> {
+    let flags = kind | (last as u8) << 7;
+    [&[flags, 0, 0, body.len() as u8][..], body].concat()
+}
+
+fn mp4_box(kind: &[u8], body: &[u8]) -> Vec<u8> {
+    [&(body.len() as u32 + 8).to_be_bytes()[..], kind, body].concat()
+}
+
+// Test finding the audio data of MP3 files between their tags
+#[test]
+fn test_mp3_essence() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_media_mp3");
+    fs::create_dir_all(&dir)?;
+    let path = dir.join("song.mp3");
+    let id3v1 = [&b"TAG"[..], &[0; 125]].concat();
+    fs::write(&path, [id3v2(20), b"frames".to_vec(), id3v1].concat())?;
+    assert_eq!(ranges(&path, Format::Mp3)?, [(30, 36)]);
+
+    let mut ape = b"APETAGEX".to_vec();
+    ape.extend(2000u32.to_le_bytes());
+    ape.extend(40u32.to_le_bytes());
+    ape.extend(0u32.to_le_bytes());
+    ape.extend((1u32 << 31).to_le_bytes());
+    ape.extend([0; 8]);
+    let items = vec![0; 8];
+    fs::write(
+        &path,
+        [b"frames".to_vec(), vec![0; 32], items, ape].concat(),
+    )?;
+    assert_eq!(ranges(&path, Format::Mp3)?, [(0, 6)]);
+
+    fs::write(&path, b"frames")?;
+    assert_eq!(ranges(&path, Format::Mp3)?, [(0, 6)]);
+    fs::remove_dir_all(&dir)
+}
+
+// Test finding the stream info and frames of FLAC files
+#[test]
+fn test_flac_essence() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_media_flac");
+    fs::create_dir_all(&dir)?;
+    let path = dir.join("song.flac");
+    let flac = [
+        b"fLaC".to_vec(),
+        flac_block(0, false, b"info"),
+        flac_block(4, false, b"ARTIST=someone"),
+        flac_block(1, true, &[0; 10]),
+        b"frames".to_vec(),
+    ]
+    .concat();
+    fs::write(&path, &flac)?;
+    assert_eq!(ranges(&path, Format::Flac)?, [(8, 12), (44, 50)]);
+    fs::write(&path, &flac[..30])?;
+    assert!(essence(&path, Format::Flac).is_err());
+    fs::write(&path, b"frames")?;
+    assert!(essence(&path, Format::Flac).is_err());
+    fs::remove_dir_all(&dir)
+}
+
+fn write_mp4(path: &Path, title: &[u8], samples: &[u8]) -> io::Result<()> {
+    let udta = mp4_box(b"udta", title);
+    let moov = mp4_box(b"moov", &[mp4_box(b"mvhd", &[0; 8]), udta].concat());
+    let mp4 = [mp4_box(b"ftyp", b"M4A "), moov, mp4_box(b"mdat", samples)].concat();
+    fs::write(path, mp4)
+}
+
+// Test comparing retagged media files by their audio data
+#[test]
+fn test_compare_media() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_media");
+    fs::create_dir_all(&dir)?;
+    let (path1, path2) = (dir.join("1.m4a"), dir.join("2.m4a"));
+    write_mp4(&path1, b"old title", b"samples")?;
+    write_mp4(&path2, b"a new, longer title", b"samples")?;
+    assert_eq!(ranges(&path1, Format::Mp4)?, [(61, 68)]);
+
+    let options = CompareOptions::default();
+    assert_eq!(compare_media(&path1, &path2, Format::Mp4, &options)?, Equal);
+    assert!(matches!(
+        compare_files_with(&path1, &path2, &options)?,
+        Different(_)
+    ));
+    let media = CompareOptions {
+        media: true,
+        ..Default::default()
+    };
+    assert_eq!(compare_files_with(&path1, &path2, &media)?, Equal);
+
+    write_mp4(&path2, b"old title", b"sampler")?;
+    assert_eq!(compare_files_with(&path1, &path2, &media)?, Different(6));
+    fs::write(&path2, [0, 0, 0, 40, b'm', b'd', b'a', b't'])?;
+    assert!(compare_files_with(&path1, &path2, &media).is_err());
+
+    let (path1, path2) = (dir.join("1.mp3"), dir.join("2.mp3"));
+    fs::write(&path1, [id3v2(5), b"frames".to_vec()].concat())?;
+    fs::write(&path2, b"frames")?;
+    assert_eq!(compare_files_with(&path1, &path2, &media)?, Equal);
+    fs::remove_dir_all(&dir)
+}
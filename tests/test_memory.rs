@@ -0,0 +1,81 @@
+use file_cmp::memory::{buffer_usage, fit};
+use file_cmp::CompareOptions;
+use std::fs;
+use std::io;
+use std::process::Command;
+
+const MIB: u64 = 1024 * 1024;
+
+// Test scaling the chunks, threads and pipeline down to a memory limit
+#[test]
+fn test_fit() {
+    let mut options = CompareOptions::default();
+    assert_eq!(buffer_usage(&options), 16 * MIB);
+    assert_eq!(fit(&mut options, 16 * MIB), Ok(()));
+    assert_eq!(options.chunk_size, None);
+    assert_eq!(fit(&mut options, 4 * MIB), Ok(()));
+    assert_eq!(options.chunk_size, Some(2 << 20));
+
+    // Chunks shrink to 1 MiB first, then the larger of threads and pipeline
+    let mut options = CompareOptions {
+        threads: 8,
+        pipeline_depth: 2,
+        ..Default::default()
+    };
+    assert_eq!(buffer_usage(&options), 128 * MIB);
+    assert_eq!(fit(&mut options, 8 * MIB), Ok(()));
+    assert_eq!(
+        (options.chunk_size, options.threads, options.pipeline_depth),
+        (Some(1 << 20), 4, 2)
+    );
+    assert_eq!(fit(&mut options, 8 * 1024), Ok(()));
+    assert_eq!(
+        (options.chunk_size, options.threads, options.pipeline_depth),
+        (Some(4096), 1, 0)
+    );
+    assert!(fit(&mut options, 4096).is_err());
+
+    // Pairs of files compared at once are cut back before the threads
+    let mut options = CompareOptions {
+        jobs: 4,
+        threads: 2,
+        ..Default::default()
+    };
+    assert_eq!(buffer_usage(&options), 128 * MIB);
+    assert_eq!(fit(&mut options, 4 * MIB), Ok(()));
+    assert_eq!(
+        (options.chunk_size, options.jobs, options.threads),
+        (Some(1 << 20), 1, 2)
+    );
+}
+
+// Test comparing with --max-memory
+#[test]
+fn test_max_memory() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_max_memory");
+    fs::create_dir_all(&dir)?;
+    let data: Vec<u8> = (0..100_000).map(|i| (i % 251) as u8).collect();
+    fs::write(dir.join("a"), &data)?;
+    let mut changed = data.clone();
+    changed[70_000] ^= 1;
+    fs::write(dir.join("b"), changed)?;
+
+    let run = |limit: &str| {
+        Command::new(env!("CARGO_BIN_EXE_file_cmp"))
+            .env_remove("FILE_CMP_MACHINE")
+            .args(["-m", "--threads", "4", "--max-memory", limit])
+            .arg(dir.join("a"))
+            .arg(dir.join("b"))
+            .output()
+    };
+    let output = run("16k")?;
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "70000");
+    let output = run("1k")?;
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "Error: cannot compare with less than 8.00 KiB of buffers\n"
+    );
+    fs::remove_dir_all(&dir)
+}
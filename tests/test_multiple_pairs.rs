@@ -0,0 +1,50 @@
+use std::io;
+use std::process::{Command, Output};
+
+fn file_cmp(args: &[&str]) -> io::Result<Output> {
+    Command::new(env!("CARGO_BIN_EXE_file_cmp"))
+        .current_dir("tests/testfiles")
+        .env_remove("FILE_CMP_DIFFS_ONLY")
+        .args(["-m"])
+        .args(args)
+        .output()
+}
+
+// Test comparing several pairs given as positional arguments
+#[test]
+fn test_positional_pairs() -> io::Result<()> {
+    let output = file_cmp(&["test.txt", "tesx.txt", "test.txt", "test.txt"])?;
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "3\ttest.txt\ttesx.txt\n-1\ttest.txt\ttest.txt\n"
+    );
+    assert!(!file_cmp(&["test.txt", "tesx.txt", "test.txt"])?
+        .status
+        .success());
+    Ok(())
+}
+
+// Test comparing pairs given with --pair, where one that can't be compared
+// fails the run but not the others
+#[test]
+fn test_pair_flag() -> io::Result<()> {
+    let output = file_cmp(&[
+        "--pair",
+        "missing.txt,test.txt",
+        "--pair",
+        "test.txt,text.txt",
+    ])?;
+    assert!(!output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "2\ttest.txt\ttext.txt\n"
+    );
+    assert!(!file_cmp(&["--pair", "test.txt"])?.status.success());
+    assert!(
+        !file_cmp(&["--pair", "test.txt,text.txt", "test.txt", "text.txt"])?
+            .status
+            .success()
+    );
+    Ok(())
+}
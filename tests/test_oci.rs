@@ -0,0 +1,121 @@
+#![cfg(feature = "oci")]
+
+use file_cmp::hash::HashAlgorithm;
+use file_cmp::oci::{compare_images, Entry, Image};
+use file_cmp::FileDiff::*;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Builds a layer from (path, contents) pairs, with `None` for directories.
+fn layer(files: &[(&str, Option<&str>)]) -> io::Result<Vec<u8>> {
+    let mut builder = tar::Builder::new(vec![]);
+    for (path, contents) in files {
+        let mut header = tar::Header::new_gnu();
+        match contents {
+            Some(contents) => {
+                header.set_size(contents.len() as u64);
+                header.set_entry_type(tar::EntryType::Regular);
+                builder.append_data(&mut header, path, contents.as_bytes())?;
+            }
+            None => {
+                header.set_size(0);
+                header.set_entry_type(tar::EntryType::Directory);
+                builder.append_data(&mut header, path, io::empty())?;
+            }
+        }
+    }
+    builder.into_inner()
+}
+
+fn gzip(data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(vec![], Compression::fast());
+    encoder.write_all(data)?;
+    encoder.finish()
+}
+
+/// Writes an OCI image layout with the given layers.
+fn layout(name: &str, layers: &[Vec<u8>]) -> io::Result<PathBuf> {
+    let dir = std::env::temp_dir().join(format!("file_cmp_test_oci_{}", name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("blobs/sha256"))?;
+    let write_blob = |data: &[u8]| -> io::Result<String> {
+        let hex: String = Sha256::digest(data)
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        fs::write(dir.join("blobs/sha256").join(&hex), data)?;
+        Ok(format!("sha256:{}", hex))
+    };
+    let mut descriptors = vec![];
+    for layer in layers {
+        descriptors.push(json!({ "digest": write_blob(layer)? }));
+    }
+    let manifest = json!({ "layers": descriptors }).to_string();
+    let digest = write_blob(manifest.as_bytes())?;
+    let index = json!({ "manifests": [{ "digest": digest }] });
+    fs::write(dir.join("index.json"), index.to_string())?;
+    Ok(dir)
+}
+
+#[test]
+fn test_flatten_layers() -> io::Result<()> {
+    // Test that later layers replace files and whiteouts remove them
+    let base = layer(&[
+        ("etc", None),
+        ("etc/a.conf", Some("a")),
+        ("etc/b.conf", Some("b")),
+        ("var/cache/x", Some("x")),
+    ])?;
+    let update = layer(&[
+        ("etc/.wh.a.conf", Some("")),
+        ("etc/b.conf", Some("bb")),
+        ("var/cache/.wh..wh..opq", Some("")),
+        ("var/cache/y", Some("y")),
+    ])?;
+    let dir = layout("flatten", &[gzip(&base)?, update])?;
+    let image = Image::open(&dir, HashAlgorithm::Sha256)?;
+    let paths: Vec<&str> = image.entries.keys().map(String::as_str).collect();
+    assert_eq!(paths, vec!["etc", "etc/b.conf", "var/cache/y"]);
+    assert!(matches!(
+        image.entries["etc/b.conf"],
+        Entry::File { len: 2, .. }
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_compare_images() -> io::Result<()> {
+    // Test two versions of an image, one of them saved as an archive
+    let base = layer(&[("bin/app", Some("v1")), ("etc/app.conf", Some("conf"))])?;
+    let v1 = layout("v1", std::slice::from_ref(&base))?;
+    let update = layer(&[
+        ("bin/app", Some("v2")),
+        ("bin/.wh.old", Some("")),
+        ("etc/new.conf", Some("new")),
+    ])?;
+    let v2 = layout("v2", &[base, update])?;
+
+    let archive = v2.with_extension("tar");
+    let mut builder = tar::Builder::new(fs::File::create(&archive)?);
+    builder.append_dir_all(".", &v2)?;
+    builder.finish()?;
+
+    for image2 in [&v2, &archive] {
+        let res = compare_images(v1.as_path(), image2.as_path(), HashAlgorithm::Sha256)?;
+        assert_eq!(
+            res,
+            vec![
+                (PathBuf::from("bin/app"), Different(0)),
+                (PathBuf::from("etc/app.conf"), Equal),
+                (PathBuf::from("etc/new.conf"), RightOnly),
+            ]
+        );
+    }
+    assert!(compare_images(v1.as_path(), Path::new("missing"), HashAlgorithm::Sha256).is_err());
+    Ok(())
+}
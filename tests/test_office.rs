@@ -0,0 +1,112 @@
+#![cfg(feature = "office")]
+
+use file_cmp::office::{differing_part, is_office, normalize_xml};
+use file_cmp::FileDiff::*;
+use file_cmp::{compare_files_with, CompareOptions};
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+const DOCUMENT: &str = r#"<w:document><w:body><w:p w:rsidR="00A1" w:rsidRDefault="00B2"><w:r><w:t>Hello</w:t></w:r></w:p></w:body></w:document>"#;
+
+fn write_docx(path: &Path, parts: &[(&str, &str)]) -> io::Result<()> {
+    let mut zip = ZipWriter::new(File::create(path)?);
+    for (name, contents) in parts {
+        zip.start_file(*name, SimpleFileOptions::default())?;
+        zip.write_all(contents.as_bytes())?;
+    }
+    zip.finish()?;
+    Ok(())
+}
+
+fn core(modified: &str, revision: u32) -> String {
+    format!(
+        "<cp:coreProperties>\n  <dc:creator>someone</dc:creator>\n  <cp:revision>{}</cp:revision>\n  <dcterms:modified xsi:type=\"dcterms:W3CDTF\">{}</dcterms:modified>\n</cp:coreProperties>",
+        revision, modified
+    )
+}
+
+// Test normalizing the XML of document parts
+#[test]
+fn test_normalize_xml() {
+    assert_eq!(
+        normalize_xml(DOCUMENT),
+        "<w:document><w:body><w:p><w:r><w:t>Hello</w:t></w:r></w:p></w:body></w:document>"
+    );
+    assert_eq!(
+        normalize_xml("<a y='1'  x=\"2\" />\n  <b/>"),
+        "<a x=\"2\" y=\"1\"/><b/>"
+    );
+    assert_eq!(
+        normalize_xml(&core("2024-01-01T00:00:00Z", 3)),
+        normalize_xml(&core("2025-06-30T12:00:00Z", 4))
+    );
+    assert_eq!(
+        normalize_xml("<w:settings><w:rsids><w:rsidRoot w:val=\"1\"/><w:rsid w:val=\"2\"/></w:rsids><w:zoom/></w:settings>"),
+        "<w:settings><w:zoom/></w:settings>"
+    );
+    assert_ne!(normalize_xml("<t>a b</t>"), normalize_xml("<t>a  b</t>"));
+}
+
+// Test comparing documents saved again without edits
+#[test]
+fn test_compare_office() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_office");
+    fs::create_dir_all(&dir)?;
+    let (path1, path2) = (dir.join("1.docx"), dir.join("2.docx"));
+    let core1 = core("2024-01-01T00:00:00Z", 3);
+    let core2 = core("2025-06-30T12:00:00Z", 4);
+    let resaved = DOCUMENT.replace("00A1", "00C3");
+    write_docx(
+        &path1,
+        &[
+            ("word/document.xml", DOCUMENT),
+            ("docProps/core.xml", &core1),
+        ],
+    )?;
+    write_docx(
+        &path2,
+        &[
+            ("docProps/core.xml", &core2),
+            ("word/document.xml", &resaved),
+        ],
+    )?;
+    assert!(is_office(&path1));
+    assert!(!is_office(dir.join("1.zip")));
+    assert_eq!(differing_part(&path1, &path2)?, None);
+
+    let options = CompareOptions {
+        office: true,
+        ..Default::default()
+    };
+    assert_eq!(compare_files_with(&path1, &path2, &options)?, Equal);
+    assert_ne!(
+        compare_files_with(&path1, &path2, &CompareOptions::default())?,
+        Equal
+    );
+
+    let edited = DOCUMENT.replace("Hello", "Goodbye");
+    write_docx(
+        &path2,
+        &[
+            ("docProps/core.xml", &core2),
+            ("word/document.xml", &edited),
+        ],
+    )?;
+    assert_eq!(
+        differing_part(&path1, &path2)?.as_deref(),
+        Some("word/document.xml")
+    );
+    assert_eq!(compare_files_with(&path1, &path2, &options)?, Different(0));
+    write_docx(&path2, &[("word/document.xml", DOCUMENT)])?;
+    assert_eq!(
+        differing_part(&path1, &path2)?.as_deref(),
+        Some("docProps/core.xml")
+    );
+
+    fs::write(&path2, "not a zip")?;
+    assert!(differing_part(&path1, &path2).is_err());
+    fs::remove_dir_all(&dir)
+}
@@ -0,0 +1,110 @@
+#![cfg(feature = "otel")]
+
+use file_cmp::hooks::Summary;
+use file_cmp::otel::Tracer;
+use file_cmp::{compare_files_with, CompareOptions, FileDiff};
+use serde_json::Value;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// Accepts OTLP requests, sending the path and JSON body of each.
+fn serve() -> io::Result<(String, Receiver<(String, Value)>)> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let endpoint = format!("http://{}", listener.local_addr()?);
+    let (sender, requests) = mpsc::channel();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request = String::new();
+            reader.read_line(&mut request).unwrap();
+            let mut len = 0;
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 2 {
+                if let Some((name, value)) = line.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        len = value.trim().parse().unwrap();
+                    }
+                }
+                line.clear();
+            }
+            let mut body = vec![0; len];
+            reader.read_exact(&mut body).unwrap();
+            let path = request.split(' ').nth(1).unwrap_or_default().to_string();
+            let _ = sender.send((path, serde_json::from_slice(&body).unwrap()));
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\n{}")
+                .unwrap();
+        }
+    });
+    Ok((endpoint, requests))
+}
+
+fn attribute<'a>(attributes: &'a Value, key: &str) -> &'a Value {
+    let attribute = attributes
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|attribute| attribute["key"] == key)
+        .unwrap();
+    &attribute["value"]
+}
+
+// Test exporting a span per comparison under a span of the run, and the
+// counters of the results
+#[test]
+fn test_export() -> io::Result<()> {
+    let (endpoint, requests) = serve()?;
+    let tracer = Arc::new(Tracer::from_env(None).with_endpoint(&endpoint));
+    let options = CompareOptions {
+        tracer: Some(tracer.clone()),
+        ..Default::default()
+    };
+    let result = compare_files_with(
+        "./tests/testfiles/test.txt",
+        "./tests/testfiles/tesx.txt",
+        &options,
+    )?;
+    assert_eq!(result, FileDiff::Different(3));
+    let mut summary = Summary::new();
+    summary.add(&result);
+    tracer.finish(&summary, true)?;
+
+    let timeout = Duration::from_secs(10);
+    let (path, traces) = requests.recv_timeout(timeout).unwrap();
+    assert_eq!(path, "/v1/traces");
+    let spans = &traces["resourceSpans"][0]["scopeSpans"][0]["spans"];
+    let (compare, run) = (&spans[0], &spans[1]);
+    assert_eq!(compare["name"], "compare");
+    assert_eq!(run["name"], "file_cmp");
+    assert_eq!(compare["traceId"], tracer.trace_id());
+    assert_eq!(run["traceId"], tracer.trace_id());
+    assert_eq!(compare["parentSpanId"], run["spanId"]);
+    assert_eq!(
+        attribute(&compare["attributes"], "file_cmp.result")["stringValue"],
+        "diff"
+    );
+    assert_eq!(
+        attribute(&compare["attributes"], "file_cmp.offset")["intValue"],
+        "3"
+    );
+    assert_eq!(
+        attribute(&run["attributes"], "file_cmp.different")["intValue"],
+        "1"
+    );
+
+    let (path, metrics) = requests.recv_timeout(timeout).unwrap();
+    assert_eq!(path, "/v1/metrics");
+    let metrics = &metrics["resourceMetrics"][0]["scopeMetrics"][0]["metrics"];
+    assert_eq!(metrics[0]["name"], "file_cmp.files");
+    let points = metrics[0]["sum"]["dataPoints"].as_array().unwrap();
+    assert_eq!(points.len(), 4);
+    assert_eq!(points[1]["asInt"], "1");
+    assert_eq!(metrics[1]["name"], "file_cmp.bytes");
+    assert_eq!(metrics[1]["sum"]["dataPoints"][0]["asInt"], "7");
+    Ok(())
+}
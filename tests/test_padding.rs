@@ -0,0 +1,139 @@
+use file_cmp::blank::{padding_end, Padding};
+use file_cmp::FileDiff::*;
+use file_cmp::{compare_files_with, CompareOptions, Side};
+use std::fs;
+use std::io;
+use std::process::Command;
+
+// Test finding the end of trailing padding
+#[test]
+fn test_padding_end() -> io::Result<()> {
+    assert_eq!(padding_end(&mut &[][..], Padding::Filler)?, None);
+    assert_eq!(padding_end(&mut &[0xff; 5][..], Padding::Filler)?, None);
+    assert_eq!(
+        padding_end(&mut &[0, 0, 0xff][..], Padding::Filler)?,
+        Some(2)
+    );
+    assert_eq!(padding_end(&mut &[0x5a][..], Padding::Filler)?, Some(0));
+    assert_eq!(padding_end(&mut &[0x5a; 3][..], Padding::Byte(0x5a))?, None);
+    assert_eq!(padding_end(&mut &[0xff][..], Padding::Byte(0))?, Some(0));
+    let mut padded = vec![0xff; 3 << 20];
+    padded[(2 << 20) + 1] = 0;
+    assert_eq!(
+        padding_end(&mut &padded[..], Padding::Filler)?,
+        Some((2 << 20) + 1)
+    );
+    Ok(())
+}
+
+// Test comparing files that are padded to different sizes
+#[test]
+fn test_compare_padded() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_padding");
+    fs::create_dir_all(&dir)?;
+    let (image, dump) = (dir.join("image"), dir.join("dump"));
+    fs::write(&image, b"firmware")?;
+    fs::write(&dump, [&b"firmware"[..], &[0xff; 100]].concat())?;
+
+    let padded = |padding, quick| CompareOptions {
+        trailing_padding: Some(padding),
+        quick,
+        ..Default::default()
+    };
+    let exact = CompareOptions::default();
+    assert_eq!(
+        compare_files_with(&image, &dump, &exact)?,
+        Truncated(Side::Left, 8)
+    );
+    assert_eq!(
+        compare_files_with(&image, &dump, &padded(Padding::Filler, false))?,
+        Equal
+    );
+    assert_eq!(
+        compare_files_with(&dump, &image, &padded(Padding::Filler, true))?,
+        Equal
+    );
+    assert_eq!(
+        compare_files_with(&image, &dump, &padded(Padding::Byte(0), false))?,
+        Different(8)
+    );
+
+    fs::write(&dump, [&b"firmware"[..], &[0xff; 10], b"x"].concat())?;
+    assert_eq!(
+        compare_files_with(&image, &dump, &padded(Padding::Filler, false))?,
+        Different(18)
+    );
+    assert_eq!(
+        compare_files_with(&image, &dump, &padded(Padding::Filler, true))?,
+        Different(0)
+    );
+    fs::write(&dump, b"firmwarf\xff")?;
+    assert_eq!(
+        compare_files_with(&image, &dump, &padded(Padding::Filler, false))?,
+        Different(7)
+    );
+    fs::remove_dir_all(&dir)
+}
+
+// Test the --ignore-trailing-padding flag with and without a byte
+#[test]
+fn test_ignore_trailing_padding() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_padding_flag");
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join("image"), b"firmware")?;
+    fs::write(dir.join("dump"), b"firmware\0\0\0")?;
+    let run = |args: &[&str]| -> io::Result<String> {
+        let output = Command::new(env!("CARGO_BIN_EXE_file_cmp"))
+            .current_dir(&dir)
+            .env_remove("FILE_CMP_MACHINE")
+            .args(["-m", "image", "dump"])
+            .args(args)
+            .output()?;
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    };
+    assert_eq!(run(&[])?, "8");
+    assert_eq!(run(&["--ignore-trailing-padding"])?, "-1");
+    assert_eq!(run(&["--ignore-trailing-padding=0"])?, "-1");
+    assert_eq!(run(&["--ignore-trailing-padding=0xFF"])?, "8");
+    fs::remove_dir_all(&dir)
+}
+
+// Test ignoring a final newline with --ignore-final-newline, in trees too
+#[test]
+fn test_ignore_final_newline() -> io::Result<()> {
+    let newline = |rest: &[u8]| padding_end(&mut &rest[..], Padding::FinalNewline);
+    assert_eq!(newline(b"\n")?, None);
+    assert_eq!(newline(b"\r\n")?, None);
+    assert_eq!(newline(b"")?, None);
+    assert_eq!(newline(b"\n\n")?, Some(1));
+    assert_eq!(newline(b"\r")?, Some(0));
+    assert_eq!(newline(b" \n")?, Some(0));
+
+    let dir = std::env::temp_dir().join("file_cmp_test_final_newline");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("a"))?;
+    fs::create_dir_all(dir.join("b"))?;
+    fs::write(dir.join("a/main.rs"), b"fn main() {}")?;
+    fs::write(dir.join("b/main.rs"), b"fn main() {}\r\n")?;
+    fs::write(dir.join("a/lib.rs"), b"mod a;\n")?;
+    fs::write(dir.join("b/lib.rs"), b"mod a;\n\n\n")?;
+    let run = |args: &[&str]| -> io::Result<String> {
+        let output = Command::new(env!("CARGO_BIN_EXE_file_cmp"))
+            .current_dir(&dir)
+            .env_remove("FILE_CMP_MACHINE")
+            .args(["-m"])
+            .args(args)
+            .output()?;
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    };
+    assert_eq!(run(&["a/main.rs", "b/main.rs"])?, "12");
+    assert_eq!(
+        run(&["--ignore-final-newline", "b/main.rs", "a/main.rs"])?,
+        "-1"
+    );
+    let output = run(&["--ignore-final-newline", "a", "b"])?;
+    let mut lines: Vec<&str> = output.lines().collect();
+    lines.sort();
+    assert_eq!(lines, ["-1\ta/main.rs", "8\ta/lib.rs"]);
+    fs::remove_dir_all(&dir)
+}
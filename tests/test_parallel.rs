@@ -0,0 +1,64 @@
+use file_cmp::parallel::SEGMENT_SIZE;
+use file_cmp::FileDiff::*;
+use file_cmp::{compare_files_with, CompareOptions, Side};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+fn large_file(name: &str, len: usize, changes: &[usize]) -> io::Result<PathBuf> {
+    let dir = std::env::temp_dir().join("file_cmp_test_parallel");
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(name);
+    let mut data: Vec<u8> = (0..len).map(|i| (i % 251) as u8).collect();
+    for &i in changes {
+        data[i] ^= 0xff;
+    }
+    fs::write(&path, data)?;
+    Ok(path)
+}
+
+fn threaded(quick: bool) -> CompareOptions {
+    CompareOptions {
+        quick,
+        threads: 4,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_compare_parallel_equal() -> io::Result<()> {
+    // Test when large files are equal
+    let len = 3 * SEGMENT_SIZE as usize + 123;
+    let a = large_file("equal_a", len, &[])?;
+    let b = large_file("equal_b", len, &[])?;
+    assert_eq!(compare_files_with(&a, &b, &threaded(false))?, Equal);
+    Ok(())
+}
+
+#[test]
+fn test_compare_parallel_earliest_diff() -> io::Result<()> {
+    // Test that the earliest difference is reported, not the first one found
+    let len = 3 * SEGMENT_SIZE as usize + 123;
+    let first = SEGMENT_SIZE as usize + 5;
+    let a = large_file("diff_a", len, &[])?;
+    let b = large_file("diff_b", len, &[len - 1, 2 * SEGMENT_SIZE as usize, first])?;
+    assert_eq!(
+        compare_files_with(&a, &b, &threaded(false))?,
+        Different(first)
+    );
+    assert_eq!(compare_files_with(&b, &a, &threaded(true))?, Different(0));
+    Ok(())
+}
+
+#[test]
+fn test_compare_parallel_different_length() -> io::Result<()> {
+    // Test when one large file is a prefix of the other
+    let len = 2 * SEGMENT_SIZE as usize + 7;
+    let a = large_file("len_a", len, &[])?;
+    let b = large_file("len_b", len + 10, &[])?;
+    assert_eq!(
+        compare_files_with(&a, &b, &threaded(false))?,
+        Truncated(Side::Left, len)
+    );
+    Ok(())
+}
@@ -0,0 +1,76 @@
+use file_cmp::hash::HashAlgorithm;
+use file_cmp::paranoid::{check_rereads, Reread, BLOCK_SIZE};
+use file_cmp::{compare_files_with, CompareOptions, FileDiff};
+use std::fs;
+use std::io;
+use std::process::Command;
+
+// Test reading files twice, which agree on storage that works
+#[test]
+fn test_check_rereads() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_check_rereads");
+    fs::create_dir_all(&dir)?;
+    let data: Vec<u8> = (0..BLOCK_SIZE * 2 + 100).map(|i| (i % 251) as u8).collect();
+    fs::write(dir.join("a"), &data)?;
+    fs::write(dir.join("empty"), b"")?;
+
+    let sha256 = HashAlgorithm::Sha256;
+    assert_eq!(
+        check_rereads(dir.join("a"), Reread::Cached, sha256, None)?,
+        None
+    );
+    assert_eq!(
+        check_rereads(dir.join("empty"), Reread::Cached, sha256, None)?,
+        None
+    );
+    #[cfg(any(target_os = "linux", target_os = "android", target_os = "freebsd"))]
+    assert_eq!(
+        check_rereads(dir.join("a"), Reread::DropCache, sha256, None)?,
+        None
+    );
+    // Directories can't be read, so there is nothing to check
+    assert_eq!(check_rereads(&dir, Reread::Cached, sha256, None)?, None);
+    assert!(check_rereads(dir.join("missing"), Reread::Cached, sha256, None).is_err());
+
+    assert_eq!("drop-cache".parse(), Ok(Reread::DropCache));
+    assert!("twice".parse::<Reread>().is_err());
+
+    fs::remove_dir_all(&dir)
+}
+
+// Test that --paranoid leaves the results of the comparison as they are
+#[test]
+fn test_paranoid() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_paranoid");
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join("a"), b"archived data")?;
+    fs::write(dir.join("b"), b"archived data")?;
+    fs::write(dir.join("c"), b"archived date")?;
+
+    let options = CompareOptions {
+        paranoid: Some(Reread::Cached),
+        ..Default::default()
+    };
+    let result = compare_files_with(dir.join("a"), dir.join("b"), &options)?;
+    assert_eq!(result, FileDiff::Equal);
+    let result = compare_files_with(dir.join("a"), dir.join("c"), &options)?;
+    assert_eq!(result, FileDiff::Different(12));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_file_cmp"))
+        .env_remove("FILE_CMP_MACHINE")
+        .arg("--paranoid")
+        .arg(dir.join("a"))
+        .arg(dir.join("b"))
+        .output()?;
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "Files are equal");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_file_cmp"))
+        .arg("--paranoid=twice")
+        .arg(dir.join("a"))
+        .arg(dir.join("b"))
+        .output()?;
+    assert_eq!(output.status.code(), Some(2));
+
+    fs::remove_dir_all(&dir)
+}
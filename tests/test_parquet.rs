@@ -0,0 +1,88 @@
+#![cfg(feature = "parquet")]
+
+use file_cmp::parquet::{compare_parquet, TableDiff};
+use parquet::basic::Compression;
+use parquet::data_type::Int64Type;
+use parquet::errors::Result;
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::parser::parse_message_type;
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+fn write_table(
+    path: &Path,
+    column: &str,
+    values: &[i64],
+    rows_per_group: usize,
+    compression: Compression,
+) -> Result<()> {
+    let schema = parse_message_type(&format!("message table {{ required int64 {}; }}", column))?;
+    let properties = WriterProperties::builder()
+        .set_compression(compression)
+        .build();
+    let mut writer =
+        SerializedFileWriter::new(File::create(path)?, Arc::new(schema), Arc::new(properties))?;
+    for group in values.chunks(rows_per_group) {
+        let mut row_group = writer.next_row_group()?;
+        while let Some(mut column) = row_group.next_column()? {
+            column.typed::<Int64Type>().write_batch(group, None, None)?;
+            column.close()?;
+        }
+        row_group.close()?;
+    }
+    writer.close()?;
+    Ok(())
+}
+
+// Test comparing Parquet files by their data instead of their bytes
+#[test]
+fn test_compare_parquet() -> Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_parquet");
+    fs::create_dir_all(&dir)?;
+    let values: Vec<i64> = (0..1000).collect();
+    let path = |name: &str| dir.join(name);
+    write_table(&path("a"), "id", &values, 1000, Compression::UNCOMPRESSED)?;
+    // The same rows in other row groups, compressed
+    write_table(&path("b"), "id", &values, 64, Compression::SNAPPY)?;
+    assert_ne!(fs::read(path("a"))?, fs::read(path("b"))?);
+    assert_eq!(compare_parquet(path("a"), path("b"))?, TableDiff::Equal);
+
+    let mut changed = values.clone();
+    changed[700] = -1;
+    write_table(&path("changed"), "id", &changed, 64, Compression::SNAPPY)?;
+    assert_eq!(
+        compare_parquet(path("a"), path("changed"))?,
+        TableDiff::Row(700)
+    );
+    write_table(
+        &path("short"),
+        "id",
+        &values[..500],
+        64,
+        Compression::SNAPPY,
+    )?;
+    assert_eq!(
+        compare_parquet(path("short"), path("a"))?,
+        TableDiff::Row(500)
+    );
+    write_table(
+        &path("renamed"),
+        "key",
+        &values,
+        1000,
+        Compression::UNCOMPRESSED,
+    )?;
+    assert_eq!(
+        compare_parquet(path("a"), path("renamed"))?,
+        TableDiff::Schema
+    );
+
+    fs::write(path("text"), "not parquet")?;
+    let error = compare_parquet(path("a"), path("text")).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
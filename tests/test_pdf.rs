@@ -0,0 +1,91 @@
+#![cfg(feature = "pdf")]
+
+use file_cmp::pdf::{compare_pdf, page_text, TextDiff};
+use lopdf::content::{Content, Operation};
+use lopdf::{dictionary, Document, Object, Stream};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Writes a PDF with a page for each list of text runs, each drawn at its
+/// own position, and a creation date.
+fn write_pdf(path: &Path, pages: &[&[&str]], date: &str) -> io::Result<()> {
+    let mut doc = Document::with_version("1.5");
+    let pages_id = doc.new_object_id();
+    let font_id = doc.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "Type1",
+        "BaseFont" => "Courier",
+    });
+    let resources_id = doc.add_object(dictionary! {
+        "Font" => dictionary! { "F1" => font_id },
+    });
+    let mut kids = vec![];
+    for runs in pages {
+        let mut operations = vec![
+            Operation::new("BT", vec![]),
+            Operation::new("Tf", vec!["F1".into(), 12.into()]),
+        ];
+        for (i, run) in runs.iter().enumerate() {
+            operations.push(Operation::new(
+                "Td",
+                vec![10.into(), (700 - 20 * i as i64).into()],
+            ));
+            operations.push(Operation::new("Tj", vec![Object::string_literal(*run)]));
+        }
+        operations.push(Operation::new("ET", vec![]));
+        let content = Content { operations }.encode().unwrap();
+        let content_id = doc.add_object(Stream::new(dictionary! {}, content));
+        kids.push(
+            doc.add_object(dictionary! {
+                "Type" => "Page",
+                "Parent" => pages_id,
+                "Contents" => content_id,
+            })
+            .into(),
+        );
+    }
+    let count = kids.len() as i64;
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => kids,
+            "Count" => count,
+            "Resources" => resources_id,
+            "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+        }),
+    );
+    let catalog_id = doc.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+    let info_id = doc.add_object(dictionary! { "CreationDate" => Object::string_literal(date) });
+    doc.trailer.set("Root", catalog_id);
+    doc.trailer.set("Info", info_id);
+    doc.save(path).map(|_| ())
+}
+
+// Test comparing PDF files by the words on their pages
+#[test]
+fn test_compare_pdf() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_pdf");
+    fs::create_dir_all(&dir)?;
+    let (path1, path2) = (dir.join("1.pdf"), dir.join("2.pdf"));
+    write_pdf(&path1, &[&["Hello world"], &["Second page"]], "D:20240101")?;
+    write_pdf(
+        &path2,
+        &[&["Hello ", "world"], &["Second page"]],
+        "D:20250505",
+    )?;
+    assert_ne!(fs::read(&path1)?, fs::read(&path2)?);
+    assert_eq!(page_text(&path1)?, ["Hello world", "Second page"]);
+    assert_eq!(compare_pdf(&path1, &path2)?, TextDiff::Equal);
+
+    write_pdf(&path2, &[&["Hello world"], &["Second pages"]], "D:20240101")?;
+    assert_eq!(compare_pdf(&path1, &path2)?, TextDiff::Page(2));
+    write_pdf(&path2, &[&["Hello world"]], "D:20240101")?;
+    assert_eq!(compare_pdf(&path1, &path2)?, TextDiff::Page(2));
+    assert_eq!(TextDiff::Page(2).as_number(), "2");
+
+    fs::write(&path2, "not a pdf")?;
+    assert!(compare_pdf(&path1, &path2).is_err());
+    fs::remove_dir_all(&dir)
+}
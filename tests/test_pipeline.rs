@@ -0,0 +1,64 @@
+use file_cmp::chunk::ChunkSizer;
+use file_cmp::pipeline::{compare_readers, MIN_PIPELINE_LEN};
+use file_cmp::FileDiff::*;
+use file_cmp::{compare_files_with, CompareOptions};
+use std::fs;
+use std::io::{self, Cursor};
+
+fn data(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i % 253) as u8).collect()
+}
+
+fn compare(a: &[u8], b: &[u8], chunk_size: Option<usize>) -> io::Result<Option<u64>> {
+    let mut chunks = ChunkSizer::new(chunk_size, a.len().max(b.len()) as u64, 4096);
+    compare_readers(&mut Cursor::new(a), &mut Cursor::new(b), &mut chunks, 2)
+}
+
+#[test]
+fn test_pipeline_equal() -> io::Result<()> {
+    // Test when inputs are equal, with fixed and adaptive chunks
+    let a = data(1_000_000);
+    assert_eq!(compare(&a, &a.clone(), Some(1000))?, None);
+    assert_eq!(compare(&a, &a.clone(), None)?, None);
+    assert_eq!(compare(&[], &[], None)?, None);
+    Ok(())
+}
+
+#[test]
+fn test_pipeline_differ() -> io::Result<()> {
+    // Test when inputs differ after several chunks have been read ahead
+    let a = data(1_000_000);
+    let mut b = a.clone();
+    b[654_321] ^= 1;
+    assert_eq!(compare(&a, &b, Some(1000))?, Some(654_321));
+    assert_eq!(compare(&b, &a, None)?, Some(654_321));
+    Ok(())
+}
+
+#[test]
+fn test_pipeline_prefix() -> io::Result<()> {
+    // Test when one input is a prefix of the other
+    let a = data(100_000);
+    assert_eq!(compare(&a[..70_000], &a, Some(4096))?, Some(70_000));
+    assert_eq!(compare(&a, &a[..70_000], None)?, Some(70_000));
+    Ok(())
+}
+
+#[test]
+fn test_pipeline_files() -> io::Result<()> {
+    // Test comparing files large enough to use the pipeline
+    let dir = std::env::temp_dir().join("file_cmp_test_pipeline");
+    fs::create_dir_all(&dir)?;
+    let a = data(MIN_PIPELINE_LEN as usize + 5000);
+    let mut b = a.clone();
+    b[MIN_PIPELINE_LEN as usize + 10] ^= 1;
+    fs::write(dir.join("a"), &a)?;
+    fs::write(dir.join("b"), &b)?;
+    let options = CompareOptions {
+        pipeline_depth: 3,
+        ..Default::default()
+    };
+    let res = compare_files_with(dir.join("a"), dir.join("b"), &options)?;
+    assert_eq!(res, Different(MIN_PIPELINE_LEN as usize + 10));
+    Ok(())
+}
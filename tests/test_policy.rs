@@ -0,0 +1,125 @@
+use file_cmp::config::PROJECT_CONFIG;
+use file_cmp::policy::{glob_match, Policies, Policy};
+use file_cmp::walk::DirWalk;
+use file_cmp::FileDiff::*;
+use file_cmp::{compare_files_with, CompareOptions};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::process::Command;
+use std::sync::Arc;
+
+// Test matching names against patterns
+#[test]
+fn test_glob_match() {
+    assert!(glob_match("*.iso", "disk.iso"));
+    assert!(glob_match("*.ISO", "disk.iso"));
+    assert!(glob_match("*", ""));
+    assert!(glob_match("a*b*c", "aXbYbZc"));
+    assert!(glob_match("data-??.csv", "data-01.csv"));
+    assert!(!glob_match("data-??.csv", "data-1.csv"));
+    assert!(!glob_match("*.iso", "disk.iso.part"));
+    assert!(!glob_match("a*b", "ac"));
+}
+
+// Test choosing the policy of the longest matching pattern or the default
+#[test]
+fn test_policies() {
+    let mut policies = Policies::new();
+    assert!(policies.is_empty());
+    policies.insert("*.log", Policy::Ignore);
+    policies.insert("audit*.log", Policy::Byte);
+    policies.insert("logs/*.json", Policy::HashOnly);
+    assert_eq!(policies.get(Path::new("dir/app.log")), Some(Policy::Ignore));
+    assert_eq!(
+        policies.get(Path::new("dir/audit-1.log")),
+        Some(Policy::Byte)
+    );
+    assert_eq!(
+        policies.get(Path::new("dir/logs/a.json")),
+        Some(Policy::HashOnly)
+    );
+    assert_eq!(policies.get(Path::new("dir/a.json")), None);
+    policies.insert("default", Policy::Semantic);
+    policies.insert("*.log", Policy::HashOnly);
+    assert_eq!(
+        policies.get(Path::new("dir/a.json")),
+        Some(Policy::Semantic)
+    );
+    assert_eq!(policies.get(Path::new("app.log")), Some(Policy::HashOnly));
+    assert_eq!("hash-only".parse(), Ok(Policy::HashOnly));
+    assert!("fuzzy".parse::<Policy>().is_err());
+}
+
+fn options(policies: &[(&str, Policy)]) -> CompareOptions {
+    let mut by_pattern = Policies::new();
+    for (pattern, policy) in policies {
+        by_pattern.insert(pattern, *policy);
+    }
+    CompareOptions {
+        policies: Some(Arc::new(by_pattern)),
+        ..Default::default()
+    }
+}
+
+// Test comparing files with the hash-only and semantic policies, and
+// leaving ignored files out of directory walks
+#[test]
+fn test_compare_with_policies() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_policy");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("a"))?;
+    fs::create_dir_all(dir.join("b"))?;
+    fs::write(dir.join("a/x.json"), r#"{"a": 1, "b": [1, 2]}"#)?;
+    fs::write(dir.join("b/x.json"), "{\n  \"b\": [1, 2],\n  \"a\": 1\n}\n")?;
+    fs::write(dir.join("a/x.iso"), "abcdef")?;
+    fs::write(dir.join("b/x.iso"), "abcxef")?;
+    fs::write(dir.join("a/x.log"), "one")?;
+    fs::write(dir.join("b/y.log"), "two")?;
+
+    let (a, b) = (dir.join("a"), dir.join("b"));
+    let byte = options(&[]);
+    assert_eq!(
+        compare_files_with(a.join("x.json"), b.join("x.json"), &byte)?,
+        Different(1)
+    );
+    let policies = options(&[
+        ("*.iso", Policy::HashOnly),
+        ("*.log", Policy::Ignore),
+        ("*.json", Policy::Semantic),
+    ]);
+    assert_eq!(
+        compare_files_with(a.join("x.json"), b.join("x.json"), &policies)?,
+        Equal
+    );
+    assert_eq!(
+        compare_files_with(a.join("x.iso"), b.join("x.iso"), &policies)?,
+        Different(0)
+    );
+    let mut results: Vec<_> = DirWalk::new(&a, &b, &policies).collect();
+    results.sort_by(|a, b| a.0.cmp(&b.0));
+    assert_eq!(
+        results,
+        vec![(a.join("x.iso"), Different(0)), (a.join("x.json"), Equal)]
+    );
+
+    // Test the policies of a configuration file
+    fs::write(
+        dir.join(PROJECT_CONFIG),
+        "[policies]\n\"*.log\" = \"ignore\"\ndefault = \"hash-only\"\n",
+    )?;
+    let output = Command::new(env!("CARGO_BIN_EXE_file_cmp"))
+        .current_dir(&dir)
+        .env("XDG_CONFIG_HOME", &dir)
+        .env("APPDATA", &dir)
+        .env_remove("FILE_CMP_MACHINE")
+        .args(["-m", "a", "b"])
+        .output()?;
+    let mut lines: Vec<_> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::to_string)
+        .collect();
+    lines.sort();
+    assert_eq!(lines, ["0\ta/x.iso", "0\ta/x.json"]);
+    fs::remove_dir_all(&dir)
+}
@@ -0,0 +1,71 @@
+use std::fs;
+use std::io;
+use std::process::Command;
+
+// Test the frozen layout of --porcelain for a pair, trees and listed pairs
+#[test]
+fn test_porcelain() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_porcelain");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("a"))?;
+    fs::create_dir_all(dir.join("b"))?;
+    fs::write(dir.join("a/differ"), b"abcdef")?;
+    fs::write(dir.join("b/differ"), b"abcxef")?;
+    fs::write(dir.join("a/equal"), b"same")?;
+    fs::write(dir.join("b/equal"), b"same")?;
+    fs::write(dir.join("a/short"), b"abc")?;
+    fs::write(dir.join("b/short"), b"abcdef")?;
+    fs::write(dir.join("a/left"), b"")?;
+    fs::write(dir.join("b/right"), b"")?;
+    let run = |args: &[&str]| -> io::Result<String> {
+        let output = Command::new(env!("CARGO_BIN_EXE_file_cmp"))
+            .current_dir(&dir)
+            .env("FILE_CMP_MACHINE", "1")
+            .args(args)
+            .output()?;
+        assert!(output.status.success());
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    };
+
+    assert_eq!(
+        run(&["--porcelain", "a/differ", "b/differ"])?,
+        "differ\t3\ta/differ\tb/differ\n"
+    );
+    assert_eq!(
+        run(&["--porcelain=v1", "a/short", "b/short"])?,
+        "eof-left\t3\ta/short\tb/short\n"
+    );
+
+    let output = run(&["--porcelain", "a", "b"])?;
+    let mut lines: Vec<&str> = output.lines().collect();
+    lines.sort();
+    assert_eq!(
+        lines,
+        [
+            "differ\t3\ta/differ\tb/differ",
+            "eof-left\t3\ta/short\tb/short",
+            "equal\t-\ta/equal\tb/equal",
+            "left-only\t-\ta/left\tb/left",
+            "right-only\t-\ta/right\tb/right",
+        ]
+    );
+
+    assert_eq!(
+        run(&[
+            "--porcelain",
+            "-d",
+            "--pair",
+            "a/equal,b/equal",
+            "--pair",
+            "b/short,a/short"
+        ])?,
+        "eof-right\t3\tb/short\ta/short\n"
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_file_cmp"))
+        .current_dir(&dir)
+        .args(["--porcelain=v0", "a", "b"])
+        .output()?;
+    assert!(!output.status.success());
+    fs::remove_dir_all(&dir)
+}
@@ -0,0 +1,122 @@
+use file_cmp::prefetch::{Listing, Prefetch};
+use file_cmp::walk::{DirWalk, Links};
+use file_cmp::{CompareOptions, FileDiff, Policy};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::process::Command;
+
+/// Compares the trees with these options, returning the results sorted.
+fn walk(a: &Path, b: &Path, options: &CompareOptions) -> Vec<(String, FileDiff)> {
+    let mut results: Vec<_> = DirWalk::new(a, b, options)
+        .map(|(path, diff)| (path.to_string_lossy().into_owned(), diff))
+        .collect();
+    results.sort_by(|x, y| x.0.cmp(&y.0));
+    results
+}
+
+// Test listing a directory with and without the metadata of its files
+#[test]
+fn test_listing() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_listing");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("sub"))?;
+    for i in 0..40 {
+        fs::write(dir.join(format!("f{}", i)), vec![b'x'; i])?;
+    }
+
+    let listing = Listing::read(&dir, true)?;
+    assert_eq!(listing.paths().len(), 41);
+    let entry = listing.get(&dir.join("f7")).unwrap();
+    assert!(entry.file_type.is_file());
+    assert_eq!(entry.meta.as_ref().map(|meta| meta.len()), Some(7));
+    let sub = listing.get(&dir.join("sub")).unwrap();
+    assert!(sub.file_type.is_dir() && sub.meta.is_none());
+    assert!(listing.get(&dir.join("missing")).is_none());
+
+    let listing = Listing::read(&dir, false)?;
+    assert!(listing.get(&dir.join("f7")).unwrap().meta.is_none());
+
+    assert_eq!("always".parse(), Ok(Prefetch::Always));
+    assert!("sometimes".parse::<Prefetch>().is_err());
+
+    fs::remove_dir_all(&dir)
+}
+
+// Test that prefetching metadata leaves the results of walks as they are
+#[test]
+fn test_prefetch() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_prefetch");
+    let _ = fs::remove_dir_all(&dir);
+    let (a, b) = (dir.join("a"), dir.join("b"));
+    for side in [&a, &b] {
+        fs::create_dir_all(side.join("sub"))?;
+        fs::write(side.join("same"), "data")?;
+        fs::write(side.join("sub/same"), "data")?;
+    }
+    fs::write(a.join("longer"), "data")?;
+    fs::write(b.join("longer"), "data and more")?;
+    fs::write(a.join("changed"), "data")?;
+    fs::write(b.join("changed"), "date")?;
+    fs::write(a.join("left"), "")?;
+    fs::create_dir(b.join("right"))?;
+    fs::write(b.join("sub/right"), "")?;
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink("same", a.join("link"))?;
+        std::os::unix::fs::symlink("changed", b.join("link"))?;
+    }
+
+    let variants = [
+        CompareOptions::default(),
+        CompareOptions {
+            quick: true,
+            ..Default::default()
+        },
+        CompareOptions {
+            policy: Policy::SizeOnly,
+            ..Default::default()
+        },
+        CompareOptions {
+            policy: Policy::HashOnly,
+            links: Links::Compare,
+            ..Default::default()
+        },
+    ];
+    for options in variants {
+        let never = CompareOptions {
+            prefetch: Prefetch::Never,
+            ..options.clone()
+        };
+        let always = CompareOptions {
+            prefetch: Prefetch::Always,
+            ..options
+        };
+        assert_eq!(walk(&a, &b, &always), walk(&a, &b, &never));
+    }
+
+    let output = Command::new(env!("CARGO_BIN_EXE_file_cmp"))
+        .current_dir(&dir)
+        .env_remove("FILE_CMP_MACHINE")
+        .args(["-m", "-q", "--prefetch-metadata", "always", "a", "b"])
+        .output()?;
+    let mut lines: Vec<_> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| !line.ends_with("link"))
+        .map(str::to_string)
+        .collect();
+    lines.sort();
+    assert_eq!(
+        lines,
+        [
+            "-1\ta/same",
+            "-1\ta/sub/same",
+            "-2\ta/left",
+            "-3\tb/right",
+            "-3\tb/sub/right",
+            "0\ta/changed",
+            "0\ta/longer"
+        ]
+    );
+    fs::remove_dir_all(&dir)
+}
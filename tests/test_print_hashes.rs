@@ -0,0 +1,69 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::process::Command;
+
+const ABC: &str = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+const ABD: &str = "a52d159f262b2c6ddb724a61840befc36eb30c88877a4030b65cbe86298449c9";
+
+fn file_cmp(dir: &Path, args: &[&str]) -> io::Result<String> {
+    let output = Command::new(env!("CARGO_BIN_EXE_file_cmp"))
+        .current_dir(dir)
+        .env_remove("FILE_CMP_MACHINE")
+        .env_remove("FILE_CMP_HASH")
+        .args(args)
+        .output()?;
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+// Test printing the digests of both sides of each pair
+#[test]
+fn test_print_hashes() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_print_hashes");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("a"))?;
+    fs::create_dir_all(dir.join("b"))?;
+    fs::write(dir.join("a").join("same"), "abc")?;
+    fs::write(dir.join("b").join("same"), "abc")?;
+    fs::write(dir.join("a").join("changed"), "abc")?;
+    fs::write(dir.join("b").join("changed"), "abd")?;
+    fs::write(dir.join("b").join("new"), "abc")?;
+
+    let (a, b) = (Path::new("a"), Path::new("b"));
+    let output = file_cmp(&dir, &["-m", "--print-hashes", "a", "b"])?;
+    let mut lines: Vec<_> = output.lines().collect();
+    lines.sort();
+    assert_eq!(
+        lines,
+        [
+            format!("-1\t{}\t{}\t{}", a.join("same").display(), ABC, ABC),
+            format!("-3\t{}\t-\t{}", b.join("new").display(), ABC),
+            format!("2\t{}\t{}\t{}", a.join("changed").display(), ABC, ABD),
+        ]
+    );
+
+    let output = file_cmp(&dir, &["--print-hashes", "a/same", "b/changed"])?;
+    assert_eq!(
+        output,
+        format!("Files differ at byte 2, sha256 {} and {}", ABC, ABD)
+    );
+    let output = file_cmp(
+        &dir,
+        &[
+            "-m",
+            "--print-hashes",
+            "a/same",
+            "b/same",
+            "a/same",
+            "b/changed",
+        ],
+    )?;
+    assert_eq!(
+        output,
+        format!(
+            "-1\ta/same\tb/same\t{}\t{}\n2\ta/same\tb/changed\t{}\t{}\n",
+            ABC, ABC, ABC, ABD
+        )
+    );
+    fs::remove_dir_all(&dir)
+}
@@ -0,0 +1,86 @@
+use file_cmp::quorum::compare_replicas;
+use file_cmp::FileDiff::*;
+use file_cmp::{CompareOptions, Side};
+use std::fs;
+use std::io;
+use std::process::Command;
+
+// Test grouping replicas and the offsets where they disagree
+#[test]
+fn test_compare_replicas() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_compare_replicas");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir)?;
+    let data: Vec<u8> = (0..300_000u32).map(|i| (i % 241) as u8).collect();
+    let mut flipped = data.clone();
+    flipped[150_000] ^= 1;
+    let mut late = data.clone();
+    late[299_999] ^= 1;
+    let replicas = [
+        (dir.join("0"), &data[..]),
+        (dir.join("1"), &flipped[..]),
+        (dir.join("2"), &data[..]),
+        (dir.join("3"), &data[..200_000]),
+        (dir.join("4"), &data[..]),
+        (dir.join("5"), &late[..]),
+        (dir.join("6"), &data[..]),
+    ];
+    for (path, contents) in &replicas {
+        fs::write(path, contents)?;
+    }
+    let paths: Vec<_> = replicas.iter().map(|(path, _)| path).collect();
+    let options = CompareOptions {
+        chunk_size: Some(64 * 1024),
+        ..Default::default()
+    };
+    let result = compare_replicas(&paths, &options)?;
+    assert_eq!(result.groups, [vec![0, 2, 4, 6], vec![1], vec![3], vec![5]]);
+    assert_eq!(result.majority(), Some(&[0, 2, 4, 6][..]));
+    assert_eq!(result.diff(0, 2), Equal);
+    assert_eq!(result.diff(0, 1), Different(150_000));
+    assert_eq!(result.diff(0, 3), Truncated(Side::Right, 200_000));
+    assert_eq!(result.diff(3, 0), Truncated(Side::Left, 200_000));
+    assert_eq!(result.diff(0, 5), Different(299_999));
+    assert_eq!(result.diff(1, 5), Different(150_000));
+
+    // Three against three is no majority
+    let result = compare_replicas(&paths[..6], &options)?;
+    assert_eq!(result.groups[0], [0, 2, 4]);
+    assert_eq!(result.majority(), None);
+    fs::remove_dir_all(&dir)
+}
+
+// Test the output of --quorum with and without a majority
+#[test]
+fn test_quorum_output() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_quorum_output");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join("a"), b"replica")?;
+    fs::write(dir.join("b"), b"replica")?;
+    fs::write(dir.join("c"), b"replicA")?;
+    fs::write(dir.join("d"), b"replica")?;
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_file_cmp"))
+            .current_dir(&dir)
+            .env_remove("FILE_CMP_MACHINE")
+            .arg("--quorum")
+            .args(args)
+            .output()
+    };
+    let output = run(&["a", "b", "c", "d"])?;
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "-1\ta\t(equal)\n-1\tb\t(equal)\n6\tc\t(diff)\n-1\td\t(equal)\n"
+    );
+    let output = run(&["-d", "--porcelain", "c", "a", "b"])?;
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "differ\t6\ta\tc\n");
+
+    fs::write(dir.join("d"), b"replicA")?;
+    let output = run(&["a", "b", "c", "d"])?;
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("a, b; c, d"));
+    assert!(!run(&["a", "b"])?.status.success());
+    fs::remove_dir_all(&dir)
+}
@@ -0,0 +1,48 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::process::Command;
+
+// Test listing the files to copy again after copying a tree
+#[test]
+fn test_recopy_list() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_recopy_list");
+    let (src, dst) = (dir.join("src"), dir.join("dst"));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(src.join("sub"))?;
+    fs::create_dir_all(dst.join("sub"))?;
+    for (name, src_data, dst_data) in [
+        ("copied", "data", Some("data")),
+        ("changed", "data", Some("date")),
+        ("partial", "data", Some("da")),
+        ("extra", "", None),
+    ] {
+        fs::write(src.join("sub").join(name), src_data)?;
+        if let Some(dst_data) = dst_data {
+            fs::write(dst.join("sub").join(name), dst_data)?;
+        }
+    }
+    fs::write(dst.join("stale"), "")?;
+
+    let list = dir.join("recopy.txt");
+    let status = Command::new(env!("CARGO_BIN_EXE_file_cmp"))
+        .arg("--recopy-list")
+        .arg(&list)
+        .arg(&src)
+        .arg(&dst)
+        .output()?
+        .status;
+    assert!(status.success());
+    assert!(!dir.join("recopy.txt.partial").exists());
+    let sub = Path::new("sub");
+    let mut listed: Vec<_> = fs::read_to_string(&list)?
+        .lines()
+        .map(str::to_string)
+        .collect();
+    listed.sort();
+    assert_eq!(
+        listed,
+        ["changed", "extra", "partial"].map(|name| sub.join(name).display().to_string())
+    );
+    fs::remove_dir_all(&dir)
+}
@@ -0,0 +1,16 @@
+use file_cmp::reflink::shares_extents;
+use std::fs::{self, File};
+use std::io;
+
+#[test]
+fn test_shares_extents_plain_copy() -> io::Result<()> {
+    // Test that an independent copy isn't mistaken for a reflink
+    let dir = std::env::temp_dir().join("file_cmp_test_reflink");
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join("original"), vec![7u8; 64 * 1024])?;
+    fs::write(dir.join("copy"), vec![7u8; 64 * 1024])?;
+    let original = File::open(dir.join("original"))?;
+    let copy = File::open(dir.join("copy"))?;
+    assert!(!shares_extents(&original, &copy));
+    Ok(())
+}
@@ -0,0 +1,36 @@
+use file_cmp::report::{partial_path, write_atomically, ReportFile};
+use std::fs;
+use std::io::{self, Write};
+
+// Test that reports only appear under their name once finished
+#[test]
+fn test_report_file() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_report_file");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir)?;
+    let path = dir.join("list.txt");
+    assert_eq!(partial_path(&path), dir.join("list.txt.partial"));
+
+    let mut report = ReportFile::create(&path)?;
+    writeln!(report, "first")?;
+    report.flush()?;
+    assert!(!path.exists());
+    assert_eq!(fs::read_to_string(report.partial())?, "first\n");
+    writeln!(report, "second")?;
+    report.finish()?;
+    assert_eq!(fs::read_to_string(&path)?, "first\nsecond\n");
+    assert!(!partial_path(&path).exists());
+
+    // A report cut short leaves the last complete one as it was
+    let mut report = ReportFile::create(&path)?;
+    writeln!(report, "third")?;
+    drop(report);
+    assert_eq!(fs::read_to_string(&path)?, "first\nsecond\n");
+    assert_eq!(fs::read_to_string(partial_path(&path))?, "third\n");
+
+    write_atomically(&path, "replaced")?;
+    assert_eq!(fs::read_to_string(&path)?, "replaced");
+    assert!(!partial_path(&path).exists());
+
+    fs::remove_dir_all(&dir)
+}
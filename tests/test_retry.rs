@@ -0,0 +1,49 @@
+#![cfg(any(unix, windows))]
+
+use file_cmp::retry::{is_transient, with_retries};
+use std::io;
+
+// An EIO, or a network name that went away on Windows
+fn transient() -> io::Error {
+    #[cfg(unix)]
+    return io::Error::from_raw_os_error(libc::EIO);
+    #[cfg(windows)]
+    return io::Error::from_raw_os_error(64);
+}
+
+// Test telling transient errors apart from the others
+#[test]
+fn test_is_transient() {
+    assert!(is_transient(&transient()));
+    assert!(!is_transient(&io::Error::from(io::ErrorKind::NotFound)));
+    assert!(!is_transient(&io::Error::new(
+        io::ErrorKind::TimedOut,
+        "no data from the pipe"
+    )));
+}
+
+// Test retrying until success or until the retries run out
+#[test]
+fn test_with_retries() {
+    let flaky = |failures: u32| {
+        let mut calls = 0;
+        move || {
+            calls += 1;
+            match calls <= failures {
+                true => Err(transient()),
+                false => Ok(calls),
+            }
+        }
+    };
+    assert_eq!(with_retries(2, flaky(2)).ok(), Some(3));
+    assert!(with_retries(1, flaky(2)).is_err());
+    assert!(with_retries(0, flaky(1)).is_err());
+
+    let mut calls = 0;
+    let result: io::Result<()> = with_retries(3, || {
+        calls += 1;
+        Err(io::Error::from(io::ErrorKind::NotFound))
+    });
+    assert!(result.is_err());
+    assert_eq!(calls, 1);
+}
@@ -0,0 +1,195 @@
+use file_cmp::rpc::serve_rpc;
+use file_cmp::CompareOptions;
+use serde_json::{json, Value};
+use std::fs;
+use std::io::{self, BufReader, Read, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver, Sender};
+
+/// Sends everything written to it.
+struct Written(Sender<String>);
+
+impl Write for Written {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let _ = self.0.send(String::from_utf8_lossy(buf).into_owned());
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn messages(written: Receiver<String>) -> Vec<Value> {
+    let written: String = written.iter().collect();
+    written
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+fn make_dirs(dir: &Path) -> io::Result<()> {
+    let _ = fs::remove_dir_all(dir);
+    fs::create_dir_all(dir.join("a"))?;
+    fs::create_dir_all(dir.join("b"))?;
+    fs::write(dir.join("a/x"), b"one")?;
+    fs::write(dir.join("b/x"), b"two")?;
+    fs::write(dir.join("a/y"), b"same")?;
+    fs::write(dir.join("b/y"), b"same")?;
+    Ok(())
+}
+
+// Test the methods and errors of the JSON-RPC server
+#[test]
+fn test_serve_rpc() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_serve_rpc");
+    make_dirs(&dir)?;
+    let (a, b) = (dir.join("a"), dir.join("b"));
+    let requests = [
+        json!({ "jsonrpc": "2.0", "id": 1, "method": "compareFiles",
+            "params": { "path1": a.join("x"), "path2": b.join("x") } }),
+        json!({ "jsonrpc": "2.0", "id": 2, "method": "compareDirs",
+            "params": { "path1": a, "path2": b } }),
+        json!({ "jsonrpc": "2.0", "id": 3, "method": "compareFiles", "params": {} }),
+        json!({ "jsonrpc": "2.0", "id": 4, "method": "unknown" }),
+        json!({ "jsonrpc": "2.0", "id": 5, "method": "compareFiles",
+            "params": { "path1": a.join("missing"), "path2": b.join("x") } }),
+    ];
+    let mut input: String = requests.iter().map(|r| format!("{}\n", r)).collect();
+    input.push_str("not json\n");
+    let (sender, written) = mpsc::channel();
+    serve_rpc(input.as_bytes(), Written(sender), CompareOptions::default())?;
+
+    let messages = messages(written);
+    let reply = |id: Value| {
+        messages
+            .iter()
+            .find(|m| m["id"] == id && m.get("method").is_none())
+            .unwrap_or_else(|| panic!("no reply to {}", id))
+    };
+    assert_eq!(
+        reply(json!(1))["result"],
+        json!({ "status": "diff", "offset": 0 })
+    );
+    assert_eq!(
+        reply(json!(2))["result"],
+        json!({ "equal": 1, "different": 1, "left_only": 0, "right_only": 0 })
+    );
+    assert_eq!(reply(json!(3))["error"]["code"], -32602);
+    assert_eq!(reply(json!(4))["error"]["code"], -32601);
+    assert_eq!(reply(json!(5))["error"]["code"], -32000);
+    assert_eq!(reply(Value::Null)["error"]["code"], -32700);
+
+    let mut entries: Vec<&Value> = messages
+        .iter()
+        .filter(|m| m["method"] == "dirEntry")
+        .map(|m| &m["params"])
+        .collect();
+    entries.sort_by_key(|e| e["path"].as_str().unwrap().to_string());
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0]["id"], 2);
+    assert_eq!(entries[0]["path"], json!(a.join("x")));
+    assert_eq!(entries[0]["status"], "diff");
+    assert_eq!(entries[1]["status"], "equal");
+    fs::remove_dir_all(&dir)
+}
+
+/// Gives one line per read, waiting before the cancel request until the
+/// walk is under way and signalling when asked for more after it, by which
+/// point the server has handled the cancel.
+struct Script {
+    lines: Vec<String>,
+    started: Receiver<()>,
+    cancelled: Sender<()>,
+}
+
+impl Read for Script {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.lines.len() == 1 {
+            self.started.recv().unwrap();
+        }
+        if self.lines.is_empty() {
+            let _ = self.cancelled.send(());
+            return Ok(0);
+        }
+        let line = self.lines.remove(0);
+        buf[..line.len()].copy_from_slice(line.as_bytes());
+        Ok(line.len())
+    }
+}
+
+/// Blocks on the first directory entry until the cancel was handled.
+struct Blocking {
+    written: Sender<String>,
+    started: Option<Sender<()>>,
+    cancelled: Receiver<()>,
+}
+
+impl Write for Blocking {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let Some(started) = self.started.take() {
+            started.send(()).unwrap();
+            self.cancelled.recv().unwrap();
+        }
+        let _ = self.written.send(String::from_utf8_lossy(buf).into_owned());
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// Test cancelling a directory comparison between files
+#[test]
+fn test_cancel() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_rpc_cancel");
+    make_dirs(&dir)?;
+    let compare = json!({ "jsonrpc": "2.0", "id": "walk", "method": "compareDirs",
+        "params": { "path1": dir.join("a"), "path2": dir.join("b") } });
+    let cancel = json!({ "jsonrpc": "2.0", "method": "cancel", "params": { "id": "walk" } });
+    let (started_sender, started) = mpsc::channel();
+    let (cancelled_sender, cancelled) = mpsc::channel();
+    let script = Script {
+        lines: vec![format!("{}\n", compare), format!("{}\n", cancel)],
+        started,
+        cancelled: cancelled_sender,
+    };
+    let (sender, written) = mpsc::channel();
+    let blocking = Blocking {
+        written: sender,
+        started: Some(started_sender),
+        cancelled,
+    };
+    serve_rpc(BufReader::new(script), blocking, CompareOptions::default())?;
+
+    let messages = messages(written);
+    assert_eq!(messages.len(), 2, "{:?}", messages);
+    assert_eq!(messages[0]["method"], "dirEntry");
+    assert_eq!(messages[1]["id"], "walk");
+    assert_eq!(messages[1]["error"]["code"], -32800);
+    fs::remove_dir_all(&dir)
+}
+
+// Test running --rpc as a backend over stdin and stdout
+#[test]
+fn test_rpc_option() -> io::Result<()> {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_file_cmp"))
+        .env_remove("FILE_CMP_MACHINE")
+        .arg("--rpc")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+    let request = json!({ "jsonrpc": "2.0", "id": 1, "method": "compareFiles",
+        "params": { "path1": "./tests/testfiles/test.txt", "path2": "./tests/testfiles/test.txt" } });
+    writeln!(child.stdin.take().unwrap(), "{}", request)?;
+    let output = child.wait_with_output()?;
+    assert!(output.status.success());
+    let reply: Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(
+        reply,
+        json!({ "jsonrpc": "2.0", "id": 1, "result": { "status": "equal" } })
+    );
+    Ok(())
+}
@@ -0,0 +1,122 @@
+#![cfg(feature = "s3")]
+
+use file_cmp::s3::{checksum_matches, compare_tree, Client, ObjectInfo, S3Url};
+use file_cmp::CompareOptions;
+use file_cmp::FileDiff::*;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::thread;
+
+fn p(p: &str) -> String {
+    format!("./tests/testfiles/{}", p)
+}
+
+fn object(etag: Option<&str>, checksum_sha256: Option<&str>) -> ObjectInfo {
+    ObjectInfo {
+        key: "test.txt".to_string(),
+        len: 7,
+        etag: etag.map(str::to_string),
+        checksum_sha256: checksum_sha256.map(str::to_string),
+    }
+}
+
+#[test]
+fn test_s3_url() {
+    let url = S3Url::parse("s3://bucket/dir/file.bin").unwrap();
+    assert_eq!(url.bucket, "bucket");
+    assert_eq!(url.key, "dir/file.bin");
+    assert_eq!(S3Url::parse("s3://bucket").unwrap().key, "");
+    assert_eq!(S3Url::parse("s3:///key"), None);
+    assert_eq!(S3Url::parse("https://bucket/key"), None);
+}
+
+#[test]
+fn test_checksum_matches() -> io::Result<()> {
+    // Test single part and multipart ETags, and SHA-256 checksums
+    let single = object(Some("\"a55ab7512f0d0ff4527d898d06afd5c5\""), None);
+    assert_eq!(checksum_matches(p("test.txt"), &single)?, Some(true));
+    assert_eq!(checksum_matches(p("tesx.txt"), &single)?, Some(false));
+    let multipart = object(Some("\"39596743c2d0dba5b7955452de3bef2f-1\""), None);
+    assert_eq!(checksum_matches(p("test.txt"), &multipart)?, Some(true));
+    assert_eq!(checksum_matches(p("tesx.txt"), &multipart)?, None);
+    let sha256 = object(None, Some("ZyKu7V9dgpfeTrLLocWruQCv5KTNZLEOKDVGMIeTZmY="));
+    assert_eq!(checksum_matches(p("test.txt"), &sha256)?, Some(true));
+    assert_eq!(checksum_matches(p("tesx.txt"), &sha256)?, Some(false));
+
+    // Test ETags that aren't an MD5 of the content
+    let encrypted = object(Some("\"not-an-md5\""), None);
+    assert_eq!(checksum_matches(p("test.txt"), &encrypted)?, None);
+    Ok(())
+}
+
+/// Serves a listing of `a.txt`, `b.txt` and `d.txt` under `prefix/`. The
+/// body of `a.txt` doesn't match its ETag, to tell when the ETag was used.
+fn serve() -> io::Result<String> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let endpoint = format!("http://{}", listener.local_addr()?);
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request = String::new();
+            reader.read_line(&mut request).unwrap();
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 2 {
+                line.clear();
+            }
+            let target = request.split(' ').nth(1).unwrap_or_default();
+            let (status, body) = match target {
+                t if t.starts_with("/bucket/?") => (
+                    "200 OK",
+                    concat!(
+                        "<ListBucketResult><IsTruncated>false</IsTruncated>",
+                        "<Contents><Key>prefix/a.txt</Key><Size>7</Size>",
+                        "<ETag>&quot;a55ab7512f0d0ff4527d898d06afd5c5&quot;</ETag></Contents>",
+                        "<Contents><Key>prefix/b.txt</Key><Size>7</Size>",
+                        "<ETag>&quot;00000000000000000000000000000000&quot;</ETag></Contents>",
+                        "<Contents><Key>prefix/d.txt</Key><Size>7</Size></Contents>",
+                        "</ListBucketResult>"
+                    ),
+                ),
+                "/bucket/prefix/a.txt" => ("200 OK", "xxxxxxx"),
+                "/bucket/prefix/b.txt" | "/bucket/prefix/d.txt" => ("200 OK", "test \r\n"),
+                _ => ("404 Not Found", ""),
+            };
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+    });
+    Ok(endpoint)
+}
+
+#[test]
+fn test_compare_tree() -> io::Result<()> {
+    // Test a local tree against a prefix, with matching and mismatching ETags
+    let dir = std::env::temp_dir().join("file_cmp_test_s3_tree");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir)?;
+    fs::copy(p("test.txt"), dir.join("a.txt"))?;
+    fs::copy(p("tesx.txt"), dir.join("b.txt"))?;
+    fs::copy(p("test.txt"), dir.join("c.txt"))?;
+
+    let client = Client::from_env(None).with_endpoint(&serve()?);
+    let url = S3Url::parse("s3://bucket/prefix").unwrap();
+    let res = compare_tree(&client, &url, &dir, &CompareOptions::default())?;
+    assert_eq!(
+        res,
+        vec![
+            (dir.join("a.txt"), Equal),
+            (dir.join("b.txt"), Different(3)),
+            (dir.join("c.txt"), LeftOnly),
+            (PathBuf::from("s3://bucket/prefix/d.txt"), RightOnly),
+        ]
+    );
+    Ok(())
+}
@@ -0,0 +1,61 @@
+#![cfg(target_os = "linux")]
+
+use file_cmp::selinux::{context, contexts_differ, XATTR};
+use std::ffi::CString;
+use std::fs;
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use std::process::Command;
+
+/// Labels a file, which needs CAP_SYS_ADMIN without SELinux.
+fn set_context(path: &Path, context: &str) -> io::Result<()> {
+    let c_path = CString::new(path.as_os_str().as_bytes()).unwrap();
+    let name = CString::new(XATTR).unwrap();
+    let value = format!("{}\0", context);
+    let set = unsafe {
+        libc::setxattr(
+            c_path.as_ptr(),
+            name.as_ptr(),
+            value.as_ptr().cast(),
+            value.len(),
+            0,
+        )
+    };
+    match set {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+// Test comparing the security contexts of files
+#[test]
+fn test_selinux() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_selinux");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir)?;
+    let (path1, path2) = (dir.join("1"), dir.join("2"));
+    fs::write(&path1, b"test")?;
+    fs::write(&path2, b"test")?;
+    if set_context(&path1, "system_u:object_r:bin_t:s0").is_err() {
+        // Not allowed to label files, or the filesystem can't
+        fs::remove_dir_all(&dir)?;
+        return Ok(());
+    }
+    assert_eq!(
+        context(&path1)?.as_deref(),
+        Some("system_u:object_r:bin_t:s0")
+    );
+    set_context(&path2, "system_u:object_r:bin_t:s0")?;
+    assert!(!contexts_differ(&path1, &path2)?);
+    set_context(&path2, "system_u:object_r:tmp_t:s0")?;
+    assert!(contexts_differ(&path1, &path2)?);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_file_cmp"))
+        .current_dir(&dir)
+        .env_remove("FILE_CMP_MACHINE")
+        .args(["-m", "--selinux", "1", "2"])
+        .output()?;
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "-1\tselinux");
+    fs::remove_dir_all(&dir)
+}
@@ -0,0 +1,59 @@
+use file_cmp::serve::serve;
+use file_cmp::CompareOptions;
+use serde_json::{json, Value};
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+fn p(p: &str) -> PathBuf {
+    PathBuf::from(format!("./tests/testfiles/{}", p))
+}
+
+/// Sends a request and returns the status code and JSON body.
+fn request(addr: SocketAddr, method: &str, path: &str) -> io::Result<(u16, Value)> {
+    let mut stream = TcpStream::connect(addr)?;
+    write!(
+        stream,
+        "{} {} HTTP/1.1\r\nHost: localhost\r\n\r\n",
+        method, path
+    )?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    let status = head.split(' ').nth(1).unwrap().parse().unwrap();
+    Ok((status, serde_json::from_str(body)?))
+}
+
+#[test]
+fn test_serve() -> io::Result<()> {
+    // Test starting a comparison and polling for its result
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let addr = listener.local_addr()?;
+    let pairs = vec![
+        (p("test.txt"), p("tesx.txt")),
+        (p("test.txt"), p("test.txt")),
+    ];
+    thread::spawn(move || serve(listener, pairs, CompareOptions::default()));
+
+    let (status, list) = request(addr, "GET", "/pairs")?;
+    assert_eq!(status, 200);
+    assert_eq!(list.as_array().unwrap().len(), 2);
+    assert_eq!(list[1]["status"], "idle");
+
+    assert_eq!(request(addr, "POST", "/pairs/0/compare")?.0, 202);
+    let mut pair = json!({});
+    for _ in 0..100 {
+        pair = request(addr, "GET", "/pairs/0")?.1;
+        if pair["status"] == "done" {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(pair["result"], json!({ "status": "diff", "offset": 3 }));
+
+    assert_eq!(request(addr, "GET", "/pairs/2")?.0, 404);
+    assert_eq!(request(addr, "GET", "/other")?.0, 404);
+    Ok(())
+}
@@ -0,0 +1,98 @@
+use file_cmp::hash::to_hex;
+use file_cmp::sidecar::{checksums, find, parse, Kind};
+use file_cmp::{compare_files_with, CompareOptions, FileDiff};
+use std::fs;
+use std::io;
+use std::process::Command;
+
+// Test parsing the formats of sidecars
+#[test]
+fn test_parse() {
+    let sha256 = "ab".repeat(32);
+    let md5 = "cd".repeat(16);
+    let gnu = format!("{}  other.iso\n{} *disk.iso\n", "00".repeat(32), sha256);
+    assert_eq!(parse(&gnu, Kind::Sha256, "disk.iso"), Some(vec![0xab; 32]));
+    let bsd = format!("MD5 (disk.iso) = {}\n", md5);
+    assert_eq!(parse(&bsd, Kind::Md5, "disk.iso"), Some(vec![0xcd; 16]));
+    // A single entry is taken whatever its name, a bare checksum too
+    let single = format!("{}  disk.iso\n", sha256);
+    assert_eq!(
+        parse(&single, Kind::Sha256, "renamed.iso"),
+        Some(vec![0xab; 32])
+    );
+    assert_eq!(parse(&md5, Kind::Md5, "disk.iso"), Some(vec![0xcd; 16]));
+    let sfv = "; made by hand\nfirst part.bin 0badf00d\ndisk.iso CBF43926\n";
+    assert_eq!(
+        parse(sfv, Kind::Sfv, "disk.iso"),
+        Some(vec![0xcb, 0xf4, 0x39, 0x26])
+    );
+    // Checksums of the wrong length and lists without the file don't count
+    assert_eq!(parse(&md5, Kind::Sha256, "disk.iso"), None);
+    assert_eq!(parse(sfv, Kind::Sfv, "missing.iso"), None);
+}
+
+// Test computing checksums of several kinds in one pass
+#[test]
+fn test_checksums() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_checksums");
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join("digits"), b"123456789")?;
+
+    let kinds = [Kind::Sfv, Kind::Md5, Kind::Sha256];
+    let hex: Vec<_> = checksums(dir.join("digits"), &kinds, None)?
+        .iter()
+        .map(|checksum| to_hex(checksum))
+        .collect();
+    assert_eq!(
+        hex,
+        [
+            "cbf43926",
+            "25f9e794323b453885f5181f1b624d0b",
+            "15e2b0d3c33891ebb0f1ef609ec419420c20e320ce94c65fbc8c3312448eb225"
+        ]
+    );
+
+    fs::remove_dir_all(&dir)
+}
+
+// Test comparing against sidecars instead of the files next to them
+#[test]
+fn test_sidecars() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_sidecars");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join("a"), b"123456789")?;
+    fs::write(dir.join("c"), b"123456780")?;
+    fs::write(dir.join("b.sfv"), b"b cbf43926\n")?;
+    fs::write(dir.join("d.md5"), b"00000000000000000000000000000000  d\n")?;
+
+    assert_eq!(find(dir.join("a"))?, None);
+    assert_eq!(find(dir.join("b"))?.map(|s| s.kind), Some(Kind::Sfv));
+
+    let options = CompareOptions {
+        sidecars: true,
+        ..Default::default()
+    };
+    // b itself doesn't exist, so it can only be compared by its sidecar
+    let result = compare_files_with(dir.join("a"), dir.join("b"), &options)?;
+    assert_eq!(result, FileDiff::Equal);
+    let result = compare_files_with(dir.join("b"), dir.join("c"), &options)?;
+    assert_eq!(result, FileDiff::Different(0));
+    assert!(compare_files_with(dir.join("a"), dir.join("b"), &Default::default()).is_err());
+
+    // A file read that no longer matches its own sidecar fails
+    fs::write(dir.join("d"), b"123456789")?;
+    let error = compare_files_with(dir.join("d"), dir.join("b"), &options).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_file_cmp"))
+        .env_remove("FILE_CMP_MACHINE")
+        .arg("--sidecars")
+        .arg(dir.join("a"))
+        .arg(dir.join("b"))
+        .output()?;
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "Files are equal");
+
+    fs::remove_dir_all(&dir)
+}
@@ -0,0 +1,96 @@
+use file_cmp::similar::{classify, similarity, Likeness};
+use file_cmp::CompareOptions;
+use std::fs;
+use std::io;
+use std::process::Command;
+
+/// Returns pseudo-random bytes, the same for the same seed.
+fn noise(len: usize, seed: u64) -> Vec<u8> {
+    let mut state = seed;
+    (0..len)
+        .map(|_| {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (state >> 56) as u8
+        })
+        .collect()
+}
+
+// Test measuring how much content an edited and an unrelated file share
+#[test]
+fn test_similarity() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_similarity");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir)?;
+    let data = noise(1 << 20, 1);
+    let mut edited = data.clone();
+    edited.splice(1000..1000, *b"inserted");
+    fs::write(dir.join("data"), &data)?;
+    fs::write(dir.join("edited"), &edited)?;
+    fs::write(dir.join("other"), noise(1 << 20, 2))?;
+    fs::write(dir.join("empty1"), "")?;
+    fs::write(dir.join("empty2"), "")?;
+    let options = CompareOptions::default();
+
+    let percent = similarity(dir.join("data"), dir.join("edited"), &options)?;
+    assert!(percent > 99.0 && percent < 100.0, "{}", percent);
+    assert_eq!(
+        similarity(dir.join("data"), dir.join("other"), &options)?,
+        0.0
+    );
+    assert_eq!(
+        similarity(dir.join("data"), dir.join("empty1"), &options)?,
+        0.0
+    );
+    assert_eq!(
+        similarity(dir.join("empty1"), dir.join("empty2"), &options)?,
+        100.0
+    );
+    assert_eq!(
+        classify(dir.join("data"), dir.join("edited"), 90.0, &options)?,
+        Likeness::SimilarTo(percent)
+    );
+    assert_eq!(
+        classify(dir.join("data"), dir.join("other"), 90.0, &options)?,
+        Likeness::Different
+    );
+    fs::remove_dir_all(&dir)
+}
+
+// Test reporting similar files in a directory comparison
+#[test]
+fn test_similar_threshold() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_similar_threshold");
+    let (a, b) = (dir.join("a"), dir.join("b"));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&a)?;
+    fs::create_dir_all(&b)?;
+    let data = noise(100_000, 3);
+    let mut drifted = data.clone();
+    drifted[50_000] ^= 1;
+    fs::write(a.join("drifted"), &data)?;
+    fs::write(b.join("drifted"), &drifted)?;
+    fs::write(a.join("wrong"), &data)?;
+    fs::write(b.join("wrong"), noise(100_000, 4))?;
+
+    let output = Command::new(env!("CARGO_BIN_EXE_file_cmp"))
+        .env_remove("FILE_CMP_MACHINE")
+        .arg("--similar-threshold=90")
+        .arg(&a)
+        .arg(&b)
+        .output()?;
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let line = |name: &str| {
+        stdout
+            .lines()
+            .find(|line| line.contains(name))
+            .unwrap()
+            .to_string()
+    };
+    assert!(line("drifted").starts_with("50000\t"));
+    assert!(line("drifted").contains("(similar 9"), "{}", stdout);
+    assert!(line("wrong").ends_with("(diff)"));
+    fs::remove_dir_all(&dir)
+}
@@ -0,0 +1,55 @@
+use file_cmp::walk::DirWalk;
+use file_cmp::FileDiff::*;
+use file_cmp::{compare_files_with, CompareOptions, Policy};
+use std::fs;
+use std::io;
+use std::process::Command;
+
+// Test comparing files and trees by their sizes only
+#[test]
+fn test_size_only() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_size_only");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("a"))?;
+    fs::create_dir_all(dir.join("b"))?;
+    fs::write(dir.join("a/same.bin"), "abcdef")?;
+    fs::write(dir.join("b/same.bin"), "abcxef")?;
+    fs::write(dir.join("a/longer.bin"), "abcdef")?;
+    fs::write(dir.join("b/longer.bin"), "abcdefg")?;
+
+    let (a, b) = (dir.join("a"), dir.join("b"));
+    let options = CompareOptions {
+        policy: Policy::SizeOnly,
+        ..Default::default()
+    };
+    assert_eq!(
+        compare_files_with(a.join("same.bin"), b.join("same.bin"), &options)?,
+        Equal
+    );
+    assert_eq!(
+        compare_files_with(a.join("longer.bin"), b.join("longer.bin"), &options)?,
+        Different(0)
+    );
+    let mut results: Vec<_> = DirWalk::new(&a, &b, &options).collect();
+    results.sort_by(|x, y| x.0.cmp(&y.0));
+    assert_eq!(
+        results,
+        vec![
+            (a.join("longer.bin"), Different(0)),
+            (a.join("same.bin"), Equal)
+        ]
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_file_cmp"))
+        .current_dir(&dir)
+        .env_remove("FILE_CMP_MACHINE")
+        .args(["-m", "--size-only", "a", "b"])
+        .output()?;
+    let mut lines: Vec<_> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::to_string)
+        .collect();
+    lines.sort();
+    assert_eq!(lines, ["-1\ta/same.bin", "0\ta/longer.bin"]);
+    fs::remove_dir_all(&dir)
+}
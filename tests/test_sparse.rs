@@ -0,0 +1,88 @@
+use file_cmp::sparse::{compare_layouts, data_extents, merge_extents};
+use file_cmp::FileDiff::*;
+use file_cmp::{compare_files, compare_files_with, CompareOptions, Policy, Side};
+use std::fs::{self, File};
+use std::io::{self, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+
+fn sparse_file(name: &str, len: u64, data: &[(u64, &[u8])]) -> io::Result<PathBuf> {
+    let dir = std::env::temp_dir().join("file_cmp_test_sparse");
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(name);
+    let mut file = File::create(&path)?;
+    file.set_len(len)?;
+    for (offset, bytes) in data {
+        file.seek(SeekFrom::Start(*offset))?;
+        file.write_all(bytes)?;
+    }
+    Ok(path)
+}
+
+#[test]
+fn test_merge_extents() {
+    // Test overlapping and adjacent extents are merged and clipped
+    let merged = merge_extents(&[0..10, 50..60], &[5..20, 20..30, 55..100], 80);
+    assert_eq!(merged, vec![0..30, 50..80]);
+}
+
+#[test]
+fn test_compare_sparse_equal() -> io::Result<()> {
+    // Test when sparse files have the same data at the same offsets
+    let data: &[(u64, &[u8])] = &[(1 << 20, b"data"), (40 << 20, b"more")];
+    let a = sparse_file("equal_a", 64 << 20, data)?;
+    let b = sparse_file("equal_b", 64 << 20, data)?;
+    assert_eq!(compare_files(&a, &b, Policy::ByteExact)?, Equal);
+    Ok(())
+}
+
+#[test]
+fn test_compare_sparse_data_vs_hole() -> io::Result<()> {
+    // Test when one file has data where the other has a hole
+    let a = sparse_file("hole_a", 64 << 20, &[(1 << 20, b"data")])?;
+    let b = sparse_file("hole_b", 64 << 20, &[(1 << 20, b"data"), (30 << 20, b"x")])?;
+    assert_eq!(
+        compare_files(&a, &b, Policy::ByteExact)?,
+        Different(30 << 20)
+    );
+    assert_eq!(
+        compare_files(&b, &a, Policy::ByteExact)?,
+        Different(30 << 20)
+    );
+    let quick = CompareOptions {
+        quick: true,
+        ..Default::default()
+    };
+    assert_eq!(compare_files_with(&a, &b, &quick)?, Different(0));
+    Ok(())
+}
+
+#[test]
+fn test_compare_sparse_different_length() -> io::Result<()> {
+    // Test when sparse files are equal up to the length of the shorter one
+    let a = sparse_file("len_a", 32 << 20, &[(1 << 20, b"data")])?;
+    let b = sparse_file("len_b", 64 << 20, &[(1 << 20, b"data")])?;
+    assert_eq!(
+        compare_files(&a, &b, Policy::ByteExact)?,
+        Truncated(Side::Left, 32 << 20)
+    );
+    Ok(())
+}
+
+#[test]
+fn test_compare_layouts() -> io::Result<()> {
+    // Test telling a densified copy from one whose holes moved
+    let data: &[(u64, &[u8])] = &[(1 << 20, b"data")];
+    let sparse = sparse_file("layout_sparse", 8 << 20, data)?;
+    let same = sparse_file("layout_same", 8 << 20, data)?;
+    let dense = sparse_file("layout_dense", 8 << 20, &[(0, &vec![0; 8 << 20])])?;
+    let moved = sparse_file("layout_moved", 8 << 20, &[(6 << 20, b"data")])?;
+    if data_extents(&File::open(&sparse)?, 8 << 20)?.is_none() {
+        // Holes can't be found on this platform
+        return Ok(());
+    }
+    assert_eq!(compare_layouts(&sparse, &same)?, None);
+    assert_eq!(compare_layouts(&sparse, &dense)?, Some("densified"));
+    assert_eq!(compare_layouts(&dense, &sparse)?, Some("extents"));
+    assert_eq!(compare_layouts(&sparse, &moved)?, Some("extents"));
+    Ok(())
+}
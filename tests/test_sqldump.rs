@@ -0,0 +1,101 @@
+use file_cmp::sqldump::{compare_sql_dumps, is_sql_dump};
+use file_cmp::FileDiff::*;
+use file_cmp::{compare_files_with, CompareOptions};
+use std::fs;
+use std::io;
+
+const PG_DUMP: &str = "--
+-- PostgreSQL database dump
+--
+
+\\restrict abc123
+
+-- Dumped from database version 16.2
+-- Dumped by pg_dump version 16.2
+
+SET statement_timeout = 0;
+SET client_encoding = 'UTF8';
+SELECT pg_catalog.set_config('search_path', '', false);
+
+CREATE TABLE public.t (id integer);
+COPY public.t (id) FROM stdin;
+1
+2
+\\.
+
+\\unrestrict abc123
+";
+
+// Test comparing pg_dump files made by another version and with the
+// settings in another order
+#[test]
+fn test_compare_pg_dumps() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_sqldump");
+    fs::create_dir_all(&dir)?;
+    let (path1, path2) = (dir.join("1.sql"), dir.join("2.sql"));
+    fs::write(&path1, PG_DUMP)?;
+    let redumped = PG_DUMP
+        .replace("abc123", "xyz789")
+        .replace("version 16.2", "version 16.3")
+        .replace(
+            "SET statement_timeout = 0;\nSET client_encoding = 'UTF8';",
+            "SET client_encoding = 'UTF8';\nSET statement_timeout = 0;",
+        )
+        .replace('\n', "\r\n");
+    fs::write(&path2, &redumped)?;
+    assert_eq!(compare_sql_dumps(&path1, &path2, false)?, Equal);
+
+    fs::write(&path2, redumped.replace("\r\n2\r\n", "\r\n3\r\n"))?;
+    let row = PG_DUMP.find("\n2\n").unwrap() + 1;
+    assert_eq!(compare_sql_dumps(&path1, &path2, false)?, Different(row));
+    assert_eq!(compare_sql_dumps(&path1, &path2, true)?, Different(0));
+
+    fs::write(&path2, redumped.replace("= 0;", "= 5;"))?;
+    let setting = PG_DUMP.find("SET statement_timeout").unwrap();
+    assert_eq!(
+        compare_sql_dumps(&path1, &path2, false)?,
+        Different(setting)
+    );
+
+    fs::write(
+        &path2,
+        PG_DUMP.replace("CREATE TABLE public.t (id integer);\n", ""),
+    )?;
+    let create = PG_DUMP.find("CREATE").unwrap();
+    assert_eq!(compare_sql_dumps(&path1, &path2, false)?, Different(create));
+    fs::remove_dir_all(&dir)
+}
+
+// Test comparing mysqldump files with the sql_dump option
+#[test]
+fn test_compare_mysql_dumps() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_sqldump_mysql");
+    fs::create_dir_all(&dir)?;
+    let dump = |host: &str, date: &str, names: &str| {
+        format!(
+            "-- MySQL dump 10.13\n--\n-- Host: {}    Database: shop\n-- Server version\t8.0.36\n\n/*!40101 SET NAMES {} */;\n/*!40103 SET TIME_ZONE='+00:00' */;\nINSERT INTO `t` VALUES (1),(2);\n-- Dump completed on {}\n",
+            host, names, date
+        )
+    };
+    let (path1, path2) = (dir.join("1.sql"), dir.join("2.sql"));
+    fs::write(&path1, dump("db1", "2024-01-01 00:00:00", "utf8mb4"))?;
+    fs::write(&path2, dump("db2", "2024-02-02 12:00:00", "utf8mb4"))?;
+    assert!(is_sql_dump(&path1));
+    assert!(!is_sql_dump(dir.join("1.sql.gz")));
+    let options = CompareOptions {
+        sql_dump: true,
+        ..Default::default()
+    };
+    assert_eq!(compare_files_with(&path1, &path2, &options)?, Equal);
+    assert_ne!(
+        compare_files_with(&path1, &path2, &CompareOptions::default())?,
+        Equal
+    );
+
+    fs::write(&path2, dump("db1", "2024-01-01 00:00:00", "latin1"))?;
+    assert!(matches!(
+        compare_files_with(&path1, &path2, &options)?,
+        Different(_)
+    ));
+    fs::remove_dir_all(&dir)
+}
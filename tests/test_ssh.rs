@@ -0,0 +1,126 @@
+use file_cmp::ssh::{quote, RemotePath};
+
+#[test]
+fn test_remote_path() {
+    let remote = RemotePath::parse("sftp://user@host:2222/srv/data").unwrap();
+    assert_eq!(remote.destination, "user@host");
+    assert_eq!(remote.port, Some(2222));
+    assert_eq!(remote.path, "/srv/data");
+    let remote = RemotePath::parse("ssh://host/~/backup").unwrap();
+    assert_eq!(remote.destination, "host");
+    assert_eq!(remote.port, None);
+    assert_eq!(remote.path, "backup");
+    assert_eq!(RemotePath::parse("sftp://host"), None);
+    assert_eq!(RemotePath::parse("sftp://host:port/x"), None);
+    assert_eq!(RemotePath::parse("/srv/data"), None);
+}
+
+#[test]
+fn test_quote() {
+    assert_eq!(quote("/srv/my data"), "'/srv/my data'");
+    assert_eq!(quote("it's"), r"'it'\''s'");
+}
+
+#[cfg(unix)]
+mod fake_ssh {
+    use file_cmp::ssh::{
+        compare_file, compare_file_with_agent, compare_tree, compare_tree_with_agent, RemotePath,
+        Session,
+    };
+    use file_cmp::CompareOptions;
+    use file_cmp::FileDiff::*;
+    use std::fs;
+    use std::io;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::{Path, PathBuf};
+
+    fn p(p: &str) -> String {
+        format!("./tests/testfiles/{}", p)
+    }
+
+    /// Creates a stand-in for ssh that runs the remote command locally.
+    fn fake_ssh(dir: &Path) -> io::Result<Session> {
+        let path = dir.join("ssh");
+        let script =
+            "#!/bin/sh\nwhile [ \"$1\" != \"--\" ]; do shift; done\nshift 2\nexec sh -c \"$1\"\n";
+        fs::write(&path, script)?;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755))?;
+        Ok(Session::new(&path.to_string_lossy()))
+    }
+
+    fn remote(path: &Path) -> RemotePath {
+        RemotePath::parse(&format!("sftp://host{}", path.display())).unwrap()
+    }
+
+    #[test]
+    fn test_compare_remote() -> io::Result<()> {
+        // Test files and trees read through the ssh command
+        let dir = std::env::temp_dir().join("file_cmp_test_ssh");
+        let _ = fs::remove_dir_all(&dir);
+        for side in ["local", "remote"] {
+            fs::create_dir_all(dir.join(side).join("sub"))?;
+            fs::copy(p("test.txt"), dir.join(side).join("equal.txt"))?;
+        }
+        fs::copy(p("test.txt"), dir.join("local/sub/diff.txt"))?;
+        fs::copy(p("tesx.txt"), dir.join("remote/sub/diff.txt"))?;
+        fs::copy(p("test.txt"), dir.join("local/left.txt"))?;
+        fs::copy(p("test.txt"), dir.join("remote/right.txt"))?;
+        let session = fake_ssh(&dir)?;
+        let options = CompareOptions::default();
+
+        let file = remote(&dir.join("remote/sub/diff.txt"));
+        assert_eq!(
+            compare_file(&session, &file, p("test.txt"), &options)?,
+            Different(3)
+        );
+        let missing = remote(&dir.join("remote/missing.txt"));
+        assert!(compare_file(&session, &missing, p("test.txt"), &options).is_err());
+
+        let local = dir.join("local");
+        let res = compare_tree(&session, &remote(&dir.join("remote")), &local, &options)?;
+        let url = format!("sftp://host{}/right.txt", dir.join("remote").display());
+        assert_eq!(
+            res,
+            vec![
+                (local.join("equal.txt"), Equal),
+                (local.join("left.txt"), LeftOnly),
+                (local.join("sub").join("diff.txt"), Different(3)),
+                (PathBuf::from(url.clone()), RightOnly),
+            ]
+        );
+
+        // Test the same trees hashed by the agent on the remote side
+        let program = env!("CARGO_BIN_EXE_file_cmp");
+        let res = compare_tree_with_agent(
+            &session,
+            &remote(&dir.join("remote")),
+            program,
+            &local,
+            &options,
+        )?;
+        assert_eq!(
+            res,
+            vec![
+                (local.join("equal.txt"), Equal),
+                (PathBuf::from(url), RightOnly),
+                (local.join("sub").join("diff.txt"), Different(0)),
+                (local.join("left.txt"), LeftOnly),
+            ]
+        );
+
+        // Test a file compared by the signature of its blocks from the agent
+        let mut data: Vec<u8> = (0..20_000u64)
+            .map(|i| (i.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 56) as u8)
+            .collect();
+        fs::write(dir.join("remote/big.bin"), &data)?;
+        data[15_000] ^= 1;
+        fs::write(dir.join("local/big.bin"), &data)?;
+        let file = remote(&dir.join("remote/big.bin"));
+        let delta =
+            compare_file_with_agent(&session, &file, program, local.join("big.bin"), &options)?;
+        assert_eq!(delta.file_diff, Different(14_336));
+        assert_eq!(delta.ranges.len(), 1);
+        assert_eq!(delta.ranges[0], 14_336..15_360);
+        Ok(())
+    }
+}
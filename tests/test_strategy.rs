@@ -0,0 +1,64 @@
+use file_cmp::strategy::{choose, Strategy, MIN_PARALLEL_LEN};
+use file_cmp::{compare_files_with, CompareOptions, FileDiff};
+use std::fs;
+use std::io;
+use std::process::Command;
+
+const MIB: u64 = 1024 * 1024;
+
+// Test picking how to read a pair of files
+#[test]
+fn test_choose() {
+    let gib = Some(1024 * MIB);
+    assert_eq!(choose(4096, false, gib, 8), Strategy::Buffered);
+    assert_eq!(choose(64 * MIB, false, gib, 8), Strategy::Mmap);
+    assert_eq!(choose(64 * MIB, false, None, 8), Strategy::Mmap);
+    // Both files have to fit in half the available memory to be mapped
+    assert_eq!(choose(300 * MIB, false, gib, 1), Strategy::Buffered);
+    assert_eq!(
+        choose(MIN_PARALLEL_LEN, false, gib, 4),
+        Strategy::Parallel(4)
+    );
+    assert_eq!(
+        choose(MIN_PARALLEL_LEN, false, gib, 64),
+        Strategy::Parallel(8)
+    );
+    assert_eq!(choose(64 * MIB, true, gib, 8), Strategy::Buffered);
+    assert_eq!(choose(MIN_PARALLEL_LEN, true, gib, 8), Strategy::Buffered);
+    #[cfg(target_os = "linux")]
+    assert!(file_cmp::strategy::available_memory().is_some_and(|available| available > 0));
+}
+
+// Test comparing with the automatic choice
+#[test]
+fn test_auto_io() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_auto_io");
+    fs::create_dir_all(&dir)?;
+    let data: Vec<u8> = (0..3 * MIB).map(|i| (i % 251) as u8).collect();
+    fs::write(dir.join("a"), &data)?;
+    let mut changed = data.clone();
+    changed[2_500_000] ^= 1;
+    fs::write(dir.join("b"), changed)?;
+
+    let options = CompareOptions {
+        auto_io: true,
+        ..Default::default()
+    };
+    let result = compare_files_with(dir.join("a"), dir.join("b"), &options)?;
+    assert_eq!(result, FileDiff::Different(2_500_000));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_file_cmp"))
+        .env_remove("FILE_CMP_MACHINE")
+        .args(["-m", "--auto-io"])
+        .arg(dir.join("a"))
+        .arg(dir.join("b"))
+        .output()?;
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "2500000");
+    let output = Command::new(env!("CARGO_BIN_EXE_file_cmp"))
+        .args(["--auto-io", "--mmap"])
+        .arg(dir.join("a"))
+        .arg(dir.join("b"))
+        .output()?;
+    assert_eq!(output.status.code(), Some(2));
+    fs::remove_dir_all(&dir)
+}
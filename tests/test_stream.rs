@@ -0,0 +1,48 @@
+use file_cmp::FileDiff::*;
+use file_cmp::{compare_streams, CompareOptions};
+use std::fs::File;
+use std::io::{self, Cursor};
+
+fn p(p: &str) -> String {
+    format!("./tests/testfiles/{}", p)
+}
+
+#[test]
+fn test_streams() -> io::Result<()> {
+    // Test a stream against a file, with and without read-ahead
+    for pipeline_depth in [0, 2] {
+        let options = CompareOptions {
+            pipeline_depth,
+            ..Default::default()
+        };
+        let stdin = Cursor::new(b"test \r\n");
+        assert_eq!(
+            compare_streams(stdin, File::open(p("test.txt"))?, &options)?,
+            Equal
+        );
+        let stdin = Cursor::new(b"test \r\n");
+        assert_eq!(
+            compare_streams(File::open(p("tesx.txt"))?, stdin, &options)?,
+            Different(3)
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn test_streams_prefix() -> io::Result<()> {
+    // Test when one stream ends early, which has to be found without knowing the lengths
+    let options = CompareOptions::default();
+    let a = Cursor::new(b"test");
+    assert_eq!(
+        compare_streams(a, File::open(p("testing.txt"))?, &options)?,
+        Different(4)
+    );
+    let a = Cursor::new(b"test");
+    let quick = CompareOptions {
+        quick: true,
+        ..Default::default()
+    };
+    assert_eq!(compare_streams(a, Cursor::new(b""), &quick)?, Different(0));
+    Ok(())
+}
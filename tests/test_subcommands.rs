@@ -0,0 +1,40 @@
+use std::io;
+use std::process::{Command, Output};
+
+fn file_cmp(args: &[&str]) -> io::Result<Output> {
+    Command::new(env!("CARGO_BIN_EXE_file_cmp"))
+        .current_dir("tests/testfiles")
+        .env_remove("FILE_CMP_MACHINE")
+        .args(args)
+        .output()
+}
+
+// Test that the compare subcommand is the same as giving no subcommand
+#[test]
+fn test_compare() -> io::Result<()> {
+    for args in [
+        &["test.txt", "tesx.txt"][..],
+        &["compare", "test.txt", "tesx.txt"],
+    ] {
+        let output = file_cmp(args)?;
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"Files differ at byte 3");
+    }
+    let output = file_cmp(&["compare", "-m", "test.txt", "test.txt"])?;
+    assert_eq!(output.stdout, b"-1");
+    assert!(!file_cmp(&["compare", "test.txt"])?.status.success());
+    Ok(())
+}
+
+// Test hashing files in the format of sha256sum
+#[test]
+fn test_hash() -> io::Result<()> {
+    let output = file_cmp(&["hash", "emptyfile.txt", "missing.txt"])?;
+    assert!(!output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855  emptyfile.txt\n"
+    );
+    assert!(String::from_utf8_lossy(&output.stderr).contains("missing.txt"));
+    Ok(())
+}
@@ -0,0 +1,59 @@
+use serde_json::Value;
+use std::fs;
+use std::io;
+use std::process::Command;
+
+// Test writing the summary of a run to a file, apart from the results
+#[test]
+fn test_summary_json_file() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_summary_json_file");
+    let (a, b) = (dir.join("a"), dir.join("b"));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&a)?;
+    fs::create_dir_all(&b)?;
+    fs::write(a.join("same"), "data")?;
+    fs::write(b.join("same"), "data")?;
+    fs::write(a.join("changed"), "data")?;
+    fs::write(b.join("changed"), "date")?;
+    fs::write(a.join("extra"), "")?;
+
+    let summary = dir.join("summary.json");
+    let output = Command::new(env!("CARGO_BIN_EXE_file_cmp"))
+        .env_remove("FILE_CMP_MACHINE")
+        .arg(format!("--summary-json={}", summary.display()))
+        .arg(&a)
+        .arg(&b)
+        .output()?;
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout).lines().count(), 3);
+    let json: Value = serde_json::from_str(&fs::read_to_string(&summary)?)?;
+    assert_eq!(json["equal"], 1);
+    assert_eq!(json["different"], 1);
+    assert_eq!(json["left_only"], 1);
+    assert_eq!(json["right_only"], 0);
+    assert_eq!(json["bytes"], 8);
+    assert_eq!(json["exit_code"], 0);
+    assert_eq!(json["success"], true);
+    assert!(json["seconds"].is_f64());
+    fs::remove_dir_all(&dir)
+}
+
+// Test writing the summary to a file descriptor set up by the caller
+#[cfg(unix)]
+#[test]
+fn test_summary_json_fd() -> io::Result<()> {
+    let script = format!(
+        "'{}' --summary-json=3 tests/testfiles/test.txt missing 3>&1 1>/dev/null 2>/dev/null",
+        env!("CARGO_BIN_EXE_file_cmp")
+    );
+    let output = Command::new("sh")
+        .env_remove("FILE_CMP_MACHINE")
+        .arg("-c")
+        .arg(script)
+        .output()?;
+    assert!(!output.status.success());
+    let json: Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(json["exit_code"], 1);
+    assert_eq!(json["success"], false);
+    Ok(())
+}
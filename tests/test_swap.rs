@@ -0,0 +1,80 @@
+use file_cmp::swap::{compare_swapped, SwappedReader};
+use file_cmp::FileDiff::*;
+use file_cmp::{compare_files_with, CompareOptions, Side};
+use std::fs;
+use std::io::{self, Read};
+use std::process::Command;
+
+// Test swapping the bytes of words, leaving a partial word at the end
+#[test]
+fn test_swapped_reader() -> io::Result<()> {
+    let mut swapped = vec![];
+    SwappedReader::new(&b"abcdefg"[..], 2).read_to_end(&mut swapped)?;
+    assert_eq!(swapped, b"badcfeg");
+    swapped.clear();
+    SwappedReader::new(&b"abcdefghij"[..], 4).read_to_end(&mut swapped)?;
+    assert_eq!(swapped, b"dcbahgfeij");
+
+    // Across the reads of the inner reader
+    let data: Vec<u8> = (0..200_000u32).map(|i| (i % 253) as u8).collect();
+    swapped.clear();
+    SwappedReader::new(&data[..], 4).read_to_end(&mut swapped)?;
+    assert_eq!(swapped.len(), data.len());
+    assert!(swapped
+        .chunks(4)
+        .zip(data.chunks(4))
+        .all(|(s, d)| s.iter().rev().eq(d)));
+    Ok(())
+}
+
+// Test comparing a little-endian dump with a big-endian capture
+#[test]
+fn test_compare_swapped() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_compare_swapped");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir)?;
+    let samples: Vec<u32> = (0..100_000u32)
+        .map(|i| i.wrapping_mul(2_654_435_761))
+        .collect();
+    let little: Vec<u8> = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+    let big: Vec<u8> = samples.iter().flat_map(|s| s.to_be_bytes()).collect();
+    fs::write(dir.join("little"), &little)?;
+    fs::write(dir.join("big"), &big)?;
+    let options = CompareOptions::default();
+    let compare = |word| compare_swapped(dir.join("little"), dir.join("big"), word, &options);
+    assert_eq!(compare(4)?, Equal);
+    assert!(matches!(compare(2)?, Different(_)));
+    assert!(compare(3).is_err());
+
+    // Offsets are those of the files
+    let mut damaged = big.clone();
+    damaged[40_001] ^= 1;
+    fs::write(dir.join("big"), &damaged)?;
+    assert_eq!(compare(4)?, Different(40_002));
+    fs::write(dir.join("big"), &big[..4000])?;
+    assert_eq!(compare(4)?, Truncated(Side::Right, 4000));
+
+    // Through the options, as for directory trees
+    fs::write(dir.join("big"), &big)?;
+    let swapped = CompareOptions {
+        word_swap: Some(4),
+        ..Default::default()
+    };
+    assert_eq!(
+        compare_files_with(dir.join("little"), dir.join("big"), &swapped)?,
+        Equal
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_file_cmp"))
+        .current_dir(&dir)
+        .env_remove("FILE_CMP_MACHINE")
+        .args(["--word-swap", "4", "little", "big"])
+        .output()?;
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "Files are equal");
+    let output = Command::new(env!("CARGO_BIN_EXE_file_cmp"))
+        .current_dir(&dir)
+        .args(["--word-swap", "8", "little", "big"])
+        .output()?;
+    assert!(!output.status.success());
+    fs::remove_dir_all(&dir)
+}
@@ -0,0 +1,25 @@
+use file_cmp::throttle::{Throttle, Throttled};
+use std::io::{self, Cursor, Read};
+use std::time::{Duration, Instant};
+
+#[test]
+fn test_throttle_limits_rate() -> io::Result<()> {
+    // Test that reading 400 KB at 1 MB/s takes at least the time of the last three reads
+    let throttle = Throttle::new(1_000_000);
+    let mut reader = Throttled::new(Cursor::new(vec![0u8; 400_000]), Some(&throttle));
+    let mut buffer = vec![0; 100_000];
+    let start = Instant::now();
+    while reader.read(&mut buffer)? > 0 {}
+    assert!(start.elapsed() >= Duration::from_millis(290));
+    Ok(())
+}
+
+#[test]
+fn test_unthrottled() -> io::Result<()> {
+    // Test that a reader without a throttle reads everything
+    let mut reader = Throttled::new(Cursor::new(vec![1u8; 1000]), None);
+    let mut data = vec![];
+    reader.read_to_end(&mut data)?;
+    assert_eq!(data, vec![1u8; 1000]);
+    Ok(())
+}
@@ -0,0 +1,20 @@
+#![cfg(unix)]
+
+use file_cmp::tool::launch;
+use std::fs;
+use std::io;
+
+// Test passing paths with spaces and quotes to the tool
+#[test]
+fn test_launch() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_tool");
+    fs::create_dir_all(&dir)?;
+    let (path1, path2) = (dir.join("left 'one'.txt"), dir.join("right \"two\".txt"));
+    fs::write(&path1, "left")?;
+    let _ = fs::remove_file(&path2);
+    launch("cp -f", &path1, &path2)?;
+    assert_eq!(fs::read_to_string(&path2)?, "left");
+    // The tool's exit status doesn't matter
+    launch("false", &path1, &path2)?;
+    Ok(())
+}
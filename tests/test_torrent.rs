@@ -0,0 +1,175 @@
+#![cfg(feature = "torrent")]
+
+use file_cmp::torrent::{verify_pieces, verify_torrent, Piece, Torrent, TorrentFile};
+use file_cmp::FileDiff::*;
+use sha1::{Digest, Sha1};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::process::Command;
+
+fn bytes(s: &[u8]) -> Vec<u8> {
+    [format!("{}:", s.len()).as_bytes(), s].concat()
+}
+
+/// Encodes a multi-file torrent for the files, with an `attr` of `p` for
+/// the ones named `.pad`.
+fn encode(name: &str, piece_len: usize, files: &[(&str, &[u8])]) -> Vec<u8> {
+    let data: Vec<u8> = files
+        .iter()
+        .flat_map(|(_, content)| content.to_vec())
+        .collect();
+    let pieces: Vec<u8> = data
+        .chunks(piece_len)
+        .flat_map(|piece| Sha1::digest(piece).to_vec())
+        .collect();
+    let mut list = b"l".to_vec();
+    for (path, content) in files {
+        list.push(b'd');
+        if *path == ".pad" {
+            list.extend([bytes(b"attr"), bytes(b"p")].concat());
+        }
+        list.extend(bytes(b"length"));
+        list.extend(format!("i{}e", content.len()).as_bytes());
+        list.extend(bytes(b"path"));
+        list.push(b'l');
+        for component in path.split('/') {
+            list.extend(bytes(component.as_bytes()));
+        }
+        list.extend(b"ee");
+    }
+    list.push(b'e');
+    [
+        b"d".to_vec(),
+        bytes(b"announce"),
+        bytes(b"http://tracker/announce"),
+        bytes(b"info"),
+        b"d".to_vec(),
+        bytes(b"files"),
+        list,
+        bytes(b"name"),
+        bytes(name.as_bytes()),
+        bytes(b"piece length"),
+        format!("i{}e", piece_len).into_bytes(),
+        bytes(b"pieces"),
+        bytes(&pieces),
+        b"ee".to_vec(),
+    ]
+    .concat()
+}
+
+fn ranges(ranges: &[std::ops::Range<u64>]) -> Vec<(u64, u64)> {
+    ranges.iter().map(|r| (r.start, r.end)).collect()
+}
+
+// Test parsing the files of single and multi-file torrents
+#[test]
+fn test_parse() -> io::Result<()> {
+    let torrent = Torrent::parse(&encode(
+        "name",
+        8,
+        &[("a.bin", b"0123"), (".pad", &[0; 4]), ("dir/b.bin", b"4")],
+    ))?;
+    assert_eq!(torrent.piece_len, 8);
+    assert_eq!(torrent.pieces.len(), 2);
+    assert_eq!(
+        torrent.files,
+        [
+            TorrentFile {
+                path: PathBuf::from("name").join("a.bin"),
+                len: 4,
+                padding: false,
+            },
+            TorrentFile {
+                path: PathBuf::from("name").join(".pad"),
+                len: 4,
+                padding: true,
+            },
+            TorrentFile {
+                path: PathBuf::from("name").join("dir").join("b.bin"),
+                len: 1,
+                padding: false,
+            },
+        ]
+    );
+
+    let single = b"d4:infod6:lengthi3e4:name5:a.bin12:piece lengthi8e6:pieces0:ee";
+    let torrent = Torrent::parse(single)?;
+    assert_eq!(torrent.files[0].path, PathBuf::from("a.bin"));
+    assert_eq!(torrent.files[0].len, 3);
+
+    // Test rejecting paths that escape the download directory
+    assert!(Torrent::parse(&encode("name", 8, &[("../a.bin", b"0")])).is_err());
+    assert!(
+        Torrent::parse(b"d4:infod6:lengthi3e4:name2:..12:piece lengthi8e6:pieces0:ee").is_err()
+    );
+    assert!(Torrent::parse(b"d4:infod6:lengthi3e").is_err());
+    Ok(())
+}
+
+// Test reporting incomplete and corrupt pieces by file
+#[test]
+fn test_verify_torrent() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_torrent");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("name"))?;
+    let a: Vec<u8> = (1..=20).collect();
+    let b: Vec<u8> = (21..=30).collect();
+    let file = dir.join("name.torrent");
+    fs::write(
+        &file,
+        encode(
+            "name",
+            8,
+            &[("a.bin", &a), (".pad", &[0; 4]), ("b.bin", &b)],
+        ),
+    )?;
+    fs::write(dir.join("name").join("a.bin"), &a)?;
+    fs::write(dir.join("name").join("b.bin"), &b)?;
+    let torrent = Torrent::read(&file)?;
+    assert_eq!(verify_pieces(&torrent, &dir)?, [Piece::Ok; 5]);
+
+    // The end of a.bin is missing, b.bin is corrupt in the fourth piece and
+    // hasn't been downloaded in the last
+    fs::write(dir.join("name").join("a.bin"), &a[..12])?;
+    let mut corrupt = b.clone();
+    corrupt[3] = 0;
+    corrupt[8..].fill(0);
+    fs::write(dir.join("name").join("b.bin"), &corrupt)?;
+    assert_eq!(
+        verify_pieces(&torrent, &dir)?,
+        [
+            Piece::Ok,
+            Piece::Incomplete,
+            Piece::Incomplete,
+            Piece::Corrupt,
+            Piece::Incomplete
+        ]
+    );
+    let reports = verify_torrent(&file, &dir)?;
+    assert_eq!(reports.len(), 2);
+    assert_eq!(reports[0].path, dir.join("name").join("a.bin"));
+    assert_eq!(ranges(&reports[0].incomplete), [(8, 20)]);
+    assert_eq!(ranges(&reports[0].corrupt), []);
+    assert_eq!(reports[0].file_diff(), Different(8));
+    assert_eq!(ranges(&reports[1].incomplete), [(8, 10)]);
+    assert_eq!(ranges(&reports[1].corrupt), [(0, 8)]);
+    assert_eq!(reports[1].file_diff(), Different(0));
+
+    // Test the output of the subcommand
+    let output = Command::new(env!("CARGO_BIN_EXE_file_cmp"))
+        .arg("verify-torrent")
+        .arg(&file)
+        .arg(&dir)
+        .output()?;
+    assert!(!output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        format!(
+            "8\t{}\t(incomplete 8-19)\n0\t{}\t(incomplete 8-9, corrupt 0-7)\n",
+            dir.join("name").join("a.bin").display(),
+            dir.join("name").join("b.bin").display()
+        )
+    );
+    fs::remove_dir_all(&dir)
+}
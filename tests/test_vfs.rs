@@ -0,0 +1,77 @@
+use file_cmp::vfs::{compare_dirs_in, compare_files_in, MemoryFs, StdFs, Vfs};
+use file_cmp::CompareOptions;
+use file_cmp::FileDiff::*;
+use std::io;
+use std::path::{Path, PathBuf};
+
+fn memory_fs() -> MemoryFs {
+    let mut vfs = MemoryFs::new();
+    vfs.insert("a/equal.txt", b"test \r\n".to_vec());
+    vfs.insert("b/equal.txt", b"test \r\n".to_vec());
+    vfs.insert("a/sub/diff.txt", b"test \r\n".to_vec());
+    vfs.insert("b/sub/diff.txt", b"tesx \r\n".to_vec());
+    vfs.insert("a/left.txt", vec![]);
+    vfs.insert("b/right/file.txt", vec![]);
+    vfs
+}
+
+#[test]
+fn test_memory_fs() -> io::Result<()> {
+    // Test listing and metadata of implicit directories
+    let vfs = memory_fs();
+    let entries = vfs.list(Path::new("a"))?;
+    assert_eq!(
+        entries,
+        vec![
+            PathBuf::from("a/equal.txt"),
+            PathBuf::from("a/left.txt"),
+            PathBuf::from("a/sub")
+        ]
+    );
+    assert!(vfs.metadata(Path::new("b/right"))?.is_dir);
+    assert_eq!(vfs.metadata(Path::new("a/equal.txt"))?.len, 7);
+    assert!(vfs.open(Path::new("a/missing.txt")).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_compare_in_memory() -> io::Result<()> {
+    // Test comparing files and trees held in memory
+    let vfs = memory_fs();
+    let options = CompareOptions::default();
+    let res = compare_files_in(
+        &vfs,
+        Path::new("a/sub/diff.txt"),
+        Path::new("b/sub/diff.txt"),
+        &options,
+    )?;
+    assert_eq!(res, Different(3));
+
+    let res = compare_dirs_in(&vfs, Path::new("a"), Path::new("b"), &options)?;
+    assert_eq!(
+        res,
+        vec![
+            (PathBuf::from("a/equal.txt"), Equal),
+            (PathBuf::from("a/left.txt"), LeftOnly),
+            (PathBuf::from("b/right"), RightOnly),
+            (PathBuf::from("a/sub/diff.txt"), Different(3)),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_compare_std_fs() -> io::Result<()> {
+    // Test that the std backend reads the real files
+    let options = CompareOptions::default();
+    let p = |name: &str| PathBuf::from(format!("./tests/testfiles/{}", name));
+    assert_eq!(
+        compare_files_in(&StdFs, &p("test.txt"), &p("west.txt"), &options)?,
+        Different(0)
+    );
+    assert_eq!(
+        compare_files_in(&StdFs, &p("test.txt"), &p("test.txt"), &options)?,
+        Equal
+    );
+    Ok(())
+}
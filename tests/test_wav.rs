@@ -0,0 +1,99 @@
+use file_cmp::media::{compare_media, Format};
+use file_cmp::wav::{compare_samples, parse, PCM};
+use file_cmp::FileDiff::*;
+use file_cmp::{CompareOptions, Side};
+use std::fs;
+use std::io;
+
+fn chunk(id: &[u8], body: &[u8]) -> Vec<u8> {
+    let padding = vec![0; body.len() % 2];
+    [id, &(body.len() as u32).to_le_bytes(), body, &padding].concat()
+}
+
+/// Returns a mono 16-bit WAV file with the samples and the chunks before
+/// the data chunk.
+fn wav(samples: &[i16], extra: &[u8]) -> Vec<u8> {
+    let mut fmt = vec![];
+    fmt.extend(PCM.to_le_bytes());
+    fmt.extend(1u16.to_le_bytes());
+    fmt.extend(44100u32.to_le_bytes());
+    fmt.extend(88200u32.to_le_bytes());
+    fmt.extend(2u16.to_le_bytes());
+    fmt.extend(16u16.to_le_bytes());
+    let data: Vec<u8> = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+    let body = [
+        b"WAVE".to_vec(),
+        chunk(b"fmt ", &fmt),
+        extra.to_vec(),
+        chunk(b"data", &data),
+    ]
+    .concat();
+    [
+        b"RIFF".to_vec(),
+        (body.len() as u32).to_le_bytes().to_vec(),
+        body,
+    ]
+    .concat()
+}
+
+// Test finding the format and data chunks of WAV files
+#[test]
+fn test_parse_wav() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_wav_parse");
+    fs::create_dir_all(&dir)?;
+    let path = dir.join("a.wav");
+    fs::write(&path, wav(&[1, 2, 3], &chunk(b"LIST", b"INFOtitle")))?;
+    let parsed = parse(&path)?;
+    assert_eq!((parsed.format, parsed.bits_per_sample), (PCM, 16));
+    assert_eq!(parsed.fmt, 20..36);
+    assert_eq!(parsed.data, 62..68);
+    assert_eq!(parsed.sample_size(), 2);
+
+    fs::write(&path, b"RIFF\0\0\0\0WAVE")?;
+    assert!(parse(&path).is_err());
+    fs::write(&path, b"not a wav")?;
+    assert!(parse(&path).is_err());
+    fs::remove_dir_all(&dir)
+}
+
+// Test comparing the samples of WAV files with metadata chunks
+#[test]
+fn test_compare_wav() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("file_cmp_test_wav");
+    fs::create_dir_all(&dir)?;
+    let (path1, path2) = (dir.join("1.wav"), dir.join("2.wav"));
+    let options = CompareOptions::default();
+    let tolerant = CompareOptions {
+        sample_tolerance: 2,
+        ..Default::default()
+    };
+    fs::write(&path1, wav(&[100, -100, 7], &[]))?;
+    fs::write(&path2, wav(&[100, -100, 7], &chunk(b"bext", b"tool")))?;
+    assert_eq!(compare_media(&path1, &path2, Format::Wav, &options)?, Equal);
+    assert_eq!(
+        compare_media(&path1, &path2, Format::Wav, &tolerant)?,
+        Equal
+    );
+
+    fs::write(&path2, wav(&[102, -98, 6], &[]))?;
+    assert_eq!(
+        compare_media(&path1, &path2, Format::Wav, &options)?,
+        Different(16)
+    );
+    assert_eq!(
+        compare_media(&path1, &path2, Format::Wav, &tolerant)?,
+        Equal
+    );
+    fs::write(&path2, wav(&[100, -103, 7], &[]))?;
+    assert_eq!(
+        compare_media(&path1, &path2, Format::Wav, &tolerant)?,
+        Different(18)
+    );
+    assert_eq!(compare_samples(&path1, &path2, 3, true)?, Equal);
+    fs::write(&path2, wav(&[100, -100, 7, 0], &[]))?;
+    assert_eq!(
+        compare_media(&path1, &path2, Format::Wav, &tolerant)?,
+        Truncated(Side::Left, 22)
+    );
+    fs::remove_dir_all(&dir)
+}